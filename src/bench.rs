@@ -0,0 +1,259 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+
+use js_sys::{Array, Function, Promise};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    console, Event, MutationObserver, MutationObserverInit, PointerEvent, PointerEventInit,
+    SvgsvgElement, WheelEvent, WheelEventInit,
+};
+
+use crate::js_utils::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BenchOptions {
+    /// How many frames to drive gestures for
+    pub frames: u32,
+}
+
+impl Default for BenchOptions {
+    fn default() -> BenchOptions {
+        BenchOptions { frames: 600 }
+    }
+}
+
+/// What `benchmark()` resolves to, every time is in milliseconds
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+    /// How many synthetic events were sent
+    pub inputs: usize,
+    /// From sending an event to the viewBox it caused being written
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub frames: usize,
+    /// Frames that took more than one and a half frames at 60hz
+    pub dropped_frames: usize,
+}
+
+/// Each cycle wheels in and back out, then drags the diagram around in a circle
+static CYCLE_FRAMES: u32 = 120;
+static WHEEL_DELTA: f64 = 40.0;
+static DRAG_RADIUS: f64 = 60.0;
+static DROPPED_FRAME_MS: f64 = 1.5 * 1000.0 / 60.0;
+
+/// Drives synthetic gestures against a diagram, one event per frame, and measures how long each
+/// takes to reach the viewBox
+struct Bench {
+    svg: SvgsvgElement,
+    frames: u32,
+    frame: Cell<u32>,
+    last_frame_time: Cell<Option<f64>>,
+    /// When each event still waiting on a viewBox change was sent. Moves are coalesced, so one
+    /// change can answer several of them.
+    sent: RefCell<VecDeque<f64>>,
+    latencies: RefCell<Vec<f64>>,
+    frame_times: RefCell<Vec<f64>>,
+    resolve: Function,
+    observer: RefCell<Option<MutationObserver>>,
+    on_mutation: Closure<dyn FnMut()>,
+    on_frame: Closure<dyn FnMut(f64)>,
+}
+
+/// Runs the benchmark against `svg`, resolving to a `BenchReport`
+pub fn run_benchmark(svg: &SvgsvgElement, options: &BenchOptions) -> Promise {
+    let svg = svg.clone();
+    let frames = options.frames.max(1);
+
+    Promise::new(&mut move |resolve, reject| {
+        let bench = Rc::new_cyclic(|weak_ref: &Weak<Bench>| {
+            let mutation_ref = weak_ref.clone();
+            let frame_ref = weak_ref.clone();
+
+            Bench {
+                svg: svg.clone(),
+                frames,
+                frame: Cell::new(0),
+                last_frame_time: Cell::new(None),
+                sent: RefCell::new(VecDeque::new()),
+                latencies: RefCell::new(vec![]),
+                frame_times: RefCell::new(vec![]),
+                resolve,
+                observer: RefCell::new(None),
+                on_mutation: Closure::wrap(Box::new(move || {
+                    if let Some(bench) = mutation_ref.upgrade() {
+                        bench.view_box_changed();
+                    }
+                }) as Box<dyn FnMut()>),
+                on_frame: Closure::wrap(Box::new(move |time: f64| {
+                    // the closure keeps the bench alive until it's done
+                    if let Some(bench) = frame_ref.upgrade() {
+                        Bench::frame(&bench, time);
+                    }
+                }) as Box<dyn FnMut(f64)>),
+            }
+        });
+
+        if let Err(error) = Bench::start(&bench) {
+            let _ = reject.call1(&JsValue::NULL, &error);
+        }
+    })
+}
+
+thread_local! {
+    /// Keeps the running benchmarks alive between frames
+    static RUNNING: RefCell<Vec<Rc<Bench>>> = const { RefCell::new(vec![]) };
+}
+
+impl Bench {
+    fn start(bench: &Rc<Bench>) -> Result<(), JsValue> {
+        let observer = MutationObserver::new(bench.on_mutation.as_ref().unchecked_ref())?;
+        let options = MutationObserverInit::new();
+        options.set_attributes(true);
+        options.set_attribute_filter(&Array::of1(&"viewBox".into()));
+        observer.observe_with_options(&bench.svg, &options)?;
+        bench.observer.replace(Some(observer));
+
+        RUNNING.with(|running| running.borrow_mut().push(bench.clone()));
+        bench.request_frame()
+    }
+
+    fn request_frame(&self) -> Result<(), JsValue> {
+        window()
+            .request_animation_frame(self.on_frame.as_ref().unchecked_ref())
+            .map(|_| ())
+    }
+
+    fn frame(bench: &Rc<Bench>, time: f64) {
+        if let Some(last) = bench.last_frame_time.replace(Some(time)) {
+            bench.frame_times.borrow_mut().push(time - last);
+        }
+
+        let frame = bench.frame.get();
+        if frame >= bench.frames {
+            bench.finish();
+            return;
+        }
+        bench.frame.set(frame + 1);
+
+        if let Err(error) = bench.send(frame) {
+            console::warn_2(&"Couldn't send a benchmark event".into(), &error);
+        }
+
+        if let Err(error) = bench.request_frame() {
+            console::error_2(&"Couldn't request a benchmark frame".into(), &error);
+            bench.finish();
+        }
+    }
+
+    fn send(&self, frame: u32) -> Result<(), JsValue> {
+        let bounds = self.svg.get_bounding_client_rect();
+        let center_x = bounds.x() + bounds.width() / 2.0;
+        let center_y = bounds.y() + bounds.height() / 2.0;
+
+        let step = frame % CYCLE_FRAMES;
+        let half = CYCLE_FRAMES / 2;
+        let event: Event = if step < half {
+            let init = WheelEventInit::new();
+            init.set_bubbles(true);
+            init.set_cancelable(true);
+            // ctrl so every prevent default policy zooms
+            init.set_ctrl_key(true);
+            init.set_client_x(center_x as i32);
+            init.set_client_y(center_y as i32);
+            init.set_delta_y(if step < half / 2 {
+                -WHEEL_DELTA
+            } else {
+                WHEEL_DELTA
+            });
+
+            WheelEvent::new_with_event_init_dict("wheel", &init)?.into()
+        } else {
+            let drag_step = step - half;
+            let angle = drag_step as f64 / (half - 1) as f64 * std::f64::consts::PI * 2.0;
+            let event_type = match drag_step {
+                0 => "pointerdown",
+                step if step == half - 1 => "pointerup",
+                _ => "pointermove",
+            };
+
+            let init = PointerEventInit::new();
+            init.set_bubbles(true);
+            init.set_cancelable(true);
+            init.set_pointer_id(1);
+            init.set_is_primary(true);
+            init.set_buttons(if event_type == "pointerup" { 0 } else { 1 });
+            init.set_client_x((center_x + angle.cos() * DRAG_RADIUS) as i32);
+            init.set_client_y((center_y + angle.sin() * DRAG_RADIUS) as i32);
+
+            PointerEvent::new_with_event_init_dict(event_type, &init)?.into()
+        };
+
+        self.sent.borrow_mut().push_back(now());
+        self.svg.dispatch_event(&event)?;
+
+        Ok(())
+    }
+
+    fn view_box_changed(&self) {
+        let changed = now();
+        let mut latencies = self.latencies.borrow_mut();
+        for sent in self.sent.borrow_mut().drain(..) {
+            latencies.push(changed - sent);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(observer) = self.observer.replace(None) {
+            observer.disconnect();
+        }
+
+        let mut latencies = self.latencies.borrow().clone();
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let frame_times = self.frame_times.borrow();
+
+        let report = BenchReport {
+            inputs: self.frame.get() as usize,
+            p50: percentile(&latencies, 0.5),
+            p90: percentile(&latencies, 0.9),
+            p99: percentile(&latencies, 0.99),
+            max: latencies.last().cloned().unwrap_or(0.0),
+            frames: frame_times.len(),
+            dropped_frames: frame_times
+                .iter()
+                .filter(|time| **time > DROPPED_FRAME_MS)
+                .count(),
+        };
+
+        match to_js(&report) {
+            Ok(report) => {
+                let _ = self.resolve.call1(&JsValue::NULL, &report);
+            }
+            Err(error) => console::error_2(&"Couldn't report the benchmark".into(), &error),
+        }
+
+        let this: *const Bench = self;
+        RUNNING.with(|running| {
+            running
+                .borrow_mut()
+                .retain(|bench| !std::ptr::eq(Rc::as_ptr(bench), this))
+        });
+    }
+}
+
+/// `sorted` has to be sorted already
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let index = ((sorted.len() - 1) as f64 * fraction).round() as usize;
+
+    sorted[index.min(sorted.len() - 1)]
+}
@@ -0,0 +1,161 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::console;
+
+use crate::js_utils::*;
+
+/// How long each summary covers
+static SUMMARY_MS: i32 = 2000;
+
+/// While enabled, collects how long our event handlers take, how often they read layout, and
+/// how many updates get sent out, then logs one line summarizing them every couple of seconds
+/// there's been activity
+#[derive(Default)]
+struct Diagnostics {
+    enabled: Cell<bool>,
+    /// Set while a summary is waiting to be logged
+    summary_pending: Cell<bool>,
+    events: RefCell<BTreeMap<String, EventStats>>,
+    layout_reads: Cell<u32>,
+    dispatches: Cell<u32>,
+}
+
+#[derive(Default)]
+struct EventStats {
+    count: u32,
+    total_ms: f64,
+    max_ms: f64,
+}
+
+thread_local! {
+    static DIAGNOSTICS: Diagnostics = Diagnostics::default();
+}
+
+pub fn set_enabled(enabled: bool) {
+    DIAGNOSTICS.with(|diagnostics| {
+        diagnostics.enabled.set(enabled);
+
+        if !enabled {
+            diagnostics.reset();
+        }
+    });
+}
+
+/// Runs the handler for an `event_type` event, timing it if diagnostics are enabled
+pub fn time_event<R, F: FnOnce() -> R>(event_type: &str, handler: F) -> R {
+    if !DIAGNOSTICS.with(|diagnostics| diagnostics.enabled.get()) {
+        return handler();
+    }
+
+    let start = now();
+    let result = handler();
+    let duration = now() - start;
+
+    DIAGNOSTICS.with(|diagnostics| {
+        {
+            let mut events = diagnostics.events.borrow_mut();
+            let stats = match events.get_mut(event_type) {
+                Some(stats) => stats,
+                None => events.entry(event_type.to_string()).or_default(),
+            };
+            stats.count += 1;
+            stats.total_ms += duration;
+            stats.max_ms = stats.max_ms.max(duration);
+        }
+
+        diagnostics.schedule_summary();
+    });
+
+    result
+}
+
+/// Counts a read that can force layout, like `getBBox` or `getBoundingClientRect`
+pub fn layout_read() {
+    DIAGNOSTICS.with(|diagnostics| {
+        if diagnostics.enabled.get() {
+            diagnostics
+                .layout_reads
+                .set(diagnostics.layout_reads.get() + 1);
+            diagnostics.schedule_summary();
+        }
+    });
+}
+
+/// Counts an update sent to a listener or dispatched on the page
+pub fn dispatched() {
+    DIAGNOSTICS.with(|diagnostics| {
+        if diagnostics.enabled.get() {
+            diagnostics.dispatches.set(diagnostics.dispatches.get() + 1);
+            diagnostics.schedule_summary();
+        }
+    });
+}
+
+impl Diagnostics {
+    fn schedule_summary(&self) {
+        if self.summary_pending.replace(true) {
+            return;
+        }
+
+        let callback = Closure::once_into_js(move || {
+            DIAGNOSTICS.with(|diagnostics| diagnostics.log_summary());
+        });
+
+        if let Err(error) = window().set_timeout_with_callback_and_timeout_and_arguments_0(
+            callback.unchecked_ref(),
+            SUMMARY_MS,
+        ) {
+            console::warn_2(&"Couldn't schedule the diagnostics summary".into(), &error);
+            self.summary_pending.set(false);
+        }
+    }
+
+    fn log_summary(&self) {
+        if !self.summary_pending.get() {
+            // disabled since the summary was scheduled
+            return;
+        }
+
+        let events: Vec<String> = self
+            .events
+            .borrow()
+            .iter()
+            .map(|(event_type, stats)| {
+                format!(
+                    "{} {}x avg {:.2}ms max {:.2}ms",
+                    event_type,
+                    stats.count,
+                    stats.total_ms / stats.count as f64,
+                    stats.max_ms
+                )
+            })
+            .collect();
+
+        console::log_1(
+            &format!(
+                "ArchiZoom over {}s: {}; {} layout reads, {} dispatches",
+                SUMMARY_MS / 1000,
+                if events.is_empty() {
+                    "no events".to_string()
+                } else {
+                    events.join(", ")
+                },
+                self.layout_reads.get(),
+                self.dispatches.get()
+            )
+            .into(),
+        );
+
+        self.reset();
+    }
+
+    fn reset(&self) {
+        self.summary_pending.set(false);
+        self.events.borrow_mut().clear();
+        self.layout_reads.set(0);
+        self.dispatches.set(0);
+    }
+}
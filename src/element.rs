@@ -0,0 +1,201 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{console, Element, Url};
+
+use crate::js_utils::*;
+use crate::mount::{mount_with, ArchiZoomMount};
+use crate::options::Options;
+use crate::zoom::ArchiZoom;
+
+/// The element class, which hands its lifecycle to the `hooks` it's built with. Custom elements
+/// have to extend `HTMLElement` with a real class, which can't be done from Rust.
+static ELEMENT_SOURCE: &str = r#"
+return class extends HTMLElement {
+  static get observedAttributes() {
+    return ["src", "zoom", "focus", "interactive"];
+  }
+
+  connectedCallback() {
+    hooks.connected(this);
+  }
+
+  disconnectedCallback() {
+    hooks.disconnected(this);
+  }
+
+  attributeChangedCallback(name, oldValue, newValue) {
+    if (oldValue !== newValue) {
+      hooks.attributeChanged(this, name);
+    }
+  }
+};
+"#;
+
+static DEFAULT_NAME: &str = "archi-zoom";
+
+/// Each element on the page and the viewer mounted in it
+struct ElementViewer {
+    element: Element,
+    mount: ArchiZoomMount,
+}
+
+thread_local! {
+    /// The options every element starts from, before its attributes
+    static ELEMENT_OPTIONS: RefCell<Options> = RefCell::new(Options::default());
+    static VIEWERS: RefCell<Vec<ElementViewer>> = const { RefCell::new(vec![]) };
+}
+
+/// Registers `<archi-zoom src="...">`, or `name` instead, which shows its diagram while it's on
+/// the page. `options` apply to every element, and these attributes are followed as they change:
+///
+/// - `src` swaps in another diagram
+/// - `zoom` is the zoom level relative to the initial view
+/// - `focus` is a space separated list of the ids to frame
+/// - `interactive="false"` ignores the user's pan and zoom gestures
+///
+/// The viewer fills the element, so give it a size and make it a block.
+#[wasm_bindgen(js_name = defineElement)]
+pub fn define_element(
+    name: Option<String>,
+    #[wasm_bindgen(unchecked_param_type = "ArchiZoomOptions")] options: JsValue,
+) -> Result<(), JsValue> {
+    console_error_panic_hook::set_once();
+
+    let options = Options::from_js(&options)?;
+    ELEMENT_OPTIONS.with(|defaults| defaults.replace(options));
+
+    let name = name.unwrap_or_else(|| DEFAULT_NAME.to_string());
+    let registry = window().custom_elements();
+    if !registry.get(&name).is_undefined() {
+        return Ok(());
+    }
+
+    // the element is defined for the life of the page, so its hooks are too
+    let hooks = Object::new();
+    Reflect::set(
+        &hooks,
+        &"connected".into(),
+        &Closure::wrap(Box::new(connected) as Box<dyn FnMut(Element)>).into_js_value(),
+    )?;
+    Reflect::set(
+        &hooks,
+        &"disconnected".into(),
+        &Closure::wrap(Box::new(disconnected) as Box<dyn FnMut(Element)>).into_js_value(),
+    )?;
+    Reflect::set(
+        &hooks,
+        &"attributeChanged".into(),
+        &Closure::wrap(Box::new(attribute_changed) as Box<dyn FnMut(Element, String)>)
+            .into_js_value(),
+    )?;
+
+    let class = Function::new_with_args("hooks", ELEMENT_SOURCE).call1(&JsValue::NULL, &hooks)?;
+    registry.define(&name, class.unchecked_ref())
+}
+
+fn connected(element: Element) {
+    if let Err(error) = mount_element(&element) {
+        console::warn_2(&"Couldn't show the diagram".into(), &error);
+    }
+}
+
+fn disconnected(element: Element) {
+    let removed = VIEWERS.with(|viewers| {
+        let mut viewers = viewers.borrow_mut();
+        let index = viewers.iter().position(|viewer| viewer.element == element);
+
+        index.map(|index| viewers.remove(index))
+    });
+
+    // unmounted outside the borrow, in case taking it down touches the element again
+    if let Some(viewer) = removed {
+        viewer.mount.unmount();
+    }
+}
+
+fn attribute_changed(element: Element, name: String) {
+    // attributes are set before the element is on the page too, it reads them when it mounts
+    if !element.is_connected() {
+        return;
+    }
+
+    if name == "src" {
+        connected(element);
+    } else if let Some(archizoom) = viewer_of(&element) {
+        apply_attribute(&element, &archizoom.borrow(), &name);
+    }
+}
+
+/// Mounts the element's diagram, replacing whatever it was showing
+fn mount_element(element: &Element) -> Result<(), JsValue> {
+    disconnected(element.clone());
+
+    let src = match element.get_attribute("src") {
+        Some(src) => src,
+        None => return Ok(()),
+    };
+
+    // the diagram might be fetched in a worker, which has nothing to resolve it against
+    let base = element.base_uri()?.unwrap_or_default();
+    let mut options = ELEMENT_OPTIONS.with(|defaults| defaults.borrow().clone());
+    options.src = Some(Url::new_with_base(&src, &base)?.href());
+    options.locked = is_locked(element);
+
+    let ready_element = element.clone();
+    let mount = mount_with(element.clone(), options, move |archizoom| {
+        apply_attribute(&ready_element, archizoom, "zoom");
+        apply_attribute(&ready_element, archizoom, "focus");
+    })?;
+
+    VIEWERS.with(|viewers| {
+        viewers.borrow_mut().push(ElementViewer {
+            element: element.clone(),
+            mount,
+        })
+    });
+
+    Ok(())
+}
+
+fn viewer_of(element: &Element) -> Option<Rc<RefCell<ArchiZoom>>> {
+    VIEWERS.with(|viewers| {
+        viewers
+            .borrow()
+            .iter()
+            .find(|viewer| viewer.element == *element)
+            .and_then(|viewer| viewer.mount.archizoom())
+    })
+}
+
+fn apply_attribute(element: &Element, archizoom: &ArchiZoom, name: &str) {
+    match name {
+        "zoom" => {
+            let level = element
+                .get_attribute("zoom")
+                .and_then(|zoom| zoom.trim().parse::<f32>().ok());
+
+            if let Some(level) = level.filter(|level| *level > 0.0) {
+                archizoom.set_zoom_level(level);
+            }
+        }
+        "focus" => {
+            let focus = element.get_attribute("focus").unwrap_or_default();
+            let targets: Vec<&str> = focus.split_whitespace().collect();
+
+            if !targets.is_empty() {
+                archizoom.frame_elements(&targets);
+            }
+        }
+        "interactive" => archizoom.set_locked(is_locked(element)),
+        _ => (),
+    }
+}
+
+fn is_locked(element: &Element) -> bool {
+    element.get_attribute("interactive").as_deref() == Some("false")
+}
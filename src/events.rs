@@ -8,7 +8,7 @@ impl<E, F: Fn(&E)> EventListener<E> for F {
     }
 }
 
-impl<E> EventListener<E> for Fn(&E) {
+impl<E> EventListener<E> for dyn Fn(&E) {
     fn receive(&self, event: &E) {
         self(event)
     }
@@ -1,8 +1,22 @@
+use std::cell::Cell;
+
+use futures::Future;
+use js_sys::{Promise, JSON};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::convert::FromWasmAbi;
 use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{console, Document, Element, EventTarget, NodeList, Window};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    console, AddEventListenerOptions, CustomEvent, CustomEventInit, Document, Element, EventTarget,
+    NodeList, Request, RequestInit, Response, Window,
+};
+
+use crate::diagnostics;
+use crate::leak_check::{Live, Tracked};
 
+#[allow(dead_code)]
 pub trait EnhancedDocument {
     fn safe_get_by_id<T: JsCast>(&self, id: &str) -> Option<T>;
 
@@ -54,6 +68,11 @@ impl EnhancedDocument for Document {
 
 pub trait EnhancedElement {
     fn safe_cast<T: JsCast>(self) -> Option<T>;
+
+    /// A deep copy that can go in the same document as the original. Its ids would clash with
+    /// the original's so they're dropped, references like `url(#gradient)` in it find the
+    /// original's instead.
+    fn clone_without_ids(&self) -> Result<Element, JsValue>;
 }
 
 impl EnhancedElement for Element {
@@ -70,6 +89,16 @@ impl EnhancedElement for Element {
             }
         }
     }
+
+    fn clone_without_ids(&self) -> Result<Element, JsValue> {
+        let copy: Element = self.clone_node_with_deep(true)?.unchecked_into();
+        copy.remove_attribute("id")?;
+        for nested in copy.query_selector_all("[id]")?.safe_filter::<Element>() {
+            nested.remove_attribute("id")?;
+        }
+
+        Ok(copy)
+    }
 }
 
 pub trait EnhancedNodeList {
@@ -81,9 +110,8 @@ impl EnhancedNodeList for NodeList {
         let mut valid_nodes = vec![];
 
         for i in 0..self.length() {
-            match self.get(i).and_then(|node| node.dyn_into::<T>().ok()) {
-                Some(t) => valid_nodes.push(t),
-                None => (),
+            if let Some(t) = self.get(i).and_then(|node| node.dyn_into::<T>().ok()) {
+                valid_nodes.push(t);
             }
         }
 
@@ -98,15 +126,18 @@ pub trait JsEventListener {
 struct JsEventListenerImpl<T: ?Sized> {
     event_type: String,
     target: EventTarget,
+    capture: bool,
     closure: Option<Closure<T>>,
+    _live: Live,
 }
 
 impl<T: ?Sized> JsEventListener for JsEventListenerImpl<T> {
     fn remove(&mut self) {
         if let Some(ref closure) = self.closure {
-            match self.target.remove_event_listener_with_callback(
+            match self.target.remove_event_listener_with_callback_and_bool(
                 &self.event_type,
                 closure.as_ref().unchecked_ref(),
+                self.capture,
             ) {
                 Ok(_) => (),
                 Err(error) => console::warn_2(&"Failed to remove event handler".into(), &error),
@@ -129,7 +160,39 @@ pub trait EnhancedEventTarget {
         &self,
         event_type: &str,
         callback: C,
-    ) -> Result<Box<JsEventListener>, JsValue>
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
+    where
+        C: Fn(E) + 'static,
+        E: FromWasmAbi + 'static;
+
+    /// Like `new_event_listener`, but tells the browser up front that `callback` never calls
+    /// `preventDefault`, so scrolling doesn't have to wait on it
+    fn new_passive_event_listener<C, E>(
+        &self,
+        event_type: &str,
+        callback: C,
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
+    where
+        C: Fn(E) + 'static,
+        E: FromWasmAbi + 'static;
+
+    /// A passive listener that also hears events that don't bubble, like `scroll`, from
+    /// anywhere below the target
+    fn new_capturing_event_listener<C, E>(
+        &self,
+        event_type: &str,
+        callback: C,
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
+    where
+        C: Fn(E) + 'static,
+        E: FromWasmAbi + 'static;
+
+    fn new_event_listener_with_options<C, E>(
+        &self,
+        event_type: &str,
+        callback: C,
+        options: AddEventListenerOptions,
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
     where
         C: Fn(E) + 'static,
         E: FromWasmAbi + 'static;
@@ -140,24 +203,102 @@ impl EnhancedEventTarget for EventTarget {
         &self,
         event_type: &str,
         callback: C,
-    ) -> Result<Box<JsEventListener>, JsValue>
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
     where
         C: Fn(E) + 'static,
         E: FromWasmAbi + 'static,
     {
-        let closure = Closure::wrap(Box::new(callback) as Box<Fn(E)>);
-
-        self.add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())
-            .map(|_| -> Box<JsEventListener> {
-                Box::new(JsEventListenerImpl {
-                    event_type: event_type.to_string(),
-                    target: self.clone(),
-                    closure: Some(closure),
-                })
-            })
+        listen(self, event_type, callback, None)
+    }
+
+    fn new_passive_event_listener<C, E>(
+        &self,
+        event_type: &str,
+        callback: C,
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
+    where
+        C: Fn(E) + 'static,
+        E: FromWasmAbi + 'static,
+    {
+        let options = AddEventListenerOptions::new();
+        options.set_passive(true);
+
+        listen(self, event_type, callback, Some(options))
+    }
+
+    fn new_capturing_event_listener<C, E>(
+        &self,
+        event_type: &str,
+        callback: C,
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
+    where
+        C: Fn(E) + 'static,
+        E: FromWasmAbi + 'static,
+    {
+        let options = AddEventListenerOptions::new();
+        options.set_passive(true);
+        options.set_capture(true);
+
+        listen(self, event_type, callback, Some(options))
+    }
+
+    fn new_event_listener_with_options<C, E>(
+        &self,
+        event_type: &str,
+        callback: C,
+        options: AddEventListenerOptions,
+    ) -> Result<Box<dyn JsEventListener>, JsValue>
+    where
+        C: Fn(E) + 'static,
+        E: FromWasmAbi + 'static,
+    {
+        listen(self, event_type, callback, Some(options))
     }
 }
 
+fn listen<C, E>(
+    target: &EventTarget,
+    event_type: &str,
+    callback: C,
+    options: Option<AddEventListenerOptions>,
+) -> Result<Box<dyn JsEventListener>, JsValue>
+where
+    C: Fn(E) + 'static,
+    E: FromWasmAbi + 'static,
+{
+    let timed_type = event_type.to_string();
+    let closure = Closure::wrap(Box::new(move |event: E| {
+        diagnostics::time_event(&timed_type, || callback(event))
+    }) as Box<dyn Fn(E)>);
+    let capture = options
+        .as_ref()
+        .and_then(|options| options.get_capture())
+        .unwrap_or(false);
+
+    match options {
+        Some(ref options) => target
+            .add_event_listener_with_callback_and_add_event_listener_options(
+                event_type,
+                closure.as_ref().unchecked_ref(),
+                options,
+            ),
+        None => {
+            target.add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())
+        }
+    }
+    .map(|_| -> Box<dyn JsEventListener> {
+        Box::new(JsEventListenerImpl {
+            event_type: event_type.to_string(),
+            target: target.clone(),
+            capture,
+            closure: Some(closure),
+            _live: Live::new(Tracked::JsListener),
+        })
+    })
+}
+
+pub static SVG_NS: &str = "http://www.w3.org/2000/svg";
+
 pub fn window() -> Window {
     web_sys::window().expect("Missing window")
 }
@@ -165,3 +306,141 @@ pub fn window() -> Window {
 pub fn document() -> Document {
     window().document().expect("Missing document")
 }
+
+/// Milliseconds from `performance.now()`, or 0 without the Performance API
+pub fn now() -> f64 {
+    window()
+        .performance()
+        .map(|performance| performance.now())
+        .unwrap_or(0.0)
+}
+
+/// How many raster pixels make up a CSS pixel on this display
+pub fn device_pixel_ratio() -> f32 {
+    let ratio = window().device_pixel_ratio() as f32;
+
+    if ratio.is_finite() && ratio > 0.0 {
+        ratio
+    } else {
+        1.0
+    }
+}
+
+/// The value of the CSS custom property `name` as it applies to `element`, so canvas drawing
+/// can be themed the same way as our DOM
+pub fn css_variable(element: &Element, name: &str) -> Option<String> {
+    window()
+        .get_computed_style(element)
+        .ok()
+        .and_then(|style| style)
+        .and_then(|style| style.get_property_value(name).ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Converts a serializable value into a plain JS object by way of JSON
+pub fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    serde_json::to_string(value)
+        .map_err(|error| JsValue::from(error.to_string()))
+        .and_then(|json| JSON::parse(&json))
+}
+
+/// Converts a plain JS object into a deserializable value by way of JSON
+pub fn from_js<T: DeserializeOwned>(value: &JsValue) -> Result<T, JsValue> {
+    JSON::stringify(value)?
+        .as_string()
+        .ok_or_else(|| JsValue::from("Couldn't stringify the value"))
+        .and_then(|json| {
+            serde_json::from_str(&json).map_err(|error| JsValue::from(error.to_string()))
+        })
+}
+
+/// Dispatches a bubbling `CustomEvent` named `event_type` on `target` with `detail` as a plain
+/// JS object
+pub fn dispatch_custom_event<T: Serialize>(
+    target: &EventTarget,
+    event_type: &str,
+    detail: &T,
+) -> Result<(), JsValue> {
+    let init = CustomEventInit::new();
+    init.set_bubbles(true);
+    init.set_detail(&to_js(detail)?);
+
+    let event = CustomEvent::new_with_event_init_dict(event_type, &init)?;
+    diagnostics::dispatched();
+    target.dispatch_event(&event)?;
+
+    Ok(())
+}
+
+/// Fetches `url` and resolves to the text of the response
+pub fn fetch_text(url: &str) -> Result<impl Future<Item = String, Error = JsValue>, JsValue> {
+    let opts = RequestInit::new();
+    opts.set_method("GET");
+
+    let request = Request::new_with_str_and_init(url, &opts)?;
+
+    let request_promise = window().fetch_with_request(&request);
+
+    Ok(JsFuture::from(request_promise)
+        .and_then(|resp_value| {
+            // grab the text from our response
+            resp_value.dyn_into::<Response>().and_then(|response| {
+                if response.ok() {
+                    response.text()
+                } else {
+                    Err(format!("{} {}", response.status(), response.status_text()).into())
+                }
+            })
+        })
+        .and_then(|text: Promise| {
+            // Convert the response promise into a future
+            JsFuture::from(text)
+        })
+        .and_then(|text_value| {
+            text_value
+                .as_string()
+                .ok_or_else(|| JsValue::from("The response wasn't text"))
+        }))
+}
+
+/// A reusable `setTimeout`, restarting it cancels the pending call
+pub struct Timeout {
+    closure: Closure<dyn FnMut()>,
+    handle: Cell<Option<i32>>,
+    _live: Live,
+}
+
+impl Timeout {
+    pub fn new<C: FnMut() + 'static>(callback: C) -> Timeout {
+        Timeout {
+            closure: Closure::wrap(Box::new(callback) as Box<dyn FnMut()>),
+            handle: Cell::new(None),
+            _live: Live::new(Tracked::Timeout),
+        }
+    }
+
+    pub fn restart(&self, delay_ms: i32) {
+        self.cancel();
+
+        match window().set_timeout_with_callback_and_timeout_and_arguments_0(
+            self.closure.as_ref().unchecked_ref(),
+            delay_ms,
+        ) {
+            Ok(handle) => self.handle.set(Some(handle)),
+            Err(error) => console::warn_2(&"Couldn't set a timeout".into(), &error),
+        }
+    }
+
+    pub fn cancel(&self) {
+        if let Some(handle) = self.handle.take() {
+            window().clear_timeout_with_handle(handle);
+        }
+    }
+}
+
+impl Drop for Timeout {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
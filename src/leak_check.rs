@@ -0,0 +1,100 @@
+#[cfg(feature = "leak-check")]
+use std::cell::Cell;
+
+#[cfg(feature = "leak-check")]
+use serde::Serialize;
+
+use crate::diagnostics;
+use crate::events::EventListener;
+
+/// The kinds of things that should all be released when a viewer is torn down
+#[derive(Debug, Clone, Copy)]
+pub enum Tracked {
+    JsListener,
+    SourceListener,
+    Timeout,
+    Instance,
+}
+
+/// Counts one live `Tracked` thing until it's dropped. Without the `leak-check` feature this is
+/// empty and counts nothing.
+pub struct Live {
+    #[cfg(feature = "leak-check")]
+    kind: Tracked,
+}
+
+impl Live {
+    #[cfg_attr(not(feature = "leak-check"), allow(unused_variables))]
+    pub fn new(kind: Tracked) -> Live {
+        #[cfg(feature = "leak-check")]
+        {
+            COUNTS.with(|counts| {
+                let count = &counts[kind as usize];
+                count.set(count.get() + 1);
+            });
+
+            Live { kind }
+        }
+
+        #[cfg(not(feature = "leak-check"))]
+        Live {}
+    }
+}
+
+#[cfg(feature = "leak-check")]
+impl Drop for Live {
+    fn drop(&mut self) {
+        COUNTS.with(|counts| {
+            let count = &counts[self.kind as usize];
+            count.set(count.get() - 1);
+        });
+    }
+}
+
+#[cfg(feature = "leak-check")]
+thread_local! {
+    static COUNTS: [Cell<i32>; 4] = const { [Cell::new(0), Cell::new(0), Cell::new(0), Cell::new(0)] };
+}
+
+/// An `EventSource` listener that's counted while it's registered
+pub struct CountedListener<T> {
+    listener: T,
+    _live: Live,
+}
+
+impl<T> CountedListener<T> {
+    pub fn new(listener: T) -> CountedListener<T> {
+        CountedListener {
+            listener,
+            _live: Live::new(Tracked::SourceListener),
+        }
+    }
+}
+
+impl<E, T: EventListener<E>> EventListener<E> for CountedListener<T> {
+    fn receive(&self, event: &E) {
+        diagnostics::dispatched();
+        self.listener.receive(event)
+    }
+}
+
+/// How many of each tracked thing are alive right now
+#[cfg(feature = "leak-check")]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveCounts {
+    pub js_listeners: i32,
+    pub event_source_listeners: i32,
+    pub timeouts: i32,
+    pub instances: i32,
+}
+
+#[cfg(feature = "leak-check")]
+pub fn live_counts() -> LiveCounts {
+    COUNTS.with(|counts| LiveCounts {
+        js_listeners: counts[Tracked::JsListener as usize].get(),
+        event_source_listeners: counts[Tracked::SourceListener as usize].get(),
+        timeouts: counts[Tracked::Timeout as usize].get(),
+        instances: counts[Tracked::Instance as usize].get(),
+    })
+}
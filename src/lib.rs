@@ -2,41 +2,625 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use futures::Future;
-use js_sys::{Array, Promise};
+use js_sys::{Array, Function, Promise, Reflect};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::future_to_promise;
-use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
 use web_sys::{
-    console, HtmlDivElement, HtmlImageElement, Request, RequestInit, Response, SvgsvgElement,
+    console, Blob, BlobPropertyBag, Element, HtmlDivElement, HtmlImageElement, SvgGraphicsElement,
+    SvgsvgElement,
 };
 
 use js_utils::*;
+use options::Options;
+use overlays::context_menu::ContextMenuItem;
+use overlays::controls::ZOOM_STEP;
+#[cfg(feature = "annotations")]
+use zoom::annotations::{Annotation, AnnotationSet};
+use zoom::diagram::parse_svg;
+use zoom::hit_test::DEFAULT_SELECTOR;
+use zoom::loader::{load_diagram, LoadedDiagram};
+use zoom::matrix::Size2D;
+use zoom::pins::PinData;
+use zoom::recording::{replay, Trace};
+use zoom::space::ScreenPoint;
+use zoom::timing::Span;
+use zoom::view_link::ViewLink;
+use zoom::viewport::Viewport;
 use zoom::*;
 
+#[cfg(feature = "bench")]
+mod bench;
+mod diagnostics;
+mod element;
 mod events;
 mod js_utils;
+mod leak_check;
+mod mount;
+mod options;
+mod overlays;
+mod strings;
+mod styles;
+mod typescript;
 mod zoom;
 
 #[wasm_bindgen]
 pub struct ArchiZoomContainer {
-    _value: Rc<RefCell<ArchiZoom>>,
+    value: Rc<RefCell<ArchiZoom>>,
+}
+
+#[wasm_bindgen]
+impl ArchiZoomContainer {
+    /// The current viewport as `{ origin: { x, y }, size: { width, height }, scale }`
+    #[wasm_bindgen(js_name = getViewport, unchecked_return_type = "Viewport | null")]
+    pub fn viewport(&self) -> Result<JsValue, JsValue> {
+        match self.value.borrow().viewport() {
+            Some(viewport) => to_js(&viewport),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    #[wasm_bindgen(js_name = setViewport)]
+    pub fn set_viewport(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Viewport")] viewport: JsValue,
+    ) -> Result<(), JsValue> {
+        let viewport = from_js::<Viewport>(&viewport)?;
+
+        self.value.borrow().set_viewport(&viewport);
+
+        Ok(())
+    }
+
+    /// Frames all of the elements matching the given ids or selectors together
+    #[wasm_bindgen(js_name = frameElements)]
+    pub fn frame_elements(&self, targets: Vec<String>) -> bool {
+        self.value.borrow().frame_elements(&targets)
+    }
+
+    /// The id of the selected element, or null
+    #[wasm_bindgen(js_name = getSelection)]
+    pub fn selection(&self) -> Option<String> {
+        self.value.borrow().selection()
+    }
+
+    /// Selects the element with the given id or selector, null clears the selection
+    pub fn select(&self, target: Option<String>) -> bool {
+        self.value.borrow().select(target.as_deref())
+    }
+
+    /// The smallest element matching `selector` (groups with ids by default) whose bounds
+    /// contain the client coordinates `x`, `y`, or undefined
+    #[wasm_bindgen(js_name = elementAt)]
+    pub fn element_at(
+        &self,
+        x: f32,
+        y: f32,
+        selector: Option<String>,
+    ) -> Option<SvgGraphicsElement> {
+        self.value.borrow().element_at(
+            &ScreenPoint::point(x, y),
+            selector.as_deref().unwrap_or(DEFAULT_SELECTOR),
+        )
+    }
+
+    /// Highlights the elements whose labels contain `query` and frames the first, returning
+    /// how many matched
+    #[cfg(feature = "search")]
+    pub fn search(&self, query: String) -> usize {
+        self.value.borrow().search(&query)
+    }
+
+    /// Frames the next search match, returning its index
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = searchNext)]
+    pub fn search_next(&self) -> Option<usize> {
+        self.value.borrow().search_step(true)
+    }
+
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = searchPrevious)]
+    pub fn search_previous(&self) -> Option<usize> {
+        self.value.borrow().search_step(false)
+    }
+
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = clearSearch)]
+    pub fn clear_search(&self) {
+        self.value.borrow().clear_search()
+    }
+
+    /// Searches every diagram linked from the first one, resolving to the matches grouped by
+    /// diagram. The first call has to load them all, so it can take a while.
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(
+        js_name = searchModel,
+        unchecked_return_type = "Promise<ModelSearchResult[]>"
+    )]
+    pub fn search_model(&self, query: String) -> Promise {
+        let future = self.value.borrow().search_model(&query);
+
+        future_to_promise(future.and_then(|results| to_js(&results)))
+    }
+
+    /// Shows the diagram at `url` and frames the element `id` on it, like a `searchModel` match
+    #[wasm_bindgen(js_name = openSearchResult)]
+    pub fn open_search_result(&self, url: String, id: Option<String>) {
+        self.value.borrow().open_and_frame(&url, id)
+    }
+
+    /// Dims everything that doesn't match `query`, either a CSS selector or a function called
+    /// with each element. Returns how many elements matched.
+    pub fn filter(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "FilterQuery")] query: JsValue,
+    ) -> Result<usize, JsValue> {
+        if let Some(selector) = query.as_string() {
+            Ok(self
+                .value
+                .borrow()
+                .filter(|element| element.matches(&selector).unwrap_or(false)))
+        } else if let Some(predicate) = query.dyn_ref::<Function>() {
+            // the predicate can call back into the viewer, so it runs without it borrowed
+            let candidates = self.value.borrow().filter_candidates();
+            let matches: Vec<Element> = candidates
+                .into_iter()
+                .filter(|element| match predicate.call1(&JsValue::NULL, element) {
+                    Ok(result) => result.is_truthy(),
+                    Err(error) => {
+                        console::error_2(&"The filter threw".into(), &error);
+
+                        false
+                    }
+                })
+                .map(Element::from)
+                .collect();
+
+            Ok(self
+                .value
+                .borrow()
+                .filter(|element| matches.contains(element)))
+        } else {
+            Err("The filter must be a selector or a function".into())
+        }
+    }
+
+    /// Dims everything whose text doesn't contain `text`, ignoring case
+    #[wasm_bindgen(js_name = filterByText)]
+    pub fn filter_by_text(&self, text: String) -> usize {
+        let text = text.to_lowercase();
+
+        self.value.borrow().filter(|element| {
+            element
+                .text_content()
+                .map(|content| content.to_lowercase().contains(&text))
+                .unwrap_or(false)
+        })
+    }
+
+    #[wasm_bindgen(js_name = clearFilter)]
+    pub fn clear_filter(&self) {
+        self.value.borrow().clear_filter()
+    }
+
+    /// Dims everything but the element with the given id or selector, or the selection, and
+    /// what it's connected to. Returns how many connectors touch it, undefined if there's
+    /// nothing to isolate.
+    pub fn isolate(&self, target: Option<String>) -> Option<usize> {
+        self.value.borrow().isolate(target.as_deref())
+    }
+
+    #[wasm_bindgen(js_name = clearIsolation)]
+    pub fn clear_isolation(&self) {
+        self.value.borrow().clear_isolation()
+    }
+
+    /// The layers of the current diagram as `[{ name, visible }]`
+    #[wasm_bindgen(js_name = getLayers, unchecked_return_type = "LayerInfo[]")]
+    pub fn layers(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.value.borrow().layers())
+    }
+
+    /// Shows or hides a layer, returning false if there isn't one called `name`
+    #[wasm_bindgen(js_name = setLayerVisible)]
+    pub fn set_layer_visible(&self, name: String, visible: bool) -> bool {
+        self.value.borrow().set_layer_visible(&name, visible)
+    }
+
+    /// Pins a note `{ x, y, text, id?, diagram? }` to a point in diagram coordinates, returning
+    /// its id. It goes on the current diagram unless `diagram` gives another url.
+    #[cfg(feature = "annotations")]
+    #[wasm_bindgen(js_name = addAnnotation)]
+    pub fn add_annotation(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Annotation")] annotation: JsValue,
+    ) -> Result<String, JsValue> {
+        let annotation = from_js::<Annotation>(&annotation)?;
+
+        Ok(self.value.borrow().add_annotation(annotation))
+    }
+
+    #[cfg(feature = "annotations")]
+    #[wasm_bindgen(js_name = removeAnnotation)]
+    pub fn remove_annotation(&self, id: String) -> bool {
+        self.value.borrow().remove_annotation(&id)
+    }
+
+    /// All of the annotations as `{ annotations: [...] }`, which `setAnnotations` accepts
+    #[cfg(feature = "annotations")]
+    #[wasm_bindgen(js_name = getAnnotations, unchecked_return_type = "AnnotationSet")]
+    pub fn annotations(&self) -> Result<JsValue, JsValue> {
+        to_js(&*self.value.borrow().annotations())
+    }
+
+    #[cfg(feature = "annotations")]
+    #[wasm_bindgen(js_name = setAnnotations)]
+    pub fn set_annotations(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "AnnotationSet")] annotations: JsValue,
+    ) -> Result<(), JsValue> {
+        let annotations = from_js::<AnnotationSet>(&annotations)?;
+
+        self.value.borrow().set_annotations(annotations);
+
+        Ok(())
+    }
+
+    /// Adds an entry to the context menu, `callback` is called with the right-clicked element
+    #[wasm_bindgen(js_name = addContextMenuItem)]
+    pub fn add_context_menu_item(
+        &self,
+        label: String,
+        #[wasm_bindgen(unchecked_param_type = "(target: Element) => void")] callback: Function,
+    ) {
+        self.value
+            .borrow()
+            .add_context_menu_item(ContextMenuItem { label, callback })
+    }
+
+    #[wasm_bindgen(js_name = zoomIn)]
+    pub fn zoom_in(&self) {
+        self.value.borrow().zoom_by(ZOOM_STEP)
+    }
+
+    #[wasm_bindgen(js_name = zoomOut)]
+    pub fn zoom_out(&self) {
+        self.value.borrow().zoom_by(1.0 / ZOOM_STEP)
+    }
+
+    /// Zooms by `factor` around the center of the view, greater than 1 zooms in
+    #[wasm_bindgen(js_name = zoomBy)]
+    pub fn zoom_by(&self, factor: f32) {
+        self.value.borrow().zoom_by(factor)
+    }
+
+    /// Returns to the view the diagram was loaded with
+    pub fn reset(&self) {
+        self.value.borrow().reset()
+    }
+
+    #[wasm_bindgen(js_name = fitToContent)]
+    pub fn fit_to_content(&self) {
+        self.value.borrow().fit_to_content()
+    }
+
+    /// Shows or hides the crosshair with a readout of the pointer in diagram coordinates
+    #[wasm_bindgen(js_name = toggleCrosshair)]
+    pub fn toggle_crosshair(&self) {
+        let archizoom = self.value.borrow();
+        archizoom.set_crosshair(!archizoom.is_crosshair_active())
+    }
+
+    /// Mirrors pan and zoom between this viewer and `other`, so two versions of a diagram can
+    /// be compared side by side
+    #[wasm_bindgen(js_name = syncWith)]
+    pub fn sync_with(&self, other: &ArchiZoomContainer) {
+        if !Rc::ptr_eq(&self.value, &other.value) {
+            self.value.borrow().sync_with(&other.value);
+        }
+    }
+
+    /// Stops mirroring pan and zoom with every viewer this one is synced with
+    pub fn unsync(&self) {
+        self.value.borrow().unsync();
+    }
+
+    /// Makes this viewer an overview of `detail`: it stays fitted to the same diagram and shows
+    /// where `detail` is looking as a rectangle that can be dragged to move it
+    #[wasm_bindgen(js_name = showOverviewOf)]
+    pub fn show_overview_of(&self, detail: &ArchiZoomContainer) -> Result<(), JsValue> {
+        if Rc::ptr_eq(&self.value, &detail.value) {
+            return Ok(());
+        }
+
+        self.value.borrow().show_overview_of(&detail.value)
+    }
+
+    /// Stops being the overview of another viewer
+    #[wasm_bindgen(js_name = clearOverview)]
+    pub fn clear_overview(&self) {
+        self.value.borrow().clear_overview();
+    }
+
+    /// Replaces the pins with `{ pins: [...] }`, each over an element id or at a point
+    #[wasm_bindgen(js_name = setPins)]
+    pub fn set_pins(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "PinData")] pins: JsValue,
+    ) -> Result<(), JsValue> {
+        let data = from_js::<PinData>(&pins)?;
+
+        self.value.borrow().set_pins(data.pins);
+
+        Ok(())
+    }
+
+    /// Replaces the pins with the `{ pins: [...] }` JSON at `url`, resolving to how many there are
+    #[wasm_bindgen(js_name = loadPins, unchecked_return_type = "Promise<number>")]
+    pub fn load_pins(&self, url: String) -> Result<Promise, JsValue> {
+        let future = self.value.borrow().load_pins(&url)?;
+
+        Ok(future_to_promise(
+            future.map(|count| JsValue::from(count as u32)),
+        ))
+    }
+
+    /// `handler` is called with the pin and the click event when a pin is clicked, pass null
+    /// to stop
+    #[wasm_bindgen(js_name = setPinClickHandler)]
+    pub fn set_pin_click_handler(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "((pin: Pin, event: MouseEvent) => void) | null")]
+        handler: Option<Function>,
+    ) {
+        self.value.borrow().set_pin_click_handler(handler)
+    }
+
+    /// Fetches an earlier version of the diagram from `url` and color codes what's been added,
+    /// removed, and changed since, matching elements up by id. Resolves to how many changes
+    /// there are.
+    #[wasm_bindgen(js_name = compareWith, unchecked_return_type = "Promise<number>")]
+    pub fn compare_with(&self, url: String) -> Result<Promise, JsValue> {
+        let future = self.value.borrow().compare_with(&url)?;
+
+        Ok(future_to_promise(
+            future.map(|count| JsValue::from(count as u32)),
+        ))
+    }
+
+    /// Frames the next change of the diff, returning its index
+    #[wasm_bindgen(js_name = diffNext)]
+    pub fn diff_next(&self) -> Option<usize> {
+        self.value.borrow().diff_step(true)
+    }
+
+    /// Frames the previous change of the diff, returning its index
+    #[wasm_bindgen(js_name = diffPrevious)]
+    pub fn diff_previous(&self) -> Option<usize> {
+        self.value.borrow().diff_step(false)
+    }
+
+    #[wasm_bindgen(js_name = clearDiff)]
+    pub fn clear_diff(&self) {
+        self.value.borrow().clear_diff()
+    }
+
+    #[wasm_bindgen(js_name = isLocked)]
+    pub fn is_locked(&self) -> bool {
+        self.value.borrow().is_locked()
+    }
+
+    /// Changes one option of this viewer, named by its path like `filter.selector`. Throws if
+    /// there's no such option, the value doesn't fit it, or it can only be set up front.
+    #[wasm_bindgen(js_name = setOption)]
+    pub fn set_option(&self, name: String, value: JsValue) -> Result<(), JsValue> {
+        self.value.borrow().set_option(&name, from_js(&value)?)
+    }
+
+    /// Ignores the user's pan and zoom gestures while `locked` is set
+    #[wasm_bindgen(js_name = setLocked)]
+    pub fn set_locked(&self, locked: bool) {
+        self.value.borrow().set_locked(locked);
+    }
+
+    /// Call after moving the diagram in a way we can't detect, like transforming one of its
+    /// ancestors with script, so positions are measured again
+    #[wasm_bindgen(js_name = invalidateGeometry)]
+    pub fn invalidate_geometry(&self) {
+        self.value.borrow().invalidate_geometry()
+    }
+
+    /// The title, viewBox, width, and height prefetched for the linked diagram at `url`, or null
+    /// if it hasn't been fetched yet
+    #[wasm_bindgen(js_name = getDiagramInfo, unchecked_return_type = "DiagramInfo | null")]
+    pub fn diagram_info(&self, url: String) -> Result<JsValue, JsValue> {
+        match self.value.borrow().diagram_info(&url) {
+            Some(info) => to_js(&info),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    /// How long the last fetch, parse, inject, first view update, navigation, and progressive
+    /// injection took, in milliseconds. Each is also measured as `archizoom:<phase>` in the
+    /// performance timeline.
+    #[wasm_bindgen(js_name = getTimings, unchecked_return_type = "Record<string, number>")]
+    pub fn timings(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.value.borrow().timings().durations())
+    }
+
+    /// Receives the `analytics` records in batches, pass null to stop
+    #[wasm_bindgen(js_name = setAnalyticsHandler)]
+    pub fn set_analytics_handler(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "((records: AnalyticsRecord[]) => void) | null")]
+        handler: Option<Function>,
+    ) {
+        self.value.borrow().set_analytics_handler(handler)
+    }
+
+    /// Forgets where the reader left the diagram that's showing, when `rememberView` is on
+    #[wasm_bindgen(js_name = forgetView)]
+    pub fn forget_view(&self) {
+        self.value.borrow().forget_view()
+    }
+
+    /// Copies a link to this page that opens on the current view, resolving to the link once
+    /// it's on the clipboard
+    #[wasm_bindgen(js_name = copyViewLink, unchecked_return_type = "Promise<string>")]
+    pub fn copy_view_link(&self) -> Result<Promise, JsValue> {
+        let future = self.value.borrow().copy_view_link()?;
+
+        Ok(future_to_promise(future.map(JsValue::from)))
+    }
+
+    /// The part of the diagram in view as a standalone svg document, with anything it uses
+    /// from elsewhere on the page copied in
+    #[wasm_bindgen(js_name = exportSvg)]
+    pub fn export_svg(&self) -> Result<String, JsValue> {
+        self.value.borrow().export_svg()
+    }
+
+    /// `exportSvg` as an `image/svg+xml` Blob, ready for a download link
+    #[wasm_bindgen(js_name = exportSvgBlob)]
+    pub fn export_svg_blob(&self) -> Result<Blob, JsValue> {
+        let markup = self.value.borrow().export_svg()?;
+
+        let options = BlobPropertyBag::new();
+        options.set_type("image/svg+xml");
+        Blob::new_with_str_sequence_and_options(&Array::of1(&markup.into()), &options)
+    }
+
+    /// Captures everything that moves the view until `stopRecording`, for a bug report
+    #[wasm_bindgen(js_name = startRecording)]
+    pub fn start_recording(&self) -> Result<(), JsValue> {
+        self.value.borrow().start_recording()
+    }
+
+    /// The trace of what was recorded, `null` if we weren't recording
+    #[wasm_bindgen(js_name = stopRecording, unchecked_return_type = "Trace | null")]
+    pub fn stop_recording(&self) -> Result<JsValue, JsValue> {
+        match self.value.borrow().stop_recording() {
+            Some(trace) => to_js(&trace),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    /// Plays a recorded trace back into this viewer, resolving to where the recording and the
+    /// replay each ended up
+    #[wasm_bindgen(unchecked_return_type = "Promise<ReplayReport>")]
+    pub fn replay(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Trace")] trace: JsValue,
+    ) -> Result<Promise, JsValue> {
+        let trace = from_js::<Trace>(&trace)?;
+
+        Ok(replay(Rc::downgrade(&self.value), trace))
+    }
+
+    #[wasm_bindgen(js_name = toggleFullscreen)]
+    pub fn toggle_fullscreen(&self) {
+        self.value.borrow().toggle_fullscreen()
+    }
+
+    /// Wheels and drags the diagram for `{ frames }` frames, resolving to the input to viewBox
+    /// latency percentiles and how many frames were dropped, in milliseconds
+    #[cfg(feature = "bench")]
+    #[wasm_bindgen(unchecked_return_type = "Promise<BenchReport>")]
+    pub fn benchmark(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "BenchOptions | undefined")] options: JsValue,
+    ) -> Result<Promise, JsValue> {
+        let options = if options.is_undefined() || options.is_null() {
+            bench::BenchOptions::default()
+        } else {
+            from_js::<bench::BenchOptions>(&options)?
+        };
+
+        Ok(bench::run_benchmark(self.value.borrow().svg(), &options))
+    }
 }
 
 static PREFIX_ALIAS: &str = "archizoom";
 
-#[wasm_bindgen]
-pub fn init() -> Result<Promise, JsValue> {
+/// Logs and returns how many event listeners, timeouts, and viewers are alive, to check that
+/// tearing a viewer down releases everything
+#[cfg(feature = "leak-check")]
+#[wasm_bindgen(js_name = liveObjects, unchecked_return_type = "LiveCounts")]
+pub fn live_objects() -> Result<JsValue, JsValue> {
+    let counts = to_js(&leak_check::live_counts())?;
+    console::log_2(&"ArchiZoom live objects".into(), &counts);
+
+    Ok(counts)
+}
+
+/// Turns the rolling summary of event handling time, layout reads, and dispatches on or off
+/// without reloading the page
+#[wasm_bindgen(js_name = setEventDiagnostics)]
+pub fn set_event_diagnostics(enabled: bool) {
+    diagnostics::set_enabled(enabled)
+}
+
+/// Whether this browser has everything the viewer needs. `init` checks it too and leaves the
+/// page alone when it doesn't, so the images and any links around them keep working.
+#[wasm_bindgen(js_name = isSupported)]
+pub fn is_supported() -> bool {
+    let window: JsValue = window().into();
+    let is_function = |target: &JsValue, name: &str| {
+        Reflect::get(target, &name.into())
+            .map(|value| value.is_function())
+            .unwrap_or(false)
+    };
+
+    let svg = document()
+        .create_element_ns(Some(SVG_NS), "svg")
+        .map(JsValue::from)
+        .unwrap_or(JsValue::UNDEFINED);
+
+    ["fetch", "requestAnimationFrame", "URL"]
+        .iter()
+        .all(|name| is_function(&window, name))
+        && ["getScreenCTM", "createSVGPoint", "getBBox"]
+            .iter()
+            .all(|name| is_function(&svg, name))
+}
+
+/// Changes the options every viewer created from now on starts from, on top of any earlier
+/// calls. The options passed to `init` or `mount` still win.
+#[wasm_bindgen(js_name = setDefaults)]
+pub fn set_defaults(
+    #[wasm_bindgen(unchecked_param_type = "ArchiZoomOptions")] options: JsValue,
+) -> Result<(), JsValue> {
+    Options::set_defaults(&options)
+}
+
+#[wasm_bindgen(unchecked_return_type = "Promise<ArchiZoomContainer[]>")]
+pub fn init(
+    #[wasm_bindgen(unchecked_param_type = "ArchiZoomOptions | undefined")] options: JsValue,
+) -> Result<Promise, JsValue> {
     console_error_panic_hook::set_once();
 
+    let options = Options::from_js(&options)?;
+    if options.event_diagnostics {
+        diagnostics::set_enabled(true);
+    }
+
+    if !is_supported() {
+        console::warn_1(&"ArchiZoom isn't supported in this browser, leaving the images".into());
+
+        return Ok(Promise::all(&Array::new()));
+    }
+
     // grab all the images with our marking attribute
     let zoom_nodes = document()
         .query_selector_all(&format!("[data-{}]", PREFIX_ALIAS))?
         .safe_filter::<HtmlImageElement>();
 
+    // a link to the page can ask the first viewer to open somewhere
+    let mut start = ViewLink::take_from_page();
+
     let result_futures = Array::new();
-    for node in zoom_nodes.into_iter() {
-        match new_archizoom(node) {
+    for img in zoom_nodes.into_iter() {
+        match new_archizoom(img, options.clone(), start.take()) {
             Ok(p) => {
                 result_futures.push(&p);
             }
@@ -47,62 +631,204 @@ pub fn init() -> Result<Promise, JsValue> {
     Ok(Promise::all(&result_futures))
 }
 
-fn new_archizoom(img: HtmlImageElement) -> Result<Promise, JsValue> {
+/// One side of the image we're replacing
+#[derive(Debug, Clone, PartialEq)]
+enum Dimension {
+    Pixels(i32),
+    /// Sized relative to the page, which the viewer keeps doing so it resizes with it
+    Percent(String),
+    /// It hasn't loaded or been laid out, so we go by the diagram's own size
+    Unknown,
+}
+
+impl Dimension {
+    fn measure(img: &HtmlImageElement, property: &str, offset: i32) -> Dimension {
+        let declared = img
+            .style()
+            .get_property_value(property)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .or_else(|| img.get_attribute(property))
+            .map(|value| value.trim().to_string());
+
+        match declared {
+            Some(value) if value.ends_with('%') => Dimension::Percent(value),
+            _ if offset > 0 => Dimension::Pixels(offset),
+            _ => Dimension::Unknown,
+        }
+    }
+
+    fn css(&self, intrinsic: Option<f32>) -> String {
+        match self {
+            Dimension::Pixels(pixels) => format!("{}px", pixels),
+            Dimension::Percent(percent) => percent.clone(),
+            Dimension::Unknown => intrinsic
+                .map(|pixels| format!("{}px", pixels))
+                .unwrap_or_else(|| "100%".to_string()),
+        }
+    }
+}
+
+/// The viewer's size, the image's where we know it and the diagram's own where we don't, or
+/// its parent's when the diagram doesn't have one either
+fn container_size(width: &Dimension, height: &Dimension, svg: &SvgsvgElement) -> String {
+    let intrinsic = intrinsic_size(svg);
+
+    match (width, height, intrinsic) {
+        // a width without a height is a responsive image, so keep the diagram's shape
+        (width, Dimension::Unknown, Some(size)) if *width != Dimension::Unknown => format!(
+            "width: {}; aspect-ratio: {} / {};",
+            width.css(None),
+            size.width,
+            size.height
+        ),
+        (width, height, intrinsic) => format!(
+            "width: {}; height: {};",
+            width.css(intrinsic.as_ref().map(|size| size.width)),
+            height.css(intrinsic.as_ref().map(|size| size.height))
+        ),
+    }
+}
+
+/// The size the svg asks for, from its width and height or else its viewBox
+fn intrinsic_size(svg: &SvgsvgElement) -> Option<Size2D> {
+    let pixels = |name: &str| {
+        svg.get_attribute(name)
+            .and_then(|value| value.trim().trim_end_matches("px").parse::<f32>().ok())
+            .filter(|value| *value > 0.0)
+    };
+
+    match (pixels("width"), pixels("height")) {
+        (Some(width), Some(height)) => Some(Size2D::new(width, height)),
+        _ => svg
+            .view_box()
+            .base_val()
+            .map(|view_box| Size2D::new(view_box.width(), view_box.height()))
+            .filter(|size| size.width > 0.0 && size.height > 0.0),
+    }
+}
+
+fn new_archizoom(
+    img: HtmlImageElement,
+    options: Options,
+    start: Option<ViewLink>,
+) -> Result<Promise, JsValue> {
+    // it doesn't have a size until it's loaded, broken or not. Lazy images might never load.
+    if img.complete() || img.get_attribute("loading").as_deref() == Some("lazy") {
+        return Ok(future_to_promise(show_viewer(img, options, start)?));
+    }
+
+    let future = JsFuture::from(img.decode())
+        .then(move |_| show_viewer(img, options, start))
+        .flatten();
+
+    Ok(future_to_promise(future))
+}
+
+fn show_viewer(
+    img: HtmlImageElement,
+    mut options: Options,
+    start: Option<ViewLink>,
+) -> Result<impl Future<Item = JsValue, Error = JsValue>, JsValue> {
+    if start.is_some() {
+        // the link is still in the page url after a reload, so it wins over the saved path
+        options.restore_navigation.enabled = false;
+    }
+
     let src = img.src();
     let parent = img
         .parent_element()
         .ok_or::<JsValue>("The image element must have a parent".into())?;
 
-    let mut opts = RequestInit::new();
-    opts.method("GET");
+    // a link around the image is the page's fallback for when we don't run, the viewer has its
+    // own way into the diagram so it replaces the link too
+    let (replaced, parent): (Element, Element) =
+        if parent.tag_name().eq_ignore_ascii_case("a") && parent.child_element_count() == 1 {
+            let grandparent = parent
+                .parent_element()
+                .ok_or::<JsValue>("The image's link must have a parent".into())?;
 
-    let request = Request::new_with_str_and_init(&src, &opts)?;
+            (parent, grandparent)
+        } else {
+            (img.clone().into(), parent)
+        };
 
-    let request_promise = window().fetch_with_request(&request);
+    // measured once the diagram's ready, right before the one write that swaps the viewer in
+    let size = move |svg: &SvgsvgElement| {
+        let width = Dimension::measure(&img, "width", img.offset_width());
+        let height = Dimension::measure(&img, "height", img.offset_height());
 
-    let future = JsFuture::from(request_promise)
-        .and_then(|resp_value| {
-            // grab the text from our response
-            resp_value
-                .dyn_into::<Response>()
-                .and_then(|response| response.text())
-        })
-        .and_then(|text: Promise| {
-            // Convert the response promise into a future
-            JsFuture::from(text)
-        })
-        .and_then(move |text_value| {
-            let text = text_value.as_string();
+        container_size(&width, &height, svg)
+    };
+    let future = load_viewer(src, options, size, move |container| {
+        parent.replace_child(container, &replaced).map(|_| ())
+    })?
+    .map(move |az| {
+        if let Some(start) = start {
+            az.borrow().open_link(start);
+        }
+
+        JsValue::from(ArchiZoomContainer { value: az })
+    });
+
+    Ok(future)
+}
+
+/// Fetches the diagram at `src` and builds the whole viewer for it off the page, then hands
+/// its container to `place` to put it on the page with a single write. `size` is the CSS
+/// sizing the container, given the parsed diagram.
+fn load_viewer<S, P>(
+    src: String,
+    options: Options,
+    size: S,
+    place: P,
+) -> Result<impl Future<Item = Rc<RefCell<ArchiZoom>>, Error = JsValue>, JsValue>
+where
+    S: FnOnce(&SvgsvgElement) -> String + 'static,
+    P: FnOnce(&HtmlDivElement) -> Result<(), JsValue> + 'static,
+{
+    let fetch = Span::start("fetch");
+    Ok(
+        load_diagram(&src, options.load_in_worker)?.and_then(move |loaded| {
+            let fetch_ms = fetch.end();
+            let LoadedDiagram { text, description } = loaded;
 
-            // create a new container
             let container = document()
                 .safe_create_element::<HtmlDivElement>("div")
-                .unwrap();
+                .ok_or_else(|| JsValue::from("Couldn't create the container"))?;
 
+            container.set_class_name("archizoom-container");
+            // built in a fragment, off the page, until `place` moves it on in one write
+            let fragment = document().create_document_fragment();
+            fragment.append_child(&container)?;
+
+            let parse = Span::start("parse");
+            let svg = parse_svg(&text, &options)?;
             container
                 .style()
-                .set_property(&"height", &format!("{:?}px", img.offset_height()))?;
-            container
-                .style()
-                .set_property(&"width", &format!("{:?}px", img.offset_width()))?;
-            container.set_inner_html(&text.unwrap());
-
-            // find the embedded SvgsvgElement
-            let svg = container
-                .first_element_child()
-                .ok_or::<JsValue>("The image element must have a parent".into())
-                .and_then(|child| child.dyn_into::<SvgsvgElement>().map_err(|e| e.into()))?;
-
-            svg.style().set_property(&"height", &"100%")?;
-            svg.style().set_property(&"width", &"100%")?;
-
-            ArchiZoom::new(svg).and_then(|az| {
-                parent
-                    .replace_child(&container, &img)
-                    .map(|_| JsValue::from(ArchiZoomContainer { _value: az }))
-            })
-        });
+                .set_css_text(&format!("position: relative; {}", size(&svg)));
+            container.append_child(&svg)?;
+            let parse_ms = parse.end();
 
-    // Convert this Rust `Future` back into a JS `Promise`.
-    Ok(future_to_promise(future))
+            let inject = Span::start("inject");
+            ArchiZoom::new(container.clone().into(), svg, &src, options).and_then(|az| {
+                if let Some(ref description) = description {
+                    az.borrow().described(&src, description);
+                }
+                az.borrow().cache_diagram(&src, text);
+                place(&container)?;
+                az.borrow().fit_sprite_group();
+
+                {
+                    let archizoom = az.borrow();
+                    let timings = archizoom.timings();
+                    timings.record("fetch", fetch_ms);
+                    timings.record("parse", parse_ms);
+                    timings.end(inject);
+                }
+
+                Ok(az)
+            })
+        }),
+    )
 }
@@ -0,0 +1,170 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use futures::Future;
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use web_sys::{Element, HtmlDivElement, SvgsvgElement};
+
+use crate::options::Options;
+use crate::zoom::view_link::ViewLink;
+use crate::zoom::ArchiZoom;
+use crate::{load_viewer, ArchiZoomContainer};
+
+/// A viewer put into an element by `mount`. Unlike `init` it doesn't look through the page, and
+/// `unmount` takes it down right away, even while it's still loading.
+#[wasm_bindgen]
+pub struct ArchiZoomMount {
+    state: Rc<MountState>,
+    ready: Promise,
+}
+
+struct MountState {
+    host: Element,
+    unmounted: Cell<bool>,
+    container: RefCell<Option<HtmlDivElement>>,
+    archizoom: RefCell<Option<Rc<RefCell<ArchiZoom>>>>,
+}
+
+thread_local! {
+    /// Every live mount, so mounting into the same element again replaces the old viewer
+    static MOUNTS: RefCell<Vec<Weak<MountState>>> = const { RefCell::new(vec![]) };
+}
+
+#[wasm_bindgen]
+impl ArchiZoomMount {
+    /// Resolves to the viewer once its diagram is showing, or rejects if it's unmounted first
+    #[wasm_bindgen(js_name = whenReady, unchecked_return_type = "Promise<ArchiZoomContainer>")]
+    pub fn when_ready(&self) -> Promise {
+        self.ready.clone()
+    }
+}
+
+impl ArchiZoomMount {
+    /// The viewer, once it's showing and until it's unmounted
+    pub(crate) fn archizoom(&self) -> Option<Rc<RefCell<ArchiZoom>>> {
+        self.state.archizoom.borrow().clone()
+    }
+
+    pub(crate) fn unmount(&self) {
+        self.state.unmount();
+    }
+}
+
+/// Shows the diagram at `options.src` inside `element`, filling it. Mounting into an element
+/// that already has a viewer replaces it.
+#[wasm_bindgen]
+pub fn mount(
+    element: Element,
+    #[wasm_bindgen(unchecked_param_type = "ArchiZoomOptions")] options: JsValue,
+) -> Result<ArchiZoomMount, JsValue> {
+    console_error_panic_hook::set_once();
+
+    mount_with(element, Options::from_js(&options)?, |_| ())
+}
+
+/// `mount`, calling `on_ready` once the viewer is showing
+pub(crate) fn mount_with<F>(
+    element: Element,
+    mut options: Options,
+    on_ready: F,
+) -> Result<ArchiZoomMount, JsValue>
+where
+    F: FnOnce(&ArchiZoom) + 'static,
+{
+    let src = options
+        .src
+        .clone()
+        .ok_or_else(|| JsValue::from("mount needs a src to show"))?;
+
+    // frameworks can mount twice without unmounting in between, the newest mount wins
+    for previous in live_mounts().iter().filter(|state| state.host == element) {
+        previous.unmount();
+    }
+
+    // a link to the page can ask the first viewer to open somewhere
+    let start = ViewLink::take_from_page();
+    if start.is_some() {
+        // the link is still in the page url after a reload, so it wins over the saved path
+        options.restore_navigation.enabled = false;
+    }
+
+    let state = Rc::new(MountState {
+        host: element,
+        unmounted: Cell::new(false),
+        container: RefCell::new(None),
+        archizoom: RefCell::new(None),
+    });
+    MOUNTS.with(|mounts| mounts.borrow_mut().push(Rc::downgrade(&state)));
+
+    let place_ref = Rc::downgrade(&state);
+    let ready_ref = Rc::downgrade(&state);
+    let future = load_viewer(
+        src,
+        options,
+        |_: &SvgsvgElement| "width: 100%; height: 100%;".to_string(),
+        move |container| match place_ref.upgrade() {
+            Some(state) if !state.unmounted.get() => {
+                state.host.append_child(container)?;
+                state.container.replace(Some(container.clone()));
+
+                Ok(())
+            }
+            _ => Err(unmounted_error()),
+        },
+    )?
+    .and_then(move |az| match ready_ref.upgrade() {
+        // it could have been unmounted by something the viewer did while it was being set up
+        Some(ref state) if !state.unmounted.get() => {
+            state.archizoom.replace(Some(az.clone()));
+            if let Some(start) = start {
+                az.borrow().open_link(start);
+            }
+            on_ready(&az.borrow());
+
+            Ok(JsValue::from(ArchiZoomContainer { value: az }))
+        }
+        _ => Err(unmounted_error()),
+    });
+
+    Ok(ArchiZoomMount {
+        state,
+        ready: future_to_promise(future),
+    })
+}
+
+/// Takes the viewer off the page and releases it. Calling it again does nothing.
+#[wasm_bindgen]
+pub fn unmount(mount: &ArchiZoomMount) {
+    mount.unmount();
+}
+
+impl MountState {
+    fn unmount(&self) {
+        if self.unmounted.replace(true) {
+            return;
+        }
+
+        if let Some(container) = self.container.replace(None) {
+            container.remove();
+        }
+        // released here unless a callback that's still running holds on to it, then it's
+        // released when that returns
+        self.archizoom.replace(None);
+
+        MOUNTS.with(|mounts| {
+            mounts
+                .borrow_mut()
+                .retain(|state| state.upgrade().is_some_and(|state| !state.unmounted.get()))
+        });
+    }
+}
+
+fn live_mounts() -> Vec<Rc<MountState>> {
+    MOUNTS.with(|mounts| mounts.borrow().iter().filter_map(Weak::upgrade).collect())
+}
+
+fn unmounted_error() -> JsValue {
+    JsValue::from("The viewer was unmounted before it loaded")
+}
@@ -0,0 +1,386 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use wasm_bindgen::JsValue;
+
+use crate::js_utils::from_js;
+use crate::overlays::activation_veil::ClickToActivateOptions;
+use crate::overlays::announcer::AnnouncerOptions;
+use crate::overlays::breadcrumbs::BreadcrumbsOptions;
+#[cfg(feature = "canvas")]
+use crate::overlays::canvas_renderer::CanvasOptions;
+use crate::overlays::context_menu::ContextMenuOptions;
+use crate::overlays::controls::ControlsOptions;
+use crate::overlays::crosshair::CrosshairOptions;
+use crate::overlays::diff_legend::DiffLegendOptions;
+use crate::overlays::focus_ring::FocusRingOptions;
+use crate::overlays::grid::GridOptions;
+use crate::overlays::hover_highlight::HoverHighlightOptions;
+use crate::overlays::layer_list::LayerListOptions;
+#[cfg(feature = "minimap")]
+use crate::overlays::minimap::MinimapOptions;
+#[cfg(feature = "search")]
+use crate::overlays::search_box::SearchBoxOptions;
+use crate::overlays::title_bar::TitleBarOptions;
+use crate::overlays::tooltips::TooltipsOptions;
+use crate::overlays::zoom_indicator::ZoomIndicatorOptions;
+use crate::overlays::zoom_slider::ZoomSliderOptions;
+use crate::strings::Strings;
+use crate::styles::RenderingHintsOptions;
+use crate::zoom::analytics::AnalyticsOptions;
+use crate::zoom::animation::{MotionPreference, Transition};
+use crate::zoom::broadcast::BroadcastOptions;
+use crate::zoom::culling::CullingOptions;
+use crate::zoom::diagram_cache::DiagramCacheOptions;
+use crate::zoom::diff::DiffOptions;
+use crate::zoom::embed::EmbedOptions;
+use crate::zoom::filter::FilterOptions;
+use crate::zoom::gamepad::GamepadOptions;
+use crate::zoom::hover_prefetch::HoverPrefetchOptions;
+use crate::zoom::isolate::IsolateOptions;
+use crate::zoom::keyboard::KeyAction;
+use crate::zoom::layers::LayersOptions;
+#[cfg(feature = "search")]
+use crate::zoom::model_search::ModelSearchOptions;
+use crate::zoom::momentum::MomentumOptions;
+use crate::zoom::navigation::RestoreNavigationOptions;
+use crate::zoom::pins::PinsOptions;
+use crate::zoom::prefetch::PrefetchOptions;
+use crate::zoom::print::PrintOptions;
+use crate::zoom::progressive::ProgressiveOptions;
+use crate::zoom::quality::QualityOptions;
+use crate::zoom::remembered_view::RememberViewOptions;
+use crate::zoom::selection::SelectionOptions;
+use crate::zoom::semantic_zoom::{OverviewOptions, ZoomBreakpoint};
+use crate::zoom::svg_view_controller::{GestureRendering, PreventDefaultPolicy, ZoomOptions};
+use crate::zoom::text_culling::TextCullingOptions;
+
+/// The options passed to `init`, any that are missing fall back to their defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Options {
+    /// The diagram `mount` shows, `init` uses the src of each image instead
+    pub src: Option<String>,
+    /// Line the viewBox up with whole device pixels when a gesture ends so thin lines stay sharp
+    pub pixel_snapping: bool,
+    /// Strip comments, editor metadata, and unused defs from diagrams before showing them
+    pub minify: bool,
+    /// Fetch diagrams in a worker, which also reads their titles, sizes, and zoom links out of the
+    /// markup. Building their DOM still happens on the page's thread, DOMParser doesn't exist in
+    /// workers.
+    pub load_in_worker: bool,
+    /// Show huge diagrams right away and fill in their groups over the next few frames
+    pub progressive_injection: ProgressiveOptions,
+    /// How much memory diagrams we've navigated away from can hold on to
+    pub diagram_cache: DiagramCacheOptions,
+    /// Fetch the titles of linked diagrams in the background
+    pub prefetch: PrefetchOptions,
+    /// Fetch the diagram a zoom element opens while the pointer rests on it
+    pub hover_prefetch: HoverPrefetchOptions,
+    /// Whether viewport changes are animated, by default only when the user hasn't asked for
+    /// reduced motion
+    pub motion: MotionPreference,
+    /// How the view animates when we move it for the reader, like framing a search match. Moves
+    /// to somewhere out of sight fly there instead.
+    pub transition: Transition,
+    /// Keep the view gliding after a drag is let go, slowing down with a friction spring
+    pub momentum: MomentumOptions,
+    /// Print the current view in place of the viewer, without its overlays. Off unless asked
+    /// for, since it takes over printing the whole page.
+    pub print: PrintOptions,
+    /// Share the view with other tabs on a BroadcastChannel and follow theirs, so a presenter
+    /// can drive an audience's tab
+    pub broadcast: BroadcastOptions,
+    /// Take setView, navigate, and getState messages from the page we're framed in, and post
+    /// it our state as it changes
+    pub embed: EmbedOptions,
+    /// Record what readers open and look at, for `setAnalyticsHandler` or an endpoint
+    pub analytics: AnalyticsOptions,
+    /// Markers from outside the diagram, like the live status of what it describes
+    pub pins: PinsOptions,
+    /// Remember where the reader left each diagram and open on that view next time
+    pub remember_view: RememberViewOptions,
+    /// Keep the drill-down path in sessionStorage so a reload comes back to the same diagram
+    pub restore_navigation: RestoreNavigationOptions,
+    /// Ignore the user's pan and zoom gestures, the api can still move the view
+    pub locked: bool,
+    /// When the wheel zooms the diagram instead of scrolling the page
+    pub prevent_default: PreventDefaultPolicy,
+    /// How fast the wheel zooms and how far in zoom elements open
+    pub zoom: ZoomOptions,
+    /// Whether gestures redraw the diagram through the viewBox or move it with a CSS transform
+    pub gesture_rendering: GestureRendering,
+    /// CSS containment on the container so interacting with the viewer doesn't repaint the page
+    pub rendering_hints: RenderingHintsOptions,
+    /// Give up culling, overlay updates, and then text while gestures can't keep up with the
+    /// frame rate, until they stop
+    pub quality: QualityOptions,
+    /// Listen for pointer and wheel events once on the document instead of on every diagram,
+    /// for pages with lots of them
+    pub delegate_events: bool,
+    /// Log a summary of how long event handling takes, how often it reads layout, and how many
+    /// updates it sends every couple of seconds, to help find out why a diagram is slow
+    pub event_diagnostics: bool,
+    /// Diagram names by url or file name, overriding the svg's `<title>`
+    pub titles: HashMap<String, String>,
+    /// Pan, zoom, and navigate with a gamepad while the viewer has focus
+    pub gamepad: GamepadOptions,
+    /// Keys for each keyboard action, like `Ctrl+Shift+ArrowUp`, in place of the defaults
+    pub key_bindings: HashMap<KeyAction, Vec<String>>,
+    /// Classes toggled on the svg root at zoom levels, on top of any the diagram declares
+    pub zoom_breakpoints: Vec<ZoomBreakpoint>,
+    /// The simplified rendering shown when zoomed far out
+    pub overview: OverviewOptions,
+    /// Labels, tooltips, and announcements, override these to translate the viewer
+    pub strings: Strings,
+    /// Draw heavy diagrams to a canvas that's rendered again when the view settles
+    #[cfg(feature = "canvas")]
+    pub canvas: CanvasOptions,
+    /// Hide groups that are out of view so they aren't painted while panning
+    pub culling: CullingOptions,
+    /// Hide text too small to read
+    pub text_culling: TextCullingOptions,
+    pub selection: SelectionOptions,
+    pub filter: FilterOptions,
+    /// Which shapes `isolate` follows as connectors
+    pub isolate: IsolateOptions,
+    pub layers: LayersOptions,
+    /// Which elements `compareWith` matches up between versions
+    pub diff: DiffOptions,
+    pub title_bar: TitleBarOptions,
+    #[cfg(feature = "search")]
+    pub search_box: SearchBoxOptions,
+    /// How far `searchModel` crawls
+    #[cfg(feature = "search")]
+    pub model_search: ModelSearchOptions,
+    /// The counts of a diff and the buttons to step through it
+    pub diff_legend: DiffLegendOptions,
+    pub controls: ControlsOptions,
+    pub breadcrumbs: BreadcrumbsOptions,
+    pub announcer: AnnouncerOptions,
+    pub context_menu: ContextMenuOptions,
+    pub grid: GridOptions,
+    pub crosshair: CrosshairOptions,
+    pub focus_ring: FocusRingOptions,
+    pub hover_highlight: HoverHighlightOptions,
+    pub tooltips: TooltipsOptions,
+    pub layer_list: LayerListOptions,
+    pub zoom_indicator: ZoomIndicatorOptions,
+    pub zoom_slider: ZoomSliderOptions,
+    #[cfg(feature = "minimap")]
+    pub minimap: MinimapOptions,
+    /// For diagrams embedded in long pages, so scrolling past them doesn't zoom them
+    pub click_to_activate: ClickToActivateOptions,
+}
+
+/// The options a live viewer reads as it uses them, so `setOption` can change them, by their
+/// path or the path of the object holding them. The rest are only read while a viewer or one of
+/// its parts is set up.
+pub static LIVE_OPTIONS: [&str; 14] = [
+    "pixelSnapping",
+    "preventDefault",
+    "zoom",
+    "gestureRendering",
+    "locked",
+    "motion",
+    "transition",
+    "momentum",
+    "loadInWorker",
+    "restoreNavigation",
+    "filter",
+    "isolate",
+    "diff",
+    "pins.src",
+];
+
+thread_local! {
+    /// What `setDefaults` changed, the options of every new viewer are merged over these
+    static DEFAULTS: RefCell<Value> = RefCell::new(Value::Object(Map::new()));
+}
+
+impl Options {
+    pub fn from_js(value: &JsValue) -> Result<Options, JsValue> {
+        let mut merged = DEFAULTS.with(|defaults| defaults.borrow().clone());
+        if !value.is_undefined() && !value.is_null() {
+            merge(&mut merged, from_js(value)?);
+        }
+
+        parse(merged)
+    }
+
+    /// Changes the defaults of the viewers created from now on, on top of any earlier changes
+    pub fn set_defaults(value: &JsValue) -> Result<(), JsValue> {
+        let mut merged = DEFAULTS.with(|defaults| defaults.borrow().clone());
+        merge(&mut merged, from_js(value)?);
+
+        // don't keep anything that would break every viewer after it
+        parse(merged.clone())?;
+        DEFAULTS.with(|defaults| defaults.replace(merged));
+
+        Ok(())
+    }
+
+    /// Whether the option at `path` takes effect on a viewer that's already running
+    pub fn is_live(path: &str) -> bool {
+        LIVE_OPTIONS.iter().any(|live| {
+            path == *live
+                || path
+                    .strip_prefix(live)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// These options with the one at `path`, like `hoverPrefetch.delayMs`, set to `value`
+    pub fn with_option(&self, path: &str, value: Value) -> Result<Options, JsValue> {
+        let mut json =
+            serde_json::to_value(self).map_err(|error| JsValue::from(error.to_string()))?;
+
+        let mut option = &mut json;
+        for key in path.split('.') {
+            option = option
+                .get_mut(key)
+                .ok_or_else(|| JsValue::from(format!("There's no option called {}", path)))?;
+        }
+        *option = value;
+
+        serde_json::from_value(json)
+            .map_err(|error| format!("Can't set {}, {}", path, error))
+            .and_then(Options::validate)
+            .map_err(JsValue::from)
+    }
+
+    /// Catches values that fit an option's type but not what it means
+    fn validate(self) -> Result<Options, String> {
+        self.zoom.validate()?;
+
+        Ok(self)
+    }
+}
+
+fn parse(value: Value) -> Result<Options, JsValue> {
+    serde_json::from_value(value)
+        .map_err(|error| error.to_string())
+        .and_then(Options::validate)
+        .map_err(JsValue::from)
+}
+
+/// Merges `changes` into `base` key by key, anything that isn't an object replaces what's there
+fn merge(base: &mut Value, changes: Value) {
+    match (base, changes) {
+        (Value::Object(base), Value::Object(changes)) => {
+            for (key, value) in changes {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, changes) => *base = changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::zoom::spring::Spring;
+
+    #[test]
+    fn merging_replaces_values_and_keeps_the_rest_of_objects() {
+        let mut base = json!({
+            "locked": false,
+            "hoverPrefetch": { "enabled": true, "delayMs": 100 },
+        });
+        merge(
+            &mut base,
+            json!({ "locked": true, "hoverPrefetch": { "delayMs": 50 }, "minify": true }),
+        );
+
+        assert_eq!(
+            base,
+            json!({
+                "locked": true,
+                "hoverPrefetch": { "enabled": true, "delayMs": 50 },
+                "minify": true,
+            })
+        );
+
+        let options = parse(base).unwrap();
+        assert!(options.locked);
+        assert!(options.hover_prefetch.enabled);
+        assert_eq!(options.hover_prefetch.delay_ms, 50);
+    }
+
+    #[test]
+    fn with_option_changes_only_the_option_at_the_path() {
+        let options = Options::default()
+            .with_option("hoverPrefetch.delayMs", json!(25))
+            .unwrap()
+            .with_option("locked", json!(true))
+            .unwrap();
+
+        assert_eq!(options.hover_prefetch.delay_ms, 25);
+        assert!(options.locked);
+        assert_eq!(
+            options.hover_prefetch.enabled,
+            Options::default().hover_prefetch.enabled
+        );
+    }
+
+    #[test]
+    fn transitions_can_be_springs() {
+        let options = parse(json!({
+            "transition": { "type": "spring", "stiffness": 200 },
+            "momentum": { "enabled": true },
+        }))
+        .unwrap();
+
+        match options.transition {
+            Transition::Spring(ref spring) => {
+                assert_eq!(spring.stiffness, 200.0);
+                assert_eq!(spring.damping, Spring::default().damping);
+            }
+            ref transition => panic!("expected a spring, got {:?}", transition),
+        }
+        assert!(options.momentum.enabled);
+
+        let options = options
+            .with_option("transition", json!({ "type": "eased", "durationMs": 500 }))
+            .unwrap();
+        assert!(matches!(
+            options.transition,
+            Transition::Eased { duration_ms, .. } if duration_ms == 500.0
+        ));
+    }
+
+    #[test]
+    fn options_that_dont_make_sense_are_rejected() {
+        let invalid = |value| serde_json::from_value::<Options>(value).unwrap().validate();
+
+        assert!(invalid(json!({ "zoom": { "wheelFactor": 0 } })).is_err());
+        assert!(invalid(json!({ "zoom": { "openThreshold": 1.5 } })).is_err());
+        assert!(invalid(json!({ "zoom": { "openThreshold": 1 } })).is_ok());
+
+        let options = Options::default()
+            .with_option("zoom.openThreshold", json!(0.6))
+            .unwrap();
+        assert_eq!(options.zoom.open_threshold, 0.6);
+        assert_eq!(
+            options.zoom.wheel_factor,
+            ZoomOptions::default().wheel_factor
+        );
+    }
+
+    #[test]
+    fn live_options_match_whole_path_segments() {
+        assert!(Options::is_live("locked"));
+        assert!(Options::is_live("filter.selector"));
+        assert!(Options::is_live("pins.src"));
+        assert!(Options::is_live("zoom.wheelFactor"));
+        assert!(Options::is_live("gestureRendering"));
+        assert!(!Options::is_live("zoomBreakpoints"));
+        assert!(!Options::is_live("pins"));
+        assert!(!Options::is_live("filterSelector"));
+        assert!(!Options::is_live("hoverPrefetch.delayMs"));
+    }
+}
@@ -0,0 +1,155 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, Event, HtmlElement, KeyboardEvent, MouseEvent, Node};
+
+use crate::js_utils::*;
+use crate::overlays::Overlay;
+use crate::strings::Strings;
+use crate::zoom::keyboard::is_editing;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClickToActivateOptions {
+    /// Leave the wheel and touch to the page until the viewer is clicked or focused
+    pub enabled: bool,
+}
+
+static INACTIVE_CLASS: &str = "archizoom-inactive";
+
+/// Covers the viewer until it's clicked or focused, so scrolling past a diagram embedded in a
+/// long page scrolls the page instead of zooming. Clicking outside or Escape covers it again.
+pub struct ActivationVeil {
+    state: Rc<VeilState>,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+struct VeilState {
+    veil: HtmlElement,
+    container: Element,
+}
+
+impl ActivationVeil {
+    pub fn new(container: &Element, strings: &Strings) -> Result<ActivationVeil, JsValue> {
+        let veil = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the activation veil"))?;
+        veil.set_class_name("archizoom-veil");
+
+        let message = document()
+            .safe_create_element::<HtmlElement>("span")
+            .ok_or_else(|| JsValue::from("Couldn't create the activation veil"))?;
+        message.set_class_name("archizoom-veil-message");
+        message.set_text_content(Some(&strings.click_to_interact));
+        veil.append_child(&message)?;
+
+        let style = veil.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("top", "0")?;
+        style.set_property("left", "0")?;
+        style.set_property("width", "100%")?;
+        style.set_property("height", "100%")?;
+        style.set_property("display", "flex")?;
+        style.set_property("align-items", "center")?;
+        style.set_property("justify-content", "center")?;
+        style.set_property("cursor", "pointer")?;
+
+        container.append_child(&veil)?;
+
+        let state = Rc::new(VeilState {
+            veil,
+            container: container.clone(),
+        });
+        state.set_active(false);
+
+        let mut listeners = vec![];
+
+        // the click only wakes the viewer up, it shouldn't also drill into whatever is under it
+        let clicked = state.clone();
+        listeners.push(
+            state
+                .veil
+                .new_event_listener("click", move |event: MouseEvent| {
+                    event.stop_propagation();
+                    clicked.set_active(true);
+                    if let Some(container) = clicked.container.dyn_ref::<HtmlElement>() {
+                        if let Err(error) = container.focus() {
+                            console::warn_2(&"Couldn't focus the viewer".into(), &error);
+                        }
+                    }
+                })?,
+        );
+
+        // tabbing in counts as wanting to use it
+        let focused = state.clone();
+        listeners.push(container.new_event_listener("focusin", move |_: Event| {
+            focused.set_active(true);
+        })?);
+
+        let outside = state.clone();
+        listeners.push(
+            document().new_event_listener("pointerdown", move |event: Event| {
+                let inside = event
+                    .target()
+                    .and_then(|target| target.dyn_into::<Node>().ok())
+                    .map(|target| outside.container.contains(Some(&target)))
+                    .unwrap_or(false);
+                if !inside {
+                    outside.set_active(false);
+                }
+            })?,
+        );
+
+        let escaped = state.clone();
+        listeners.push(
+            container.new_event_listener("keydown", move |event: KeyboardEvent| {
+                if event.key() == "Escape" && !is_editing(&event) {
+                    escaped.set_active(false);
+                    // otherwise the arrow keys would still pan us rather than scroll the page
+                    if let Some(container) = escaped.container.dyn_ref::<HtmlElement>() {
+                        if let Err(error) = container.blur() {
+                            console::warn_2(&"Couldn't blur the viewer".into(), &error);
+                        }
+                    }
+                }
+            })?,
+        );
+
+        Ok(ActivationVeil {
+            state,
+            _listeners: listeners,
+        })
+    }
+}
+
+impl VeilState {
+    fn set_active(&self, active: bool) {
+        let result = self
+            .veil
+            .style()
+            .set_property("display", if active { "none" } else { "flex" })
+            .and_then(|_| {
+                if active {
+                    self.container.class_list().remove_1(INACTIVE_CLASS)
+                } else {
+                    self.container.class_list().add_1(INACTIVE_CLASS)
+                }
+            });
+
+        if let Err(error) = result {
+            console::warn_2(&"Couldn't update the activation veil".into(), &error);
+        }
+    }
+}
+
+impl Overlay for ActivationVeil {}
+
+impl Drop for ActivationVeil {
+    fn drop(&mut self) {
+        self.state.veil.remove();
+        if let Err(error) = self.state.container.class_list().remove_1(INACTIVE_CLASS) {
+            console::warn_2(&"Couldn't update the activation veil".into(), &error);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, ViewerEvent};
+use crate::zoom::annotations::AnnotationSet;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::{Transform, UserPoint};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+/// Draws the annotations of the current diagram as pins that follow the diagram around but stay
+/// the same size on screen
+pub struct Annotations {
+    layer: HtmlElement,
+    container: Element,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+}
+
+impl Annotations {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+    ) -> Result<Annotations, JsValue> {
+        let layer = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the annotation layer"))?;
+        layer.set_class_name("archizoom-annotations");
+
+        let style = layer.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("top", "0")?;
+        style.set_property("left", "0")?;
+        style.set_property("width", "100%")?;
+        style.set_property("height", "100%")?;
+        style.set_property("overflow", "hidden")?;
+        style.set_property("pointer-events", "none")?;
+
+        container.append_child(&layer)?;
+
+        Ok(Annotations {
+            layer,
+            container: container.clone(),
+            archizoom: Rc::downgrade(archizoom),
+        })
+    }
+
+    fn redraw(&self) {
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            let archizoom = archizoom.borrow();
+            let annotations = archizoom.annotations();
+
+            if let Err(error) = self.render(&archizoom, &annotations) {
+                console::warn_2(&"Couldn't draw the annotations".into(), &error);
+            }
+        }
+    }
+
+    fn render(&self, archizoom: &ArchiZoom, annotations: &AnnotationSet) -> Result<(), JsValue> {
+        self.layer.set_inner_html("");
+
+        let to_client = match (
+            archizoom.screen_ctm(),
+            Transform::client_to_screen(&self.container).inverse(),
+        ) {
+            (Some(to_screen), Some(to_client)) => to_screen.then(&to_client),
+            _ => return Ok(()),
+        };
+
+        let url = match archizoom.navigation().last() {
+            Some(current) => current.url.as_str(),
+            None => return Ok(()),
+        };
+
+        for annotation in annotations.for_diagram(url) {
+            let position = to_client.point(&UserPoint::point(annotation.x, annotation.y));
+
+            let pin = document()
+                .safe_create_element::<HtmlElement>("div")
+                .ok_or_else(|| JsValue::from("Couldn't create an annotation"))?;
+            pin.set_class_name("archizoom-annotation");
+            pin.set_attribute("data-annotation-id", &annotation.id)?;
+            pin.set_text_content(Some(&annotation.text));
+
+            // the bottom middle of the pin points at the annotated spot
+            let style = pin.style();
+            style.set_property("position", "absolute")?;
+            style.set_property("left", &format!("{}px", position.x))?;
+            style.set_property("top", &format!("{}px", position.y))?;
+            style.set_property("transform", "translate(-50%, -100%)")?;
+            style.set_property("white-space", "nowrap")?;
+
+            self.layer.append_child(&pin)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Overlay for Annotations {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        self.redraw();
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        self.redraw();
+    }
+
+    fn notify(&self, event: &ViewerEvent) {
+        let annotations = match event {
+            ViewerEvent::AnnotationsChanged(annotations) => annotations,
+            _ => return,
+        };
+
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            if let Err(error) = self.render(&archizoom.borrow(), annotations) {
+                console::warn_2(&"Couldn't draw the annotations".into(), &error);
+            }
+        }
+    }
+}
+
+impl Drop for Annotations {
+    fn drop(&mut self) {
+        self.layer.remove();
+    }
+}
@@ -0,0 +1,137 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{Element, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, ViewerEvent};
+use crate::strings::Strings;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnnouncerOptions {
+    pub enabled: bool,
+    /// How long the zoom level has to settle before it's announced
+    pub zoom_delay_ms: i32,
+}
+
+impl Default for AnnouncerOptions {
+    fn default() -> AnnouncerOptions {
+        AnnouncerOptions {
+            enabled: true,
+            zoom_delay_ms: 750,
+        }
+    }
+}
+
+/// A visually hidden live region that tells screen readers when we open a diagram or the zoom
+/// level changes
+pub struct Announcer {
+    region: HtmlElement,
+    /// The zoom percentage waiting to be announced
+    pending_zoom: Rc<Cell<Option<i32>>>,
+    announced_zoom: Cell<i32>,
+    zoom_delay_ms: i32,
+    announce_zoom: Timeout,
+    opened_diagram: String,
+    view_link_copied: String,
+}
+
+/// Zoom levels are rounded to this many percent so small changes aren't announced
+static ZOOM_ROUNDING: f32 = 25.0;
+
+impl Announcer {
+    pub fn new(
+        container: &Element,
+        options: &AnnouncerOptions,
+        strings: &Strings,
+    ) -> Result<Announcer, JsValue> {
+        let region = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the live region"))?;
+        region.set_class_name("archizoom-announcer");
+        region.set_attribute("aria-live", "polite")?;
+        region.set_attribute("aria-atomic", "true")?;
+
+        // the usual visually hidden styles, screen readers still read it
+        let style = region.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("width", "1px")?;
+        style.set_property("height", "1px")?;
+        style.set_property("overflow", "hidden")?;
+        style.set_property("clip", "rect(0 0 0 0)")?;
+        style.set_property("white-space", "nowrap")?;
+
+        container.append_child(&region)?;
+
+        let pending_zoom: Rc<Cell<Option<i32>>> = Rc::new(Cell::new(None));
+        let timeout_zoom = pending_zoom.clone();
+        let timeout_region = region.clone();
+        let zoomed_to = strings.zoomed_to.clone();
+        let announce_zoom = Timeout::new(move || {
+            if let Some(percent) = timeout_zoom.take() {
+                let message = Strings::fill(&zoomed_to, &[("percent", &percent.to_string())]);
+                timeout_region.set_text_content(Some(&message));
+            }
+        });
+
+        Ok(Announcer {
+            region,
+            pending_zoom,
+            announced_zoom: Cell::new(100),
+            zoom_delay_ms: options.zoom_delay_ms,
+            announce_zoom,
+            opened_diagram: strings.opened_diagram.clone(),
+            view_link_copied: strings.view_link_copied.clone(),
+        })
+    }
+
+    fn announce(&self, message: &str) {
+        self.region.set_text_content(Some(message));
+    }
+}
+
+impl Overlay for Announcer {
+    fn view_update(&self, event: &ViewUpdateEvent) {
+        if !event.zoom().is_finite() {
+            return;
+        }
+
+        let percent = ((event.zoom() * 100.0 / ZOOM_ROUNDING).round() * ZOOM_ROUNDING) as i32;
+        if percent != self.announced_zoom.get() {
+            self.announced_zoom.set(percent);
+            self.pending_zoom.set(Some(percent));
+            self.announce_zoom.restart(self.zoom_delay_ms);
+        }
+    }
+
+    fn notify(&self, event: &ViewerEvent) {
+        if let ViewerEvent::ViewLinkCopied = event {
+            self.announce(&self.view_link_copied);
+        }
+    }
+
+    fn navigated(&self, path: &[NavigationEntry]) {
+        // a new diagram starts at its fitted view, so there's no zoom change to announce
+        self.announce_zoom.cancel();
+        self.pending_zoom.set(None);
+        self.announced_zoom.set(100);
+
+        if let Some(current) = path.last() {
+            self.announce(&Strings::fill(
+                &self.opened_diagram,
+                &[("title", &current.title)],
+            ));
+        }
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        self.region.remove();
+    }
+}
@@ -0,0 +1,126 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, Event, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, OverlayPosition};
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BreadcrumbsOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+}
+
+impl Default for BreadcrumbsOptions {
+    fn default() -> BreadcrumbsOptions {
+        BreadcrumbsOptions {
+            enabled: true,
+            position: OverlayPosition::TopLeft,
+        }
+    }
+}
+
+/// The drill-down path, e.g. Root › Application Layer › CRM View, where each parent can be
+/// clicked to go back up to it
+pub struct Breadcrumbs {
+    element: HtmlElement,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+}
+
+static SEPARATOR: &str = " \u{203a} ";
+
+impl Breadcrumbs {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &BreadcrumbsOptions,
+    ) -> Result<Breadcrumbs, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("nav")
+            .ok_or_else(|| JsValue::from("Couldn't create the breadcrumbs"))?;
+        element.set_class_name("archizoom-breadcrumbs");
+        options.position.apply(&element.style())?;
+
+        container.append_child(&element)?;
+
+        let breadcrumbs = Breadcrumbs {
+            element,
+            archizoom: Rc::downgrade(archizoom),
+            listeners: RefCell::new(vec![]),
+        };
+        breadcrumbs.render(archizoom.borrow().navigation())?;
+
+        Ok(breadcrumbs)
+    }
+
+    fn render(&self, path: &[NavigationEntry]) -> Result<(), JsValue> {
+        self.listeners.borrow_mut().clear();
+        self.element.set_inner_html("");
+
+        // there's nothing to go back to until we've drilled down
+        self.element
+            .style()
+            .set_property("display", if path.len() > 1 { "block" } else { "none" })?;
+
+        for (index, entry) in path.iter().enumerate() {
+            if index > 0 {
+                let separator = document()
+                    .safe_create_element::<HtmlElement>("span")
+                    .ok_or_else(|| JsValue::from("Couldn't create a breadcrumb separator"))?;
+                separator.set_class_name("archizoom-breadcrumb-separator");
+                separator.set_text_content(Some(SEPARATOR));
+                self.element.append_child(&separator)?;
+            }
+
+            let is_current = index + 1 == path.len();
+            let crumb = document()
+                .safe_create_element::<HtmlElement>(if is_current { "span" } else { "a" })
+                .ok_or_else(|| JsValue::from("Couldn't create a breadcrumb"))?;
+            crumb.set_class_name("archizoom-breadcrumb");
+            crumb.set_text_content(Some(&entry.title));
+
+            if is_current {
+                crumb.set_attribute("aria-current", "page")?;
+            } else {
+                crumb.set_attribute("href", &entry.url)?;
+
+                let weak_ref = self.archizoom.clone();
+                self.listeners.borrow_mut().push(crumb.new_event_listener(
+                    "click",
+                    move |event: Event| {
+                        event.prevent_default();
+
+                        if let Some(archizoom) = weak_ref.upgrade() {
+                            archizoom.borrow().navigate_back(index);
+                        }
+                    },
+                )?);
+            }
+
+            self.element.append_child(&crumb)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Overlay for Breadcrumbs {
+    fn navigated(&self, path: &[NavigationEntry]) {
+        if let Err(error) = self.render(path) {
+            console::error_2(&"Couldn't render the breadcrumbs".into(), &error);
+        }
+    }
+}
+
+impl Drop for Breadcrumbs {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
@@ -0,0 +1,552 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    console, CanvasRenderingContext2d, Element, Event, HtmlCanvasElement, HtmlImageElement,
+    SvgsvgElement, XmlSerializer,
+};
+
+use crate::diagnostics;
+use crate::js_utils::*;
+use crate::overlays::Overlay;
+use crate::zoom::matrix::{Point2D, Rect, Size2D};
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::{raster_size, ClientRect, ClientSpace, SvgUserSpace, Transform, UserRect};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::tiles::{TileKey, TilePyramid, MAX_LEVEL};
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CanvasOptions {
+    pub enabled: bool,
+    /// Diagrams with fewer elements than this are light enough to keep in the DOM
+    pub min_elements: u32,
+    /// How long the view has to stay still before it's rendered again at full resolution
+    pub settle_ms: i32,
+    /// Render a pyramid of tiles at doubling zoom levels instead of the whole view at once, for
+    /// diagrams so large that even a single rendering is slow
+    pub tiled: bool,
+    /// The width and height of each tile in CSS pixels
+    pub tile_size: u32,
+    /// The deepest level of the pyramid, each level doubles the detail. Anything past
+    /// `MAX_LEVEL` is cut down to it.
+    #[serde(deserialize_with = "deserialize_max_level")]
+    pub max_level: u32,
+    /// How many tiles to keep around before dropping the oldest
+    pub max_tiles: usize,
+}
+
+impl Default for CanvasOptions {
+    fn default() -> CanvasOptions {
+        CanvasOptions {
+            enabled: false,
+            min_elements: 5000,
+            settle_ms: 150,
+            tiled: false,
+            tile_size: 256,
+            max_level: 12,
+            max_tiles: 256,
+        }
+    }
+}
+
+fn deserialize_max_level<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    Ok(u32::deserialize(deserializer)?.min(MAX_LEVEL))
+}
+
+/// Draws heavy diagrams as a bitmap instead of letting the browser repaint every element while
+/// panning and zooming. The svg stays in place with its contents hidden so it still takes our
+/// gestures, we scale the last rendering to follow the view and render it again once the view
+/// settles.
+///
+/// Renderings go through an `<img>`, which browsers decode off the main thread, so they can't
+/// show our stylesheet's classes or load anything the svg links to.
+pub struct CanvasRenderer {
+    raster: Rc<Raster>,
+    options: CanvasOptions,
+}
+
+struct Raster {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    container: Element,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    active: Cell<bool>,
+    /// The latest rendering and the region of the diagram it shows
+    image: RefCell<Option<(HtmlImageElement, UserRect)>>,
+    /// A rendering that's still decoding, replaced if the view changes again before it's done
+    loading: RefCell<Option<Box<dyn JsEventListener>>>,
+    /// Set instead of `image` when we're rendering tiles
+    tiles: RefCell<Option<TileSet>>,
+    settle: Timeout,
+}
+
+struct TileSet {
+    pyramid: TilePyramid,
+    /// The encoded diagram before and after its viewBox, so each tile only has to fill that in
+    markup: (String, String),
+    tiles: HashMap<TileKey, Tile>,
+    /// Least recently visible first, so we know which tiles to drop
+    order: VecDeque<TileKey>,
+    max_tiles: usize,
+}
+
+struct Tile {
+    image: HtmlImageElement,
+    _listener: Box<dyn JsEventListener>,
+}
+
+static RASTERIZED_CLASS: &str = "archizoom-rasterized";
+static VIEW_BOX_PLACEHOLDER: &str = "archizoom-view-box";
+
+impl CanvasRenderer {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &CanvasOptions,
+    ) -> Result<CanvasRenderer, JsValue> {
+        let canvas = document()
+            .safe_create_element::<HtmlCanvasElement>("canvas")
+            .ok_or_else(|| JsValue::from("Couldn't create the diagram canvas"))?;
+        canvas.set_class_name("archizoom-canvas");
+
+        let style = canvas.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("top", "0")?;
+        style.set_property("left", "0")?;
+        style.set_property("width", "100%")?;
+        style.set_property("height", "100%")?;
+        style.set_property("pointer-events", "none")?;
+        style.set_property("display", "none")?;
+
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from("Canvas 2d isn't supported"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        container.append_child(&canvas)?;
+
+        let raster = Rc::new_cyclic(|weak_ref: &Weak<Raster>| {
+            let weak_ref = weak_ref.clone();
+
+            Raster {
+                canvas,
+                context,
+                container: container.clone(),
+                archizoom: Rc::downgrade(archizoom),
+                active: Cell::new(false),
+                image: RefCell::new(None),
+                loading: RefCell::new(None),
+                tiles: RefCell::new(None),
+                settle: Timeout::new(move || {
+                    if let Some(raster) = weak_ref.upgrade() {
+                        Raster::render(&raster);
+                    }
+                }),
+            }
+        });
+
+        let renderer = CanvasRenderer {
+            raster,
+            options: options.clone(),
+        };
+        renderer.activate();
+
+        Ok(renderer)
+    }
+
+    /// Switches to the canvas if the current diagram is heavy enough to need it
+    fn activate(&self) {
+        let raster = &self.raster;
+        let archizoom = match raster.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return,
+        };
+        let svg = archizoom.borrow().svg().clone();
+
+        let element_count = svg
+            .query_selector_all("*")
+            .map(|elements| elements.length())
+            .unwrap_or(0);
+        let active = element_count >= self.options.min_elements;
+
+        raster.settle.cancel();
+        raster.loading.replace(None);
+        raster.image.replace(None);
+        raster.tiles.replace(None);
+        raster.active.set(active);
+
+        let toggled = svg
+            .class_list()
+            .toggle_with_force(RASTERIZED_CLASS, active)
+            .and_then(|_| {
+                raster
+                    .canvas
+                    .style()
+                    .set_property("display", if active { "block" } else { "none" })
+            });
+        if let Err(error) = toggled {
+            console::warn_2(&"Couldn't switch the canvas renderer".into(), &error);
+        }
+
+        if !active {
+            return;
+        }
+
+        if self.options.tiled {
+            // every tile is cut from one encoding of the whole drawing, culled groups included
+            let tiles = {
+                let archizoom = archizoom.borrow();
+                archizoom.unculled(|| TileSet::new(&svg, archizoom.content_bounds(), &self.options))
+            };
+            match tiles {
+                Ok(tiles) => {
+                    raster.tiles.replace(tiles);
+                    Raster::update_tiles(raster);
+                }
+                Err(error) => console::warn_2(&"Couldn't prepare the tiles".into(), &error),
+            }
+        } else {
+            Raster::render(raster);
+        }
+    }
+}
+
+impl Raster {
+    /// Renders the visible part of the diagram at full resolution, it's drawn once it decodes
+    fn render(raster: &Rc<Raster>) {
+        if let Err(error) = Raster::try_render(raster) {
+            console::warn_2(&"Couldn't render the diagram to the canvas".into(), &error);
+        }
+    }
+
+    fn try_render(raster: &Rc<Raster>) -> Result<(), JsValue> {
+        let archizoom = match raster.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return Ok(()),
+        };
+        let svg = archizoom.borrow().svg().clone();
+
+        let visible = match raster.visible_region(&archizoom.borrow()) {
+            Some(visible) => visible,
+            None => return Ok(()),
+        };
+        let (raster_width, raster_height) = raster_size(&raster.client_bounds().size());
+        let markup = encoded_markup(
+            &svg,
+            raster_width,
+            raster_height,
+            &view_box_attribute(&visible),
+        )?;
+
+        let image = HtmlImageElement::new()?;
+        let weak_ref = Rc::downgrade(raster);
+        let loaded_image = image.clone();
+        let listener = image.new_event_listener("load", move |_: Event| {
+            if let Some(raster) = weak_ref.upgrade() {
+                raster
+                    .image
+                    .replace(Some((loaded_image.clone(), visible.clone())));
+                raster.draw();
+            }
+        })?;
+        raster.loading.replace(Some(listener));
+        image.set_src(&svg_data_url(&markup));
+
+        Ok(())
+    }
+
+    /// Draws what we have and starts rendering the tiles the current view is missing
+    fn update_tiles(raster: &Rc<Raster>) {
+        raster.draw();
+
+        if let Err(error) = Raster::request_tiles(raster) {
+            console::warn_2(&"Couldn't render the diagram tiles".into(), &error);
+        }
+    }
+
+    fn request_tiles(raster: &Rc<Raster>) -> Result<(), JsValue> {
+        let archizoom = match raster.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return Ok(()),
+        };
+        let (visible, scale) = match (
+            raster.visible_region(&archizoom.borrow()),
+            raster.to_client(&archizoom.borrow()),
+        ) {
+            (Some(visible), Some(to_client)) => (visible, to_client.matrix().decompose().scale()),
+            _ => return Ok(()),
+        };
+
+        let mut tiles = raster.tiles.borrow_mut();
+        let tiles = match tiles.as_mut() {
+            Some(tiles) => tiles,
+            None => return Ok(()),
+        };
+
+        let level = tiles.pyramid.level_for(scale);
+        let visible_keys = tiles.pyramid.tiles_in(level, &visible);
+        for &key in visible_keys.iter() {
+            if tiles.tiles.contains_key(&key) {
+                continue;
+            }
+
+            let view_box = UserRect::new(tiles.pyramid.tile_rect(&key));
+            let image = HtmlImageElement::new()?;
+            let weak_ref = Rc::downgrade(raster);
+            let listener = image.new_event_listener("load", move |_: Event| {
+                if let Some(raster) = weak_ref.upgrade() {
+                    raster.draw();
+                }
+            })?;
+            image.set_src(&svg_data_url(&format!(
+                "{}{}{}",
+                tiles.markup.0,
+                js_sys::encode_uri_component(&view_box_attribute(&view_box)),
+                tiles.markup.1
+            )));
+
+            tiles.insert(
+                key,
+                Tile {
+                    image,
+                    _listener: listener,
+                },
+            );
+        }
+        tiles.evict(&visible_keys);
+
+        Ok(())
+    }
+
+    /// Draws the latest rendering wherever its region of the diagram is now
+    fn draw(&self) {
+        if let Err(error) = self.try_draw() {
+            console::warn_2(&"Couldn't draw the diagram canvas".into(), &error);
+        }
+    }
+
+    fn try_draw(&self) -> Result<(), JsValue> {
+        let archizoom = match self.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return Ok(()),
+        };
+        let to_client = match self.to_client(&archizoom.borrow()) {
+            Some(to_client) => to_client,
+            None => return Ok(()),
+        };
+
+        let client_bounds = self.client_bounds();
+        let (raster_width, raster_height) = raster_size(&client_bounds.size());
+        if self.canvas.width() != raster_width || self.canvas.height() != raster_height {
+            self.canvas.set_width(raster_width);
+            self.canvas.set_height(raster_height);
+        }
+
+        let ratio = device_pixel_ratio() as f64;
+        let context = &self.context;
+        context.set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0)?;
+        context.clear_rect(
+            0.0,
+            0.0,
+            client_bounds.width() as f64,
+            client_bounds.height() as f64,
+        );
+
+        if let Some((image, region)) = self.image.borrow().as_ref() {
+            self.draw_image(image, &to_client.rect(region))?;
+        }
+
+        if let Some(tiles) = self.tiles.borrow().as_ref() {
+            let visible = match to_client.inverse() {
+                Some(to_user) => to_user.rect(&client_bounds),
+                None => return Ok(()),
+            };
+            let level = tiles
+                .pyramid
+                .level_for(to_client.matrix().decompose().scale());
+
+            // coarse tiles fill in underneath while the detailed ones are still decoding
+            for level in 0..=level {
+                for key in tiles.pyramid.tiles_in(level, &visible) {
+                    match tiles.tiles.get(&key) {
+                        Some(tile) if tile.image.complete() && tile.image.natural_width() > 0 => {
+                            let region = UserRect::new(tiles.pyramid.tile_rect(&key));
+                            self.draw_image(&tile.image, &to_client.rect(&region))?;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_image(&self, image: &HtmlImageElement, target: &ClientRect) -> Result<(), JsValue> {
+        self.context
+            .draw_image_with_html_image_element_and_dw_and_dh(
+                image,
+                target.left() as f64,
+                target.top() as f64,
+                target.width() as f64,
+                target.height() as f64,
+            )
+    }
+
+    fn to_client(&self, archizoom: &ArchiZoom) -> Option<Transform<SvgUserSpace, ClientSpace>> {
+        match (
+            archizoom.screen_ctm(),
+            Transform::client_to_screen(&self.container).inverse(),
+        ) {
+            (Some(to_screen), Some(to_client)) => Some(to_screen.then(&to_client)),
+            _ => None,
+        }
+    }
+
+    /// The part of the diagram the container shows, in Svg User Coordinates
+    fn visible_region(&self, archizoom: &ArchiZoom) -> Option<UserRect> {
+        self.to_client(archizoom)
+            .and_then(|to_client| to_client.inverse())
+            .map(|to_user| to_user.rect(&self.client_bounds()))
+    }
+
+    fn client_bounds(&self) -> ClientRect {
+        diagnostics::layout_read();
+        let client_rect = self.container.get_bounding_client_rect();
+
+        ClientRect::new(Rect::new(
+            Point2D::new(0.0, 0.0),
+            Point2D::new(client_rect.width() as f32, client_rect.height() as f32),
+        ))
+    }
+}
+
+impl TileSet {
+    fn new(
+        svg: &SvgsvgElement,
+        bounds: Option<Rect>,
+        options: &CanvasOptions,
+    ) -> Result<Option<TileSet>, JsValue> {
+        let bounds = match bounds {
+            Some(bounds) => bounds,
+            None => return Ok(None),
+        };
+
+        // every tile is the same size, so we only encode the diagram once
+        let tile_size = options.tile_size as f32;
+        let (tile_width, tile_height) = raster_size(&Size2D::new(tile_size, tile_size));
+        let markup = encoded_markup(svg, tile_width, tile_height, VIEW_BOX_PLACEHOLDER)?;
+        let placeholder = String::from(js_sys::encode_uri_component(VIEW_BOX_PLACEHOLDER));
+        let split = markup
+            .find(&placeholder)
+            .ok_or_else(|| JsValue::from("Couldn't find the tile viewBox"))?;
+
+        Ok(Some(TileSet {
+            pyramid: TilePyramid::new(&bounds, options.tile_size as f32, options.max_level),
+            markup: (
+                markup[..split].to_string(),
+                markup[split + placeholder.len()..].to_string(),
+            ),
+            tiles: HashMap::new(),
+            order: VecDeque::new(),
+            max_tiles: options.max_tiles,
+        }))
+    }
+
+    fn insert(&mut self, key: TileKey, tile: Tile) {
+        self.tiles.insert(key, tile);
+        self.order.push_back(key);
+    }
+
+    /// Drops the least recently visible tiles past `max_tiles`. The `visible` ones are kept
+    /// even if that leaves us over, dropping them would blank out part of the view.
+    fn evict(&mut self, visible: &[TileKey]) {
+        self.order.retain(|key| !visible.contains(key));
+        let tiles = &self.tiles;
+        self.order
+            .extend(visible.iter().filter(|key| tiles.contains_key(key)));
+
+        while self.order.len() > self.max_tiles.max(1) {
+            match self.order.iter().position(|key| !visible.contains(key)) {
+                Some(index) => {
+                    if let Some(oldest) = self.order.remove(index) {
+                        self.tiles.remove(&oldest);
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+impl Overlay for CanvasRenderer {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        if !self.raster.active.get() {
+            return;
+        }
+
+        if self.options.tiled {
+            Raster::update_tiles(&self.raster);
+        } else {
+            self.raster.draw();
+            self.raster.settle.restart(self.options.settle_ms);
+        }
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        self.activate();
+    }
+}
+
+impl Drop for CanvasRenderer {
+    fn drop(&mut self) {
+        self.raster.canvas.remove();
+    }
+}
+
+/// Serializes a visible copy of `svg` sized to `width` by `height` pixels and showing
+/// `view_box`, encoded for a data url
+fn encoded_markup(
+    svg: &SvgsvgElement,
+    width: u32,
+    height: u32,
+    view_box: &str,
+) -> Result<String, JsValue> {
+    // render a copy so the live svg keeps its viewBox and stays hidden
+    let copy = svg.clone_node_with_deep(true)?.dyn_into::<Element>()?;
+    copy.class_list().remove_1(RASTERIZED_CLASS)?;
+    copy.remove_attribute("style")?;
+    for culled in copy
+        .query_selector_all(".archizoom-culled")?
+        .safe_filter::<Element>()
+    {
+        culled.class_list().remove_1("archizoom-culled")?;
+    }
+    copy.set_attribute("viewBox", view_box)?;
+    copy.set_attribute("width", &width.to_string())?;
+    copy.set_attribute("height", &height.to_string())?;
+    copy.set_attribute("preserveAspectRatio", "none")?;
+
+    let markup = XmlSerializer::new()?.serialize_to_string(&copy)?;
+
+    Ok(js_sys::encode_uri_component(&markup).into())
+}
+
+fn svg_data_url(encoded_markup: &str) -> String {
+    format!("data:image/svg+xml;charset=utf-8,{}", encoded_markup)
+}
+
+fn view_box_attribute(rect: &UserRect) -> String {
+    format!(
+        "{} {} {} {}",
+        rect.left(),
+        rect.top(),
+        rect.width(),
+        rect.height()
+    )
+}
@@ -0,0 +1,379 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use futures::Future;
+use js_sys::Function;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{console, Element, Event, HtmlElement, KeyboardEvent, MouseEvent, PointerEvent};
+
+use crate::js_utils::*;
+use crate::overlays::Overlay;
+#[cfg(feature = "annotations")]
+use crate::zoom::annotations::Annotation;
+use crate::zoom::matrix::Point2D;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::{ScreenPoint, Transform};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContextMenuOptions {
+    pub enabled: bool,
+    /// How long a touch has to be held to open the menu
+    pub long_press_ms: i32,
+}
+
+impl Default for ContextMenuOptions {
+    fn default() -> ContextMenuOptions {
+        ContextMenuOptions {
+            enabled: true,
+            long_press_ms: 500,
+        }
+    }
+}
+
+/// An entry added by the host page, `callback` is called with the element that was
+/// right-clicked
+#[derive(Debug, Clone)]
+pub struct ContextMenuItem {
+    pub label: String,
+    pub callback: Function,
+}
+
+/// The menu shown on right-click or long-press
+pub struct ContextMenu {
+    menu: Rc<Menu>,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+    _document_listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+struct Menu {
+    element: HtmlElement,
+    container: Element,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    /// Where a touch started and what it started on, while waiting to see if it's a long-press
+    press: RefCell<Option<(ScreenPoint, Element)>>,
+    long_press: Timeout,
+    long_press_ms: i32,
+    item_listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+}
+
+enum MenuAction {
+    Viewer(fn(&ArchiZoom)),
+    Navigate(String),
+    /// Prompts for a note to pin at the point that was right-clicked
+    #[cfg(feature = "annotations")]
+    Annotate(Point2D),
+    Custom(Function),
+}
+
+/// How far a touch can wander and still be a long-press
+static LONG_PRESS_SLOP: f32 = 8.0;
+
+impl ContextMenu {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &ContextMenuOptions,
+    ) -> Result<ContextMenu, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the context menu"))?;
+        element.set_class_name("archizoom-context-menu");
+        element.set_attribute("role", "menu")?;
+
+        let style = element.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("display", "none")?;
+
+        container.append_child(&element)?;
+
+        let menu = Rc::new_cyclic(|weak_ref: &Weak<Menu>| {
+            let weak_ref = weak_ref.clone();
+
+            Menu {
+                element,
+                container: container.clone(),
+                archizoom: Rc::downgrade(archizoom),
+                press: RefCell::new(None),
+                long_press: Timeout::new(move || {
+                    if let Some(menu) = weak_ref.upgrade() {
+                        if let Some((position, target)) = menu.press.replace(None) {
+                            menu.open(&position, &target);
+                        }
+                    }
+                }),
+                long_press_ms: options.long_press_ms,
+                item_listeners: RefCell::new(vec![]),
+            }
+        });
+
+        // anything outside of the menu closes it
+        let down_menu = menu.clone();
+        let key_menu = menu.clone();
+        let document_listeners = vec![
+            document().new_event_listener("pointerdown", move |event: PointerEvent| {
+                let inside = event
+                    .target()
+                    .and_then(|target| target.dyn_into::<Element>().ok())
+                    .map(|target| down_menu.element.contains(Some(&target)))
+                    .unwrap_or(false);
+
+                if !inside {
+                    down_menu.close();
+                }
+            })?,
+            document().new_event_listener("keydown", move |event: KeyboardEvent| {
+                if event.key() == "Escape" {
+                    key_menu.close();
+                }
+            })?,
+        ];
+
+        let context_menu = ContextMenu {
+            menu,
+            archizoom: Rc::downgrade(archizoom),
+            listeners: RefCell::new(vec![]),
+            _document_listeners: document_listeners,
+        };
+        context_menu.bind()?;
+
+        Ok(context_menu)
+    }
+
+    /// Listens for right-clicks and long-presses on the current svg
+    fn bind(&self) -> Result<(), JsValue> {
+        let svg = match self.archizoom.upgrade() {
+            Some(archizoom) => archizoom.borrow().svg().clone(),
+            None => return Ok(()),
+        };
+
+        let context_menu = self.menu.clone();
+        let down_menu = self.menu.clone();
+        let move_menu = self.menu.clone();
+        let up_menu = self.menu.clone();
+
+        *self.listeners.borrow_mut() = vec![
+            svg.new_event_listener("contextmenu", move |event: MouseEvent| {
+                event.prevent_default();
+
+                if let Some(target) = event_element(&event) {
+                    context_menu.open(&client_point(&event), &target);
+                }
+            })?,
+            svg.new_event_listener("pointerdown", move |event: PointerEvent| {
+                if event.pointer_type() == "touch" {
+                    if let Some(target) = event_element(&event) {
+                        down_menu
+                            .press
+                            .replace(Some((client_point(&event), target)));
+                        down_menu.long_press.restart(down_menu.long_press_ms);
+                    }
+                }
+            })?,
+            svg.new_event_listener("pointermove", move |event: PointerEvent| {
+                let wandered = match *move_menu.press.borrow() {
+                    Some((ref origin, _)) => {
+                        client_point(&event).delta_from(origin).length() > LONG_PRESS_SLOP
+                    }
+                    None => false,
+                };
+
+                if wandered {
+                    move_menu.cancel_press();
+                }
+            })?,
+            svg.new_event_listener("pointerup", move |_: PointerEvent| {
+                up_menu.cancel_press();
+            })?,
+        ];
+
+        Ok(())
+    }
+}
+
+impl Menu {
+    fn open(&self, position: &ScreenPoint, target: &Element) {
+        if let Err(error) = self.render(position, target) {
+            console::error_2(&"Couldn't open the context menu".into(), &error);
+        }
+    }
+
+    fn render(&self, position: &ScreenPoint, target: &Element) -> Result<(), JsValue> {
+        let archizoom = match self.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return Ok(()),
+        };
+
+        let strings = archizoom.borrow().strings().clone();
+        let mut items: Vec<(String, MenuAction)> = vec![
+            (
+                strings.zoom_to_fit.clone(),
+                MenuAction::Viewer(|az| az.fit_to_content()),
+            ),
+            (strings.reset.clone(), MenuAction::Viewer(|az| az.reset())),
+            (
+                strings.copy_view_link.clone(),
+                MenuAction::Viewer(copy_view_link),
+            ),
+        ];
+        {
+            let archizoom = archizoom.borrow();
+            #[cfg(feature = "annotations")]
+            if let Some(point) = archizoom.user_point(position) {
+                items.push((
+                    strings.add_note.clone(),
+                    MenuAction::Annotate(point.into_inner()),
+                ));
+            }
+            if let Some(link) = archizoom.link_target(target) {
+                items.push((
+                    strings.open_linked_diagram.clone(),
+                    MenuAction::Navigate(link),
+                ));
+            }
+            for item in archizoom.context_menu_items().iter() {
+                items.push((
+                    item.label.clone(),
+                    MenuAction::Custom(item.callback.clone()),
+                ));
+            }
+        }
+
+        self.element.set_inner_html("");
+        let mut listeners = vec![];
+        for (label, action) in items.into_iter() {
+            let button = document()
+                .safe_create_element::<HtmlElement>("button")
+                .ok_or_else(|| JsValue::from("Couldn't create a context menu item"))?;
+            button.set_class_name("archizoom-context-menu-item");
+            button.set_attribute("type", "button")?;
+            button.set_attribute("role", "menuitem")?;
+            button.set_text_content(Some(&label));
+
+            let weak_ref = Rc::downgrade(&archizoom);
+            let element = self.element.clone();
+            let target = target.clone();
+            listeners.push(button.new_event_listener("click", move |event: Event| {
+                event.stop_propagation();
+                if let Err(error) = element.style().set_property("display", "none") {
+                    console::warn_2(&"Couldn't close the context menu".into(), &error);
+                }
+
+                // the host's entries can call back into the viewer, so it isn't borrowed for them
+                if let MenuAction::Custom(ref callback) = action {
+                    if let Err(error) = callback.call1(&JsValue::NULL, &target) {
+                        console::error_2(&"Context menu item failed".into(), &error);
+                    }
+                    return;
+                }
+
+                if let Some(archizoom) = weak_ref.upgrade() {
+                    let archizoom = archizoom.borrow();
+                    match action {
+                        MenuAction::Viewer(action) => action(&archizoom),
+                        MenuAction::Navigate(ref url) => archizoom.navigate_to(url),
+                        #[cfg(feature = "annotations")]
+                        MenuAction::Annotate(ref point) => annotate(&archizoom, point),
+                        MenuAction::Custom(_) => (),
+                    }
+                }
+            })?);
+
+            self.element.append_child(&button)?;
+        }
+        self.item_listeners.replace(listeners);
+
+        let to_client = Transform::client_to_screen(&self.container)
+            .inverse()
+            .ok_or_else(|| JsValue::from("The container can't be inverted"))?;
+        let anchor = to_client.point(position);
+
+        let style = self.element.style();
+        style.set_property("left", &format!("{}px", anchor.x))?;
+        style.set_property("top", &format!("{}px", anchor.y))?;
+        style.set_property("display", "block")?;
+
+        Ok(())
+    }
+
+    fn cancel_press(&self) {
+        self.long_press.cancel();
+        self.press.replace(None);
+    }
+
+    fn close(&self) {
+        self.cancel_press();
+
+        if let Err(error) = self.element.style().set_property("display", "none") {
+            console::warn_2(&"Couldn't close the context menu".into(), &error);
+        }
+    }
+}
+
+impl Overlay for ContextMenu {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        self.menu.close();
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        self.menu.close();
+
+        if let Err(error) = self.bind() {
+            console::error_2(&"Couldn't listen for the context menu".into(), &error);
+        }
+    }
+}
+
+impl Drop for ContextMenu {
+    fn drop(&mut self) {
+        self.menu.element.remove();
+    }
+}
+
+pub fn copy_view_link(archizoom: &ArchiZoom) {
+    match archizoom.copy_view_link() {
+        Ok(copied) => spawn_local(copied.then(|result| {
+            if let Err(error) = result {
+                console::warn_2(&"Couldn't copy the view link".into(), &error);
+            }
+
+            Ok(())
+        })),
+        Err(error) => console::warn_2(&"Couldn't copy the view link".into(), &error),
+    }
+}
+
+#[cfg(feature = "annotations")]
+fn annotate(archizoom: &ArchiZoom, point: &Point2D) {
+    match window().prompt_with_message(&archizoom.strings().note_prompt) {
+        Ok(Some(text)) if !text.trim().is_empty() => {
+            archizoom.add_annotation(Annotation {
+                id: String::new(),
+                diagram: None,
+                x: point.x,
+                y: point.y,
+                text,
+            });
+        }
+        Ok(_) => (),
+        Err(error) => console::warn_2(&"Couldn't ask for the note".into(), &error),
+    }
+}
+
+fn client_point(event: &MouseEvent) -> ScreenPoint {
+    ScreenPoint::new(Point2D::new(
+        event.client_x() as f32,
+        event.client_y() as f32,
+    ))
+}
+
+fn event_element(event: &Event) -> Option<Element> {
+    event
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+}
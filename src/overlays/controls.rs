@@ -0,0 +1,174 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{Element, Event, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::context_menu::copy_view_link;
+use crate::overlays::{Overlay, OverlayPosition, ViewerEvent};
+use crate::strings::Strings;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ControlsOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+    /// Add a button that copies a link to the current view
+    pub copy_link: bool,
+}
+
+impl Default for ControlsOptions {
+    fn default() -> ControlsOptions {
+        ControlsOptions {
+            enabled: true,
+            position: OverlayPosition::TopRight,
+            copy_link: false,
+        }
+    }
+}
+
+/// The +, −, reset, fit, and fullscreen buttons, and optionally copy link
+pub struct Controls {
+    element: HtmlElement,
+    copy_link: Option<HtmlElement>,
+    /// Puts the copy link button back after it's shown that the link was copied
+    copied_reset: Timeout,
+    view_link_copied: String,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+pub static ZOOM_STEP: f32 = 1.25;
+
+static COPY_LINK_LABEL: &str = "\u{1f517}";
+static COPIED_LABEL: &str = "\u{2713}";
+static COPIED_MS: i32 = 1500;
+
+type ControlTitle = fn(&Strings) -> &str;
+type ControlAction = fn(&ArchiZoom);
+
+static BUTTONS: [(&str, &str, ControlTitle, ControlAction); 5] = [
+    ("zoom-in", "+", |s| &s.zoom_in, |az| az.zoom_by(ZOOM_STEP)),
+    (
+        "zoom-out",
+        "\u{2212}",
+        |s| &s.zoom_out,
+        |az| az.zoom_by(1.0 / ZOOM_STEP),
+    ),
+    ("reset", "\u{27f2}", |s| &s.reset, |az| az.reset()),
+    (
+        "fit",
+        "\u{2922}",
+        |s| &s.fit_to_content,
+        |az| az.fit_to_content(),
+    ),
+    (
+        "fullscreen",
+        "\u{26f6}",
+        |s| &s.fullscreen,
+        |az| az.toggle_fullscreen(),
+    ),
+];
+
+impl Controls {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &ControlsOptions,
+        strings: &Strings,
+    ) -> Result<Controls, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the controls"))?;
+        element.set_class_name("archizoom-controls");
+        options.position.apply(&element.style())?;
+        element.style().set_property("display", "flex")?;
+        element.style().set_property("flex-direction", "column")?;
+
+        let mut listeners = vec![];
+        for (name, label, title, action) in BUTTONS.iter() {
+            let button = control_button(name, label, title(strings))?;
+
+            let weak_ref = Rc::downgrade(archizoom);
+            let action = *action;
+            listeners.push(button.new_event_listener("click", move |event: Event| {
+                event.stop_propagation();
+
+                if let Some(archizoom) = weak_ref.upgrade() {
+                    action(&archizoom.borrow());
+                }
+            })?);
+
+            element.append_child(&button)?;
+        }
+
+        let copy_link = if options.copy_link {
+            let button = control_button("copy-link", COPY_LINK_LABEL, &strings.copy_view_link)?;
+
+            let weak_ref = Rc::downgrade(archizoom);
+            listeners.push(button.new_event_listener("click", move |event: Event| {
+                event.stop_propagation();
+
+                if let Some(archizoom) = weak_ref.upgrade() {
+                    copy_view_link(&archizoom.borrow());
+                }
+            })?);
+
+            element.append_child(&button)?;
+            Some(button)
+        } else {
+            None
+        };
+
+        let copied_reset = {
+            let copy_link = copy_link.clone();
+            let title = strings.copy_view_link.clone();
+            Timeout::new(move || {
+                if let Some(ref button) = copy_link {
+                    button.set_text_content(Some(COPY_LINK_LABEL));
+                    button.set_title(&title);
+                }
+            })
+        };
+
+        container.append_child(&element)?;
+
+        Ok(Controls {
+            element,
+            copy_link,
+            copied_reset,
+            view_link_copied: strings.view_link_copied.clone(),
+            _listeners: listeners,
+        })
+    }
+}
+
+fn control_button(name: &str, label: &str, title: &str) -> Result<HtmlElement, JsValue> {
+    let button = document()
+        .safe_create_element::<HtmlElement>("button")
+        .ok_or_else(|| JsValue::from("Couldn't create a control button"))?;
+    button.set_class_name(&format!("archizoom-control archizoom-control-{}", name));
+    button.set_attribute("type", "button")?;
+    button.set_title(title);
+    button.set_text_content(Some(label));
+
+    Ok(button)
+}
+
+impl Overlay for Controls {
+    fn notify(&self, event: &ViewerEvent) {
+        if let (ViewerEvent::ViewLinkCopied, Some(button)) = (event, &self.copy_link) {
+            button.set_text_content(Some(COPIED_LABEL));
+            button.set_title(&self.view_link_copied);
+            self.copied_reset.restart(COPIED_MS);
+        }
+    }
+}
+
+impl Drop for Controls {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
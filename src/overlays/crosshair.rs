@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, CssStyleDeclaration, Element, HtmlElement, PointerEvent};
+
+use crate::js_utils::*;
+use crate::overlays::OverlayPosition;
+use crate::zoom::space::{ScreenPoint, Transform};
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CrosshairOptions {
+    /// Whether the crosshair starts out active, it can be toggled at any time
+    pub enabled: bool,
+    pub position: OverlayPosition,
+}
+
+impl Default for CrosshairOptions {
+    fn default() -> CrosshairOptions {
+        CrosshairOptions {
+            enabled: false,
+            position: OverlayPosition::BottomLeft,
+        }
+    }
+}
+
+/// Lines through the pointer and a readout of where it is in Svg User Coordinates, for lining
+/// up elements while authoring diagrams. It only exists while it's active.
+pub struct Crosshair {
+    parts: Rc<CrosshairParts>,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+struct CrosshairParts {
+    container: Element,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    horizontal: HtmlElement,
+    vertical: HtmlElement,
+    readout: HtmlElement,
+}
+
+impl Crosshair {
+    pub fn new(
+        archizoom: Weak<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &CrosshairOptions,
+    ) -> Result<Crosshair, JsValue> {
+        let horizontal = crosshair_line("archizoom-crosshair-horizontal")?;
+        horizontal.style().set_property("left", "0")?;
+        horizontal.style().set_property("right", "0")?;
+        horizontal.style().set_property("height", "1px")?;
+
+        let vertical = crosshair_line("archizoom-crosshair-vertical")?;
+        vertical.style().set_property("top", "0")?;
+        vertical.style().set_property("bottom", "0")?;
+        vertical.style().set_property("width", "1px")?;
+
+        let readout = document()
+            .safe_create_element::<HtmlElement>("output")
+            .ok_or_else(|| JsValue::from("Couldn't create the coordinate readout"))?;
+        readout.set_class_name("archizoom-crosshair-readout");
+        options.position.apply(&readout.style())?;
+        hide(&readout.style());
+
+        container.append_child(&horizontal)?;
+        container.append_child(&vertical)?;
+        container.append_child(&readout)?;
+
+        let parts = Rc::new(CrosshairParts {
+            container: container.clone(),
+            archizoom,
+            horizontal,
+            vertical,
+            readout,
+        });
+
+        let move_parts = parts.clone();
+        let leave_parts = parts.clone();
+        let listeners = vec![
+            container.new_event_listener("pointermove", move |event: PointerEvent| {
+                let position = ScreenPoint::point(event.client_x() as f32, event.client_y() as f32);
+                if let Err(error) = move_parts.update(&position) {
+                    console::warn_2(&"Couldn't update the crosshair".into(), &error);
+                }
+            })?,
+            container.new_event_listener("pointerleave", move |_: PointerEvent| {
+                leave_parts.hide();
+            })?,
+        ];
+
+        Ok(Crosshair {
+            parts,
+            _listeners: listeners,
+        })
+    }
+}
+
+impl CrosshairParts {
+    fn update(&self, position: &ScreenPoint) -> Result<(), JsValue> {
+        let archizoom = match self.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return Ok(()),
+        };
+        let archizoom = archizoom.borrow();
+
+        let user_point = match archizoom.user_point(position) {
+            Some(user_point) => user_point,
+            None => return Ok(()),
+        };
+        let client_point = match Transform::client_to_screen(&self.container).inverse() {
+            Some(to_client) => to_client.point(position),
+            None => return Ok(()),
+        };
+
+        self.horizontal
+            .style()
+            .set_property("top", &format!("{}px", client_point.y))?;
+        self.vertical
+            .style()
+            .set_property("left", &format!("{}px", client_point.x))?;
+        self.readout.set_text_content(Some(&format!(
+            "x: {:.1}, y: {:.1}",
+            user_point.x, user_point.y
+        )));
+
+        for part in [&self.horizontal, &self.vertical, &self.readout].iter() {
+            part.style().set_property("display", "block")?;
+        }
+
+        Ok(())
+    }
+
+    fn hide(&self) {
+        for part in [&self.horizontal, &self.vertical, &self.readout].iter() {
+            hide(&part.style());
+        }
+    }
+}
+
+impl Drop for Crosshair {
+    fn drop(&mut self) {
+        self.parts.horizontal.remove();
+        self.parts.vertical.remove();
+        self.parts.readout.remove();
+    }
+}
+
+fn crosshair_line(class_name: &str) -> Result<HtmlElement, JsValue> {
+    let line = document()
+        .safe_create_element::<HtmlElement>("div")
+        .ok_or_else(|| JsValue::from("Couldn't create the crosshair"))?;
+    line.set_class_name(&format!("archizoom-crosshair {}", class_name));
+
+    let style = line.style();
+    style.set_property("position", "absolute")?;
+    style.set_property("pointer-events", "none")?;
+    hide(&style);
+
+    Ok(line)
+}
+
+fn hide(style: &CssStyleDeclaration) {
+    if let Err(error) = style.set_property("display", "none") {
+        console::warn_2(&"Couldn't hide the crosshair".into(), &error);
+    }
+}
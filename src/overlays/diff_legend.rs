@@ -0,0 +1,180 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{Element, Event, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, OverlayPosition, ViewerEvent};
+use crate::strings::Strings;
+use crate::zoom::diff::{Change, Diff};
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiffLegendOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+}
+
+impl Default for DiffLegendOptions {
+    fn default() -> DiffLegendOptions {
+        DiffLegendOptions {
+            enabled: true,
+            position: OverlayPosition::TopRight,
+        }
+    }
+}
+
+/// How many elements were added, removed, and changed, with previous/next buttons to step
+/// through them. Only shown while there's a diff.
+pub struct DiffLegend {
+    element: HtmlElement,
+    counts: Vec<(Change, HtmlElement)>,
+    position: HtmlElement,
+    match_count: String,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl DiffLegend {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &DiffLegendOptions,
+        strings: &Strings,
+    ) -> Result<DiffLegend, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the diff legend"))?;
+        element.set_class_name("archizoom-diff-legend");
+        options.position.apply(&element.style())?;
+        element.style().set_property("display", "none")?;
+
+        let mut counts = vec![];
+        for (change, name, label) in [
+            (Change::Added, "added", &strings.diff_added),
+            (Change::Removed, "removed", &strings.diff_removed),
+            (Change::Changed, "changed", &strings.diff_changed),
+        ]
+        .iter()
+        {
+            let row = document()
+                .safe_create_element::<HtmlElement>("div")
+                .ok_or_else(|| JsValue::from("Couldn't create a diff legend entry"))?;
+            row.set_class_name(&format!("archizoom-diff-legend-{}", name));
+
+            let swatch = document()
+                .safe_create_element::<HtmlElement>("span")
+                .ok_or_else(|| JsValue::from("Couldn't create a diff legend swatch"))?;
+            swatch.set_class_name("archizoom-diff-swatch");
+
+            let count = document()
+                .safe_create_element::<HtmlElement>("span")
+                .ok_or_else(|| JsValue::from("Couldn't create a diff legend count"))?;
+
+            row.append_child(&swatch)?;
+            row.append_with_str_1(&format!("{} ", label))?;
+            row.append_child(&count)?;
+            element.append_child(&row)?;
+
+            counts.push((*change, count));
+        }
+
+        let mut listeners = vec![];
+        for (name, label, title, forward) in [
+            ("previous", "\u{2191}", &strings.previous_change, false),
+            ("next", "\u{2193}", &strings.next_change, true),
+        ]
+        .iter()
+        {
+            let button = document()
+                .safe_create_element::<HtmlElement>("button")
+                .ok_or_else(|| JsValue::from("Couldn't create a diff button"))?;
+            button.set_class_name(&format!("archizoom-diff-{}", name));
+            button.set_attribute("type", "button")?;
+            button.set_title(title);
+            button.set_text_content(Some(label));
+
+            let weak_ref = Rc::downgrade(archizoom);
+            let forward = *forward;
+            listeners.push(button.new_event_listener("click", move |event: Event| {
+                event.stop_propagation();
+
+                if let Some(archizoom) = weak_ref.upgrade() {
+                    archizoom.borrow().diff_step(forward);
+                }
+            })?);
+
+            element.append_child(&button)?;
+        }
+
+        let position = document()
+            .safe_create_element::<HtmlElement>("output")
+            .ok_or_else(|| JsValue::from("Couldn't create the diff position"))?;
+        position.set_class_name("archizoom-diff-position");
+        element.append_child(&position)?;
+
+        container.append_child(&element)?;
+
+        Ok(DiffLegend {
+            element,
+            counts,
+            position,
+            match_count: strings.match_count.clone(),
+            _listeners: listeners,
+        })
+    }
+
+    fn diff_changed(&self, diff: Option<&Diff>) {
+        let _ = self
+            .element
+            .style()
+            .set_property("display", if diff.is_some() { "block" } else { "none" });
+
+        let diff = match diff {
+            Some(diff) => diff,
+            None => return,
+        };
+
+        for (change, count) in self.counts.iter() {
+            count.set_text_content(Some(&diff.count(*change).to_string()));
+        }
+
+        let position = Strings::fill(
+            &self.match_count,
+            &[
+                (
+                    "current",
+                    &diff
+                        .current
+                        .map(|current| current + 1)
+                        .unwrap_or(0)
+                        .to_string(),
+                ),
+                ("total", &diff.changes.len().to_string()),
+            ],
+        );
+        self.position.set_text_content(Some(&position));
+    }
+}
+
+impl Overlay for DiffLegend {
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        // the diff went away with the old diagram
+        self.diff_changed(None);
+    }
+
+    fn notify(&self, event: &ViewerEvent) {
+        if let ViewerEvent::DiffChanged(diff) = event {
+            self.diff_changed(*diff);
+        }
+    }
+}
+
+impl Drop for DiffLegend {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, FocusEvent, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, ViewerEvent};
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::{ScreenRect, Transform};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FocusRingOptions {
+    /// Let Tab move through the zoom elements and outline the one it's on. Off by default, so
+    /// Tab moves past the viewer like it does the rest of the page.
+    pub enabled: bool,
+}
+
+/// Outlines the zoom element Tab moved to
+pub struct FocusRing {
+    element: HtmlElement,
+    container: Element,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    _listener: Box<dyn JsEventListener>,
+}
+
+impl FocusRing {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+    ) -> Result<FocusRing, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the focus ring"))?;
+        element.set_class_name("archizoom-focus-ring");
+        element.set_attribute("aria-hidden", "true")?;
+
+        let style = element.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("pointer-events", "none")?;
+        style.set_property("display", "none")?;
+
+        container.append_child(&element)?;
+
+        // Tab leaving the viewer starts over from the first zoom element next time
+        let weak_ref = Rc::downgrade(archizoom);
+        let listener = container.new_event_listener("focusout", move |_: FocusEvent| {
+            if let Some(archizoom) = weak_ref.upgrade() {
+                archizoom.borrow().blur_link();
+            }
+        })?;
+
+        Ok(FocusRing {
+            element,
+            container: container.clone(),
+            archizoom: Rc::downgrade(archizoom),
+            _listener: listener,
+        })
+    }
+
+    fn place(&self, bounds: Option<&ScreenRect>) -> Result<(), JsValue> {
+        let style = self.element.style();
+
+        let to_client = Transform::client_to_screen(&self.container).inverse();
+        match (bounds, to_client) {
+            (Some(bounds), Some(to_client)) => {
+                let client_bounds = to_client.rect(bounds);
+
+                style.set_property("left", &format!("{}px", client_bounds.left()))?;
+                style.set_property("top", &format!("{}px", client_bounds.top()))?;
+                style.set_property("width", &format!("{}px", client_bounds.width()))?;
+                style.set_property("height", &format!("{}px", client_bounds.height()))?;
+                style.set_property("display", "block")?;
+            }
+            _ => style.set_property("display", "none")?,
+        }
+
+        Ok(())
+    }
+
+    fn show_at(&self, bounds: Option<&ScreenRect>) {
+        if let Err(error) = self.place(bounds) {
+            console::warn_2(&"Couldn't place the focus ring".into(), &error);
+        }
+    }
+}
+
+impl Overlay for FocusRing {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            self.show_at(archizoom.borrow().focused_link_bounds().as_ref());
+        }
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        self.show_at(None);
+    }
+
+    fn notify(&self, event: &ViewerEvent) {
+        if let ViewerEvent::LinkFocused(bounds) = event {
+            self.show_at(*bounds);
+        }
+    }
+}
+
+impl Drop for FocusRing {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
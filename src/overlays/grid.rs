@@ -0,0 +1,315 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, CanvasRenderingContext2d, Element, HtmlCanvasElement};
+
+use crate::diagnostics;
+use crate::js_utils::*;
+use crate::overlays::Overlay;
+use crate::zoom::matrix::{approx_eq, Matrix2D, Point2D, Rect};
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::{raster_size, ClientRect, ClientSpace, SvgUserSpace, Transform};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GridOptions {
+    pub enabled: bool,
+    /// Show rulers with Svg User Coordinates along the top and left edges
+    pub rulers: bool,
+    /// The closest grid lines can get in CSS pixels before the spacing steps up
+    pub min_spacing: f32,
+}
+
+impl Default for GridOptions {
+    fn default() -> GridOptions {
+        GridOptions {
+            enabled: false,
+            rulers: true,
+            min_spacing: 40.0,
+        }
+    }
+}
+
+/// A grid in Svg User Coordinates drawn over the diagram. The spacing steps through 1, 2, 5,
+/// 10, 20, ... user units so the lines stay readable at any zoom level.
+pub struct Grid {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    container: Element,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    options: GridOptions,
+}
+
+/// The custom properties we're themed with and their fallbacks
+static GRID_COLOR: (&str, &str) = ("--archizoom-grid-color", "rgba(0, 0, 0, 0.08)");
+static RULER_BACKGROUND: (&str, &str) =
+    ("--archizoom-ruler-background", "rgba(255, 255, 255, 0.85)");
+static RULER_COLOR: (&str, &str) = ("--archizoom-ruler-color", "rgba(0, 0, 0, 0.6)");
+static RULER_FONT: (&str, &str) = ("--archizoom-ruler-font", "10px sans-serif");
+static RULER_SIZE: f32 = 18.0;
+static TICK_SIZE: f32 = 5.0;
+
+impl Grid {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &GridOptions,
+    ) -> Result<Grid, JsValue> {
+        let canvas = document()
+            .safe_create_element::<HtmlCanvasElement>("canvas")
+            .ok_or_else(|| JsValue::from("Couldn't create the grid"))?;
+        canvas.set_class_name("archizoom-grid");
+
+        let style = canvas.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("top", "0")?;
+        style.set_property("left", "0")?;
+        style.set_property("width", "100%")?;
+        style.set_property("height", "100%")?;
+        style.set_property("pointer-events", "none")?;
+
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from("Canvas 2d isn't supported"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        container.append_child(&canvas)?;
+
+        let grid = Grid {
+            canvas,
+            context,
+            container: container.clone(),
+            archizoom: Rc::downgrade(archizoom),
+            options: options.clone(),
+        };
+        grid.redraw();
+
+        Ok(grid)
+    }
+
+    fn redraw(&self) {
+        if let Err(error) = self.draw() {
+            console::warn_2(&"Couldn't draw the grid".into(), &error);
+        }
+    }
+
+    fn draw(&self) -> Result<(), JsValue> {
+        let archizoom = match self.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return Ok(()),
+        };
+
+        let to_client = match (
+            archizoom.borrow().screen_ctm(),
+            Transform::client_to_screen(&self.container).inverse(),
+        ) {
+            (Some(to_screen), Some(to_client)) => to_screen.then(&to_client),
+            _ => return Ok(()),
+        };
+
+        diagnostics::layout_read();
+        let client_rect = self.container.get_bounding_client_rect();
+        let client_bounds = ClientRect::new(Rect::new(
+            Point2D::new(0.0, 0.0),
+            Point2D::new(client_rect.width() as f32, client_rect.height() as f32),
+        ));
+
+        // keep the canvas backed by whole device pixels so the lines stay crisp
+        let (raster_width, raster_height) = raster_size(&client_bounds.size());
+        if self.canvas.width() != raster_width || self.canvas.height() != raster_height {
+            self.canvas.set_width(raster_width);
+            self.canvas.set_height(raster_height);
+        }
+
+        let ratio = device_pixel_ratio() as f64;
+        let context = &self.context;
+        context.set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0)?;
+        context.clear_rect(
+            0.0,
+            0.0,
+            client_bounds.width() as f64,
+            client_bounds.height() as f64,
+        );
+
+        let to_user = match to_client.inverse() {
+            Some(to_user) => to_user,
+            None => return Ok(()),
+        };
+        let visible = to_user.rect(&client_bounds);
+        let step = grid_step(
+            to_client.matrix().decompose().scale(),
+            self.options.min_spacing,
+        );
+
+        let xs = grid_lines(visible.left(), visible.right(), step);
+        let ys = grid_lines(visible.top(), visible.bottom(), step);
+
+        context.set_stroke_style_str(&self.theme(GRID_COLOR));
+        context.set_line_width(1.0);
+        context.begin_path();
+        for x in xs.iter() {
+            line(
+                context,
+                &to_client,
+                Point2D::new(*x, visible.top()),
+                Point2D::new(*x, visible.bottom()),
+            );
+        }
+        for y in ys.iter() {
+            line(
+                context,
+                &to_client,
+                Point2D::new(visible.left(), *y),
+                Point2D::new(visible.right(), *y),
+            );
+        }
+        context.stroke();
+
+        if self.options.rulers {
+            self.draw_rulers(&to_client, &client_bounds, &xs, &ys, step)?;
+        }
+
+        Ok(())
+    }
+
+    fn theme(&self, (name, fallback): (&str, &str)) -> String {
+        css_variable(&self.container, name).unwrap_or_else(|| fallback.to_string())
+    }
+
+    fn draw_rulers(
+        &self,
+        to_client: &Transform<SvgUserSpace, ClientSpace>,
+        client_bounds: &ClientRect,
+        xs: &[f32],
+        ys: &[f32],
+        step: f32,
+    ) -> Result<(), JsValue> {
+        let context = &self.context;
+        let width = client_bounds.width() as f64;
+        let height = client_bounds.height() as f64;
+        let ruler = RULER_SIZE as f64;
+
+        context.set_fill_style_str(&self.theme(RULER_BACKGROUND));
+        context.fill_rect(0.0, 0.0, width, ruler);
+        context.fill_rect(0.0, ruler, ruler, height - ruler);
+
+        let ruler_color = self.theme(RULER_COLOR);
+        context.set_fill_style_str(&ruler_color);
+        context.set_stroke_style_str(&ruler_color);
+        context.set_font(&self.theme(RULER_FONT));
+        context.begin_path();
+
+        for x in xs.iter() {
+            let client_x = axis_position(to_client.matrix(), *x, true);
+            if let Some(client_x) = client_x.filter(|client_x| *client_x > ruler) {
+                context.move_to(client_x, ruler - TICK_SIZE as f64);
+                context.line_to(client_x, ruler);
+                context.fill_text(
+                    &label(*x, step),
+                    client_x + 2.0,
+                    ruler - TICK_SIZE as f64 - 2.0,
+                )?;
+            }
+        }
+
+        for y in ys.iter() {
+            let client_y = axis_position(to_client.matrix(), *y, false);
+            if let Some(client_y) = client_y.filter(|client_y| *client_y > ruler) {
+                context.move_to(ruler - TICK_SIZE as f64, client_y);
+                context.line_to(ruler, client_y);
+
+                // run the label up the ruler
+                context.save();
+                context.translate(ruler - TICK_SIZE as f64 - 2.0, client_y - 2.0)?;
+                context.rotate(-std::f64::consts::FRAC_PI_2)?;
+                context.fill_text(&label(*y, step), 0.0, 0.0)?;
+                context.restore();
+            }
+        }
+
+        context.stroke();
+
+        Ok(())
+    }
+}
+
+impl Overlay for Grid {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        self.redraw();
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        self.redraw();
+    }
+}
+
+impl Drop for Grid {
+    fn drop(&mut self) {
+        self.canvas.remove();
+    }
+}
+
+/// The smallest of 1, 2, or 5 times a power of 10 user units that's at least `min_spacing`
+/// pixels apart at `scale` pixels per user unit
+fn grid_step(scale: f32, min_spacing: f32) -> f32 {
+    let raw = min_spacing / scale;
+    let magnitude = 10f32.powf(raw.log10().floor());
+
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|multiple| multiple * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// The multiples of `step` between `start` and `end`
+fn grid_lines(start: f32, end: f32, step: f32) -> Vec<f32> {
+    if !step.is_finite() || step <= 0.0 {
+        return vec![];
+    }
+
+    let first = (start / step).ceil() as i64;
+    let last = (end / step).floor() as i64;
+
+    (first..=last).map(|i| i as f32 * step).collect()
+}
+
+fn line(
+    context: &CanvasRenderingContext2d,
+    to_client: &Transform<SvgUserSpace, ClientSpace>,
+    from: Point2D,
+    to: Point2D,
+) {
+    let from = from.matrix_transform(to_client.matrix());
+    let to = to.matrix_transform(to_client.matrix());
+
+    context.move_to(from.x as f64, from.y as f64);
+    context.line_to(to.x as f64, to.y as f64);
+}
+
+/// Where user space `value` lands along the horizontal or vertical client axis, `None` if the
+/// diagram is rotated since the rulers wouldn't line up with the grid
+fn axis_position(matrix: &Matrix2D, value: f32, horizontal: bool) -> Option<f64> {
+    if !approx_eq(matrix.b, 0.0) || !approx_eq(matrix.c, 0.0) {
+        return None;
+    }
+
+    let position = if horizontal {
+        value * matrix.a + matrix.e
+    } else {
+        value * matrix.d + matrix.f
+    };
+
+    Some(position as f64)
+}
+
+/// Formats a coordinate with just enough decimals to tell steps apart
+fn label(value: f32, step: f32) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+
+    format!("{:.*}", decimals, value)
+}
@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, PointerEvent, SvgElement, SvgsvgElement};
+
+use crate::js_utils::*;
+use crate::overlays::Overlay;
+use crate::zoom::metadata::ElementInfo;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::ScreenPoint;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HoverHighlightOptions {
+    /// Off by default so diagrams look the way they were drawn until a page asks for it
+    pub enabled: bool,
+    /// Which elements can be highlighted, the smallest one under the pointer wins
+    pub selector: String,
+    /// Deprecated, set `--archizoom-hover-filter` on the container instead. When it's set it
+    /// overrides that property.
+    pub filter: Option<String>,
+}
+
+impl Default for HoverHighlightOptions {
+    fn default() -> HoverHighlightOptions {
+        HoverHighlightOptions {
+            enabled: false,
+            selector: "g[id], a".to_string(),
+            filter: None,
+        }
+    }
+}
+
+/// Highlights the shape or group under the pointer and tells JS about it with an
+/// `archizoom-hover` event on the container
+pub struct HoverHighlight {
+    highlight: Rc<Highlight>,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+}
+
+struct Highlight {
+    container: Element,
+    svg: RefCell<SvgsvgElement>,
+    options: HoverHighlightOptions,
+    hovered: RefCell<Option<SvgElement>>,
+}
+
+#[derive(Debug, Serialize)]
+struct HoverDetail {
+    /// The id of the hovered element, `null` once nothing is hovered
+    id: Option<String>,
+    element: Option<ElementInfo>,
+}
+
+static HOVER_CLASS: &str = "archizoom-hover";
+static HOVER_EVENT: &str = "archizoom-hover";
+
+impl HoverHighlight {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &HoverHighlightOptions,
+    ) -> Result<HoverHighlight, JsValue> {
+        let hover_highlight = HoverHighlight {
+            highlight: Rc::new(Highlight {
+                container: container.clone(),
+                svg: RefCell::new(archizoom.borrow().svg().clone()),
+                options: options.clone(),
+                hovered: RefCell::new(None),
+            }),
+            archizoom: Rc::downgrade(archizoom),
+            listeners: RefCell::new(vec![]),
+        };
+        hover_highlight.bind()?;
+
+        Ok(hover_highlight)
+    }
+
+    /// Listens for hovering over the current svg
+    fn bind(&self) -> Result<(), JsValue> {
+        let svg = self.highlight.svg.borrow();
+
+        let move_highlight = self.highlight.clone();
+        let leave_highlight = self.highlight.clone();
+        let archizoom = self.archizoom.clone();
+
+        *self.listeners.borrow_mut() = vec![
+            svg.new_event_listener("pointermove", move |event: PointerEvent| {
+                let position = ScreenPoint::point(event.client_x() as f32, event.client_y() as f32);
+                // found through the diagram's quadtree, and let go of before the page hears
+                // about it
+                let target = archizoom.upgrade().and_then(|archizoom| {
+                    archizoom
+                        .borrow()
+                        .element_at(&position, &move_highlight.options.selector)
+                });
+
+                move_highlight.hover(target.map(SvgElement::from));
+            })?,
+            svg.new_event_listener("pointerleave", move |_: PointerEvent| {
+                leave_highlight.hover(None);
+            })?,
+        ];
+
+        Ok(())
+    }
+}
+
+impl Highlight {
+    fn hover(&self, target: Option<SvgElement>) {
+        let unchanged = match (&*self.hovered.borrow(), &target) {
+            (Some(hovered), Some(target)) => hovered == target,
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+
+        if let Err(error) = self.highlight(target) {
+            console::warn_2(&"Couldn't highlight the hovered element".into(), &error);
+        }
+    }
+
+    fn highlight(&self, target: Option<SvgElement>) -> Result<(), JsValue> {
+        if let Some(previous) = self.hovered.replace(None) {
+            previous.class_list().remove_1(HOVER_CLASS)?;
+        }
+
+        // the stylesheet does the highlighting, see --archizoom-hover-filter
+        let detail = match target {
+            Some(target) => {
+                target.class_list().add_1(HOVER_CLASS)?;
+
+                let detail = HoverDetail {
+                    id: Some(target.id()).filter(|id| !id.is_empty()),
+                    element: Some(ElementInfo::read(&target)),
+                };
+                self.hovered.replace(Some(target));
+
+                detail
+            }
+            None => HoverDetail {
+                id: None,
+                element: None,
+            },
+        };
+
+        dispatch_custom_event(&self.container, HOVER_EVENT, &detail)
+    }
+}
+
+impl Overlay for HoverHighlight {
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        self.highlight.hover(None);
+
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            self.highlight.svg.replace(archizoom.borrow().svg().clone());
+
+            if let Err(error) = self.bind() {
+                console::error_2(&"Couldn't listen for hovering".into(), &error);
+            }
+        }
+    }
+}
+
+impl Drop for HoverHighlight {
+    fn drop(&mut self) {
+        self.highlight.hover(None);
+    }
+}
@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, Event, HtmlElement, HtmlInputElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, OverlayPosition, ViewerEvent};
+use crate::strings::Strings;
+use crate::zoom::layers::LayerInfo;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LayerListOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+}
+
+impl Default for LayerListOptions {
+    fn default() -> LayerListOptions {
+        LayerListOptions {
+            enabled: false,
+            position: OverlayPosition::BottomLeft,
+        }
+    }
+}
+
+/// A checkbox for each layer of the current diagram
+pub struct LayerList {
+    element: HtmlElement,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+    legend: String,
+}
+
+impl LayerList {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &LayerListOptions,
+        strings: &Strings,
+    ) -> Result<LayerList, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("fieldset")
+            .ok_or_else(|| JsValue::from("Couldn't create the layer list"))?;
+        element.set_class_name("archizoom-layers");
+        options.position.apply(&element.style())?;
+
+        container.append_child(&element)?;
+
+        let layer_list = LayerList {
+            element,
+            archizoom: Rc::downgrade(archizoom),
+            listeners: RefCell::new(vec![]),
+            legend: strings.layers.clone(),
+        };
+        layer_list.render(&archizoom.borrow().layers())?;
+
+        Ok(layer_list)
+    }
+
+    fn render(&self, layers: &[LayerInfo]) -> Result<(), JsValue> {
+        self.listeners.borrow_mut().clear();
+        self.element.set_inner_html("");
+
+        // a single layer isn't worth toggling
+        self.element
+            .style()
+            .set_property("display", if layers.len() > 1 { "block" } else { "none" })?;
+
+        let legend = document()
+            .safe_create_element::<HtmlElement>("legend")
+            .ok_or_else(|| JsValue::from("Couldn't create the layer list legend"))?;
+        legend.set_text_content(Some(&self.legend));
+        self.element.append_child(&legend)?;
+
+        for layer in layers.iter() {
+            let label = document()
+                .safe_create_element::<HtmlElement>("label")
+                .ok_or_else(|| JsValue::from("Couldn't create a layer label"))?;
+            label.set_class_name("archizoom-layer");
+            label.style().set_property("display", "block")?;
+
+            let checkbox = document()
+                .safe_create_element::<HtmlInputElement>("input")
+                .ok_or_else(|| JsValue::from("Couldn't create a layer checkbox"))?;
+            checkbox.set_type("checkbox");
+            checkbox.set_checked(layer.visible);
+
+            let weak_ref = self.archizoom.clone();
+            let name = layer.name.clone();
+            let changed_checkbox = checkbox.clone();
+            self.listeners
+                .borrow_mut()
+                .push(checkbox.new_event_listener("change", move |event: Event| {
+                    event.stop_propagation();
+
+                    if let Some(archizoom) = weak_ref.upgrade() {
+                        archizoom
+                            .borrow()
+                            .set_layer_visible(&name, changed_checkbox.checked());
+                    }
+                })?);
+
+            label.append_child(&checkbox)?;
+            label.append_with_str_1(&layer.name)?;
+            self.element.append_child(&label)?;
+        }
+
+        Ok(())
+    }
+
+    fn rerender(&self, layers: &[LayerInfo]) {
+        if let Err(error) = self.render(layers) {
+            console::error_2(&"Couldn't render the layer list".into(), &error);
+        }
+    }
+}
+
+impl Overlay for LayerList {
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            self.rerender(&archizoom.borrow().layers());
+        }
+    }
+
+    fn notify(&self, event: &ViewerEvent) {
+        if let ViewerEvent::LayersChanged(layers) = event {
+            self.rerender(layers);
+        }
+    }
+}
+
+impl Drop for LayerList {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
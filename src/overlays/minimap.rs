@@ -0,0 +1,318 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, HtmlElement, PointerEvent, SvgElement, SvgsvgElement};
+
+use crate::events::{EventListener, EventSource};
+use crate::js_utils::*;
+use crate::leak_check::CountedListener;
+use crate::overlays::{Overlay, OverlayPosition};
+use crate::zoom::matrix::{Point2D, Rect, Size2D};
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::{ScreenPoint, Transform, UserPoint};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MinimapOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+    /// The width and height of the minimap in pixels
+    pub size: u32,
+}
+
+impl Default for MinimapOptions {
+    fn default() -> MinimapOptions {
+        MinimapOptions {
+            enabled: false,
+            position: OverlayPosition::BottomRight,
+            size: 150,
+        }
+    }
+}
+
+/// A scaled down copy of the diagram with a rectangle showing what the main view can see
+pub struct Minimap {
+    element: HtmlElement,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    content: RefCell<Option<MinimapContent>>,
+}
+
+/// The copy of the current diagram, rebuilt whenever we navigate
+struct MinimapContent {
+    svg: SvgsvgElement,
+    indicator: SvgElement,
+    _controller: Rc<RefCell<MinimapController>>,
+}
+
+/// Sent when the minimap wants the main view centered somewhere else
+#[derive(Debug)]
+pub struct MinimapPanEvent {
+    /// The new center in Svg User Coordinates
+    center: Point2D,
+}
+
+/// Turns pointer input on the minimap into pan requests for the main view
+pub struct MinimapController {
+    svg: SvgsvgElement,
+    indicator: SvgElement,
+    /// The offset from the pointer to the center of the indicator while dragging
+    drag_offset: Option<Point2D>,
+    listeners: Vec<Box<dyn EventListener<MinimapPanEvent>>>,
+    event_listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl Minimap {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &MinimapOptions,
+    ) -> Result<Minimap, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the minimap"))?;
+        element.set_class_name("archizoom-minimap");
+        options.position.apply(&element.style())?;
+        element
+            .style()
+            .set_property("width", &format!("{}px", options.size))?;
+        element
+            .style()
+            .set_property("height", &format!("{}px", options.size))?;
+
+        container.append_child(&element)?;
+
+        let minimap = Minimap {
+            element,
+            archizoom: Rc::downgrade(archizoom),
+            content: RefCell::new(None),
+        };
+        minimap.rebuild(&archizoom.borrow())?;
+
+        Ok(minimap)
+    }
+
+    /// Replaces the copy with one of the current diagram
+    fn rebuild(&self, archizoom: &ArchiZoom) -> Result<(), JsValue> {
+        if let Some(content) = self.content.borrow_mut().take() {
+            content.svg.remove();
+        }
+
+        // culled groups are shown while we copy so they're in the copy too
+        let (bounds, copy) = archizoom.unculled(|| {
+            (
+                archizoom.content_bounds(),
+                archizoom.svg().clone_without_ids(),
+            )
+        });
+        let svg = copy?
+            .dyn_into::<SvgsvgElement>()
+            .map_err(|_| JsValue::from("The minimap copy isn't an svg"))?;
+        svg.set_attribute("class", "archizoom-minimap-diagram")?;
+        svg.style().set_property("width", "100%")?;
+        svg.style().set_property("height", "100%")?;
+
+        // always show the whole drawing
+        if let Some(bounds) = bounds {
+            svg.set_attribute(
+                "viewBox",
+                &format!(
+                    "{} {} {} {}",
+                    bounds.left(),
+                    bounds.top(),
+                    bounds.width(),
+                    bounds.height()
+                ),
+            )?;
+        }
+
+        let indicator = document()
+            .safe_create_element_ns::<SvgElement>(Some(SVG_NS), "rect")
+            .ok_or_else(|| JsValue::from("Couldn't create the minimap indicator"))?;
+        indicator.set_attribute("class", "archizoom-minimap-viewport")?;
+        indicator.set_attribute("vector-effect", "non-scaling-stroke")?;
+        svg.append_child(&indicator)?;
+
+        self.element.append_child(&svg)?;
+
+        let controller = MinimapController::new(&svg, &indicator)?;
+
+        let weak_ref = self.archizoom.clone();
+        controller
+            .borrow_mut()
+            .register_listener(move |event: &MinimapPanEvent| {
+                if let Some(archizoom) = weak_ref.upgrade() {
+                    archizoom.borrow().center_on(&event.center);
+                }
+            });
+
+        self.content.replace(Some(MinimapContent {
+            svg,
+            indicator,
+            _controller: controller,
+        }));
+        self.update_indicator();
+
+        Ok(())
+    }
+
+    fn update_indicator(&self) {
+        let viewport = self
+            .archizoom
+            .upgrade()
+            .and_then(|archizoom| archizoom.borrow().viewport());
+
+        if let (Some(viewport), Some(content)) = (viewport, self.content.borrow().as_ref()) {
+            let view_box = viewport.view_box();
+            let indicator = &content.indicator;
+
+            let _ = indicator.set_attribute("x", &view_box.left().to_string());
+            let _ = indicator.set_attribute("y", &view_box.top().to_string());
+            let _ = indicator.set_attribute("width", &view_box.width().to_string());
+            let _ = indicator.set_attribute("height", &view_box.height().to_string());
+        }
+    }
+}
+
+impl Overlay for Minimap {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        self.update_indicator();
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            if let Err(error) = self.rebuild(&archizoom.borrow()) {
+                console::error_2(&"Couldn't rebuild the minimap".into(), &error);
+            }
+        }
+    }
+}
+
+impl Drop for Minimap {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
+
+impl MinimapController {
+    fn new(
+        svg: &SvgsvgElement,
+        indicator: &SvgElement,
+    ) -> Result<Rc<RefCell<MinimapController>>, JsValue> {
+        let controller = Rc::new(RefCell::new(MinimapController {
+            svg: svg.clone(),
+            indicator: indicator.clone(),
+            drag_offset: None,
+            listeners: vec![],
+            event_listeners: vec![],
+        }));
+
+        let mut events = vec![
+            add_minimap_event(&controller, "pointerdown", |controller, event| {
+                controller.borrow_mut().on_pointer_down(event)
+            })?,
+            add_minimap_event(&controller, "pointermove", |controller, event| {
+                controller.borrow().on_pointer_move(event)
+            })?,
+            add_minimap_event(&controller, "pointerup", |controller, _| {
+                controller.borrow_mut().drag_offset = None
+            })?,
+            add_minimap_event(&controller, "pointercancel", |controller, _| {
+                controller.borrow_mut().drag_offset = None
+            })?,
+        ];
+
+        controller.borrow_mut().event_listeners.append(&mut events);
+
+        Ok(controller)
+    }
+
+    fn on_pointer_down(&mut self, event: PointerEvent) {
+        event.prevent_default();
+        event.stop_propagation();
+
+        if let Some(point) = self.get_point(&event) {
+            let indicator = self.indicator_rect();
+
+            // grabbing the indicator keeps the grab point under the pointer, clicking anywhere
+            // else jumps there first
+            self.drag_offset = match indicator {
+                Some(ref rect) if rect.contains(&point) => Some(&rect.center() - &point),
+                _ => {
+                    self.dispatch_event(point.clone());
+                    Some(Point2D::new(0.0, 0.0))
+                }
+            };
+
+            let _ = self.svg.set_pointer_capture(event.pointer_id());
+        }
+    }
+
+    fn on_pointer_move(&self, event: PointerEvent) {
+        if let Some(ref offset) = self.drag_offset {
+            event.prevent_default();
+            event.stop_propagation();
+
+            if let Some(point) = self.get_point(&event) {
+                self.dispatch_event(&point + offset);
+            }
+        }
+    }
+
+    fn get_point(&self, event: &PointerEvent) -> Option<Point2D> {
+        let position = ScreenPoint::point(event.client_x() as f32, event.client_y() as f32);
+
+        Transform::screen_ctm(&self.svg)
+            .and_then(|to_screen| to_screen.inverse())
+            .map(|to_user| UserPoint::into_inner(to_user.point(&position)))
+    }
+
+    fn indicator_rect(&self) -> Option<Rect> {
+        let attribute = |name: &str| {
+            self.indicator
+                .get_attribute(name)
+                .and_then(|value| value.parse::<f32>().ok())
+        };
+
+        Some(Rect::from_origin(
+            Point2D::new(attribute("x")?, attribute("y")?),
+            &Size2D::new(attribute("width")?, attribute("height")?),
+        ))
+    }
+
+    fn dispatch_event(&self, center: Point2D) {
+        let event = MinimapPanEvent { center };
+
+        for listener in self.listeners.iter() {
+            listener.receive(&event);
+        }
+    }
+}
+
+impl EventSource<MinimapPanEvent> for MinimapController {
+    fn register_listener<T: EventListener<MinimapPanEvent> + 'static>(&mut self, callback: T) {
+        self.listeners
+            .push(Box::new(CountedListener::new(callback)));
+    }
+}
+
+fn add_minimap_event<C>(
+    controller_ref: &Rc<RefCell<MinimapController>>,
+    event_type: &str,
+    callback: C,
+) -> Result<Box<dyn JsEventListener>, JsValue>
+where
+    C: Fn(Rc<RefCell<MinimapController>>, PointerEvent) + 'static,
+{
+    let svg = &controller_ref.borrow().svg;
+
+    let weak_ref = Rc::downgrade(controller_ref);
+    svg.new_event_listener(event_type, move |event: PointerEvent| {
+        if let Some(real_ref) = weak_ref.upgrade() {
+            callback(real_ref, event)
+        }
+    })
+}
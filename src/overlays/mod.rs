@@ -0,0 +1,264 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{CssStyleDeclaration, Element};
+
+use crate::options::Options;
+#[cfg(feature = "annotations")]
+use crate::zoom::annotations::AnnotationSet;
+use crate::zoom::diff::Diff;
+use crate::zoom::layers::LayerInfo;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::pins::Pin;
+#[cfg(feature = "search")]
+use crate::zoom::search::SearchResults;
+use crate::zoom::space::ScreenRect;
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+pub mod activation_veil;
+#[cfg(feature = "annotations")]
+pub mod annotations;
+pub mod announcer;
+pub mod breadcrumbs;
+#[cfg(feature = "canvas")]
+pub mod canvas_renderer;
+pub mod context_menu;
+pub mod controls;
+pub mod crosshair;
+pub mod diff_legend;
+pub mod focus_ring;
+pub mod grid;
+pub mod hover_highlight;
+pub mod layer_list;
+#[cfg(feature = "minimap")]
+pub mod minimap;
+pub mod pins;
+#[cfg(feature = "search")]
+pub mod search_box;
+pub mod title_bar;
+pub mod tooltips;
+pub mod zoom_indicator;
+pub mod zoom_slider;
+
+/// UI layered on top of the svg inside of the container
+pub trait Overlay {
+    fn view_update(&self, _event: &ViewUpdateEvent) {}
+
+    /// A new diagram is showing, `path` is the drill-down path ending with it
+    fn navigated(&self, _path: &[NavigationEntry]) {}
+
+    /// Something changed that only some overlays follow
+    fn notify(&self, _event: &ViewerEvent) {}
+}
+
+/// What `Overlay::notify` tells overlays about
+pub enum ViewerEvent<'a> {
+    #[cfg(feature = "annotations")]
+    AnnotationsChanged(&'a AnnotationSet),
+    #[cfg(feature = "search")]
+    SearchChanged(Option<&'a SearchResults>),
+    /// The search key was pressed
+    #[cfg(feature = "search")]
+    SearchRequested,
+    LayersChanged(&'a [LayerInfo]),
+    DiffChanged(Option<&'a Diff>),
+    /// The pins from `setPins` or `loadPins` were replaced
+    PinsChanged(&'a [Pin]),
+    /// The view link made it to the clipboard
+    ViewLinkCopied,
+    /// Tab moved to a zoom element, this is where it is on screen
+    LinkFocused(Option<&'a ScreenRect>),
+}
+
+/// Which corner of the container an overlay sits in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+static OVERLAY_INSET: &str = "var(--archizoom-overlay-inset, 8px)";
+
+impl OverlayPosition {
+    pub fn apply(&self, style: &CssStyleDeclaration) -> Result<(), JsValue> {
+        let (vertical, horizontal) = match *self {
+            OverlayPosition::TopLeft => ("top", "left"),
+            OverlayPosition::TopRight => ("top", "right"),
+            OverlayPosition::BottomLeft => ("bottom", "left"),
+            OverlayPosition::BottomRight => ("bottom", "right"),
+        };
+
+        style.set_property("position", "absolute")?;
+        style.set_property(vertical, OVERLAY_INSET)?;
+        style.set_property(horizontal, OVERLAY_INSET)?;
+
+        Ok(())
+    }
+}
+
+/// Creates all of the overlays enabled in `options`
+pub fn create_overlays(
+    archizoom: &Rc<RefCell<ArchiZoom>>,
+    container: &Element,
+    options: &Options,
+) -> Result<Vec<Box<dyn Overlay>>, JsValue> {
+    let mut overlays: Vec<Box<dyn Overlay>> = vec![];
+
+    // the canvas stands in for the diagram itself, so it goes under everything
+    #[cfg(feature = "canvas")]
+    if options.canvas.enabled {
+        overlays.push(Box::new(canvas_renderer::CanvasRenderer::new(
+            archizoom,
+            container,
+            &options.canvas,
+        )?));
+    }
+
+    // the grid sits right on top of the diagram, under everything else
+    if options.grid.enabled {
+        overlays.push(Box::new(grid::Grid::new(
+            archizoom,
+            container,
+            &options.grid,
+        )?));
+    }
+
+    if options.announcer.enabled {
+        overlays.push(Box::new(announcer::Announcer::new(
+            container,
+            &options.announcer,
+            &options.strings,
+        )?));
+    }
+
+    #[cfg(feature = "annotations")]
+    overlays.push(Box::new(annotations::Annotations::new(
+        archizoom, container,
+    )?));
+
+    overlays.push(Box::new(pins::Pins::new(archizoom, container)?));
+
+    if options.focus_ring.enabled {
+        overlays.push(Box::new(focus_ring::FocusRing::new(archizoom, container)?));
+    }
+
+    if options.title_bar.enabled {
+        overlays.push(Box::new(title_bar::TitleBar::new(
+            archizoom,
+            container,
+            &options.title_bar,
+        )?));
+    }
+
+    #[cfg(feature = "search")]
+    if options.search_box.enabled {
+        overlays.push(Box::new(search_box::SearchBox::new(
+            archizoom,
+            container,
+            &options.search_box,
+            &options.strings,
+        )?));
+    }
+
+    if options.diff_legend.enabled {
+        overlays.push(Box::new(diff_legend::DiffLegend::new(
+            archizoom,
+            container,
+            &options.diff_legend,
+            &options.strings,
+        )?));
+    }
+
+    if options.controls.enabled {
+        overlays.push(Box::new(controls::Controls::new(
+            archizoom,
+            container,
+            &options.controls,
+            &options.strings,
+        )?));
+    }
+
+    if options.breadcrumbs.enabled {
+        overlays.push(Box::new(breadcrumbs::Breadcrumbs::new(
+            archizoom,
+            container,
+            &options.breadcrumbs,
+        )?));
+    }
+
+    if options.context_menu.enabled {
+        overlays.push(Box::new(context_menu::ContextMenu::new(
+            archizoom,
+            container,
+            &options.context_menu,
+        )?));
+    }
+
+    if options.hover_highlight.enabled {
+        overlays.push(Box::new(hover_highlight::HoverHighlight::new(
+            archizoom,
+            container,
+            &options.hover_highlight,
+        )?));
+    }
+
+    if options.tooltips.enabled {
+        overlays.push(Box::new(tooltips::Tooltips::new(
+            archizoom,
+            container,
+            &options.tooltips,
+        )?));
+    }
+
+    if options.layer_list.enabled {
+        overlays.push(Box::new(layer_list::LayerList::new(
+            archizoom,
+            container,
+            &options.layer_list,
+            &options.strings,
+        )?));
+    }
+
+    if options.zoom_indicator.enabled {
+        overlays.push(Box::new(zoom_indicator::ZoomIndicator::new(
+            archizoom,
+            container,
+            &options.zoom_indicator,
+            &options.strings,
+        )?));
+    }
+
+    if options.zoom_slider.enabled {
+        overlays.push(Box::new(zoom_slider::ZoomSlider::new(
+            archizoom,
+            container,
+            &options.zoom_slider,
+            &options.strings,
+        )?));
+    }
+
+    #[cfg(feature = "minimap")]
+    if options.minimap.enabled {
+        overlays.push(Box::new(minimap::Minimap::new(
+            archizoom,
+            container,
+            &options.minimap,
+        )?));
+    }
+
+    // the veil goes over everything, the first click anywhere only wakes the viewer up
+    if options.click_to_activate.enabled {
+        overlays.push(Box::new(activation_veil::ActivationVeil::new(
+            container,
+            &options.strings,
+        )?));
+    }
+
+    Ok(overlays)
+}
@@ -0,0 +1,181 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, HtmlElement, MouseEvent};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, ViewerEvent};
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::pins::Pin;
+use crate::zoom::space::{Transform, UserPoint};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+/// Draws the pins that go on the current diagram, following the diagram around at the same
+/// size on screen. Clicking one calls the handler set with `setPinClickHandler`.
+pub struct Pins {
+    layer: HtmlElement,
+    container: Element,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    /// The pins on screen and where they point, found once per diagram and set of pins since
+    /// finding elements means measuring them
+    placed: RefCell<Vec<(HtmlElement, UserPoint)>>,
+    _listener: Box<dyn JsEventListener>,
+}
+
+impl Pins {
+    pub fn new(archizoom: &Rc<RefCell<ArchiZoom>>, container: &Element) -> Result<Pins, JsValue> {
+        let layer = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the pin layer"))?;
+        layer.set_class_name("archizoom-pins");
+
+        let style = layer.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("top", "0")?;
+        style.set_property("left", "0")?;
+        style.set_property("width", "100%")?;
+        style.set_property("height", "100%")?;
+        style.set_property("overflow", "hidden")?;
+        style.set_property("pointer-events", "none")?;
+
+        let weak_ref = Rc::downgrade(archizoom);
+        let listener = layer.new_event_listener("click", move |event: MouseEvent| {
+            let index = event
+                .target()
+                .and_then(|target| target.dyn_into::<Element>().ok())
+                .and_then(|target| target.closest("[data-pin-index]").ok().flatten())
+                .and_then(|pin| pin.get_attribute("data-pin-index"))
+                .and_then(|index| index.parse::<usize>().ok());
+
+            if let (Some(index), Some(archizoom)) = (index, weak_ref.upgrade()) {
+                // the click is for the pin, not the diagram under it
+                event.stop_propagation();
+                archizoom.borrow().pin_clicked(index, &event);
+            }
+        })?;
+
+        container.append_child(&layer)?;
+
+        Ok(Pins {
+            layer,
+            container: container.clone(),
+            archizoom: Rc::downgrade(archizoom),
+            placed: RefCell::new(vec![]),
+            _listener: listener,
+        })
+    }
+
+    fn replace_pins(&self, pins: &[Pin]) {
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            let result = self
+                .create_pins(&archizoom.borrow(), pins)
+                .and_then(|_| self.reposition(&archizoom.borrow()));
+
+            if let Err(error) = result {
+                console::warn_2(&"Couldn't draw the pins".into(), &error);
+            }
+        }
+    }
+
+    fn create_pins(&self, archizoom: &ArchiZoom, pins: &[Pin]) -> Result<(), JsValue> {
+        self.layer.set_inner_html("");
+        self.placed.borrow_mut().clear();
+
+        let url = match archizoom.navigation().last() {
+            Some(current) => current.url.clone(),
+            None => return Ok(()),
+        };
+
+        for (index, pin) in pins.iter().enumerate() {
+            let anchor = match pin.anchor(&url, archizoom.svg()) {
+                Some(anchor) => anchor,
+                None => continue,
+            };
+
+            let element = document()
+                .safe_create_element::<HtmlElement>("div")
+                .ok_or_else(|| JsValue::from("Couldn't create a pin"))?;
+            element.set_class_name("archizoom-pin");
+            if let Some(status) = pin.status_class() {
+                element.class_list().add_1(&status)?;
+            }
+            element.set_attribute("data-pin-index", &index.to_string())?;
+            if let Some(ref id) = pin.id {
+                element.set_attribute("data-pin-id", id)?;
+            }
+            if let Some(ref label) = pin.label {
+                element.set_text_content(Some(label));
+                element.set_title(label);
+            }
+
+            // the bottom middle of the pin points at its spot
+            let style = element.style();
+            style.set_property("position", "absolute")?;
+            style.set_property("transform", "translate(-50%, -100%)")?;
+            style.set_property("white-space", "nowrap")?;
+            style.set_property("pointer-events", "auto")?;
+            style.set_property("cursor", "pointer")?;
+            if let Some(ref color) = pin.color {
+                style.set_property("--archizoom-pin-color", color)?;
+            }
+
+            self.layer.append_child(&element)?;
+            self.placed
+                .borrow_mut()
+                .push((element, UserPoint::point(anchor.x, anchor.y)));
+        }
+
+        Ok(())
+    }
+
+    fn reposition(&self, archizoom: &ArchiZoom) -> Result<(), JsValue> {
+        let to_client = match (
+            archizoom.screen_ctm(),
+            Transform::client_to_screen(&self.container).inverse(),
+        ) {
+            (Some(to_screen), Some(to_client)) => to_screen.then(&to_client),
+            _ => return Ok(()),
+        };
+
+        for (element, anchor) in self.placed.borrow().iter() {
+            let position = to_client.point(anchor);
+
+            let style = element.style();
+            style.set_property("left", &format!("{}px", position.x))?;
+            style.set_property("top", &format!("{}px", position.y))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Overlay for Pins {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            if let Err(error) = self.reposition(&archizoom.borrow()) {
+                console::warn_2(&"Couldn't move the pins".into(), &error);
+            }
+        }
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            let pins = archizoom.borrow().pins().clone();
+            self.replace_pins(&pins);
+        }
+    }
+
+    fn notify(&self, event: &ViewerEvent) {
+        if let ViewerEvent::PinsChanged(pins) = event {
+            self.replace_pins(pins);
+        }
+    }
+}
+
+impl Drop for Pins {
+    fn drop(&mut self) {
+        self.layer.remove();
+    }
+}
@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, Event, HtmlElement, HtmlInputElement, KeyboardEvent};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, OverlayPosition, ViewerEvent};
+use crate::strings::Strings;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::search::SearchResults;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchBoxOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+}
+
+impl Default for SearchBoxOptions {
+    fn default() -> SearchBoxOptions {
+        SearchBoxOptions {
+            enabled: false,
+            position: OverlayPosition::TopLeft,
+        }
+    }
+}
+
+/// A search field with previous/next buttons and a "2 of 5" count
+pub struct SearchBox {
+    element: HtmlElement,
+    input: HtmlInputElement,
+    count: HtmlElement,
+    no_matches: String,
+    match_count: String,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl SearchBox {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &SearchBoxOptions,
+        strings: &Strings,
+    ) -> Result<SearchBox, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the search box"))?;
+        element.set_class_name("archizoom-search");
+        element.set_attribute("role", "search")?;
+        options.position.apply(&element.style())?;
+
+        let input = document()
+            .safe_create_element::<HtmlInputElement>("input")
+            .ok_or_else(|| JsValue::from("Couldn't create the search field"))?;
+        input.set_class_name("archizoom-search-input");
+        input.set_type("search");
+        input.set_placeholder(&strings.search);
+        input.set_attribute("aria-label", &strings.search_label)?;
+
+        let count = document()
+            .safe_create_element::<HtmlElement>("output")
+            .ok_or_else(|| JsValue::from("Couldn't create the search count"))?;
+        count.set_class_name("archizoom-search-count");
+
+        element.append_child(&input)?;
+
+        let mut listeners = vec![];
+
+        let weak_ref = Rc::downgrade(archizoom);
+        let changed_input = input.clone();
+        listeners.push(input.new_event_listener("input", move |_: Event| {
+            if let Some(archizoom) = weak_ref.upgrade() {
+                archizoom.borrow().search(&changed_input.value());
+            }
+        })?);
+
+        let weak_ref = Rc::downgrade(archizoom);
+        let key_input = input.clone();
+        listeners.push(
+            input.new_event_listener("keydown", move |event: KeyboardEvent| {
+                // keep our keys away from any shortcuts on the page
+                event.stop_propagation();
+
+                if let Some(archizoom) = weak_ref.upgrade() {
+                    match event.key().as_str() {
+                        "Enter" => {
+                            event.prevent_default();
+                            archizoom.borrow().search_step(!event.shift_key());
+                        }
+                        "Escape" => {
+                            key_input.set_value("");
+                            archizoom.borrow().clear_search();
+                        }
+                        _ => (),
+                    }
+                }
+            })?,
+        );
+
+        for (name, label, title, forward) in [
+            ("previous", "\u{2191}", &strings.previous_match, false),
+            ("next", "\u{2193}", &strings.next_match, true),
+        ]
+        .iter()
+        {
+            let button = document()
+                .safe_create_element::<HtmlElement>("button")
+                .ok_or_else(|| JsValue::from("Couldn't create a search button"))?;
+            button.set_class_name(&format!("archizoom-search-{}", name));
+            button.set_attribute("type", "button")?;
+            button.set_title(title);
+            button.set_text_content(Some(label));
+
+            let weak_ref = Rc::downgrade(archizoom);
+            let forward = *forward;
+            listeners.push(button.new_event_listener("click", move |event: Event| {
+                event.stop_propagation();
+
+                if let Some(archizoom) = weak_ref.upgrade() {
+                    archizoom.borrow().search_step(forward);
+                }
+            })?);
+
+            element.append_child(&button)?;
+        }
+
+        element.append_child(&count)?;
+        container.append_child(&element)?;
+
+        Ok(SearchBox {
+            element,
+            input,
+            count,
+            no_matches: strings.no_matches.clone(),
+            match_count: strings.match_count.clone(),
+            _listeners: listeners,
+        })
+    }
+
+    fn search_changed(&self, results: Option<&SearchResults>) {
+        let text = match results {
+            Some(results) if results.matches.is_empty() => self.no_matches.clone(),
+            Some(results) => Strings::fill(
+                &self.match_count,
+                &[
+                    (
+                        "current",
+                        &results
+                            .current
+                            .map(|current| current + 1)
+                            .unwrap_or(0)
+                            .to_string(),
+                    ),
+                    ("total", &results.matches.len().to_string()),
+                ],
+            ),
+            None => String::new(),
+        };
+
+        self.count.set_text_content(Some(&text));
+    }
+
+    fn search_requested(&self) {
+        if let Err(error) = self.input.focus() {
+            console::warn_2(&"Couldn't focus the search box".into(), &error);
+        }
+        self.input.select();
+    }
+}
+
+impl Overlay for SearchBox {
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        // the old matches went away with the old diagram
+        self.input.set_value("");
+        self.count.set_text_content(None);
+    }
+
+    fn notify(&self, event: &ViewerEvent) {
+        match event {
+            ViewerEvent::SearchChanged(results) => self.search_changed(*results),
+            ViewerEvent::SearchRequested => self.search_requested(),
+            _ => (),
+        }
+    }
+}
+
+impl Drop for SearchBox {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
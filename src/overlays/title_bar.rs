@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{Element, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::Overlay;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TitleBarOptions {
+    pub enabled: bool,
+}
+
+/// A header strip across the top of the container with the name of the current diagram
+pub struct TitleBar {
+    element: HtmlElement,
+}
+
+impl TitleBar {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        _options: &TitleBarOptions,
+    ) -> Result<TitleBar, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("header")
+            .ok_or_else(|| JsValue::from("Couldn't create the title bar"))?;
+        element.set_class_name("archizoom-title-bar");
+
+        let style = element.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("top", "0")?;
+        style.set_property("left", "0")?;
+        style.set_property("right", "0")?;
+        // let the svg underneath keep getting pointer events
+        style.set_property("pointer-events", "none")?;
+
+        container.append_child(&element)?;
+
+        let title_bar = TitleBar { element };
+        title_bar.navigated(archizoom.borrow().navigation());
+
+        Ok(title_bar)
+    }
+}
+
+impl Overlay for TitleBar {
+    fn navigated(&self, path: &[NavigationEntry]) {
+        if let Some(current) = path.last() {
+            self.element.set_text_content(Some(&current.title));
+        }
+    }
+}
+
+impl Drop for TitleBar {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
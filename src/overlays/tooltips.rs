@@ -0,0 +1,295 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, HtmlElement, PointerEvent, SvgGraphicsElement, SvgsvgElement};
+
+use crate::js_utils::*;
+use crate::overlays::Overlay;
+use crate::strings::Strings;
+use crate::zoom::bounds::element_bounds;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::space::{Transform, UserRect};
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TooltipsOptions {
+    /// Off by default, which leaves the browser's own tooltips
+    pub enabled: bool,
+    /// How long the pointer has to rest on an element before its tooltip shows
+    pub delay_ms: i32,
+}
+
+impl Default for TooltipsOptions {
+    fn default() -> TooltipsOptions {
+        TooltipsOptions {
+            enabled: false,
+            delay_ms: 500,
+        }
+    }
+}
+
+/// Shows the `<title>` and `<desc>` of the hovered element. The browser's own svg tooltips
+/// don't reliably show up once the svg has been injected with innerHTML.
+pub struct Tooltips {
+    tooltip: Rc<Tooltip>,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+}
+
+struct Tooltip {
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    element: HtmlElement,
+    container: Element,
+    svg: RefCell<SvgsvgElement>,
+    hovered: RefCell<Option<SvgGraphicsElement>>,
+    delay_ms: i32,
+    show_timeout: Timeout,
+}
+
+static TOOLTIP_GAP: f32 = 4.0;
+
+impl Tooltips {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &TooltipsOptions,
+    ) -> Result<Tooltips, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the tooltip"))?;
+        element.set_class_name("archizoom-tooltip");
+        element.set_attribute("role", "tooltip")?;
+
+        let style = element.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("transform", "translateX(-50%)")?;
+        style.set_property("pointer-events", "none")?;
+        style.set_property("display", "none")?;
+
+        container.append_child(&element)?;
+
+        let tooltip = Rc::new_cyclic(|weak_ref: &Weak<Tooltip>| {
+            let weak_ref = weak_ref.clone();
+
+            Tooltip {
+                archizoom: Rc::downgrade(archizoom),
+                element,
+                container: container.clone(),
+                svg: RefCell::new(archizoom.borrow().svg().clone()),
+                hovered: RefCell::new(None),
+                delay_ms: options.delay_ms,
+                show_timeout: Timeout::new(move || {
+                    if let Some(tooltip) = weak_ref.upgrade() {
+                        if let Err(error) = tooltip.show() {
+                            console::warn_2(&"Couldn't show the tooltip".into(), &error);
+                        }
+                    }
+                }),
+            }
+        });
+
+        let tooltips = Tooltips {
+            tooltip,
+            archizoom: Rc::downgrade(archizoom),
+            listeners: RefCell::new(vec![]),
+        };
+        tooltips.bind()?;
+
+        Ok(tooltips)
+    }
+
+    /// Listens for hovering over the current svg
+    fn bind(&self) -> Result<(), JsValue> {
+        let svg = self.tooltip.svg.borrow();
+
+        let move_tooltip = self.tooltip.clone();
+        let leave_tooltip = self.tooltip.clone();
+        let down_tooltip = self.tooltip.clone();
+
+        *self.listeners.borrow_mut() = vec![
+            svg.new_event_listener("pointermove", move |event: PointerEvent| {
+                // dragging pans the view, so there's nothing to describe
+                if event.buttons() != 0 {
+                    move_tooltip.hide();
+                } else {
+                    move_tooltip.hover(event.target().and_then(|target| target.dyn_into().ok()));
+                }
+            })?,
+            svg.new_event_listener("pointerleave", move |_: PointerEvent| {
+                leave_tooltip.hide();
+            })?,
+            svg.new_event_listener("pointerdown", move |_: PointerEvent| {
+                down_tooltip.hide();
+            })?,
+        ];
+
+        Ok(())
+    }
+}
+
+impl Tooltip {
+    fn hover(&self, target: Option<Element>) {
+        let described = target.and_then(|target| {
+            described_element(&self.svg.borrow(), target.clone()).or_else(|| {
+                self.linked_title(&target)
+                    .and_then(|_| self.link_element(&target))
+            })
+        });
+        if *self.hovered.borrow() == described {
+            return;
+        }
+
+        self.hide();
+
+        if described.is_some() {
+            self.show_timeout.restart(self.delay_ms);
+        }
+        self.hovered.replace(described);
+    }
+
+    /// Positions the tooltip centered under the hovered element
+    fn show(&self) -> Result<(), JsValue> {
+        let svg = self.svg.borrow();
+        let hovered = match *self.hovered.borrow() {
+            Some(ref hovered) => hovered.clone(),
+            None => return Ok(()),
+        };
+
+        let bounds = match element_bounds(&svg, &hovered) {
+            Some(bounds) => UserRect::new(bounds),
+            None => return Ok(()),
+        };
+        let to_client = match (
+            self.archizoom
+                .upgrade()
+                .and_then(|archizoom| archizoom.borrow().screen_ctm()),
+            Transform::client_to_screen(&self.container).inverse(),
+        ) {
+            (Some(to_screen), Some(to_client)) => to_screen.then(&to_client),
+            _ => return Ok(()),
+        };
+        let client_bounds = to_client.rect(&bounds);
+
+        self.element.set_inner_html("");
+        for (index, child) in ["title", "desc"].iter().enumerate() {
+            if let Some(text) = child_text(&hovered, child) {
+                let line = document()
+                    .safe_create_element::<HtmlElement>(if index == 0 { "strong" } else { "div" })
+                    .ok_or_else(|| JsValue::from("Couldn't create the tooltip text"))?;
+                line.set_class_name(&format!("archizoom-tooltip-{}", child));
+                line.set_text_content(Some(&text));
+
+                self.element.append_child(&line)?;
+            }
+        }
+
+        if let Some(title) = self.linked_title(&hovered) {
+            let line = document()
+                .safe_create_element::<HtmlElement>("div")
+                .ok_or_else(|| JsValue::from("Couldn't create the tooltip text"))?;
+            line.set_class_name("archizoom-tooltip-target");
+            line.set_text_content(Some(&title));
+
+            self.element.append_child(&line)?;
+        }
+
+        let style = self.element.style();
+        style.set_property("left", &format!("{}px", client_bounds.center().x))?;
+        style.set_property(
+            "top",
+            &format!("{}px", client_bounds.bottom() + TOOLTIP_GAP),
+        )?;
+        style.set_property("display", "block")?;
+
+        Ok(())
+    }
+
+    /// "Opens ..." with the prefetched title of the diagram `element` links to
+    fn linked_title(&self, element: &Element) -> Option<String> {
+        let archizoom = self.archizoom.upgrade()?;
+        let archizoom = archizoom.borrow();
+        let title = archizoom
+            .link_target(element)
+            .and_then(|target| archizoom.diagram_info(&target))
+            .and_then(|info| info.title)?;
+
+        let label = Strings::fill(&archizoom.strings().opens_diagram, &[("title", &title)]);
+
+        Some(label)
+    }
+
+    /// The zoom element containing `target`
+    fn link_element(&self, target: &Element) -> Option<SvgGraphicsElement> {
+        self.archizoom.upgrade()?.borrow().link_element(target)
+    }
+
+    fn hide(&self) {
+        self.show_timeout.cancel();
+        self.hovered.replace(None);
+
+        if let Err(error) = self.element.style().set_property("display", "none") {
+            console::warn_2(&"Couldn't hide the tooltip".into(), &error);
+        }
+    }
+}
+
+impl Overlay for Tooltips {
+    fn view_update(&self, _event: &ViewUpdateEvent) {
+        // the tooltip would be left behind as the view moves
+        self.tooltip.hide();
+    }
+
+    fn navigated(&self, _path: &[NavigationEntry]) {
+        self.tooltip.hide();
+
+        if let Some(archizoom) = self.archizoom.upgrade() {
+            self.tooltip.svg.replace(archizoom.borrow().svg().clone());
+
+            if let Err(error) = self.bind() {
+                console::error_2(&"Couldn't listen for tooltips".into(), &error);
+            }
+        }
+    }
+}
+
+impl Drop for Tooltips {
+    fn drop(&mut self) {
+        self.tooltip.element.remove();
+    }
+}
+
+/// The closest element, starting from `target`, that has a `<title>` or `<desc>`. The root svg
+/// is skipped since its title names the whole diagram.
+fn described_element(svg: &SvgsvgElement, target: Element) -> Option<SvgGraphicsElement> {
+    let root: &Element = svg.as_ref();
+    let mut current = Some(target);
+
+    while let Some(element) = current {
+        if element == *root {
+            return None;
+        }
+
+        if child_text(&element, "title").is_some() || child_text(&element, "desc").is_some() {
+            return element.dyn_into().ok();
+        }
+
+        current = element.parent_element();
+    }
+
+    None
+}
+
+/// The trimmed text of the direct `child` element, if it has any
+fn child_text(element: &Element, child: &str) -> Option<String> {
+    element
+        .query_selector(&format!(":scope > {}", child))
+        .ok()
+        .and_then(|child| child)
+        .and_then(|child| child.text_content())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
@@ -0,0 +1,122 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, Event, HtmlElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, OverlayPosition};
+use crate::strings::Strings;
+use crate::zoom::matrix::approx_eq;
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ZoomIndicatorOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+    /// How long the badge stays up after the last zoom
+    pub hide_delay_ms: i32,
+}
+
+impl Default for ZoomIndicatorOptions {
+    fn default() -> ZoomIndicatorOptions {
+        ZoomIndicatorOptions {
+            enabled: true,
+            position: OverlayPosition::BottomLeft,
+            hide_delay_ms: 1500,
+        }
+    }
+}
+
+/// A "137%" badge that shows up while zooming, clicking it resets the view
+pub struct ZoomIndicator {
+    element: HtmlElement,
+    hide_delay_ms: i32,
+    last_zoom: Cell<f32>,
+    hide: Timeout,
+    _listener: Box<dyn JsEventListener>,
+}
+
+impl ZoomIndicator {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &ZoomIndicatorOptions,
+        strings: &Strings,
+    ) -> Result<ZoomIndicator, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("button")
+            .ok_or_else(|| JsValue::from("Couldn't create the zoom indicator"))?;
+        element.set_class_name("archizoom-zoom-indicator");
+        element.set_attribute("type", "button")?;
+        element.set_title(&strings.reset_zoom);
+        options.position.apply(&element.style())?;
+        element.style().set_property("display", "none")?;
+
+        let weak_ref = Rc::downgrade(archizoom);
+        let clicked_element = element.clone();
+        let listener = element.new_event_listener("click", move |event: Event| {
+            event.stop_propagation();
+
+            if let Some(archizoom) = weak_ref.upgrade() {
+                archizoom.borrow().reset();
+            }
+
+            // we're back at 100% so there's nothing left to show
+            if let Err(error) = clicked_element.style().set_property("display", "none") {
+                console::warn_2(&"Couldn't hide the zoom indicator".into(), &error);
+            }
+        })?;
+
+        let hidden_element = element.clone();
+        let hide = Timeout::new(move || {
+            if let Err(error) = hidden_element.style().set_property("display", "none") {
+                console::warn_2(&"Couldn't hide the zoom indicator".into(), &error);
+            }
+        });
+
+        container.append_child(&element)?;
+
+        Ok(ZoomIndicator {
+            element,
+            hide_delay_ms: options.hide_delay_ms,
+            last_zoom: Cell::new(1.0),
+            hide,
+            _listener: listener,
+        })
+    }
+
+    fn show(&self, zoom: f32) -> Result<(), JsValue> {
+        self.element
+            .set_text_content(Some(&format!("{}%", (zoom * 100.0).round())));
+        self.element.style().set_property("display", "block")?;
+
+        self.hide.restart(self.hide_delay_ms);
+
+        Ok(())
+    }
+}
+
+impl Overlay for ZoomIndicator {
+    fn view_update(&self, event: &ViewUpdateEvent) {
+        let zoom = event.zoom();
+
+        // only zooming brings up the badge, panning keeps the same zoom level
+        if event.is_gesture() && !approx_eq(zoom, self.last_zoom.get()) {
+            if let Err(error) = self.show(zoom) {
+                console::warn_2(&"Couldn't show the zoom indicator".into(), &error);
+            }
+        }
+
+        self.last_zoom.set(zoom);
+    }
+}
+
+impl Drop for ZoomIndicator {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
@@ -0,0 +1,121 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{Element, Event, HtmlInputElement};
+
+use crate::js_utils::*;
+use crate::overlays::{Overlay, OverlayPosition};
+use crate::strings::Strings;
+use crate::zoom::svg_view_controller::ViewUpdateEvent;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Orientation {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ZoomSliderOptions {
+    pub enabled: bool,
+    pub position: OverlayPosition,
+    pub orientation: Orientation,
+    /// The zoom level at each end of the slider, relative to the fitted view
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+}
+
+impl Default for ZoomSliderOptions {
+    fn default() -> ZoomSliderOptions {
+        ZoomSliderOptions {
+            enabled: false,
+            position: OverlayPosition::BottomRight,
+            orientation: Orientation::Vertical,
+            min_zoom: 0.125,
+            max_zoom: 32.0,
+        }
+    }
+}
+
+/// A range input bound to the zoom level. The slider moves in powers of 2 so each step feels
+/// like the same amount of zoom.
+pub struct ZoomSlider {
+    input: HtmlInputElement,
+    _listener: Box<dyn JsEventListener>,
+}
+
+static SLIDER_STEP: &str = "0.01";
+
+impl ZoomSlider {
+    pub fn new(
+        archizoom: &Rc<RefCell<ArchiZoom>>,
+        container: &Element,
+        options: &ZoomSliderOptions,
+        strings: &Strings,
+    ) -> Result<ZoomSlider, JsValue> {
+        let input = document()
+            .safe_create_element::<HtmlInputElement>("input")
+            .ok_or_else(|| JsValue::from("Couldn't create the zoom slider"))?;
+        input.set_class_name("archizoom-zoom-slider");
+        input.set_type("range");
+        input.set_min(&options.min_zoom.log2().to_string());
+        input.set_max(&options.max_zoom.log2().to_string());
+        input.set_step(SLIDER_STEP);
+        input.set_attribute("aria-label", &strings.zoom)?;
+        options.position.apply(&input.style())?;
+
+        if let Orientation::Vertical = options.orientation {
+            // zooming in moves up
+            input.set_attribute("orient", "vertical")?;
+            input.style().set_property("writing-mode", "vertical-lr")?;
+            input.style().set_property("direction", "rtl")?;
+        }
+
+        let weak_ref = Rc::downgrade(archizoom);
+        let changed_input = input.clone();
+        let listener = input.new_event_listener("input", move |event: Event| {
+            event.stop_propagation();
+
+            if let (Some(archizoom), Ok(exponent)) =
+                (weak_ref.upgrade(), changed_input.value().parse::<f32>())
+            {
+                archizoom.borrow().set_zoom_level(exponent.exp2());
+            }
+        })?;
+
+        container.append_child(&input)?;
+
+        let slider = ZoomSlider {
+            input,
+            _listener: listener,
+        };
+        if let Some(zoom) = archizoom.borrow().zoom_level() {
+            slider.show_zoom(zoom);
+        }
+
+        Ok(slider)
+    }
+
+    fn show_zoom(&self, zoom: f32) {
+        self.input.set_value(&zoom.log2().to_string());
+    }
+}
+
+impl Overlay for ZoomSlider {
+    fn view_update(&self, event: &ViewUpdateEvent) {
+        // a zero sized svg doesn't have a zoom level
+        if event.zoom().is_finite() && event.zoom() > 0.0 {
+            self.show_zoom(event.zoom());
+        }
+    }
+}
+
+impl Drop for ZoomSlider {
+    fn drop(&mut self) {
+        self.input.remove();
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+/// Every string we show to users, override any of them with the `strings` option to translate
+/// the viewer. Placeholders like `{title}` are filled in with `Strings::fill`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Strings {
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub reset: String,
+    pub fit_to_content: String,
+    pub fullscreen: String,
+    pub reset_zoom: String,
+    pub zoom: String,
+    pub search: String,
+    pub search_label: String,
+    pub previous_match: String,
+    pub next_match: String,
+    pub no_matches: String,
+    /// `{current}` and `{total}`
+    pub match_count: String,
+    pub diff_added: String,
+    pub diff_removed: String,
+    pub diff_changed: String,
+    pub previous_change: String,
+    pub next_change: String,
+    pub layers: String,
+    pub zoom_to_fit: String,
+    pub copy_view_link: String,
+    pub view_link_copied: String,
+    pub add_note: String,
+    pub note_prompt: String,
+    pub open_linked_diagram: String,
+    /// `{title}`, the prefetched title of the diagram a link opens
+    pub opens_diagram: String,
+    /// `{title}`
+    pub diagram_label: String,
+    /// `{title}`
+    pub opened_diagram: String,
+    /// `{percent}`
+    pub zoomed_to: String,
+    pub click_to_interact: String,
+}
+
+impl Default for Strings {
+    fn default() -> Strings {
+        Strings {
+            zoom_in: "Zoom in".to_string(),
+            zoom_out: "Zoom out".to_string(),
+            reset: "Reset".to_string(),
+            fit_to_content: "Fit to content".to_string(),
+            fullscreen: "Fullscreen".to_string(),
+            reset_zoom: "Reset zoom".to_string(),
+            zoom: "Zoom".to_string(),
+            search: "Search".to_string(),
+            search_label: "Search the diagram".to_string(),
+            previous_match: "Previous match".to_string(),
+            next_match: "Next match".to_string(),
+            no_matches: "No matches".to_string(),
+            match_count: "{current} of {total}".to_string(),
+            diff_added: "Added".to_string(),
+            diff_removed: "Removed".to_string(),
+            diff_changed: "Changed".to_string(),
+            previous_change: "Previous change".to_string(),
+            next_change: "Next change".to_string(),
+            layers: "Layers".to_string(),
+            zoom_to_fit: "Zoom to fit".to_string(),
+            copy_view_link: "Copy view link".to_string(),
+            view_link_copied: "Link copied".to_string(),
+            add_note: "Add note here".to_string(),
+            note_prompt: "Note".to_string(),
+            open_linked_diagram: "Open linked diagram".to_string(),
+            opens_diagram: "Opens {title}".to_string(),
+            diagram_label: "Zoomable diagram: {title}".to_string(),
+            opened_diagram: "Opened diagram: {title}".to_string(),
+            zoomed_to: "Zoomed to {percent}%".to_string(),
+            click_to_interact: "Click to interact".to_string(),
+        }
+    }
+}
+
+impl Strings {
+    /// Replaces each `{name}` in `template` with its value
+    pub fn fill(template: &str, values: &[(&str, &str)]) -> String {
+        values
+            .iter()
+            .fold(template.to_string(), |filled, (name, value)| {
+                filled.replace(&format!("{{{}}}", name), value)
+            })
+    }
+}
@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, HtmlElement};
+
+use crate::js_utils::*;
+use crate::options::Options;
+use crate::PREFIX_ALIAS;
+
+/// The look of everything we inject. Colors and sizes come from custom properties set on the
+/// container, so pages can theme the viewer by overriding them, e.g.
+/// `.archizoom-container { --archizoom-accent: rebeccapurple; }`
+static STYLESHEET: &str = "
+.archizoom-container {
+  --archizoom-accent: #2196f3;
+  --archizoom-highlight: #ff9800;
+  --archizoom-background: rgba(255, 255, 255, 0.92);
+  --archizoom-foreground: #222;
+  --archizoom-border: rgba(0, 0, 0, 0.2);
+  --archizoom-radius: 4px;
+  --archizoom-font: 12px sans-serif;
+  --archizoom-overlay-inset: 8px;
+  --archizoom-hover-filter: drop-shadow(0 0 3px var(--archizoom-accent));
+  --archizoom-selection-filter: drop-shadow(0 0 4px var(--archizoom-highlight));
+  --archizoom-dimmed-opacity: 0.15;
+  --archizoom-crosshair-color: rgba(233, 30, 99, 0.6);
+  --archizoom-grid-color: rgba(0, 0, 0, 0.08);
+  --archizoom-ruler-background: var(--archizoom-background);
+  --archizoom-ruler-color: rgba(0, 0, 0, 0.6);
+  --archizoom-ruler-font: 10px sans-serif;
+  --archizoom-diff-added: #4caf50;
+  --archizoom-diff-removed: #f44336;
+  --archizoom-diff-changed: #ff9800;
+  --archizoom-pin-color: var(--archizoom-accent);
+  --archizoom-veil-background: rgba(0, 0, 0, 0.03);
+  --archizoom-overview-fill: rgba(33, 150, 243, 0.1);
+  font: var(--archizoom-font);
+  color: var(--archizoom-foreground);
+}
+
+.archizoom-container:focus-visible {
+  outline: 2px solid var(--archizoom-accent);
+  outline-offset: 2px;
+}
+
+.archizoom-controls,
+.archizoom-breadcrumbs,
+.archizoom-title-bar,
+.archizoom-tooltip,
+.archizoom-context-menu,
+.archizoom-search,
+.archizoom-layers,
+.archizoom-diff-legend,
+.archizoom-zoom-indicator,
+.archizoom-crosshair-readout,
+.archizoom-annotation,
+.archizoom-minimap {
+  background: var(--archizoom-background);
+  color: var(--archizoom-foreground);
+  border: 1px solid var(--archizoom-border);
+  border-radius: var(--archizoom-radius);
+  padding: 2px 6px;
+  font: var(--archizoom-font);
+}
+
+.archizoom-pin {
+  background: var(--archizoom-pin-color);
+  color: #fff;
+  border-radius: var(--archizoom-radius);
+  padding: 2px 6px;
+  font: var(--archizoom-font);
+  min-width: 8px;
+  min-height: 8px;
+}
+
+.archizoom-pin-ok {
+  --archizoom-pin-color: #4caf50;
+}
+
+.archizoom-pin-warning {
+  --archizoom-pin-color: #ff9800;
+}
+
+.archizoom-pin-error {
+  --archizoom-pin-color: #f44336;
+}
+
+.archizoom-pin::after {
+  content: '';
+  position: absolute;
+  left: 50%;
+  top: 100%;
+  margin-left: -4px;
+  border: 4px solid transparent;
+  border-top-color: var(--archizoom-pin-color);
+}
+
+.archizoom-veil {
+  background: var(--archizoom-veil-background);
+}
+
+.archizoom-veil-message {
+  background: var(--archizoom-background);
+  color: var(--archizoom-foreground);
+  border-radius: var(--archizoom-radius);
+  padding: 4px 8px;
+  font: var(--archizoom-font);
+  opacity: 0.6;
+  transition: opacity 0.2s;
+}
+
+.archizoom-veil:hover .archizoom-veil-message {
+  opacity: 1;
+}
+
+.archizoom-title-bar {
+  border-radius: 0;
+  border-width: 0 0 1px 0;
+}
+
+.archizoom-diagram {
+  touch-action: none;
+}
+
+.archizoom-contained {
+  contain: layout paint;
+  isolation: isolate;
+}
+
+.archizoom-contained .archizoom-content {
+  will-change: transform;
+}
+
+.archizoom-content {
+  transform-origin: 0 0;
+}
+
+.archizoom-hover {
+  filter: var(--archizoom-hover-filter);
+}
+
+.archizoom-selected {
+  filter: var(--archizoom-selection-filter);
+}
+
+.archizoom-overview .archizoom-detail,
+.archizoom-overview [data-archizoom-detail],
+.archizoom-diagram:not(.archizoom-overview) [data-archizoom-overview] {
+  display: none;
+}
+
+.archizoom-degraded .archizoom-diagram text,
+.archizoom-degraded .archizoom-detail,
+.archizoom-degraded [data-archizoom-detail] {
+  visibility: hidden;
+}
+
+.archizoom-illegible {
+  visibility: hidden;
+}
+
+.archizoom-culled {
+  display: none;
+}
+
+.archizoom-rasterized > * {
+  visibility: hidden;
+}
+
+.archizoom-filtered-out {
+  opacity: var(--archizoom-dimmed-opacity);
+}
+
+.archizoom-search-match {
+  filter: drop-shadow(0 0 2px var(--archizoom-highlight));
+}
+
+.archizoom-search-current {
+  filter: drop-shadow(0 0 5px var(--archizoom-highlight));
+}
+
+.archizoom-diff-added {
+  filter: drop-shadow(0 0 3px var(--archizoom-diff-added));
+}
+
+.archizoom-diff-changed {
+  filter: drop-shadow(0 0 3px var(--archizoom-diff-changed));
+}
+
+.archizoom-diff-removed {
+  opacity: 0.5;
+  pointer-events: none;
+  filter: drop-shadow(0 0 3px var(--archizoom-diff-removed));
+}
+
+.archizoom-diff-current {
+  filter: drop-shadow(0 0 6px var(--archizoom-accent));
+}
+
+.archizoom-diff-swatch {
+  display: inline-block;
+  width: 8px;
+  height: 8px;
+  margin-right: 4px;
+  border-radius: 50%;
+}
+
+.archizoom-diff-legend-added .archizoom-diff-swatch {
+  background: var(--archizoom-diff-added);
+}
+
+.archizoom-diff-legend-removed .archizoom-diff-swatch {
+  background: var(--archizoom-diff-removed);
+}
+
+.archizoom-diff-legend-changed .archizoom-diff-swatch {
+  background: var(--archizoom-diff-changed);
+}
+
+.archizoom-focus-ring {
+  box-sizing: border-box;
+  border: 2px solid var(--archizoom-accent);
+  border-radius: 2px;
+}
+
+.archizoom-crosshair {
+  background: var(--archizoom-crosshair-color);
+}
+
+.archizoom-overview-viewport {
+  background: var(--archizoom-overview-fill);
+  border: 2px solid var(--archizoom-accent);
+}
+
+.archizoom-minimap-viewport {
+  fill: var(--archizoom-accent);
+  fill-opacity: 0.15;
+  stroke: var(--archizoom-accent);
+  stroke-width: 2px;
+}
+";
+
+/// Hints that keep the browser's work during interaction inside the viewer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RenderingHintsOptions {
+    /// Contain the container's layout and paint and give it its own stacking context, so
+    /// redrawing the diagram doesn't repaint the rest of the page. Turn this off if the page
+    /// needs something in the viewer to draw outside of it.
+    pub enabled: bool,
+}
+
+impl Default for RenderingHintsOptions {
+    fn default() -> RenderingHintsOptions {
+        RenderingHintsOptions { enabled: true }
+    }
+}
+
+static CONTAINED_CLASS: &str = "archizoom-contained";
+
+/// Marks the container for our rendering hints. In the `css-transform` gesture mode this also
+/// keeps the transformed content on its own compositor layer.
+pub fn apply_rendering_hints(
+    container: &Element,
+    options: &RenderingHintsOptions,
+) -> Result<(), JsValue> {
+    if options.enabled {
+        container.class_list().add_1(CONTAINED_CLASS)?;
+    }
+
+    Ok(())
+}
+
+/// Carries the options the custom properties replaced over to those properties, set inline on
+/// the container so they win over the stylesheet's defaults
+pub fn apply_deprecated_properties(container: &Element, options: &Options) -> Result<(), JsValue> {
+    let properties = [
+        (
+            "--archizoom-hover-filter",
+            options.hover_highlight.filter.clone(),
+        ),
+        (
+            "--archizoom-selection-filter",
+            options.selection.filter.clone(),
+        ),
+        (
+            "--archizoom-dimmed-opacity",
+            options
+                .filter
+                .dimmed_opacity
+                .map(|opacity| opacity.to_string()),
+        ),
+    ];
+
+    if let Some(container) = container.dyn_ref::<HtmlElement>() {
+        for (name, value) in properties.iter() {
+            if let Some(value) = value {
+                container.style().set_property(name, value)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds our stylesheet to the page, once no matter how many diagrams there are
+pub fn ensure_stylesheet() -> Result<(), JsValue> {
+    let document = document();
+    let id = format!("{}-styles", PREFIX_ALIAS);
+
+    if document.get_element_by_id(&id).is_some() {
+        return Ok(());
+    }
+
+    let style = document
+        .safe_create_element::<HtmlElement>("style")
+        .ok_or_else(|| JsValue::from("Couldn't create the stylesheet"))?;
+    style.set_id(&id);
+    style.set_text_content(Some(STYLESHEET));
+
+    let head = document
+        .head()
+        .ok_or_else(|| JsValue::from("The document doesn't have a head"))?;
+    head.append_child(&style)?;
+
+    Ok(())
+}
@@ -0,0 +1,404 @@
+use wasm_bindgen::prelude::*;
+
+// The shapes of the plain objects that cross into JS. These have to follow the serde
+// attributes of the structs they describe, every option is optional since they all fall back
+// to their defaults.
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &'static str = r#"
+export interface Point {
+  x: number;
+  y: number;
+}
+
+export interface Size {
+  width: number;
+  height: number;
+}
+
+/** A rectangle in Svg User Coordinates */
+export interface Rect {
+  top_left: Point;
+  bottom_right: Point;
+}
+
+/** The viewBox in Svg User Coordinates, and how many pixels one user unit takes up */
+export interface Viewport {
+  origin: Point;
+  size: Size;
+  scale: number;
+}
+
+export type Easing =
+  | "linear"
+  | "ease-in"
+  | "ease-out"
+  | "ease-in-out"
+  | { "cubic-bezier": [number, number, number, number] };
+
+export type Transition =
+  | { type: "eased"; easing?: Easing; durationMs?: number }
+  | { type: "spring"; stiffness?: number; damping?: number }
+  /** Zooms out and back in, for far away targets */
+  | { type: "flight"; easing?: Easing; durationMs?: number };
+
+export type OverlayPosition = "top-left" | "top-right" | "bottom-left" | "bottom-right";
+
+export interface OverlayOptions {
+  enabled?: boolean;
+  position?: OverlayPosition;
+}
+
+export type KeyAction =
+  | "panLeft"
+  | "panRight"
+  | "panUp"
+  | "panDown"
+  | "zoomIn"
+  | "zoomOut"
+  | "reset"
+  | "back"
+  | "search"
+  | "fullscreen"
+  | "nextLink"
+  | "previousLink"
+  | "activateLink"
+  | "blurLink"
+  | "peek";
+
+export interface ZoomBreakpoint {
+  className: string;
+  /** Relative to the fitted view, 1 is fitted */
+  minZoom: number;
+}
+
+export interface ArchiZoomStrings {
+  zoomIn?: string;
+  zoomOut?: string;
+  reset?: string;
+  fitToContent?: string;
+  fullscreen?: string;
+  resetZoom?: string;
+  zoom?: string;
+  search?: string;
+  searchLabel?: string;
+  previousMatch?: string;
+  nextMatch?: string;
+  noMatches?: string;
+  /** `{current}` and `{total}` */
+  matchCount?: string;
+  diffAdded?: string;
+  diffRemoved?: string;
+  diffChanged?: string;
+  previousChange?: string;
+  nextChange?: string;
+  layers?: string;
+  zoomToFit?: string;
+  copyViewLink?: string;
+  viewLinkCopied?: string;
+  addNote?: string;
+  notePrompt?: string;
+  openLinkedDiagram?: string;
+  /** `{title}` */
+  opensDiagram?: string;
+  /** `{title}` */
+  diagramLabel?: string;
+  /** `{title}` */
+  openedDiagram?: string;
+  /** `{percent}` */
+  zoomedTo?: string;
+  clickToInteract?: string;
+}
+
+/** The options passed to `init`, any that are missing fall back to their defaults */
+export interface ArchiZoomOptions {
+  /** The diagram `mount` shows, `init` uses the src of each image instead */
+  src?: string;
+  pixelSnapping?: boolean;
+  minify?: boolean;
+  /** The worker fetches diagrams and reads their titles and links, their DOM is still built on the page's thread */
+  loadInWorker?: boolean;
+  progressiveInjection?: { enabled?: boolean; minElements?: number; frameBudgetMs?: number };
+  diagramCache?: { budget?: number };
+  prefetch?: { enabled?: boolean; bytes?: number };
+  hoverPrefetch?: { enabled?: boolean; delayMs?: number };
+  motion?: "auto" | "reduce" | "full";
+  /** How the view animates when the viewer moves it, defaults to a 300ms ease-in-out */
+  transition?: Transition;
+  /** Keeps the view gliding after a drag, `friction` is how quickly it slows down per second */
+  momentum?: { enabled?: boolean; friction?: number };
+  /** Swaps the viewer for its current view when the page is printed */
+  print?: { enabled?: boolean };
+  broadcast?: { channel?: string | null; receiveOnly?: boolean };
+  embed?: { enabled?: boolean; parentOrigin?: string | null };
+  analytics?: {
+    endpoint?: string | null;
+    sampleRate?: number;
+    batchSize?: number;
+    flushMs?: number;
+    minDwellMs?: number;
+  };
+  /** `src` is a `PinData` document, loaded again every `refreshMs` */
+  pins?: { src?: string | null; refreshMs?: number };
+  rememberView?: { enabled?: boolean; saveDelayMs?: number };
+  restoreNavigation?: { enabled?: boolean };
+  locked?: boolean;
+  preventDefault?: "always" | "when-zooming" | "when-active";
+  /** `openThreshold` is how much of the view, from 0 to 1, a zoom element fills before it opens */
+  zoom?: { wheelFactor?: number; openThreshold?: number };
+  gestureRendering?: "view-box" | "css-transform";
+  renderingHints?: { enabled?: boolean };
+  quality?: {
+    enabled?: boolean;
+    frameBudgetMs?: number;
+    slowFrames?: number;
+    restoreMs?: number;
+  };
+  delegateEvents?: boolean;
+  eventDiagnostics?: boolean;
+  /** Diagram names by url or file name */
+  titles?: Record<string, string>;
+  /** Keys or combinations like `Ctrl+Shift+ArrowUp` for each action, an empty list turns it off */
+  keyBindings?: Partial<Record<KeyAction, string[]>>;
+  gamepad?: {
+    enabled?: boolean;
+    /** Follow the gamepad without the viewer having focus */
+    always?: boolean;
+    deadZone?: number;
+    panSpeed?: number;
+    zoomSpeed?: number;
+  };
+  zoomBreakpoints?: ZoomBreakpoint[];
+  overview?: { maxZoom?: number; derive?: boolean; detailSelector?: string };
+  strings?: ArchiZoomStrings;
+  /** Only with the `canvas` feature */
+  canvas?: {
+    enabled?: boolean;
+    minElements?: number;
+    settleMs?: number;
+    tiled?: boolean;
+    tileSize?: number;
+    maxLevel?: number;
+    maxTiles?: number;
+  };
+  culling?: { enabled?: boolean; selector?: string; margin?: number };
+  textCulling?: { enabled?: boolean; minPixels?: number };
+  selection?: {
+    enabled?: boolean;
+    selector?: string;
+    /** @deprecated set `--archizoom-selection-filter` on the container */
+    filter?: string | null;
+  };
+  filter?: {
+    selector?: string;
+    /** @deprecated set `--archizoom-dimmed-opacity` on the container */
+    dimmedOpacity?: number | null;
+  };
+  isolate?: { connectorSelector?: string; tolerance?: number };
+  layers?: { selector?: string };
+  diff?: { selector?: string };
+  diffLegend?: OverlayOptions;
+  titleBar?: { enabled?: boolean };
+  /** Only with the `search` feature */
+  searchBox?: OverlayOptions;
+  /** Only with the `search` feature */
+  modelSearch?: { maxDiagrams?: number };
+  controls?: OverlayOptions & { copyLink?: boolean };
+  breadcrumbs?: OverlayOptions;
+  announcer?: { enabled?: boolean; zoomDelayMs?: number };
+  contextMenu?: { enabled?: boolean; longPressMs?: number };
+  grid?: { enabled?: boolean; rulers?: boolean; minSpacing?: number };
+  crosshair?: OverlayOptions;
+  /** Tab through the diagram's links with a ring around the focused one */
+  focusRing?: { enabled?: boolean };
+  hoverHighlight?: {
+    enabled?: boolean;
+    selector?: string;
+    /** @deprecated set `--archizoom-hover-filter` on the container */
+    filter?: string | null;
+  };
+  tooltips?: { enabled?: boolean; delayMs?: number };
+  layerList?: OverlayOptions;
+  zoomIndicator?: OverlayOptions & { hideDelayMs?: number };
+  zoomSlider?: OverlayOptions & {
+    orientation?: "vertical" | "horizontal";
+    minZoom?: number;
+    maxZoom?: number;
+  };
+  /** Only with the `minimap` feature */
+  minimap?: OverlayOptions & { size?: number };
+  clickToActivate?: { enabled?: boolean };
+}
+
+export interface LayerInfo {
+  name: string;
+  visible: boolean;
+}
+
+export interface Annotation {
+  id?: string;
+  /** The url of the diagram it's pinned to, the current diagram if it's missing */
+  diagram?: string | null;
+  x: number;
+  y: number;
+  text: string;
+}
+
+/** A marker over the element with id `element`, or at `x` and `y` in diagram coordinates */
+export interface Pin {
+  id?: string | null;
+  /** The url of the diagram it goes on, any diagram with its element if it's missing */
+  diagram?: string | null;
+  element?: string | null;
+  x?: number | null;
+  y?: number | null;
+  label?: string | null;
+  /** Added as an `archizoom-pin-{status}` class */
+  status?: string | null;
+  color?: string | null;
+}
+
+export interface PinData {
+  pins: Pin[];
+}
+
+export interface AnnotationSet {
+  annotations: Annotation[];
+  nextId?: number;
+}
+
+export interface DiagramInfo {
+  title: string | null;
+  viewBox: Rect | null;
+  width: string | null;
+  height: string | null;
+}
+
+/** A viewBox in Svg User Coordinates, as the embed messages carry it */
+export interface ViewBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/** What the page framing a viewer with `embed` enabled can post to it */
+export type ArchiZoomEmbedCommand =
+  | { type: "setView"; viewBox: ViewBox }
+  | { type: "navigate"; url: string }
+  | { type: "getState" };
+
+/** What a viewer with `embed` enabled posts to the page framing it */
+export interface ArchiZoomEmbedState {
+  type: "state";
+  url: string;
+  title: string;
+  path: string[];
+  viewBox: ViewBox | null;
+  zoom: number | null;
+}
+
+/** Timestamps are milliseconds since the epoch */
+export type AnalyticsRecord =
+  | { type: "diagramOpened"; url: string; title: string; depth: number; timestamp: number }
+  | { type: "drilledInto"; from: string; to: string; timestamp: number }
+  | { type: "dwell"; url: string; viewBox: ViewBox; durationMs: number; timestamp: number };
+
+export type FilterQuery = string | ((element: Element) => boolean);
+
+export interface BenchOptions {
+  frames?: number;
+}
+
+/** Every time is in milliseconds */
+export interface BenchReport {
+  inputs: number;
+  p50: number;
+  p90: number;
+  p99: number;
+  max: number;
+  frames: number;
+  droppedFrames: number;
+}
+
+/** One recorded event, anything missing was zero or false */
+export interface RecordedInput {
+  /** Milliseconds since the recording started */
+  t: number;
+  type: string;
+  /** Pixels from the top left of the viewer */
+  x?: number;
+  y?: number;
+  pointerId?: number;
+  pointerType?: string;
+  secondary?: boolean;
+  buttons?: number;
+  deltaX?: number;
+  deltaY?: number;
+  deltaMode?: number;
+  key?: string;
+  ctrl?: boolean;
+  shift?: boolean;
+  alt?: boolean;
+  meta?: boolean;
+}
+
+export interface RecordedView {
+  t: number;
+  viewBox: ViewBox;
+  /** Set when the view moved to another diagram */
+  diagram?: string;
+}
+
+export interface Trace {
+  diagram: string;
+  width: number;
+  height: number;
+  start: ViewBox | null;
+  inputs: RecordedInput[];
+  views: RecordedView[];
+}
+
+export interface ReplayReport {
+  inputs: number;
+  recorded: ViewBox | null;
+  replayed: ViewBox | null;
+}
+
+export interface LiveCounts {
+  jsListeners: number;
+  eventSourceListeners: number;
+  timeouts: number;
+  instances: number;
+}
+
+/** What a diagram says about one of its elements, for joining it back up with the model */
+export interface ElementInfo {
+  id: string | null;
+  archimateId: string | null;
+  archimateType: string | null;
+  name: string | null;
+  classes: string[];
+  /** The other `data-` attributes, without the `data-` */
+  properties: Record<string, string>;
+}
+
+/** The matches of `searchModel` on one diagram, `id` goes to `openSearchResult` */
+export interface ModelSearchResult {
+  url: string;
+  title: string;
+  matches: { id: string | null; text: string }[];
+}
+
+/** The events dispatched on the container, each bubbles */
+export interface ArchiZoomEventMap {
+  "archizoom-selectionchange": CustomEvent<{ id: string | null; element: ElementInfo | null }>;
+  "archizoom-hover": CustomEvent<{ id: string | null; element: ElementInfo | null }>;
+  "archizoom-navigate": CustomEvent<{
+    url: string;
+    title: string;
+    path: string[];
+    /** The zoom element drilled into, null when going back up */
+    link: ElementInfo | null;
+  }>;
+}
+"#;
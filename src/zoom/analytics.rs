@@ -0,0 +1,252 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use js_sys::{Date, Function, Math};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Event};
+
+use crate::js_utils::*;
+use crate::zoom::navigation::NavigationEntry;
+use crate::zoom::viewport::ViewBox;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnalyticsOptions {
+    /// Records are posted here as a JSON array with `navigator.sendBeacon`, as well as going
+    /// to the handler set with `setAnalyticsHandler`
+    pub endpoint: Option<String>,
+    /// The share of viewers that record anything, decided once per viewer so each one that does
+    /// tells the whole story
+    pub sample_rate: f64,
+    /// Records are held until there are this many
+    pub batch_size: usize,
+    /// or until the oldest has waited this long
+    pub flush_ms: i32,
+    /// Views looked at for less than this aren't worth a dwell record
+    pub min_dwell_ms: f64,
+}
+
+impl Default for AnalyticsOptions {
+    fn default() -> AnalyticsOptions {
+        AnalyticsOptions {
+            endpoint: None,
+            sample_rate: 1.0,
+            batch_size: 20,
+            flush_ms: 5000,
+            min_dwell_ms: 1000.0,
+        }
+    }
+}
+
+/// Every record has the milliseconds since the epoch it happened at
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AnalyticsRecord {
+    #[serde(rename_all = "camelCase")]
+    DiagramOpened {
+        url: String,
+        title: String,
+        /// 0 for the diagram the viewer started on
+        depth: usize,
+        timestamp: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    DrilledInto {
+        from: String,
+        to: String,
+        timestamp: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Dwell {
+        url: String,
+        view_box: ViewBox,
+        duration_ms: f64,
+        timestamp: f64,
+    },
+}
+
+/// Where records go and what's waiting to go there
+struct Sink {
+    endpoint: Option<String>,
+    handler: RefCell<Option<Function>>,
+    batch: RefCell<Vec<AnalyticsRecord>>,
+    /// The view we're on, and since when
+    dwelling: RefCell<Option<(String, ViewBox, f64)>>,
+    min_dwell_ms: f64,
+}
+
+/// Records what readers do with the diagrams, for teams that want to know how their
+/// documentation gets used
+pub struct Analytics {
+    sink: Rc<Sink>,
+    flush: Timeout,
+    batch_size: usize,
+    flush_ms: i32,
+    /// How deep the drill-down path was, so going deeper can be told from going back
+    depth: Cell<usize>,
+    _page_hide: Box<dyn JsEventListener>,
+}
+
+impl Analytics {
+    pub fn new(options: &AnalyticsOptions) -> Result<Option<Analytics>, JsValue> {
+        if Math::random() >= options.sample_rate {
+            return Ok(None);
+        }
+
+        let sink = Rc::new(Sink {
+            endpoint: options.endpoint.clone(),
+            handler: RefCell::new(None),
+            batch: RefCell::new(vec![]),
+            dwelling: RefCell::new(None),
+            min_dwell_ms: options.min_dwell_ms,
+        });
+
+        let flush = {
+            let sink = sink.clone();
+            Timeout::new(move || sink.flush())
+        };
+
+        // the page can be gone before the next flush
+        let page_hide = {
+            let sink = sink.clone();
+            window().new_event_listener("pagehide", move |_: Event| {
+                sink.end_dwell();
+                sink.flush();
+            })?
+        };
+
+        Ok(Some(Analytics {
+            sink,
+            flush,
+            batch_size: options.batch_size,
+            flush_ms: options.flush_ms,
+            depth: Cell::new(0),
+            _page_hide: page_hide,
+        }))
+    }
+
+    pub fn set_handler(&self, handler: Option<Function>) {
+        self.sink.handler.replace(handler);
+    }
+
+    pub fn navigated(&self, navigation: &[NavigationEntry]) {
+        let current = match navigation.last() {
+            Some(current) => current,
+            None => return,
+        };
+        let depth = navigation.len() - 1;
+
+        self.end_dwell();
+        if depth > self.depth.get() && depth > 0 {
+            self.record(AnalyticsRecord::DrilledInto {
+                from: navigation[depth - 1].url.clone(),
+                to: current.url.clone(),
+                timestamp: Date::now(),
+            });
+        }
+        self.depth.set(depth);
+
+        self.record(AnalyticsRecord::DiagramOpened {
+            url: current.url.clone(),
+            title: current.title.clone(),
+            depth,
+            timestamp: Date::now(),
+        });
+    }
+
+    /// Ends the dwell on the last view and starts one on `view_box`
+    pub fn view_changed(&self, url: &str, view_box: ViewBox) {
+        self.end_dwell();
+        self.sink
+            .dwelling
+            .replace(Some((url.to_string(), view_box, Date::now())));
+    }
+
+    fn end_dwell(&self) {
+        if self.sink.end_dwell() {
+            self.queued();
+        }
+    }
+
+    fn record(&self, record: AnalyticsRecord) {
+        self.sink.batch.borrow_mut().push(record);
+        self.queued();
+    }
+
+    fn queued(&self) {
+        let queued = self.sink.batch.borrow().len();
+        // always through the timeout, we're called while the viewer is borrowed and the
+        // handler could call back into it
+        if queued >= self.batch_size {
+            self.flush.restart(0);
+        } else if queued == 1 {
+            self.flush.restart(self.flush_ms);
+        }
+    }
+}
+
+impl Sink {
+    /// Records the view we were on if it was looked at long enough, returning whether it was
+    fn end_dwell(&self) -> bool {
+        let (url, view_box, since) = match self.dwelling.replace(None) {
+            Some(dwelling) => dwelling,
+            None => return false,
+        };
+
+        let timestamp = Date::now();
+        let duration_ms = timestamp - since;
+        if duration_ms < self.min_dwell_ms {
+            return false;
+        }
+
+        self.batch.borrow_mut().push(AnalyticsRecord::Dwell {
+            url,
+            view_box,
+            duration_ms,
+            timestamp,
+        });
+
+        true
+    }
+
+    fn flush(&self) {
+        let records = self.batch.replace(vec![]);
+        if records.is_empty() {
+            return;
+        }
+
+        // the handler can swap itself out
+        let handler = self.handler.borrow().clone();
+        if let Some(handler) = handler {
+            if let Err(error) =
+                to_js(&records).and_then(|records| handler.call1(&JsValue::NULL, &records))
+            {
+                console::warn_2(&"The analytics handler failed".into(), &error);
+            }
+        }
+
+        if let Some(ref endpoint) = self.endpoint {
+            let sent = serde_json::to_string(&records)
+                .map_err(|error| error.to_string().into())
+                .and_then(|json| {
+                    window()
+                        .navigator()
+                        .send_beacon_with_opt_str(endpoint, Some(&json))
+                });
+
+            match sent {
+                Ok(true) => (),
+                Ok(false) => console::warn_1(&"The browser refused to send the analytics".into()),
+                Err(error) => console::warn_2(&"Couldn't send the analytics".into(), &error),
+            }
+        }
+    }
+}
+
+impl Drop for Analytics {
+    fn drop(&mut self) {
+        self.sink.end_dwell();
+        self.sink.flush();
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+
+use crate::js_utils::window;
+use crate::zoom::easing::Easing;
+use crate::zoom::spring::{Spring, SpringState};
+use crate::zoom::viewport::Viewport;
+use crate::zoom::zoom_path::{ZoomPath, DEFAULT_RHO};
+
+/// How a programmatic change of the viewport is animated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum Transition {
+    Eased {
+        #[serde(default)]
+        easing: Easing,
+        #[serde(default = "default_duration_ms")]
+        duration_ms: f32,
+    },
+    /// Springs towards the target, which can be interrupted and picked up again without a jolt
+    Spring(Spring),
+    /// Zooms out and back in along a van Wijk & Nuij path, for moving between distant viewports
+    Flight {
+        #[serde(default)]
+        easing: Easing,
+        #[serde(default = "default_duration_ms")]
+        duration_ms: f32,
+    },
+}
+
+fn default_duration_ms() -> f32 {
+    300.0
+}
+
+/// How far along its path a flight travels per second, see `ZoomPath::length`
+static FLIGHT_SPEED: f32 = 1.2;
+static MIN_FLIGHT_MS: f32 = 400.0;
+static MAX_FLIGHT_MS: f32 = 2000.0;
+
+impl Transition {
+    /// How to move from `from` to `to`, which is `transition` unless `to` is out of sight.
+    /// Easing there would pan through nothing recognizable, so we fly instead, zooming out until
+    /// both ends are in view.
+    pub fn between(from: &Viewport, to: &Viewport, transition: Transition) -> Transition {
+        if from.view_box().intersects(&to.view_box()) {
+            return transition;
+        }
+
+        let path = ZoomPath::new(from, to, DEFAULT_RHO);
+
+        Transition::Flight {
+            easing: Easing::EaseInOut,
+            duration_ms: path.duration_ms(FLIGHT_SPEED, MIN_FLIGHT_MS, MAX_FLIGHT_MS),
+        }
+    }
+}
+
+impl Default for Transition {
+    fn default() -> Transition {
+        Transition::Eased {
+            easing: Easing::default(),
+            duration_ms: default_duration_ms(),
+        }
+    }
+}
+
+/// When to skip animations and jump straight to the new viewport
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MotionPreference {
+    /// Follow the `prefers-reduced-motion` media query
+    #[default]
+    Auto,
+    Reduce,
+    Full,
+}
+
+impl MotionPreference {
+    pub fn reduce_motion(self) -> bool {
+        match self {
+            MotionPreference::Auto => window()
+                .match_media("(prefers-reduced-motion: reduce)")
+                .ok()
+                .and_then(|query| query)
+                .map(|query| query.matches())
+                .unwrap_or(false),
+            MotionPreference::Reduce => true,
+            MotionPreference::Full => false,
+        }
+    }
+}
+
+/// An in flight animation from one viewport to another
+#[derive(Debug, Clone)]
+pub struct ViewportAnimation {
+    from: Viewport,
+    to: Viewport,
+    transition: Transition,
+    elapsed_ms: f32,
+    progress: SpringState,
+    path: Option<ZoomPath>,
+}
+
+impl ViewportAnimation {
+    pub fn new(from: Viewport, to: Viewport, transition: Transition) -> ViewportAnimation {
+        let path = match transition {
+            Transition::Flight { .. } => Some(ZoomPath::new(&from, &to, DEFAULT_RHO)),
+            _ => None,
+        };
+        let progress = match transition {
+            Transition::Spring(ref spring) => spring.start(0.0, 1.0),
+            _ => SpringState::default(),
+        };
+
+        ViewportAnimation {
+            path,
+            from,
+            to,
+            transition,
+            elapsed_ms: 0.0,
+            progress,
+        }
+    }
+
+    /// Advances the animation and returns the viewport for this frame
+    pub fn step(&mut self, delta_ms: f32) -> Viewport {
+        self.elapsed_ms += delta_ms;
+
+        let t = match self.transition {
+            Transition::Eased {
+                ref easing,
+                duration_ms,
+            }
+            | Transition::Flight {
+                ref easing,
+                duration_ms,
+            } => easing.evaluate(self.elapsed_ms / duration_ms.max(1.0)),
+            Transition::Spring(ref spring) => {
+                self.progress = spring.step(&self.progress, 1.0, delta_ms / 1000.0);
+
+                self.progress.position
+            }
+        };
+
+        if self.is_finished() {
+            self.to.clone()
+        } else if let Some(ref path) = self.path {
+            path.at(t)
+        } else {
+            self.from.interpolate_towards(&self.to, t)
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.transition {
+            Transition::Eased { duration_ms, .. } | Transition::Flight { duration_ms, .. } => {
+                self.elapsed_ms >= duration_ms
+            }
+            Transition::Spring(ref spring) => spring.is_at_rest(&self.progress, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zoom::matrix::{Point2D, Size2D};
+
+    fn viewport(x: f32, y: f32) -> Viewport {
+        Viewport::new(Point2D::new(x, y), Size2D::new(100.0, 100.0), 1.0)
+    }
+
+    #[test]
+    fn nearby_moves_keep_the_transition() {
+        let transition = Transition::between(
+            &viewport(0.0, 0.0),
+            &viewport(50.0, 50.0),
+            Transition::default(),
+        );
+
+        assert!(matches!(transition, Transition::Eased { .. }));
+    }
+
+    #[test]
+    fn out_of_sight_moves_fly() {
+        let transition = Transition::between(
+            &viewport(0.0, 0.0),
+            &viewport(5000.0, 0.0),
+            Transition::default(),
+        );
+
+        match transition {
+            Transition::Flight { duration_ms, .. } => {
+                assert!((MIN_FLIGHT_MS..=MAX_FLIGHT_MS).contains(&duration_ms))
+            }
+            _ => panic!("expected a flight, got {:?}", transition),
+        }
+    }
+
+    #[test]
+    fn flights_land_on_the_target() {
+        let from = viewport(0.0, 0.0);
+        let to = viewport(5000.0, 0.0);
+        let transition = Transition::between(&from, &to, Transition::default());
+        let mut animation = ViewportAnimation::new(from, to.clone(), transition);
+
+        let mut widest: f32 = 0.0;
+        while !animation.is_finished() {
+            widest = widest.max(animation.step(16.0).size.width);
+        }
+
+        // the flight zoomed out on the way
+        assert!(widest > 100.0);
+        assert!(animation.step(16.0).approx_eq(&to));
+    }
+}
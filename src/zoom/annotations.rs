@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// A note pinned to a point of a diagram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    #[serde(default)]
+    pub id: String,
+    /// The url of the diagram it's pinned to, the current diagram when it's added if missing
+    #[serde(default)]
+    pub diagram: Option<String>,
+    /// Where it's pinned in Svg User Coordinates
+    pub x: f32,
+    pub y: f32,
+    pub text: String,
+}
+
+/// All of the annotations across every diagram, serializable so they can be saved and restored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationSet {
+    pub annotations: Vec<Annotation>,
+    #[serde(default)]
+    next_id: u32,
+}
+
+impl AnnotationSet {
+    /// Adds `annotation`, replacing any with the same id, and returns its id. Annotations
+    /// without an id are given one.
+    pub fn add(&mut self, mut annotation: Annotation) -> String {
+        if annotation.id.is_empty() {
+            annotation.id = self.generate_id();
+        }
+
+        self.remove(&annotation.id);
+        let id = annotation.id.clone();
+        self.annotations.push(annotation);
+
+        id
+    }
+
+    /// Returns false if there wasn't an annotation with `id`
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.annotations.len();
+        self.annotations.retain(|annotation| annotation.id != id);
+
+        self.annotations.len() != before
+    }
+
+    /// The annotations pinned to the diagram at `url`
+    pub fn for_diagram<'a>(&'a self, url: &'a str) -> impl Iterator<Item = &'a Annotation> {
+        self.annotations
+            .iter()
+            .filter(move |annotation| annotation.diagram.as_deref() == Some(url))
+    }
+
+    fn generate_id(&mut self) -> String {
+        loop {
+            self.next_id += 1;
+
+            let id = format!("annotation-{}", self.next_id);
+            if !self
+                .annotations
+                .iter()
+                .any(|annotation| annotation.id == id)
+            {
+                return id;
+            }
+        }
+    }
+}
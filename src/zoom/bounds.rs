@@ -0,0 +1,89 @@
+use web_sys::{console, SvgGraphicsElement, SvgsvgElement};
+
+use crate::diagnostics;
+use crate::js_utils::EnhancedNodeList;
+
+use crate::zoom::matrix::{Margin, Matrix2D, Rect};
+use crate::zoom::viewport::Viewport;
+
+/// The bounding box of `element` in the User Coordinates of `svg`, including any transforms of
+/// the groups between them
+pub fn element_bounds(svg: &SvgsvgElement, element: &SvgGraphicsElement) -> Option<Rect> {
+    diagnostics::layout_read();
+    let element_box = match element.get_b_box() {
+        Ok(element_box) => element_box,
+        Err(error) => {
+            // elements that aren't rendered (display: none, inside <defs>, etc.) throw here
+            console::warn_2(&"Couldn't get the bounding box of".into(), &error);
+
+            return None;
+        }
+    };
+
+    Some(Rect::from_svg(&element_box).transformed_bounds(&to_root(svg, element)))
+}
+
+/// The transform from the User Coordinates of `element` to those of `svg`
+pub fn to_root(svg: &SvgsvgElement, element: &SvgGraphicsElement) -> Matrix2D {
+    svg.get_screen_ctm()
+        .and_then(|svg_matrix| svg_matrix.inverse().ok())
+        .and_then(|inverse| {
+            element
+                .get_screen_ctm()
+                .map(|element_matrix| inverse.multiply(&element_matrix))
+        })
+        .map(|matrix| Matrix2D::from_js(&matrix))
+        .unwrap_or_else(Matrix2D::identity)
+}
+
+/// The union of the bounding boxes of `elements` in the User Coordinates of `svg`. Elements
+/// without a bounding box are skipped and `None` is returned if none of them had one.
+pub fn union_bounds<'a, I>(svg: &SvgsvgElement, elements: I) -> Option<Rect>
+where
+    I: IntoIterator<Item = &'a SvgGraphicsElement>,
+{
+    elements
+        .into_iter()
+        .filter_map(|element| element_bounds(svg, element))
+        .fold(None, |union: Option<Rect>, bounds| match union {
+            Some(union) => Some(union.union(&bounds)),
+            None => Some(bounds),
+        })
+}
+
+/// The elements of `svg` matching `target`, which is either a CSS selector or an element id
+pub fn find_elements(svg: &SvgsvgElement, target: &str) -> Vec<SvgGraphicsElement> {
+    let by_selector = svg
+        .query_selector_all(target)
+        .map(|nodes| nodes.safe_filter::<SvgGraphicsElement>())
+        .unwrap_or_default();
+
+    if !by_selector.is_empty() {
+        return by_selector;
+    }
+
+    svg.query_selector_all(&format!("[id=\"{}\"]", target.replace('"', "\\\"")))
+        .map(|nodes| nodes.safe_filter::<SvgGraphicsElement>())
+        .unwrap_or_default()
+}
+
+/// The smallest viewport at the aspect ratio of `current` that shows everything matching
+/// `targets` (ids or selectors) with `margin` around it
+pub fn covering_viewport<S: AsRef<str>>(
+    svg: &SvgsvgElement,
+    targets: &[S],
+    current: &Viewport,
+    margin: &Margin,
+) -> Option<Viewport> {
+    let elements: Vec<SvgGraphicsElement> = targets
+        .iter()
+        .flat_map(|target| find_elements(svg, target.as_ref()))
+        .collect();
+
+    union_bounds(svg, elements.iter()).map(|bounds| {
+        let mut viewport = current.clone();
+        viewport.fit_with_margin(&bounds, margin);
+
+        viewport
+    })
+}
@@ -0,0 +1,125 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, BroadcastChannel, MessageEvent};
+
+use crate::js_utils::*;
+use crate::zoom::viewport::Viewport;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BroadcastOptions {
+    /// The name of the BroadcastChannel the view is shared on, nothing is shared without one
+    pub channel: Option<String>,
+    /// Follow the other tabs without sending them our own changes, for an audience that
+    /// shouldn't be able to drive the presenter
+    pub receive_only: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum BroadcastMessage {
+    Viewport { url: String, viewport: Viewport },
+    Navigate { url: String },
+}
+
+/// Shares the viewport and the diagram we're showing with other tabs on the same channel, and
+/// follows theirs
+pub struct Broadcaster {
+    channel: BroadcastChannel,
+    receive_only: bool,
+    /// Set while we're following another tab, so we don't send its change straight back
+    receiving: Rc<Cell<bool>>,
+    _listener: Box<dyn JsEventListener>,
+}
+
+impl Broadcaster {
+    pub fn new(
+        options: &BroadcastOptions,
+        archizoom: Weak<RefCell<ArchiZoom>>,
+    ) -> Result<Option<Broadcaster>, JsValue> {
+        let name = match options.channel {
+            Some(ref name) => name,
+            None => return Ok(None),
+        };
+
+        let channel = BroadcastChannel::new(name)?;
+        let receiving = Rc::new(Cell::new(false));
+
+        let listener = {
+            let receiving = receiving.clone();
+            channel.new_event_listener("message", move |event: MessageEvent| {
+                let message = match from_js::<BroadcastMessage>(&event.data()) {
+                    Ok(message) => message,
+                    Err(error) => {
+                        console::warn_2(&"Ignoring a broadcast we don't understand".into(), &error);
+                        return;
+                    }
+                };
+
+                if let Some(real_ref) = archizoom.upgrade() {
+                    receiving.set(true);
+                    receive(&real_ref.borrow(), message);
+                    receiving.set(false);
+                }
+            })?
+        };
+
+        Ok(Some(Broadcaster {
+            channel,
+            receive_only: options.receive_only,
+            receiving,
+            _listener: listener,
+        }))
+    }
+
+    pub fn viewport_changed(&self, url: &str, viewport: &Viewport) {
+        self.send(BroadcastMessage::Viewport {
+            url: url.to_string(),
+            viewport: viewport.clone(),
+        });
+    }
+
+    pub fn navigated(&self, url: &str) {
+        self.send(BroadcastMessage::Navigate {
+            url: url.to_string(),
+        });
+    }
+
+    fn send(&self, message: BroadcastMessage) {
+        if self.receive_only || self.receiving.get() {
+            return;
+        }
+
+        if let Err(error) = to_js(&message).and_then(|message| self.channel.post_message(&message))
+        {
+            console::warn_2(&"Couldn't broadcast the view".into(), &error);
+        }
+    }
+}
+
+fn receive(archizoom: &ArchiZoom, message: BroadcastMessage) {
+    match message {
+        BroadcastMessage::Viewport { url, viewport } => {
+            let showing = archizoom
+                .navigation()
+                .last()
+                .map(|entry| entry.url.as_str());
+
+            // it'll catch up once we're showing the same diagram
+            if showing == Some(url.as_str()) {
+                archizoom.follow_view(&viewport);
+            }
+        }
+        BroadcastMessage::Navigate { url } => archizoom.show_diagram(&url),
+    }
+}
+
+impl Drop for Broadcaster {
+    fn drop(&mut self) {
+        self.channel.close();
+    }
+}
@@ -0,0 +1,176 @@
+use std::cell::{Cell, RefCell};
+
+use serde::{Deserialize, Serialize};
+use web_sys::{console, SvgGraphicsElement, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::zoom::bounds::element_bounds;
+use crate::zoom::matrix::Rect;
+use crate::zoom::quadtree::QuadTree;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CullingOptions {
+    pub enabled: bool,
+    /// Which groups are hidden when they're out of view
+    pub selector: String,
+    /// How much of the view's size to keep rendered around it, so groups are already there as
+    /// they pan in
+    pub margin: f32,
+}
+
+impl Default for CullingOptions {
+    fn default() -> CullingOptions {
+        CullingOptions {
+            enabled: false,
+            selector: ":scope > g".to_string(),
+            margin: 0.25,
+        }
+    }
+}
+
+/// Hides the groups of a large diagram that are out of view so the browser doesn't paint them
+pub struct GroupCuller {
+    svg: SvgsvgElement,
+    groups: Vec<SvgGraphicsElement>,
+    margin: f32,
+    /// Built the first time we cull since the groups have to be rendered to be measured
+    index: RefCell<Option<CullIndex>>,
+    culled: RefCell<Vec<bool>>,
+    /// Set while `revealed` runs, so nested calls don't hide the groups early
+    revealing: Cell<bool>,
+}
+
+struct CullIndex {
+    tree: QuadTree<usize>,
+    /// Groups we couldn't measure are never culled
+    measured: Vec<bool>,
+}
+
+static CULLED_CLASS: &str = "archizoom-culled";
+
+impl GroupCuller {
+    pub fn new(svg: &SvgsvgElement, options: &CullingOptions) -> GroupCuller {
+        let groups = if options.enabled {
+            svg.query_selector_all(&options.selector)
+                .map(|nodes| nodes.safe_filter::<SvgGraphicsElement>())
+                .unwrap_or_default()
+        } else {
+            vec![]
+        };
+
+        GroupCuller {
+            svg: svg.clone(),
+            culled: RefCell::new(vec![false; groups.len()]),
+            groups,
+            margin: options.margin,
+            index: RefCell::new(None),
+            revealing: Cell::new(false),
+        }
+    }
+
+    /// Hides the groups outside of `visible` (in Svg User Coordinates) and shows the ones that
+    /// came back into it
+    pub fn update(&self, visible: &Rect) {
+        if self.groups.is_empty() {
+            return;
+        }
+
+        let mut index = self.index.borrow_mut();
+        let index = index.get_or_insert_with(|| self.build_index());
+
+        let margin = visible.width().max(visible.height()) * self.margin;
+        let mut in_view: Vec<bool> = index.measured.iter().map(|measured| !measured).collect();
+        for i in index.tree.query_rect(&visible.inflate(margin)) {
+            in_view[*i] = true;
+        }
+
+        // find every change before touching the DOM so reads and writes don't interleave
+        let mut culled = self.culled.borrow_mut();
+        let changes: Vec<(usize, bool)> = in_view
+            .iter()
+            .enumerate()
+            .map(|(i, in_view)| (i, !in_view))
+            .filter(|(i, hide)| culled[*i] != *hide)
+            .collect();
+
+        for (i, hide) in changes {
+            culled[i] = hide;
+            self.set_culled(i, hide);
+        }
+    }
+
+    /// Shows every group, so they can be measured. The next view update hides whatever is still
+    /// out of view.
+    pub fn reveal_all(&self) {
+        let mut culled = self.culled.borrow_mut();
+        for (i, culled) in culled.iter_mut().enumerate() {
+            if *culled {
+                *culled = false;
+                self.set_culled(i, false);
+            }
+        }
+    }
+
+    /// Shows every culled group while `f` runs, so the whole drawing can be measured or copied,
+    /// then hides them again. Nothing is painted in between.
+    pub fn revealed<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        if self.revealing.get() {
+            return f();
+        }
+
+        let hidden: Vec<usize> = self
+            .culled
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|(_, culled)| **culled)
+            .map(|(i, _)| i)
+            .collect();
+        for &i in hidden.iter() {
+            self.set_culled(i, false);
+        }
+
+        self.revealing.set(true);
+        let result = f();
+        self.revealing.set(false);
+
+        for &i in hidden.iter() {
+            self.set_culled(i, true);
+        }
+
+        result
+    }
+
+    /// Drops the measurements so they're taken again on the next update
+    pub fn invalidate(&self) {
+        self.index.replace(None);
+    }
+
+    fn build_index(&self) -> CullIndex {
+        let bounds: Vec<Option<Rect>> = self
+            .groups
+            .iter()
+            .map(|group| element_bounds(&self.svg, group))
+            .collect();
+
+        CullIndex {
+            measured: bounds.iter().map(Option::is_some).collect(),
+            tree: QuadTree::from_items(
+                bounds
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, rect)| rect.map(|rect| (rect, i))),
+            ),
+        }
+    }
+
+    fn set_culled(&self, i: usize, culled: bool) {
+        if let Err(error) = self.groups[i]
+            .class_list()
+            .toggle_with_force(CULLED_CLASS, culled)
+        {
+            console::warn_2(&"Couldn't cull a group".into(), &error);
+        }
+    }
+}
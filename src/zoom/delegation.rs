@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{AddEventListenerOptions, Element, Event, EventTarget, SvgsvgElement};
+
+use crate::js_utils::*;
+use crate::zoom::svg_view_controller::{
+    controller_events, handle_event, is_leave_event, SvgViewController,
+};
+
+/// One set of document listeners shared by every controller that delegates its events, so a
+/// page full of diagrams doesn't need a set of closures for each of them
+#[derive(Default)]
+struct Delegate {
+    controllers: RefCell<Vec<(SvgsvgElement, Weak<RefCell<SvgViewController>>)>>,
+    /// Added with the first controller and removed with the last
+    listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+}
+
+thread_local! {
+    static DELEGATE: Rc<Delegate> = Rc::new(Delegate::default());
+}
+
+/// Routes the controller's pointer and wheel events to it from the document. It stops when the
+/// returned listener is removed or dropped.
+pub fn delegate_events(
+    controller_ref: &Rc<RefCell<SvgViewController>>,
+) -> Result<Box<dyn JsEventListener>, JsValue> {
+    let svg = controller_ref.borrow().svg().clone();
+
+    DELEGATE.with(|delegate| {
+        if delegate.listeners.borrow().is_empty() {
+            delegate.listen()?;
+        }
+
+        delegate
+            .controllers
+            .borrow_mut()
+            .push((svg.clone(), Rc::downgrade(controller_ref)));
+
+        Ok(Box::new(Registration { svg: Some(svg) }) as Box<dyn JsEventListener>)
+    })
+}
+
+impl Delegate {
+    fn listen(&self) -> Result<(), JsValue> {
+        let document: EventTarget = document().into();
+
+        let mut listeners = vec![];
+        for (event_type, passive) in controller_events() {
+            // capture so we hear `pointerleave`, and say whether we're passive since the
+            // browser assumes document level wheel listeners are
+            let options = AddEventListenerOptions::new();
+            options.set_capture(true);
+            options.set_passive(passive);
+
+            listeners.push(document.new_event_listener_with_options(
+                event_type,
+                move |event: Event| {
+                    if let Some(controller_ref) = DELEGATE.with(|delegate| delegate.route(&event)) {
+                        handle_event(&controller_ref, event);
+                    }
+                },
+                options,
+            )?);
+        }
+
+        self.listeners.replace(listeners);
+
+        Ok(())
+    }
+
+    /// The controller whose svg the event happened in
+    fn route(&self, event: &Event) -> Option<Rc<RefCell<SvgViewController>>> {
+        let target = event.target()?.dyn_into::<Element>().ok()?;
+
+        // leaving a child of the svg isn't leaving the svg
+        let svg: Element = if is_leave_event(&event.type_()) {
+            target
+        } else {
+            target.closest("svg.archizoom-diagram").ok()??
+        };
+
+        self.controllers
+            .borrow()
+            .iter()
+            .find(|(controller_svg, _)| AsRef::<Element>::as_ref(controller_svg) == &svg)
+            .and_then(|(_, controller)| controller.upgrade())
+    }
+
+    fn unregister(&self, svg: &SvgsvgElement) {
+        let mut controllers = self.controllers.borrow_mut();
+        controllers.retain(|(controller_svg, _)| controller_svg != svg);
+
+        if controllers.is_empty() {
+            self.listeners.replace(vec![]);
+        }
+    }
+}
+
+struct Registration {
+    svg: Option<SvgsvgElement>,
+}
+
+impl JsEventListener for Registration {
+    fn remove(&mut self) {
+        if let Some(svg) = self.svg.take() {
+            // the delegate is already gone if the page is being torn down
+            let _ = DELEGATE.try_with(|delegate| delegate.unregister(&svg));
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
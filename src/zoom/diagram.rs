@@ -0,0 +1,359 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, HtmlDivElement, SvgGraphicsElement, SvgsvgElement, Url};
+
+use crate::diagnostics;
+use crate::events::EventSource;
+use crate::js_utils::*;
+use crate::options::Options;
+use crate::zoom::culling::GroupCuller;
+use crate::zoom::geometry::ScreenGeometry;
+use crate::zoom::hit_test::HitTester;
+use crate::zoom::layers::Layer;
+use crate::zoom::link_clicks::LinkClicks;
+use crate::zoom::matrix::Rect;
+use crate::zoom::metadata::ElementInfo;
+use crate::zoom::minify::minify_svg;
+use crate::zoom::progressive::ProgressiveInjection;
+#[cfg(feature = "search")]
+use crate::zoom::search::SearchIndex;
+use crate::zoom::selection::Selection;
+use crate::zoom::semantic_zoom::SemanticZoom;
+use crate::zoom::space::{ScreenRect, ScreenSpace, SvgUserSpace, Transform, UserRect};
+use crate::zoom::sprite::{has_group, is_shown, link_group, show_group, split_url, SpriteGroup};
+use crate::zoom::svg_view_controller::{SvgViewController, ViewUpdateEvent};
+use crate::zoom::text_culling::TextCuller;
+use crate::zoom::transform_cache::TransformCache;
+use crate::zoom::ArchiZoom;
+use crate::PREFIX_ALIAS;
+
+static X_LINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// A single loaded svg and everything wired up to it. Navigating swaps out the whole diagram,
+/// except between the groups of a sprite, which switch in place.
+pub struct Diagram {
+    pub url: String,
+    pub title: String,
+    pub svg: SvgsvgElement,
+    pub zoom_elements: Vec<ZoomElement>,
+    pub view_controller: Rc<RefCell<SvgViewController>>,
+    pub geometry: Rc<ScreenGeometry>,
+    pub transform_cache: Rc<TransformCache>,
+    pub hit_tester: Rc<HitTester>,
+    pub selection: Rc<Selection>,
+    #[cfg(feature = "search")]
+    pub search_index: SearchIndex,
+    pub layers: Vec<Layer>,
+    pub semantic_zoom: SemanticZoom,
+    pub culler: GroupCuller,
+    pub text_culler: TextCuller,
+    pub initial_view_box: Option<Rect>,
+    /// A sprite group without a view box of its own, framed once it's on screen
+    pub fit_on_show: bool,
+    /// The groups still waiting to be put back into a huge diagram
+    pub injection: Option<Rc<ProgressiveInjection>>,
+    link_clicks: RefCell<Option<LinkClicks>>,
+}
+
+pub struct ZoomElement {
+    /// The absolute url of the diagram this links to
+    pub target: String,
+    /// Usually an `<a>`, but any group, `<use>`, or nested `<svg>` can be one
+    pub link_element: SvgGraphicsElement,
+    /// What the diagram says about the linked element
+    pub info: ElementInfo,
+}
+
+impl Diagram {
+    pub fn new(
+        svg: SvgsvgElement,
+        url: &str,
+        container: &Element,
+        options: &Options,
+    ) -> Result<Diagram, JsValue> {
+        let sprite = show_group(&svg, url)?;
+        let zoom_elements = zoom_elements(&svg, url)?;
+
+        let geometry = ScreenGeometry::new(&svg)?;
+        let view_controller = SvgViewController::new(&svg, &geometry, options)?;
+        let transform_cache = TransformCache::new(&svg)?;
+        let hit_tester = HitTester::new(&svg)?;
+        let selection = Selection::new(&svg, container, &options.selection)?;
+        #[cfg(feature = "search")]
+        let search_index = SearchIndex::new(&svg);
+        let layers = Layer::find_all(&svg, &options.layers);
+        let semantic_zoom = SemanticZoom::new(&svg, &options.zoom_breakpoints, &options.overview);
+        let culler = GroupCuller::new(&svg, &options.culling);
+        let text_culler = TextCuller::new(&svg, &options.text_culling);
+        // everything above has seen the whole diagram, so it can be rearranged now
+        view_controller.borrow().wrap_content()?;
+        let injection = ProgressiveInjection::hold_back(&svg, &options.progressive_injection);
+
+        let initial_view_box = view_box(&svg);
+
+        Ok(Diagram {
+            url: url.to_string(),
+            title: sprite_title(&svg, url, sprite.as_ref(), options),
+            svg,
+            zoom_elements,
+            view_controller,
+            geometry,
+            transform_cache,
+            hit_tester,
+            selection,
+            #[cfg(feature = "search")]
+            search_index,
+            layers,
+            semantic_zoom,
+            culler,
+            text_culler,
+            initial_view_box,
+            fit_on_show: sprite.map(|sprite| sprite.fit).unwrap_or(false),
+            injection,
+            link_clicks: RefCell::new(None),
+        })
+    }
+
+    /// Whether `url` opens another group of this sprite, which `show_group` can switch to
+    pub fn has_group(&self, url: &str) -> bool {
+        split_url(url).0 == split_url(&self.url).0 && has_group(&self.svg, url)
+    }
+
+    /// Switches a sprite to the group `url` opens in place, picking up its links, labels, and
+    /// layers. Its view is framed by `after_navigation` like any other diagram's.
+    pub fn show_group(&mut self, url: &str, options: &Options) -> Result<(), JsValue> {
+        let sprite = show_group(&self.svg, url)?;
+
+        self.url = url.to_string();
+        self.title = sprite_title(&self.svg, url, sprite.as_ref(), options);
+        self.zoom_elements = zoom_elements(&self.svg, url)?;
+        #[cfg(feature = "search")]
+        {
+            self.search_index = SearchIndex::new(&self.svg);
+        }
+        self.layers = Layer::find_all(&self.svg, &options.layers);
+        self.initial_view_box = view_box(&self.svg);
+        self.fit_on_show = sprite.map(|sprite| sprite.fit).unwrap_or(false);
+
+        // everything measured was measured with the other group showing
+        self.geometry.invalidate();
+        self.transform_cache.clear();
+        self.culler.invalidate();
+        self.text_culler.invalidate();
+        self.hit_tester.invalidate();
+        self.view_controller
+            .borrow()
+            .set_fitted_view_box(self.initial_view_box.clone());
+
+        Ok(())
+    }
+
+    /// Forwards view updates of this diagram to `archizoom`
+    pub fn attach(&self, archizoom: Weak<RefCell<ArchiZoom>>) {
+        match LinkClicks::new(&self.svg, archizoom.clone()) {
+            Ok(link_clicks) => {
+                self.link_clicks.replace(Some(link_clicks));
+            }
+            Err(error) => console::warn_2(&"Couldn't listen for link clicks".into(), &error),
+        }
+
+        if let Some(injection) = &self.injection {
+            let archizoom = archizoom.clone();
+            ProgressiveInjection::start(injection, move |span| {
+                if let Some(real_ref) = archizoom.upgrade() {
+                    real_ref.borrow().injection_finished(span)
+                }
+            });
+        }
+
+        self.view_controller
+            .borrow_mut()
+            .register_listener(move |e: &ViewUpdateEvent| {
+                if let Some(real_ref) = archizoom.upgrade() {
+                    real_ref.borrow().view_update(e)
+                }
+            });
+    }
+}
+
+impl ZoomElement {
+    /// Gets the on screen bounds of the element
+    pub fn element_rect(
+        &self,
+        geometry: &ScreenGeometry,
+        cache: &TransformCache,
+    ) -> Option<ScreenRect> {
+        // prefer the cached transform to the root, it's unaffected by panning and zooming. The
+        // cache doesn't know about nested svgs, so those use their own CTM, which includes the
+        // viewBox their bounding box is in.
+        let to_screen = match (
+            geometry.screen_ctm(),
+            cache.user_transform(&self.link_element),
+        ) {
+            (Some(svg_to_screen), Some(to_svg)) => {
+                Some(Transform::<SvgUserSpace, ScreenSpace>::new(
+                    svg_to_screen.matrix().multiply(&to_svg),
+                ))
+            }
+            _ => geometry.element_ctm(&self.link_element),
+        };
+
+        diagnostics::layout_read();
+        self.link_element.get_b_box().ok().and_then(|element_box| {
+            to_screen.map(|to_screen| to_screen.rect(&UserRect::new(Rect::from_svg(&element_box))))
+        })
+    }
+}
+
+/// Parses svg markup into a detached svg element sized to fill its container
+pub fn parse_svg(text: &str, options: &Options) -> Result<SvgsvgElement, JsValue> {
+    let parser = document()
+        .safe_create_element::<HtmlDivElement>("div")
+        .ok_or_else(|| JsValue::from("Couldn't create an element to parse the svg"))?;
+    parser.set_inner_html(text);
+
+    // find the embedded SvgsvgElement
+    let svg = parser
+        .first_element_child()
+        .ok_or_else(|| JsValue::from("The diagram doesn't contain any elements"))
+        .and_then(|child| child.dyn_into::<SvgsvgElement>().map_err(|e| e.into()))?;
+
+    if options.minify {
+        minify_svg(&svg, text);
+    }
+
+    svg.class_list().add_1("archizoom-diagram")?;
+    svg.style().set_property("height", "100%")?;
+    svg.style().set_property("width", "100%")?;
+    // exports can turn pointer events off on the root, then presses on the empty space between
+    // shapes fall through to the page and only grabbing a shape pans. The root's own box always
+    // catches them otherwise, painted or not.
+    svg.style().set_property("pointer-events", "auto")?;
+
+    Ok(svg)
+}
+
+fn zoom_elements(svg: &SvgsvgElement, url: &str) -> Result<Vec<ZoomElement>, JsValue> {
+    Ok(zoom_links(svg)?
+        .into_iter()
+        .filter_map(|(link_element, href)| {
+            let target = link_target(&href, url)?;
+
+            Some(ZoomElement {
+                target,
+                info: ElementInfo::read_link(&link_element),
+                link_element,
+            })
+        })
+        .collect())
+}
+
+fn view_box(svg: &SvgsvgElement) -> Option<Rect> {
+    svg.view_box()
+        .base_val()
+        .map(|view_box| Rect::from_svg(&view_box))
+}
+
+/// A sprite group's own title, or the diagram's
+fn sprite_title(
+    svg: &SvgsvgElement,
+    url: &str,
+    sprite: Option<&SpriteGroup>,
+    options: &Options,
+) -> String {
+    match sprite.and_then(|sprite| sprite.title.clone()) {
+        Some(title) => title,
+        None => diagram_title(svg, url, &options.titles),
+    }
+}
+
+/// The elements linking to other diagrams, with the href each one links with. That's either a
+/// link marker in the element's own href, like `child.svg#archizoom:link` on an `<a>` or
+/// `<use>`, or a `data-archizoom-link` attribute, which marks groups and nested svgs that can't
+/// have an href. Links in hidden sprite groups are left out.
+fn zoom_links(svg: &SvgsvgElement) -> Result<Vec<(SvgGraphicsElement, String)>, JsValue> {
+    let link_attribute = format!("data-{}-link", PREFIX_ALIAS);
+
+    Ok(svg
+        .query_selector_all(&format!(
+            "[*|href*=\"#{}:link\"], [{}]",
+            PREFIX_ALIAS, link_attribute
+        ))?
+        .safe_filter::<SvgGraphicsElement>()
+        .into_iter()
+        .filter(|element| is_shown(element))
+        .filter_map(|element| {
+            let href = element
+                .get_attribute(&link_attribute)
+                .or_else(|| element.get_attribute("href"))
+                .or_else(|| element.get_attribute_ns(Some(X_LINK_NS), "href"))?;
+
+            Some((element, href))
+        })
+        .collect())
+}
+
+/// The urls of the diagrams that a diagram at `url` links to, without touching the links
+#[cfg(feature = "search")]
+pub fn link_targets(svg: &SvgsvgElement, url: &str) -> Vec<String> {
+    let hrefs: Vec<String> = zoom_links(svg)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, href)| href)
+        .collect();
+
+    resolve_targets(&hrefs, url)
+}
+
+/// The urls of the diagrams that zoom links written as `hrefs` open from a diagram at `url`
+pub fn resolve_targets(hrefs: &[String], url: &str) -> Vec<String> {
+    let mut targets: Vec<String> = vec![];
+    for href in hrefs {
+        if let Some(target) = link_target(href, url) {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+
+    targets
+}
+
+/// The absolute url of the diagram a zoom link opens, with the sprite group it names if any
+fn link_target(href: &str, base: &str) -> Option<String> {
+    let file = resolve_link(href, base)?;
+
+    Some(match link_group(href) {
+        Some(group) => format!("{}#{}", file, group),
+        None => file,
+    })
+}
+
+/// The absolute url, without the fragment, of a link relative to the diagram it's in
+fn resolve_link(href: &str, base: &str) -> Option<String> {
+    Url::new_with_base(href, base).ok().map(|url| {
+        url.set_hash("");
+
+        url.href()
+    })
+}
+
+/// The name from the `titles` manifest, the svg's own `<title>`, or the file name if it doesn't
+/// have one. The manifest is keyed by either the url or the file name.
+pub fn diagram_title(svg: &SvgsvgElement, url: &str, titles: &HashMap<String, String>) -> String {
+    let file_name = url.rsplit('/').next().unwrap_or(url);
+    if let Some(title) = titles.get(url).or_else(|| titles.get(file_name)) {
+        return title.clone();
+    }
+
+    svg.query_selector(":scope > title")
+        .ok()
+        .and_then(|title| title)
+        .and_then(|title| title.text_content())
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| file_name.trim_end_matches(".svg").to_string())
+}
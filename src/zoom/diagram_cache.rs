@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+
+use crate::zoom::sprite::split_url;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiagramCacheOptions {
+    /// Roughly how many bytes of diagrams to keep around, counting the one on screen. 0 turns
+    /// the cache off.
+    pub budget: usize,
+}
+
+impl Default for DiagramCacheOptions {
+    fn default() -> DiagramCacheOptions {
+        DiagramCacheOptions {
+            budget: 32 * 1024 * 1024,
+        }
+    }
+}
+
+/// The markup of diagrams we've shown, so going back up the drill-down path doesn't have to
+/// fetch them again. The least recently viewed are dropped once the budget is exceeded and
+/// fetched again if they're needed. The groups of a sprite share their file's entry.
+pub struct DiagramCache {
+    budget: usize,
+    /// Least recently viewed first
+    entries: RefCell<Vec<CacheEntry>>,
+}
+
+struct CacheEntry {
+    url: String,
+    text: String,
+}
+
+impl CacheEntry {
+    /// The DOM built from the markup takes a few times its size, but that's only alive for the
+    /// mounted diagram so we only count the text
+    fn size(&self) -> usize {
+        self.text.len()
+    }
+}
+
+impl DiagramCache {
+    pub fn new(options: &DiagramCacheOptions) -> DiagramCache {
+        DiagramCache {
+            budget: options.budget,
+            entries: RefCell::new(vec![]),
+        }
+    }
+
+    /// The cached markup for `url`, marking it as the most recently viewed
+    pub fn get(&self, url: &str) -> Option<String> {
+        let url = file_url(url);
+        let mut entries = self.entries.borrow_mut();
+        let index = entries.iter().position(|entry| entry.url == url)?;
+        let entry = entries.remove(index);
+        let text = entry.text.clone();
+        entries.push(entry);
+
+        Some(text)
+    }
+
+    /// Caches the markup of the diagram that was just mounted, evicting the least recently
+    /// viewed diagrams until everything fits. The mounted diagram is never evicted, even if it's
+    /// over budget on its own.
+    pub fn insert(&self, url: &str, text: String) {
+        if self.budget == 0 {
+            return;
+        }
+
+        let url = file_url(url);
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|entry| entry.url != url);
+        entries.push(CacheEntry {
+            url: url.to_string(),
+            text,
+        });
+
+        self.evict(&mut entries);
+    }
+
+    /// Caches the markup of a diagram we expect to be opened soon. It goes right behind the
+    /// mounted diagram so it can't push that one out.
+    pub fn insert_prefetched(&self, url: &str, text: String) {
+        if self.budget == 0 || self.contains(url) {
+            return;
+        }
+
+        let url = file_url(url);
+        let mut entries = self.entries.borrow_mut();
+        let index = entries.len().saturating_sub(1);
+        entries.insert(
+            index,
+            CacheEntry {
+                url: url.to_string(),
+                text,
+            },
+        );
+
+        self.evict(&mut entries);
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        let url = file_url(url);
+        self.entries.borrow().iter().any(|entry| entry.url == url)
+    }
+
+    fn evict(&self, entries: &mut Vec<CacheEntry>) {
+        let mut total: usize = entries.iter().map(CacheEntry::size).sum();
+        while total > self.budget && entries.len() > 1 {
+            total -= entries.remove(0).size();
+        }
+    }
+}
+
+fn file_url(url: &str) -> &str {
+    split_url(url).0
+}
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, Node, SvgGraphicsElement, SvgsvgElement};
+
+use crate::js_utils::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiffOptions {
+    /// The elements matched up by id between the two versions
+    pub selector: String,
+}
+
+impl Default for DiffOptions {
+    fn default() -> DiffOptions {
+        DiffOptions {
+            selector: "g[id]".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    fn class_name(self) -> &'static str {
+        match self {
+            Change::Added => "archizoom-diff-added",
+            Change::Removed => "archizoom-diff-removed",
+            Change::Changed => "archizoom-diff-changed",
+        }
+    }
+}
+
+/// What changed between an earlier version of a diagram and the one showing, and which change
+/// we're showing. Elements that were removed are drawn back in as ghosts. Dropping the diff
+/// clears all of it.
+pub struct Diff {
+    /// In document order, the removed ones after the rest
+    pub changes: Vec<(Change, SvgGraphicsElement)>,
+    pub current: Option<usize>,
+    ghosts: Option<Element>,
+}
+
+static CURRENT_CLASS: &str = "archizoom-diff-current";
+
+impl Diff {
+    /// Compares the `options.selector` elements of `svg` against the ones with the same id in
+    /// `base`. Changes nested inside of another change aren't counted separately.
+    pub fn new(svg: &SvgsvgElement, base: &SvgsvgElement, options: &DiffOptions) -> Diff {
+        let current = by_id(svg, &options.selector);
+        let previous = by_id(base, &options.selector);
+        let previous_by_id: HashMap<&str, &Element> = previous
+            .iter()
+            .map(|(id, element)| (id.as_str(), element))
+            .collect();
+
+        let mut changes: Vec<(Change, SvgGraphicsElement)> = vec![];
+        for (id, element) in current.iter() {
+            let change = match previous_by_id.get(id.as_str()) {
+                None => Change::Added,
+                Some(previous) if signature(element) != signature(previous) => Change::Changed,
+                Some(_) => continue,
+            };
+
+            // everything inside of something new is new too
+            let nested = change == Change::Added
+                && changes.iter().any(|(outer_change, outer)| {
+                    *outer_change == Change::Added && outer.contains(Some(element))
+                });
+            if !nested {
+                if let Some(element) = element.dyn_ref::<SvgGraphicsElement>() {
+                    changes.push((change, element.clone()));
+                }
+            }
+        }
+
+        let ghosts = ghost_layer(svg);
+        let mut removed: Vec<Element> = vec![];
+        let current_ids: HashSet<&str> = current.iter().map(|(id, _)| id.as_str()).collect();
+        for (id, element) in previous.iter() {
+            if current_ids.contains(id.as_str())
+                || removed.iter().any(|outer| outer.contains(Some(element)))
+            {
+                continue;
+            }
+            removed.push(element.clone());
+
+            match (&ghosts, ghost(base, element, id)) {
+                (Some(ghosts), Ok(ghost)) => {
+                    if let Err(error) = ghosts.append_child(&ghost) {
+                        console::warn_2(&"Couldn't draw a removed element".into(), &error);
+                    }
+                    changes.push((Change::Removed, ghost));
+                }
+                (_, Err(error)) => {
+                    console::warn_2(&"Couldn't draw a removed element".into(), &error)
+                }
+                (None, _) => (),
+            }
+        }
+
+        for (change, element) in changes.iter() {
+            mark(element, change.class_name(), true);
+        }
+
+        Diff {
+            changes,
+            current: None,
+            ghosts,
+        }
+    }
+
+    pub fn count(&self, change: Change) -> usize {
+        self.changes
+            .iter()
+            .filter(|(this_change, _)| *this_change == change)
+            .count()
+    }
+
+    /// Moves to the next or previous change, wrapping around at the ends
+    pub fn step(&mut self, forward: bool) -> Option<&SvgGraphicsElement> {
+        let next = step_index(self.current, self.changes.len(), forward)?;
+
+        if let Some(previous) = self.current_change() {
+            mark(previous, CURRENT_CLASS, false);
+        }
+        self.current = Some(next);
+
+        let (_, current) = &self.changes[next];
+        mark(current, CURRENT_CLASS, true);
+
+        Some(current)
+    }
+
+    pub fn current_change(&self) -> Option<&SvgGraphicsElement> {
+        self.current
+            .and_then(|current| self.changes.get(current))
+            .map(|(_, element)| element)
+    }
+}
+
+/// The index after or before `current` out of `count`, wrapping around at the ends. Nothing is
+/// current yet starts from whichever end we're heading away from.
+fn step_index(current: Option<usize>, count: usize, forward: bool) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+
+    Some(match current {
+        Some(current) if forward => (current + 1) % count,
+        Some(current) => (current + count - 1) % count,
+        None if forward => 0,
+        None => count - 1,
+    })
+}
+
+impl Drop for Diff {
+    fn drop(&mut self) {
+        for (change, element) in self.changes.iter() {
+            mark(element, change.class_name(), false);
+            mark(element, CURRENT_CLASS, false);
+        }
+
+        if let Some(ref ghosts) = self.ghosts {
+            ghosts.remove();
+        }
+    }
+}
+
+/// The elements matching `selector` by their id, in document order
+fn by_id(svg: &SvgsvgElement, selector: &str) -> Vec<(String, Element)> {
+    svg.query_selector_all(selector)
+        .map(|nodes| nodes.safe_filter::<Element>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|element| !element.id().is_empty())
+        .map(|element| (element.id(), element))
+        .collect()
+}
+
+/// Everything about `element` that shows, without the elements inside it that are compared on
+/// their own or the classes we add
+fn signature(element: &Element) -> String {
+    let mut names: Vec<String> = element
+        .get_attribute_names()
+        .iter()
+        .filter_map(|name| name.as_string())
+        .collect();
+    names.sort();
+
+    let mut signature = element.tag_name();
+    for name in names.iter() {
+        let value = element.get_attribute(name).unwrap_or_default();
+        let value = if name == "class" {
+            value
+                .split_whitespace()
+                .filter(|class_name| !class_name.starts_with("archizoom-"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        } else {
+            value
+        };
+        signature.push_str(&format!(" {}={:?}", name, value));
+    }
+
+    let mut child = element.first_child();
+    while let Some(node) = child {
+        child = node.next_sibling();
+
+        match node.dyn_ref::<Element>() {
+            Some(element) if element.id().is_empty() => {
+                signature.push_str(&format!("<{}>", self::signature(element)))
+            }
+            Some(_) => (),
+            None if node.node_type() == Node::TEXT_NODE => {
+                signature.push_str(node.text_content().unwrap_or_default().trim())
+            }
+            None => (),
+        }
+    }
+
+    signature
+}
+
+/// The group the ghosts of removed elements go in, inside the content wrapper if there is one
+/// so gestures move them too
+fn ghost_layer(svg: &SvgsvgElement) -> Option<Element> {
+    let parent: Element = svg
+        .query_selector(":scope > .archizoom-content")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| svg.clone().into());
+
+    let layer = document().safe_create_element_ns::<Element>(Some(SVG_NS), "g")?;
+    layer.set_attribute("class", "archizoom-diff-ghosts").ok()?;
+    parent.append_child(&layer).ok()?;
+
+    Some(layer)
+}
+
+/// A copy of `element` from `base`, positioned the way its ancestors positioned it there
+fn ghost(base: &SvgsvgElement, element: &Element, id: &str) -> Result<SvgGraphicsElement, JsValue> {
+    let ghost = document()
+        .safe_create_element_ns::<SvgGraphicsElement>(Some(SVG_NS), "g")
+        .ok_or_else(|| JsValue::from("Couldn't create a removed element"))?;
+    ghost.set_id(&format!("archizoom-removed-{}", id));
+
+    // nested transforms apply the same as a list of them, outermost first
+    let base_root: &Element = base.as_ref();
+    let mut transforms = vec![];
+    let mut ancestor = element.parent_element();
+    while let Some(parent) = ancestor {
+        if parent == *base_root {
+            break;
+        }
+        if let Some(transform) = parent.get_attribute("transform") {
+            transforms.push(transform);
+        }
+        ancestor = parent.parent_element();
+    }
+    transforms.reverse();
+    if !transforms.is_empty() {
+        ghost.set_attribute("transform", &transforms.join(" "))?;
+    }
+
+    let copy = element.clone_without_ids()?;
+    ghost.append_child(&copy)?;
+
+    Ok(ghost)
+}
+
+fn mark(element: &SvgGraphicsElement, class_name: &str, on: bool) {
+    let class_list = element.class_list();
+    let result = if on {
+        class_list.add_1(class_name)
+    } else {
+        class_list.remove_1(class_name)
+    };
+
+    if let Err(error) = result {
+        console::warn_2(&"Couldn't mark a change".into(), &error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stepping_wraps_around_the_ends() {
+        assert_eq!(step_index(Some(2), 3, true), Some(0));
+        assert_eq!(step_index(Some(0), 3, false), Some(2));
+        assert_eq!(step_index(Some(1), 3, true), Some(2));
+        assert_eq!(step_index(Some(1), 3, false), Some(0));
+        assert_eq!(step_index(Some(0), 1, true), Some(0));
+    }
+
+    #[test]
+    fn stepping_starts_from_either_end() {
+        assert_eq!(step_index(None, 4, true), Some(0));
+        assert_eq!(step_index(None, 4, false), Some(3));
+        assert_eq!(step_index(None, 0, true), None);
+        assert_eq!(step_index(Some(0), 0, false), None);
+    }
+}
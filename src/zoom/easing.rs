@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+/// Easing curves for animations, mapping a linear progress `t` in [0, 1] to an eased progress
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+    /// A CSS style cubic-bezier(x1, y1, x2, y2)
+    CubicBezier(f32, f32, f32, f32),
+}
+
+static BEZIER_ITERATIONS: usize = 8;
+static BEZIER_EPSILON: f32 = 1e-5;
+
+impl Easing {
+    pub fn evaluate(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            Easing::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            Easing::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+/// Evaluates a bezier from (0, 0) to (1, 1) with the control points (x1, y1) and (x2, y2) at `x`
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    #[inline]
+    fn sample(p1: f32, p2: f32, t: f32) -> f32 {
+        let inverse = 1.0 - t;
+
+        (3.0 * inverse * inverse * t * p1) + (3.0 * inverse * t * t * p2) + (t * t * t)
+    }
+
+    #[inline]
+    fn slope(p1: f32, p2: f32, t: f32) -> f32 {
+        let inverse = 1.0 - t;
+
+        (3.0 * inverse * inverse * p1)
+            + (6.0 * inverse * t * (p2 - p1))
+            + (3.0 * t * t * (1.0 - p2))
+    }
+
+    // solve for the t that gives us x with Newton's method, then bisect if that doesn't converge
+    let mut t = x;
+    for _ in 0..BEZIER_ITERATIONS {
+        let error = sample(x1, x2, t) - x;
+        if error.abs() < BEZIER_EPSILON {
+            return sample(y1, y2, t);
+        }
+
+        let derivative = slope(x1, x2, t);
+        if derivative.abs() < BEZIER_EPSILON {
+            break;
+        }
+
+        t -= error / derivative;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    t = x;
+    while high - low > BEZIER_EPSILON {
+        if sample(x1, x2, t) < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.0;
+    }
+
+    sample(y1, y2, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn easings() -> Vec<Easing> {
+        vec![
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        ]
+    }
+
+    #[test]
+    fn easings_start_at_0_and_end_at_1() {
+        for easing in easings() {
+            assert!(easing.evaluate(0.0).abs() < 1e-4, "{:?}", easing);
+            assert!((easing.evaluate(1.0) - 1.0).abs() < 1e-4, "{:?}", easing);
+            // progress outside of [0, 1] is clamped
+            assert!(easing.evaluate(-1.0).abs() < 1e-4, "{:?}", easing);
+            assert!((easing.evaluate(2.0) - 1.0).abs() < 1e-4, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn easings_never_go_backwards() {
+        for easing in easings() {
+            let mut last = easing.evaluate(0.0);
+            for step in 1..=100 {
+                let eased = easing.evaluate(step as f32 / 100.0);
+                assert!(eased >= last - 1e-5, "{:?} at {}", easing, step);
+
+                last = eased;
+            }
+        }
+    }
+
+    #[test]
+    fn curves_newtons_method_cant_solve_are_bisected() {
+        // Newton's method wanders off into this curve's flat middle and runs out of iterations
+        let easing = Easing::CubicBezier(1.0, 0.0, 0.0, 1.0);
+
+        assert!((easing.evaluate(0.45) - 0.1769).abs() < 1e-3);
+        assert!((easing.evaluate(0.55) - 0.8231).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,179 @@
+use std::cell::RefCell;
+use std::rc::Weak;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, MessageEvent, Window};
+
+use crate::js_utils::*;
+use crate::zoom::matrix::Rect;
+use crate::zoom::view_link::same_origin;
+use crate::zoom::viewport::{ViewBox, Viewport};
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EmbedOptions {
+    /// Take commands from the page we're framed in and tell it when the view changes
+    pub enabled: bool,
+    /// Only take messages from, and send them to, this origin. It has to be set for `enabled`
+    /// to do anything, otherwise any page could frame us and drive the viewer.
+    pub parent_origin: Option<String>,
+}
+
+/// What the parent page can ask of us
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum EmbedCommand {
+    #[serde(rename_all = "camelCase")]
+    SetView {
+        view_box: ViewBox,
+    },
+    Navigate {
+        url: String,
+    },
+    GetState,
+}
+
+/// What we tell the parent page, after each navigation, once the view settles, and when
+/// it asks
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum EmbedMessage {
+    #[serde(rename_all = "camelCase")]
+    State {
+        url: String,
+        title: String,
+        /// The urls of the drill-down path, ending with the diagram we're showing
+        path: Vec<String>,
+        view_box: Option<ViewBox>,
+        zoom: Option<f32>,
+    },
+}
+
+/// How long the view has to stay put before the parent hears about it
+static SETTLE_MS: i32 = 150;
+
+/// Lets the page we're in an iframe of remote control the viewer with postMessage
+pub struct Embed {
+    view_settled: Timeout,
+    _listener: Box<dyn JsEventListener>,
+}
+
+impl Embed {
+    pub fn new(
+        options: &EmbedOptions,
+        archizoom: Weak<RefCell<ArchiZoom>>,
+    ) -> Result<Option<Embed>, JsValue> {
+        let parent = match window().parent()? {
+            Some(parent) if options.enabled && parent != window() => parent,
+            _ => return Ok(None),
+        };
+        let parent_origin = match options.parent_origin {
+            Some(ref origin) if !origin.is_empty() && origin != "*" => origin.clone(),
+            _ => {
+                console::warn_1(&"Embedding needs a parentOrigin to talk to, ignoring it".into());
+                return Ok(None);
+            }
+        };
+
+        let view_settled = {
+            let archizoom = archizoom.clone();
+            let parent = parent.clone();
+            let parent_origin = parent_origin.clone();
+            Timeout::new(move || {
+                if let Some(real_ref) = archizoom.upgrade() {
+                    send_state(&real_ref.borrow(), &parent, &parent_origin);
+                }
+            })
+        };
+
+        let parent_value: JsValue = parent.clone().into();
+        let listener = window().new_event_listener("message", move |event: MessageEvent| {
+            let from_parent = event
+                .source()
+                .map(|source| JsValue::from(source) == parent_value)
+                .unwrap_or(false);
+            if !from_parent || event.origin() != parent_origin {
+                return;
+            }
+
+            // the parent can be talking to anything else in here too
+            let command = match from_js::<EmbedCommand>(&event.data()) {
+                Ok(command) => command,
+                Err(_) => return,
+            };
+
+            if let Some(real_ref) = archizoom.upgrade() {
+                let archizoom = real_ref.borrow();
+                match command {
+                    EmbedCommand::SetView { view_box } => set_view(&archizoom, &view_box),
+                    EmbedCommand::Navigate { url } => {
+                        // even a trusted parent only gets to open diagrams from our own origin
+                        if let Some(url) = same_origin(&url) {
+                            archizoom.show_diagram(&url);
+                        }
+                    }
+                    EmbedCommand::GetState => send_state(&archizoom, &parent, &parent_origin),
+                }
+            }
+        })?;
+
+        Ok(Some(Embed {
+            view_settled,
+            _listener: listener,
+        }))
+    }
+
+    pub fn view_changed(&self) {
+        self.view_settled.restart(SETTLE_MS);
+    }
+
+    pub fn navigated(&self) {
+        // the settled view of the new diagram says where we went
+        self.view_settled.restart(0);
+    }
+}
+
+fn set_view(archizoom: &ArchiZoom, view_box: &ViewBox) {
+    if !(view_box.width > 0.0 && view_box.height > 0.0) {
+        console::warn_1(&"Ignoring an empty view from the parent page".into());
+        return;
+    }
+
+    if let Some(current) = archizoom.viewport() {
+        archizoom.set_viewport(&Viewport::from_view_box(
+            &Rect::from(view_box),
+            &current.size.scale(current.scale),
+        ));
+    }
+}
+
+fn send_state(archizoom: &ArchiZoom, parent: &Window, parent_origin: &str) {
+    let navigation = archizoom.navigation();
+    let (url, title) = match navigation.last() {
+        Some(entry) => (entry.url.clone(), entry.title.clone()),
+        None => return,
+    };
+
+    let view_box = archizoom
+        .viewport()
+        .map(|viewport| ViewBox::from(&viewport.view_box()));
+
+    let message = EmbedMessage::State {
+        url,
+        title,
+        path: navigation.iter().map(|entry| entry.url.clone()).collect(),
+        view_box,
+        zoom: archizoom.zoom_level(),
+    };
+
+    if let Err(error) =
+        to_js(&message).and_then(|message| parent.post_message(&message, parent_origin))
+    {
+        console::warn_2(
+            &"Couldn't tell the parent page about the view".into(),
+            &error,
+        );
+    }
+}
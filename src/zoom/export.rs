@@ -0,0 +1,312 @@
+use std::collections::HashSet;
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, SvgsvgElement, XmlSerializer};
+
+use crate::js_utils::*;
+use crate::zoom::viewport::Viewport;
+
+/// What our stylesheet can hide, which a standalone copy won't have to hide it again
+static HIDEABLE: &str = ".archizoom-detail, [data-archizoom-detail], [data-archizoom-overview]";
+
+/// Serializes a copy of `svg` cropped to `viewport`, that shows what we're showing without
+/// needing anything from the page
+pub fn export_svg(svg: &SvgsvgElement, viewport: &Viewport) -> Result<String, JsValue> {
+    let copy: Element = svg.clone_node_with_deep(true)?.dyn_into()?;
+
+    // the copy won't have our stylesheet, so drop whatever it's hiding right now instead
+    let live_hideable = svg.query_selector_all(HIDEABLE)?.safe_filter::<Element>();
+    let copy_hideable = copy.query_selector_all(HIDEABLE)?.safe_filter::<Element>();
+    for (live, copied) in live_hideable.iter().zip(copy_hideable.iter()) {
+        let hidden = window()
+            .get_computed_style(live)?
+            .map(|style| style.get_property_value("display"))
+            .transpose()?
+            .map(|display| display == "none")
+            .unwrap_or(false);
+
+        if hidden {
+            copied.remove();
+        }
+    }
+
+    for ghosts in copy
+        .query_selector_all(".archizoom-diff-ghosts")?
+        .safe_filter::<Element>()
+    {
+        ghosts.remove();
+    }
+
+    strip_our_markup(&copy)?;
+    for element in copy.query_selector_all("*")?.safe_filter::<Element>() {
+        strip_our_markup(&element)?;
+    }
+
+    let view_box = viewport.view_box();
+    copy.set_attribute(
+        "viewBox",
+        &format!(
+            "{} {} {} {}",
+            view_box.left(),
+            view_box.top(),
+            view_box.width(),
+            view_box.height()
+        ),
+    )?;
+    let pixel_size = viewport.size.scale(viewport.scale);
+    copy.set_attribute("width", &pixel_size.width.round().to_string())?;
+    copy.set_attribute("height", &pixel_size.height.round().to_string())?;
+    copy.remove_attribute("style")?;
+    copy.remove_attribute("tabindex")?;
+
+    inline_references(&copy)?;
+
+    XmlSerializer::new()?.serialize_to_string(&copy)
+}
+
+/// Removes the classes we add and the inline styles we set while the diagram is showing
+fn strip_our_markup(element: &Element) -> Result<(), JsValue> {
+    let ours: Vec<String> = element
+        .get_attribute("class")
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter(|class_name| class_name.starts_with("archizoom-"))
+        .map(str::to_string)
+        .collect();
+
+    if ours
+        .iter()
+        .any(|class_name| class_name == "archizoom-content")
+    {
+        // the css-transform wrapper can be caught in the middle of a gesture
+        element.remove_attribute("style")?;
+    }
+    for class_name in ours.iter() {
+        element.class_list().remove_1(class_name)?;
+    }
+    if element.get_attribute("class").as_deref() == Some("") {
+        element.remove_attribute("class")?;
+    }
+
+    Ok(())
+}
+
+/// Copies in the gradients, markers, symbols, and the like that the copy refers to but that
+/// live somewhere else on the page
+fn inline_references(copy: &Element) -> Result<(), JsValue> {
+    let mut defs: Option<Element> = None;
+    let mut checked: HashSet<String> = HashSet::new();
+
+    loop {
+        let elements = copy.query_selector_all("*")?.safe_filter::<Element>();
+        let present: HashSet<String> = elements.iter().map(|element| element.id()).collect();
+
+        let mut missing = vec![];
+        for element in elements.iter() {
+            for id in referenced_ids(element) {
+                if !present.contains(&id) && checked.insert(id.clone()) {
+                    missing.push(id);
+                }
+            }
+        }
+
+        let found: Vec<Element> = missing
+            .iter()
+            .filter_map(|id| document().get_element_by_id(id))
+            .collect();
+        if found.is_empty() {
+            return Ok(());
+        }
+
+        if defs.is_none() {
+            let created = document()
+                .safe_create_element_ns::<Element>(Some(SVG_NS), "defs")
+                .ok_or_else(|| JsValue::from("Couldn't create the exported defs"))?;
+            copy.insert_before(&created, copy.first_child().as_ref())?;
+            defs = Some(created);
+        }
+
+        if let Some(ref defs) = defs {
+            // what we copy in can refer to more, which the next pass picks up
+            for element in found.iter() {
+                defs.append_child(&element.clone_node_with_deep(true)?)?;
+            }
+        }
+    }
+}
+
+/// The ids `element` points to with `url(#id)` or an `#id` href
+fn referenced_ids(element: &Element) -> Vec<String> {
+    attributes(element)
+        .iter()
+        .flat_map(|(name, value)| attribute_references(name, value))
+        .collect()
+}
+
+/// Gives every id in `element` `prefix`, and points the references to them at the new ids, so a
+/// copy can go on the page next to what it was copied from
+pub fn prefix_ids(element: &Element, prefix: &str) -> Result<(), JsValue> {
+    let mut elements = element.query_selector_all("*")?.safe_filter::<Element>();
+    elements.push(element.clone());
+
+    let ids: HashSet<String> = elements
+        .iter()
+        .map(|element| element.id())
+        .filter(|id| !id.is_empty())
+        .collect();
+
+    for element in elements.iter() {
+        if ids.contains(&element.id()) {
+            element.set_id(&format!("{}{}", prefix, element.id()));
+        }
+
+        for (name, value) in attributes(element) {
+            let prefixed = prefix_references(&name, &value, &ids, prefix);
+            if prefixed != value {
+                element.set_attribute(&name, &prefixed)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn attributes(element: &Element) -> Vec<(String, String)> {
+    element
+        .get_attribute_names()
+        .iter()
+        .filter_map(|name| name.as_string())
+        .map(|name| {
+            let value = element.get_attribute(&name).unwrap_or_default();
+            (name, value)
+        })
+        .collect()
+}
+
+/// The ids an attribute points to with `url(#id)`, or with `#id` if it's an href
+fn attribute_references(name: &str, value: &str) -> Vec<String> {
+    let mut ids = vec![];
+
+    if is_href(name) {
+        if let Some(id) = value.strip_prefix('#') {
+            ids.push(id.to_string());
+        }
+    }
+
+    for (start, end) in url_references(value) {
+        ids.push(value[start..end].to_string());
+    }
+
+    ids
+}
+
+/// `value` with its references to `ids` given `prefix`
+fn prefix_references(name: &str, value: &str, ids: &HashSet<String>, prefix: &str) -> String {
+    if is_href(name) {
+        return match value.strip_prefix('#') {
+            Some(id) if ids.contains(id) => format!("#{}{}", prefix, id),
+            _ => value.to_string(),
+        };
+    }
+
+    let mut prefixed = String::with_capacity(value.len());
+    let mut copied = 0;
+    for (start, end) in url_references(value) {
+        if ids.contains(&value[start..end]) {
+            prefixed.push_str(&value[copied..start]);
+            prefixed.push_str(prefix);
+            copied = start;
+        }
+    }
+    prefixed.push_str(&value[copied..]);
+
+    prefixed
+}
+
+/// Where the id of each `url(#id)` in `value` is, quoted or not
+fn url_references(value: &str) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+
+    let mut from = 0;
+    while let Some(found) = value[from..].find("url(") {
+        let mut start = from + found + "url(".len();
+        let end = match value[start..].find(')') {
+            Some(end) => start + end,
+            None => break,
+        };
+        from = end;
+
+        let mut id_end = end;
+        if let Some(quote) = value[start..end]
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+        {
+            start += 1;
+            if value[start..end].ends_with(quote) {
+                id_end -= 1;
+            }
+        }
+
+        if value[start..id_end].starts_with('#') {
+            ranges.push((start + 1, id_end));
+        }
+    }
+
+    ranges
+}
+
+fn is_href(name: &str) -> bool {
+    name == "href" || name == "xlink:href"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_come_from_urls_and_hrefs() {
+        assert_eq!(
+            attribute_references("fill", "url(#gradient)"),
+            vec!["gradient"]
+        );
+        assert_eq!(attribute_references("href", "#symbol"), vec!["symbol"]);
+        assert_eq!(
+            attribute_references("xlink:href", "#symbol"),
+            vec!["symbol"]
+        );
+        assert_eq!(
+            attribute_references("style", "fill: url('#a'); stroke: url(\"#b\")"),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            attribute_references("style", "marker-start: url(#a); marker-end: url(#b)"),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn plain_values_have_no_references() {
+        assert!(attribute_references("fill", "#ff0000").is_empty());
+        assert!(attribute_references("href", "other.svg#symbol").is_empty());
+        assert!(attribute_references("class", "url").is_empty());
+    }
+
+    #[test]
+    fn only_known_ids_are_prefixed() {
+        let ids: HashSet<String> = vec!["a".to_string(), "b".to_string()].into_iter().collect();
+
+        assert_eq!(prefix_references("href", "#a", &ids, "p-"), "#p-a");
+        assert_eq!(prefix_references("href", "#c", &ids, "p-"), "#c");
+        assert_eq!(
+            prefix_references(
+                "style",
+                "fill: url(#a); stroke: url('#c'); mask: url(\"#b\")",
+                &ids,
+                "p-"
+            ),
+            "fill: url(#p-a); stroke: url('#c'); mask: url(\"#p-b\")"
+        );
+        assert_eq!(prefix_references("fill", "#a", &ids, "p-"), "#a");
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use web_sys::{console, Element, SvgElement, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FilterOptions {
+    /// The elements a filter is checked against, the rest of the drawing is left alone
+    pub selector: String,
+    /// Deprecated, set `--archizoom-dimmed-opacity` on the container instead. When it's set it
+    /// overrides that property.
+    pub dimmed_opacity: Option<f32>,
+}
+
+impl Default for FilterOptions {
+    fn default() -> FilterOptions {
+        FilterOptions {
+            selector: "g[id]".to_string(),
+            dimmed_opacity: None,
+        }
+    }
+}
+
+/// Dims the elements that didn't match a filter, the stylesheet sets how much with
+/// --archizoom-dimmed-opacity. Dropping the filter restores them.
+pub struct Filter {
+    dimmed: Vec<SvgElement>,
+    pub matched: usize,
+}
+
+static DIMMED_CLASS: &str = "archizoom-filtered-out";
+
+impl Filter {
+    /// The elements a filter is checked against
+    pub fn candidates(svg: &SvgsvgElement, options: &FilterOptions) -> Vec<SvgElement> {
+        svg.query_selector_all(&options.selector)
+            .map(|nodes| nodes.safe_filter::<SvgElement>())
+            .unwrap_or_default()
+    }
+
+    /// Dims every candidate that `is_match` rejects. Candidates inside of or around a match are
+    /// left alone, dimming them would dim the match too.
+    pub fn apply<P>(svg: &SvgsvgElement, options: &FilterOptions, is_match: P) -> Filter
+    where
+        P: Fn(&Element) -> bool,
+    {
+        let candidates = Filter::candidates(svg, options);
+
+        let matches: Vec<&SvgElement> = candidates
+            .iter()
+            .filter(|candidate| is_match(candidate))
+            .collect();
+
+        let mut dimmed = vec![];
+        for candidate in candidates.iter() {
+            let related = matches.iter().any(|matched| {
+                matched.contains(Some(candidate)) || candidate.contains(Some(matched))
+            });
+            if related {
+                continue;
+            }
+
+            match candidate.class_list().add_1(DIMMED_CLASS) {
+                Ok(_) => dimmed.push(candidate.clone()),
+                Err(error) => console::warn_2(&"Couldn't dim an element".into(), &error),
+            }
+        }
+
+        Filter {
+            matched: matches.len(),
+            dimmed,
+        }
+    }
+}
+
+impl Drop for Filter {
+    fn drop(&mut self) {
+        for element in self.dimmed.iter() {
+            if let Err(error) = element.class_list().remove_1(DIMMED_CLASS) {
+                console::warn_2(&"Couldn't restore a dimmed element".into(), &error);
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::console;
+
+use crate::js_utils::*;
+
+/// Something with work to do on the next animation frame
+pub trait FrameClient {
+    /// Does this frame's work, returning true to run again next frame
+    fn frame(&self, time: f64) -> bool;
+
+    /// Clients that go first, and don't get put off when the frame runs long. The viewer under
+    /// the pointer is the one the user is watching.
+    fn is_priority(&self) -> bool {
+        false
+    }
+}
+
+/// One `requestAnimationFrame` loop shared by every viewer on the page, so a page with several
+/// animating diagrams does all of their work in a single pass
+#[derive(Default)]
+struct FrameLoop {
+    clients: RefCell<Vec<Weak<dyn FrameClient>>>,
+    requested: Cell<bool>,
+}
+
+/// After this long, clients that aren't a priority wait for the next frame
+static FRAME_BUDGET_MS: f64 = 8.0;
+
+thread_local! {
+    static FRAME_LOOP: Rc<FrameLoop> = Rc::new(FrameLoop::default());
+}
+
+/// Runs `client` on the next animation frame, alongside every other client waiting for it
+pub fn schedule_frame(client: Weak<dyn FrameClient>) {
+    FRAME_LOOP.with(|frame_loop| frame_loop.schedule(client));
+}
+
+impl FrameLoop {
+    fn schedule(&self, client: Weak<dyn FrameClient>) {
+        {
+            let mut clients = self.clients.borrow_mut();
+            if !clients
+                .iter()
+                .any(|scheduled| same_client(scheduled, &client))
+            {
+                clients.push(client);
+            }
+        }
+
+        if !self.requested.replace(true) {
+            let callback = Closure::once_into_js(move |time: f64| {
+                FRAME_LOOP.with(|frame_loop| frame_loop.run(time));
+            });
+
+            if let Err(error) = window().request_animation_frame(callback.unchecked_ref()) {
+                console::error_2(&"Couldn't request an animation frame".into(), &error);
+                self.requested.set(false);
+            }
+        }
+    }
+
+    fn run(&self, time: f64) {
+        self.requested.set(false);
+
+        let mut clients: Vec<_> = self
+            .clients
+            .replace(vec![])
+            .into_iter()
+            .filter_map(|client| {
+                let strong = client.upgrade()?;
+                let priority = strong.is_priority();
+
+                Some((client, strong, priority))
+            })
+            .collect();
+        clients.sort_by_key(|(_, _, priority)| !priority);
+
+        let start = now();
+        for (client, strong, priority) in clients {
+            let over_budget = !priority && now() - start > FRAME_BUDGET_MS;
+
+            // clients run again next frame if they asked to or didn't get a turn this frame
+            if over_budget || strong.frame(time) {
+                self.schedule(client);
+            }
+        }
+    }
+}
+
+fn same_client(a: &Weak<dyn FrameClient>, b: &Weak<dyn FrameClient>) -> bool {
+    a.as_ptr() as *const () == b.as_ptr() as *const ()
+}
@@ -0,0 +1,265 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, Event, FocusEvent, Gamepad, GamepadButton, Node};
+
+use crate::js_utils::*;
+use crate::zoom::frame_loop::{schedule_frame, FrameClient};
+use crate::zoom::matrix::Point2D;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GamepadOptions {
+    pub enabled: bool,
+    /// Follow the gamepad without the viewer having focus, for kiosks that only show us
+    pub always: bool,
+    /// Stick movement under this is treated as the stick resting
+    pub dead_zone: f32,
+    /// How much of the view a fully pushed stick pans in a second
+    pub pan_speed: f32,
+    /// How much a fully pulled trigger zooms in a second
+    pub zoom_speed: f32,
+}
+
+impl Default for GamepadOptions {
+    fn default() -> GamepadOptions {
+        GamepadOptions {
+            enabled: false,
+            always: false,
+            dead_zone: 0.15,
+            pan_speed: 1.0,
+            zoom_speed: 2.0,
+        }
+    }
+}
+
+// the "standard" gamepad layout
+static LEFT_STICK_X: u32 = 0;
+static LEFT_STICK_Y: u32 = 1;
+static BUTTON_A: usize = 0;
+static BUTTON_B: usize = 1;
+static BUTTON_Y: usize = 3;
+static LEFT_BUMPER: usize = 4;
+static RIGHT_BUMPER: usize = 5;
+static LEFT_TRIGGER: u32 = 6;
+static RIGHT_TRIGGER: u32 = 7;
+
+/// The left stick pans, the triggers zoom, the bumpers move between zoom elements, A drills
+/// into one, B goes back up, and Y resets
+pub struct GamepadSupport {
+    _inner: Rc<GamepadInner>,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+struct GamepadInner {
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    options: GamepadOptions,
+    /// Whether the viewer has focus, or we don't need it to
+    active: Cell<bool>,
+    /// Set while we're polling every frame
+    polling: Cell<bool>,
+    last_frame: Cell<Option<f64>>,
+    /// Which buttons were down last frame, so holding one acts once
+    held: RefCell<Vec<bool>>,
+}
+
+impl GamepadSupport {
+    pub fn new(
+        container: &Element,
+        options: &GamepadOptions,
+        archizoom: Weak<RefCell<ArchiZoom>>,
+    ) -> Result<Option<GamepadSupport>, JsValue> {
+        if !options.enabled {
+            return Ok(None);
+        }
+
+        let inner = Rc::new(GamepadInner {
+            archizoom,
+            options: options.clone(),
+            active: Cell::new(options.always),
+            polling: Cell::new(false),
+            last_frame: Cell::new(None),
+            held: RefCell::new(vec![]),
+        });
+
+        let mut listeners = vec![];
+
+        // nothing shows up in getGamepads until a button is pressed
+        let connected = inner.clone();
+        listeners.push(
+            window().new_event_listener("gamepadconnected", move |_: Event| {
+                connected.start();
+            })?,
+        );
+
+        if !options.always {
+            let focused = inner.clone();
+            listeners.push(container.new_event_listener("focusin", move |_: Event| {
+                focused.active.set(true);
+                focused.start();
+            })?);
+
+            let blurred = inner.clone();
+            let blurred_container = container.clone();
+            listeners.push(container.new_event_listener(
+                "focusout",
+                move |event: FocusEvent| {
+                    // focus moving to one of our overlays doesn't count
+                    let inside = event
+                        .related_target()
+                        .and_then(|target| target.dyn_into::<Node>().ok())
+                        .map(|target| blurred_container.contains(Some(&target)))
+                        .unwrap_or(false);
+                    blurred.active.set(inside);
+                },
+            )?);
+        }
+
+        inner.start();
+
+        Ok(Some(GamepadSupport {
+            _inner: inner,
+            _listeners: listeners,
+        }))
+    }
+}
+
+impl GamepadInner {
+    fn start(self: &Rc<Self>) {
+        if self.active.get() && !self.polling.replace(true) {
+            self.last_frame.set(None);
+
+            let client: Rc<dyn FrameClient> = self.clone();
+            schedule_frame(Rc::downgrade(&client));
+        }
+    }
+
+    /// The first gamepad that's plugged in
+    fn gamepad(&self) -> Option<Gamepad> {
+        window()
+            .navigator()
+            .get_gamepads()
+            .ok()?
+            .iter()
+            .filter_map(|gamepad| gamepad.dyn_into::<Gamepad>().ok())
+            .find(|gamepad| gamepad.connected())
+    }
+
+    fn axis(&self, gamepad: &Gamepad, index: u32) -> f32 {
+        let value = gamepad
+            .axes()
+            .get(index)
+            .as_f64()
+            .map(|value| value as f32)
+            .unwrap_or(0.0);
+
+        if value.abs() < self.options.dead_zone {
+            0.0
+        } else {
+            value
+        }
+    }
+
+    /// Moves the view by how far the stick and triggers are pushed, for `seconds`
+    fn move_view(&self, archizoom: &ArchiZoom, gamepad: &Gamepad, seconds: f32) {
+        let buttons = gamepad.buttons();
+        let trigger = |index: u32| {
+            buttons
+                .get(index)
+                .dyn_into::<GamepadButton>()
+                .map(|button| button.value() as f32)
+                .unwrap_or(0.0)
+        };
+
+        let stick_x = self.axis(gamepad, LEFT_STICK_X);
+        let stick_y = self.axis(gamepad, LEFT_STICK_Y);
+        let zoom = trigger(RIGHT_TRIGGER) - trigger(LEFT_TRIGGER);
+        if stick_x == 0.0 && stick_y == 0.0 && zoom.abs() < self.options.dead_zone {
+            return;
+        }
+
+        if let Some(mut viewport) = archizoom.viewport() {
+            let step = self.options.pan_speed * seconds;
+            viewport.pan(&Point2D::new(
+                viewport.size.width * stick_x * step,
+                viewport.size.height * stick_y * step,
+            ));
+
+            let center = viewport.center();
+            viewport.zoom_about(&center, self.options.zoom_speed.powf(zoom * seconds));
+
+            archizoom.set_viewport(&viewport);
+        }
+    }
+
+    /// Acts on the buttons that went down since the last frame
+    fn press_buttons(&self, archizoom: &ArchiZoom, gamepad: &Gamepad) {
+        let down: Vec<bool> = gamepad
+            .buttons()
+            .iter()
+            .map(|button| {
+                button
+                    .dyn_into::<GamepadButton>()
+                    .map(|button| button.pressed())
+                    .unwrap_or(false)
+            })
+            .collect();
+        let held = self.held.replace(down.clone());
+        let pressed = |index: usize| {
+            down.get(index).cloned().unwrap_or(false) && !held.get(index).cloned().unwrap_or(false)
+        };
+
+        if pressed(LEFT_BUMPER) {
+            archizoom.focus_link(false);
+        }
+        if pressed(RIGHT_BUMPER) {
+            archizoom.focus_link(true);
+        }
+        if pressed(BUTTON_A) {
+            archizoom.activate_focused_link();
+        }
+
+        if !archizoom.is_locked() {
+            if pressed(BUTTON_B) {
+                let depth = archizoom.navigation().len();
+                if depth > 1 {
+                    archizoom.navigate_back(depth - 2);
+                }
+            }
+            if pressed(BUTTON_Y) {
+                archizoom.reset();
+            }
+        }
+    }
+}
+
+impl FrameClient for GamepadInner {
+    fn frame(&self, time: f64) -> bool {
+        let last_frame = self.last_frame.replace(Some(time));
+
+        let (archizoom, gamepad) = match (self.archizoom.upgrade(), self.gamepad()) {
+            (Some(archizoom), Some(gamepad)) if self.active.get() => (archizoom, gamepad),
+            _ => {
+                // the next focus or gamepadconnected starts us up again
+                self.polling.set(false);
+                self.held.replace(vec![]);
+                return false;
+            }
+        };
+        let archizoom = archizoom.borrow();
+
+        if let Some(last_frame) = last_frame {
+            // a long pause, like a backgrounded tab, shouldn't send the view flying
+            let seconds = ((time - last_frame) / 1000.0).min(0.1) as f32;
+            if !archizoom.is_locked() {
+                self.move_view(&archizoom, &gamepad, seconds);
+            }
+        }
+        self.press_buttons(&archizoom, &gamepad);
+
+        true
+    }
+}
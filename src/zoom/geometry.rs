@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::JsValue;
+use web_sys::{Event, EventTarget, SvgGraphicsElement, SvgsvgElement};
+
+use crate::diagnostics;
+use crate::js_utils::*;
+use crate::zoom::bounds::to_root;
+use crate::zoom::matrix::Matrix2D;
+use crate::zoom::space::{ScreenRect, ScreenSpace, SvgUserSpace, Transform};
+use crate::zoom::viewport::Viewport;
+
+type ScreenCtm = Transform<SvgUserSpace, ScreenSpace>;
+
+/// The svg's screen CTM, cached until something could have moved the svg on screen. Reading it
+/// forces a layout, and pointer moves, overlays, and drilling down all want it on every update.
+///
+/// Our own viewBox changes, page scrolls (of any ancestor), resizes, pinch zooming, and CSS
+/// transitions and animations finishing all drop the cache. Anything else that moves the svg has
+/// to call `invalidate`.
+pub struct ScreenGeometry {
+    svg: SvgsvgElement,
+    screen_ctm: Rc<RefCell<Option<ScreenCtm>>>,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl ScreenGeometry {
+    pub fn new(svg: &SvgsvgElement) -> Result<Rc<ScreenGeometry>, JsValue> {
+        let screen_ctm = Rc::new(RefCell::new(None));
+
+        let visual_viewport: Option<EventTarget> = window().visual_viewport().map(Into::into);
+        let window: EventTarget = window().into();
+        let document: EventTarget = document().into();
+        let mut targets = vec![
+            (&window, "scroll"),
+            (&window, "resize"),
+            (&document, "transitionend"),
+            (&document, "animationend"),
+        ];
+        if let Some(ref visual_viewport) = visual_viewport {
+            targets.push((visual_viewport, "scroll"));
+            targets.push((visual_viewport, "resize"));
+        }
+
+        let mut listeners = vec![];
+        for (target, event_type) in targets.iter() {
+            let screen_ctm: Rc<RefCell<Option<ScreenCtm>>> = screen_ctm.clone();
+            listeners.push(
+                target.new_capturing_event_listener(event_type, move |_: Event| {
+                    screen_ctm.replace(None);
+                })?,
+            );
+        }
+
+        Ok(Rc::new(ScreenGeometry {
+            svg: svg.clone(),
+            screen_ctm,
+            _listeners: listeners,
+        }))
+    }
+
+    /// The transform from the svg's User Coordinates to the screen, `None` while the svg isn't
+    /// rendered
+    pub fn screen_ctm(&self) -> Option<ScreenCtm> {
+        if let Some(ref cached) = *self.screen_ctm.borrow() {
+            return Some(cached.clone());
+        }
+
+        let screen_ctm = Transform::screen_ctm(&self.svg).map(|reported| self.checked(reported));
+        // there's nothing to invalidate when the svg is added to the page, so don't cache misses
+        if let Some(ref screen_ctm) = screen_ctm {
+            self.screen_ctm.replace(Some(screen_ctm.clone()));
+        }
+
+        screen_ctm
+    }
+
+    /// The transform from `element`'s User Coordinates to the screen, through the svg's so
+    /// it's measured the same way
+    pub fn element_ctm(&self, element: &SvgGraphicsElement) -> Option<ScreenCtm> {
+        self.screen_ctm().map(|to_screen| {
+            Transform::new(to_screen.matrix().multiply(&to_root(&self.svg, element)))
+        })
+    }
+
+    pub fn invalidate(&self) {
+        self.screen_ctm.replace(None);
+    }
+
+    /// Some browsers leave CSS transforms on the svg's ancestors out of its screen CTM, but its
+    /// bounding client rect, like pointer positions, always has them. When the two disagree on
+    /// where the view is, the CTM is rebuilt from the rect. A rotated CTM is left alone, the
+    /// rect can't say anything about those.
+    fn checked(&self, reported: ScreenCtm) -> ScreenCtm {
+        let view = match Viewport::from_svg(&self.svg) {
+            Some(view) => view,
+            None => return reported,
+        };
+
+        diagnostics::layout_read();
+        let client_rect = ScreenRect::from_dom_rect(&self.svg.get_bounding_client_rect());
+        let client_center = client_rect.center();
+        let view_center = view.center();
+
+        let decomposition = reported.matrix().decompose();
+        let reported_center = view_center.matrix_transform(reported.matrix());
+        // the rect is rounded to layout units, so only a real difference counts
+        let agrees = (&reported_center - &client_center).length() <= 1.0
+            && (decomposition.scale() / view.scale - 1.0).abs() <= 0.01;
+        if agrees || decomposition.rotation_degrees().abs() > 0.5 {
+            return reported;
+        }
+
+        // the default preserveAspectRatio centers the view in the svg
+        Transform::new(
+            Matrix2D::translation(client_center.x, client_center.y)
+                .multiply(&Matrix2D::scaling(view.scale))
+                .multiply(&Matrix2D::translation(-view_center.x, -view_center.y)),
+        )
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use js_sys::Array;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{MutationObserver, MutationObserverInit, SvgGraphicsElement, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::zoom::bounds::element_bounds;
+use crate::zoom::matrix::Point2D;
+use crate::zoom::quadtree::QuadTree;
+use crate::zoom::sprite::is_shown;
+
+type MutationCallback = Closure<dyn FnMut(Array)>;
+
+/// What `elementAt` looks for when it isn't given a selector, the same as the selection
+pub static DEFAULT_SELECTOR: &str = "g[id]";
+
+/// The attributes that move or resize shapes, changing any of them drops the trees
+static GEOMETRY_ATTRIBUTES: [&str; 16] = [
+    "transform",
+    "x",
+    "y",
+    "width",
+    "height",
+    "d",
+    "points",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+];
+
+/// Finds the element under a point through a quadtree of element bounds, so pointer queries on
+/// diagrams with thousands of shapes don't walk all of them. A tree is built for each selector
+/// the first time it's asked for, and they're all dropped when the diagram's shapes change.
+pub struct HitTester {
+    svg: SvgsvgElement,
+    trees: RefCell<HashMap<String, QuadTree<SvgGraphicsElement>>>,
+    observer: RefCell<Option<MutationObserver>>,
+    _callback: RefCell<Option<MutationCallback>>,
+}
+
+impl HitTester {
+    pub fn new(svg: &SvgsvgElement) -> Result<Rc<HitTester>, JsValue> {
+        let hit_tester = Rc::new(HitTester {
+            svg: svg.clone(),
+            trees: RefCell::new(HashMap::new()),
+            observer: RefCell::new(None),
+            _callback: RefCell::new(None),
+        });
+
+        let weak_hit_tester: Weak<HitTester> = Rc::downgrade(&hit_tester);
+        let callback = Closure::wrap(Box::new(move |_: Array| {
+            if let Some(hit_tester) = weak_hit_tester.upgrade() {
+                hit_tester.invalidate();
+            }
+        }) as Box<dyn FnMut(Array)>);
+
+        let observer = MutationObserver::new(callback.as_ref().unchecked_ref())?;
+        let options = MutationObserverInit::new();
+        options.set_subtree(true);
+        options.set_child_list(true);
+        options.set_attributes(true);
+        options.set_attribute_filter(
+            &GEOMETRY_ATTRIBUTES
+                .iter()
+                .map(|attribute| JsValue::from(*attribute))
+                .collect::<Array>(),
+        );
+        observer.observe_with_options(svg, &options)?;
+
+        hit_tester.observer.replace(Some(observer));
+        hit_tester._callback.replace(Some(callback));
+
+        Ok(hit_tester)
+    }
+
+    /// Whether there's a tree for `selector`, measuring the elements for one needs all of them
+    /// rendered
+    pub fn is_indexed(&self, selector: &str) -> bool {
+        self.trees.borrow().contains_key(selector)
+    }
+
+    /// Measures every element matching `selector` into a tree
+    pub fn index(&self, selector: &str) {
+        let elements = self
+            .svg
+            .query_selector_all(selector)
+            .map(|nodes| nodes.safe_filter::<SvgGraphicsElement>())
+            .unwrap_or_default();
+
+        let tree = QuadTree::from_items(
+            elements
+                .into_iter()
+                .filter(|element| is_shown(element))
+                .filter_map(|element| {
+                    element_bounds(&self.svg, &element).map(|bounds| (bounds, element))
+                }),
+        );
+
+        self.trees.borrow_mut().insert(selector.to_string(), tree);
+    }
+
+    /// The smallest element matching `selector` whose bounds contain `point` (in Svg User
+    /// Coordinates), which is usually the innermost one. `None` if `selector` isn't indexed.
+    pub fn element_at(&self, point: &Point2D, selector: &str) -> Option<SvgGraphicsElement> {
+        self.trees
+            .borrow()
+            .get(selector)
+            .and_then(|tree| tree.smallest_at(point))
+            .cloned()
+    }
+
+    /// Drops the trees so they're built again from the current diagram
+    pub fn invalidate(&self) {
+        self.trees.borrow_mut().clear();
+    }
+}
+
+impl Drop for HitTester {
+    fn drop(&mut self) {
+        if let Some(observer) = self.observer.borrow_mut().take() {
+            observer.disconnect();
+        }
+    }
+}
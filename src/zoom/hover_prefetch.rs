@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::{Rc, Weak};
+
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{console, Element, PointerEvent};
+
+use crate::js_utils::*;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HoverPrefetchOptions {
+    pub enabled: bool,
+    /// How long the pointer has to rest on a zoom element before we fetch what it opens
+    pub delay_ms: i32,
+}
+
+impl Default for HoverPrefetchOptions {
+    fn default() -> HoverPrefetchOptions {
+        HoverPrefetchOptions {
+            enabled: false,
+            delay_ms: 150,
+        }
+    }
+}
+
+/// Fetches the whole diagram a zoom element opens into the diagram cache once the pointer
+/// rests on it, so drilling in doesn't have to wait on the network. Moving off before the
+/// delay is up cancels it.
+pub struct HoverPrefetch {
+    _inner: Rc<HoverInner>,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+struct HoverInner {
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    delay_ms: i32,
+    /// The target of the zoom element under the pointer
+    hovered: RefCell<Option<String>>,
+    /// Targets being fetched right now, so dwelling again doesn't fetch them twice
+    in_flight: RefCell<HashSet<String>>,
+    timeout: Timeout,
+}
+
+impl HoverPrefetch {
+    pub fn new(
+        container: &Element,
+        options: &HoverPrefetchOptions,
+        archizoom: Weak<RefCell<ArchiZoom>>,
+    ) -> Result<Option<HoverPrefetch>, JsValue> {
+        if !options.enabled {
+            return Ok(None);
+        }
+
+        let inner = Rc::new_cyclic(|weak_ref: &Weak<HoverInner>| {
+            let weak_ref = weak_ref.clone();
+
+            HoverInner {
+                archizoom,
+                delay_ms: options.delay_ms,
+                hovered: RefCell::new(None),
+                in_flight: RefCell::new(HashSet::new()),
+                timeout: Timeout::new(move || {
+                    if let Some(inner) = weak_ref.upgrade() {
+                        HoverInner::fetch(&inner);
+                    }
+                }),
+            }
+        });
+
+        // the container outlives each diagram, so we don't have to listen again on navigation
+        let moved = inner.clone();
+        let left = inner.clone();
+        let listeners = vec![
+            container.new_passive_event_listener("pointermove", move |event: PointerEvent| {
+                // dragging pans the view, it isn't dwelling on anything
+                let target = if event.buttons() != 0 {
+                    None
+                } else {
+                    event
+                        .target()
+                        .and_then(|target| target.dyn_into::<Element>().ok())
+                };
+                moved.hover(target);
+            })?,
+            container.new_passive_event_listener("pointerleave", move |_: PointerEvent| {
+                left.hover(None);
+            })?,
+        ];
+
+        Ok(Some(HoverPrefetch {
+            _inner: inner,
+            _listeners: listeners,
+        }))
+    }
+}
+
+impl HoverInner {
+    fn hover(&self, target: Option<Element>) {
+        let url = match (target, self.archizoom.upgrade()) {
+            (Some(target), Some(archizoom)) => archizoom.borrow().link_target(&target),
+            _ => None,
+        };
+        if *self.hovered.borrow() == url {
+            return;
+        }
+
+        self.timeout.cancel();
+        if url.is_some() {
+            self.timeout.restart(self.delay_ms);
+        }
+        self.hovered.replace(url);
+    }
+
+    fn fetch(inner: &Rc<HoverInner>) {
+        let (url, archizoom) = match (inner.hovered.borrow().clone(), inner.archizoom.upgrade()) {
+            (Some(url), Some(archizoom)) => (url, archizoom),
+            _ => return,
+        };
+        if archizoom.borrow().is_cached(&url) || !inner.in_flight.borrow_mut().insert(url.clone()) {
+            return;
+        }
+
+        let future = match archizoom.borrow().fetch_diagram(&url) {
+            Ok(future) => future,
+            Err(error) => {
+                console::warn_2(&format!("Couldn't prefetch {}", url).into(), &error);
+                inner.in_flight.borrow_mut().remove(&url);
+
+                return;
+            }
+        };
+
+        let weak_ref = Rc::downgrade(inner);
+        spawn_local(future.then(move |result| {
+            if let Some(inner) = weak_ref.upgrade() {
+                inner.in_flight.borrow_mut().remove(&url);
+
+                match (result, inner.archizoom.upgrade()) {
+                    (Ok(loaded), Some(archizoom)) => {
+                        let archizoom = archizoom.borrow();
+                        if let Some(ref description) = loaded.description {
+                            archizoom.described(&url, description);
+                        }
+                        archizoom.cache_prefetched(&url, loaded.text);
+                    }
+                    (Err(error), _) => {
+                        console::warn_2(&format!("Couldn't prefetch {}", url).into(), &error)
+                    }
+                    _ => (),
+                }
+            }
+
+            Ok(())
+        }));
+    }
+}
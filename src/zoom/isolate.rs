@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+use web_sys::{Element, SvgGeometryElement, SvgGraphicsElement, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::options::Options;
+use crate::zoom::bounds::{element_bounds, to_root};
+use crate::zoom::filter::Filter;
+use crate::zoom::matrix::{Point2D, Rect};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IsolateOptions {
+    /// The shapes that can be connectors, the ones starting or ending on the isolated element
+    /// are kept along with whatever is on their other end
+    pub connector_selector: String,
+    /// How far outside of an element, in Svg User Coordinates, a connector can end and still
+    /// count as touching it
+    pub tolerance: f32,
+}
+
+impl Default for IsolateOptions {
+    fn default() -> IsolateOptions {
+        IsolateOptions {
+            connector_selector: "path, polyline, line".to_string(),
+            tolerance: 4.0,
+        }
+    }
+}
+
+/// One element and its relationships, with everything else dimmed so they can be traced
+/// through a dense view. Dropping it restores the rest.
+pub struct Isolation {
+    /// How many connectors touch the element
+    pub connections: usize,
+    _filter: Filter,
+}
+
+/// Where a connector goes, in the User Coordinates of the svg
+struct Connector {
+    element: SvgGeometryElement,
+    start: Point2D,
+    end: Point2D,
+}
+
+impl Isolation {
+    pub fn apply(
+        svg: &SvgsvgElement,
+        target: &SvgGraphicsElement,
+        options: &Options,
+    ) -> Option<Isolation> {
+        let tolerance = options.isolate.tolerance;
+        let bounds = element_bounds(svg, target)?.inflate(tolerance);
+
+        let connections: Vec<(SvgGeometryElement, Point2D)> = svg
+            .query_selector_all(&options.isolate.connector_selector)
+            .map(|nodes| nodes.safe_filter::<SvgGeometryElement>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|element| !target.contains(Some(element)))
+            .filter_map(|element| Connector::new(svg, element))
+            // closed shapes, like the outlines of other elements, aren't connectors
+            .filter(|connector| (&connector.end - &connector.start).length() > tolerance)
+            .filter_map(|connector| {
+                match (
+                    bounds.contains(&connector.start),
+                    bounds.contains(&connector.end),
+                ) {
+                    (true, false) => Some((connector.element, connector.end)),
+                    (false, true) => Some((connector.element, connector.start)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let candidates: Vec<SvgGraphicsElement> = svg
+            .query_selector_all(&options.filter.selector)
+            .map(|nodes| nodes.safe_filter::<SvgGraphicsElement>())
+            .unwrap_or_default();
+        let candidate_bounds: Vec<(&SvgGraphicsElement, Rect)> = candidates
+            .iter()
+            .filter_map(|candidate| Some((candidate, element_bounds(svg, candidate)?)))
+            .collect();
+
+        let mut kept: Vec<Element> = vec![target.clone().into()];
+        for (connector, far_end) in connections.iter() {
+            // the connector's own group, unless that's something around the element too
+            if let Some(group) = connector
+                .closest(&options.filter.selector)
+                .ok()
+                .flatten()
+                .filter(|group| !group.contains(Some(target)))
+            {
+                kept.push(group);
+            }
+
+            // the smallest element its other end is on
+            let far_element = candidate_bounds
+                .iter()
+                .filter(|(candidate, candidate_bounds)| {
+                    candidate_bounds.inflate(tolerance).contains(far_end)
+                        && !candidate.contains(Some(target))
+                        && !candidate.contains(Some(connector))
+                })
+                .min_by(|(_, a), (_, b)| a.area().total_cmp(&b.area()));
+            if let Some((candidate, _)) = far_element {
+                kept.push((*candidate).clone().into());
+            }
+        }
+
+        let filter = Filter::apply(svg, &options.filter, |element| kept.contains(element));
+
+        Some(Isolation {
+            connections: connections.len(),
+            _filter: filter,
+        })
+    }
+}
+
+impl Connector {
+    fn new(svg: &SvgsvgElement, element: SvgGeometryElement) -> Option<Connector> {
+        let length = element.get_total_length();
+        let start = element.get_point_at_length(0.0).ok()?;
+        let end = element.get_point_at_length(length).ok()?;
+
+        let to_svg = to_root(svg, element.unchecked_ref());
+        Some(Connector {
+            start: Point2D::new(start.x(), start.y()).matrix_transform(&to_svg),
+            end: Point2D::new(end.x(), end.y()).matrix_transform(&to_svg),
+            element,
+        })
+    }
+}
@@ -0,0 +1,341 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, FocusEvent, KeyboardEvent};
+
+use crate::js_utils::*;
+use crate::overlays::controls::ZOOM_STEP;
+use crate::zoom::matrix::Point2D;
+use crate::zoom::ArchiZoom;
+
+/// How far the arrow keys pan, as a fraction of the view
+static PAN_STEP: f32 = 0.1;
+
+/// Everything the keyboard can do, the keys for each can be changed with `keyBindings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyAction {
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    Reset,
+    Back,
+    Search,
+    Fullscreen,
+    NextLink,
+    PreviousLink,
+    ActivateLink,
+    BlurLink,
+    /// Zooms out to the whole diagram while it's held, and back on release
+    Peek,
+}
+
+static DEFAULT_BINDINGS: [(KeyAction, &[&str]); 15] = [
+    (KeyAction::PanLeft, &["ArrowLeft"]),
+    (KeyAction::PanRight, &["ArrowRight"]),
+    (KeyAction::PanUp, &["ArrowUp"]),
+    (KeyAction::PanDown, &["ArrowDown"]),
+    (KeyAction::ZoomIn, &["+", "="]),
+    (KeyAction::ZoomOut, &["-", "_"]),
+    (KeyAction::Reset, &["0"]),
+    (KeyAction::Back, &["Backspace"]),
+    (KeyAction::Search, &["/"]),
+    (KeyAction::Fullscreen, &["f"]),
+    (KeyAction::NextLink, &["Tab"]),
+    (KeyAction::PreviousLink, &["Shift+Tab"]),
+    (KeyAction::ActivateLink, &["Enter"]),
+    (KeyAction::BlurLink, &["Escape"]),
+    (KeyAction::Peek, &["z"]),
+];
+
+/// A key with the modifiers that have to be held with it, written like `Ctrl+Shift+ArrowUp`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyCombo {
+    /// `KeyboardEvent.key`, lowercased when it's a single character
+    key: String,
+    ctrl: bool,
+    alt: bool,
+    meta: bool,
+    /// `None` leaves Shift up to the key, so `+` still works when it takes Shift to type
+    shift: Option<bool>,
+}
+
+impl KeyCombo {
+    fn parse(combo: &str) -> Result<KeyCombo, String> {
+        let (modifiers, key) = if combo == "+" {
+            ("", "+")
+        } else if let Some(modifiers) = combo.strip_suffix("++") {
+            (modifiers, "+")
+        } else {
+            match combo.rfind('+') {
+                Some(split) => (&combo[..split], &combo[split + 1..]),
+                None => ("", combo),
+            }
+        };
+
+        if key.is_empty() {
+            return Err(format!("\"{}\" doesn't name a key", combo));
+        }
+
+        let mut parsed = KeyCombo {
+            key: normalize_key(if key == "Space" { " " } else { key }),
+            ctrl: false,
+            alt: false,
+            meta: false,
+            shift: if is_character(key) { None } else { Some(false) },
+        };
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => parsed.ctrl = true,
+                "alt" | "option" => parsed.alt = true,
+                "meta" | "cmd" | "command" => parsed.meta = true,
+                "shift" => parsed.shift = Some(true),
+                _ => {
+                    return Err(format!(
+                        "\"{}\" isn't a modifier in \"{}\"",
+                        modifier, combo
+                    ))
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn matches(&self, event: &KeyboardEvent) -> bool {
+        normalize_key(&event.key()) == self.key
+            && event.ctrl_key() == self.ctrl
+            && event.alt_key() == self.alt
+            && event.meta_key() == self.meta
+            && self
+                .shift
+                .map(|shift| event.shift_key() == shift)
+                .unwrap_or(true)
+    }
+}
+
+fn is_character(key: &str) -> bool {
+    key.chars().count() == 1
+}
+
+fn normalize_key(key: &str) -> String {
+    if is_character(key) {
+        key.to_lowercase()
+    } else {
+        key.to_string()
+    }
+}
+
+/// Which action each key combo does. Actions set in `keyBindings` replace their default keys
+/// and take them from any default action they clash with. Combos bound twice go to the
+/// first action.
+struct KeyMap {
+    bindings: Vec<(KeyCombo, KeyAction)>,
+}
+
+impl KeyMap {
+    fn new(custom: &HashMap<KeyAction, Vec<String>>) -> KeyMap {
+        let mut bindings: Vec<(KeyCombo, KeyAction)> = vec![];
+        let mut conflicts: Vec<String> = vec![];
+
+        let custom_combos = DEFAULT_BINDINGS.iter().filter_map(|(action, _)| {
+            custom.get(action).map(|combos| {
+                (
+                    *action,
+                    combos.iter().map(String::as_str).collect::<Vec<_>>(),
+                )
+            })
+        });
+        let default_combos = DEFAULT_BINDINGS
+            .iter()
+            .filter(|(action, _)| !custom.contains_key(action))
+            .map(|(action, combos)| (*action, combos.to_vec()));
+
+        // the custom ones go first so they win any clash with a default
+        for (action, combos) in custom_combos.chain(default_combos) {
+            for combo in combos {
+                let parsed = match KeyCombo::parse(combo) {
+                    Ok(parsed) => parsed,
+                    Err(error) => {
+                        console::warn_1(&format!("Ignoring a key binding, {}", error).into());
+                        continue;
+                    }
+                };
+
+                match bindings.iter().find(|(bound, _)| *bound == parsed) {
+                    Some((_, bound_to)) if custom.contains_key(&action) => conflicts.push(format!(
+                        "{} is bound to both {:?} and {:?}",
+                        combo, bound_to, action
+                    )),
+                    Some(_) => (),
+                    None => bindings.push((parsed, action)),
+                }
+            }
+        }
+
+        if !conflicts.is_empty() {
+            console::warn_1(
+                &format!(
+                    "Conflicting key bindings, the first action keeps the key: {}",
+                    conflicts.join(", ")
+                )
+                .into(),
+            );
+        }
+
+        KeyMap { bindings }
+    }
+
+    fn action(&self, event: &KeyboardEvent) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(combo, _)| combo.matches(event))
+            .map(|(_, action)| *action)
+    }
+
+    /// Whether the key let go of is one of `action`'s. The modifiers are ignored, they could
+    /// have been let go of first.
+    fn releases(&self, action: KeyAction, event: &KeyboardEvent) -> bool {
+        let key = normalize_key(&event.key());
+
+        self.bindings
+            .iter()
+            .any(|(combo, bound)| *bound == action && combo.key == key)
+    }
+}
+
+/// The keys in `key_bindings`, or the defaults, pan, zoom, reset, go back, search, peek, and
+/// move between and drill into zoom elements while the container has focus
+pub fn register_keyboard(
+    container: &Element,
+    key_bindings: &HashMap<KeyAction, Vec<String>>,
+    archizoom: Weak<RefCell<ArchiZoom>>,
+) -> Result<Vec<Box<dyn JsEventListener>>, JsValue> {
+    let key_map = Rc::new(KeyMap::new(key_bindings));
+
+    let up_key_map = key_map.clone();
+    let up_archizoom = archizoom.clone();
+    let blur_archizoom = archizoom.clone();
+    let keydown = container.new_event_listener("keydown", move |event: KeyboardEvent| {
+        if is_editing(&event) {
+            return;
+        }
+
+        let action = match key_map.action(&event) {
+            Some(action) => action,
+            None => return,
+        };
+
+        if let Some(archizoom) = archizoom.upgrade() {
+            let archizoom = archizoom.borrow();
+
+            // moving between links is still allowed when the view is locked
+            if archizoom.is_locked()
+                && !matches!(
+                    action,
+                    KeyAction::NextLink
+                        | KeyAction::PreviousLink
+                        | KeyAction::ActivateLink
+                        | KeyAction::BlurLink
+                )
+            {
+                return;
+            }
+
+            let handled = match action {
+                KeyAction::PanLeft => pan(&archizoom, -PAN_STEP, 0.0),
+                KeyAction::PanRight => pan(&archizoom, PAN_STEP, 0.0),
+                KeyAction::PanUp => pan(&archizoom, 0.0, -PAN_STEP),
+                KeyAction::PanDown => pan(&archizoom, 0.0, PAN_STEP),
+                KeyAction::ZoomIn => {
+                    archizoom.zoom_by(ZOOM_STEP);
+                    true
+                }
+                KeyAction::ZoomOut => {
+                    archizoom.zoom_by(1.0 / ZOOM_STEP);
+                    true
+                }
+                KeyAction::Reset => {
+                    archizoom.reset();
+                    true
+                }
+                KeyAction::Back => {
+                    let depth = archizoom.navigation().len();
+                    if depth > 1 {
+                        archizoom.navigate_back(depth - 2);
+                    }
+                    depth > 1
+                }
+                KeyAction::Search => archizoom.request_search(),
+                KeyAction::Fullscreen => {
+                    archizoom.toggle_fullscreen();
+                    true
+                }
+                KeyAction::NextLink => archizoom.focus_link(true),
+                KeyAction::PreviousLink => archizoom.focus_link(false),
+                KeyAction::ActivateLink => archizoom.activate_focused_link(),
+                KeyAction::BlurLink => {
+                    archizoom.blur_link();
+                    true
+                }
+                KeyAction::Peek => {
+                    // holding the key down repeats it
+                    if !event.repeat() {
+                        archizoom.start_peek();
+                    }
+                    true
+                }
+            };
+
+            if handled {
+                event.prevent_default();
+            }
+        }
+    })?;
+
+    Ok(vec![
+        keydown,
+        container.new_event_listener("keyup", move |event: KeyboardEvent| {
+            if up_key_map.releases(KeyAction::Peek, &event) {
+                if let Some(archizoom) = up_archizoom.upgrade() {
+                    archizoom.borrow().end_peek();
+                }
+            }
+        })?,
+        // the key up goes somewhere else once focus has left
+        container.new_event_listener("focusout", move |_: FocusEvent| {
+            if let Some(archizoom) = blur_archizoom.upgrade() {
+                archizoom.borrow().end_peek();
+            }
+        })?,
+    ])
+}
+
+/// Whether the key is going to a form field of one of our overlays, like the zoom slider
+pub(crate) fn is_editing(event: &KeyboardEvent) -> bool {
+    event
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+        .map(|target| matches!(target.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"))
+        .unwrap_or(false)
+}
+
+/// Pans by a fraction of the view's size
+fn pan(archizoom: &ArchiZoom, x: f32, y: f32) -> bool {
+    match archizoom.viewport() {
+        Some(mut viewport) => {
+            let delta = Point2D::new(viewport.size.width * x, viewport.size.height * y);
+            viewport.pan(&delta);
+            archizoom.set_viewport(&viewport);
+
+            true
+        }
+        None => false,
+    }
+}
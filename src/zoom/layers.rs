@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use web_sys::{console, SvgElement, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::zoom::sprite::is_shown;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LayersOptions {
+    /// Which groups count as layers
+    pub selector: String,
+}
+
+impl Default for LayersOptions {
+    fn default() -> LayersOptions {
+        LayersOptions {
+            selector: ":scope > g".to_string(),
+        }
+    }
+}
+
+/// A group of the diagram that can be hidden as a whole, like a legend or background
+pub struct Layer {
+    pub name: String,
+    element: SvgElement,
+}
+
+/// What JS sees of a layer
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerInfo {
+    pub name: String,
+    pub visible: bool,
+}
+
+impl Layer {
+    /// The layers of `svg`, named by their `data-layer`, Inkscape label, or id attributes, or by
+    /// their position if they don't have any of those. Hidden sprite groups don't have any.
+    pub fn find_all(svg: &SvgsvgElement, options: &LayersOptions) -> Vec<Layer> {
+        svg.query_selector_all(&options.selector)
+            .map(|nodes| nodes.safe_filter::<SvgElement>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|element| is_shown(element))
+            .enumerate()
+            .map(|(index, element)| {
+                let name = ["data-layer", "inkscape:label", "id"]
+                    .iter()
+                    .filter_map(|attribute| element.get_attribute(attribute))
+                    .find(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| format!("Layer {}", index + 1));
+
+                Layer { name, element }
+            })
+            .collect()
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.element
+            .style()
+            .get_property_value("display")
+            .map(|display| display != "none")
+            .unwrap_or(true)
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        let style = self.element.style();
+        let result = if visible {
+            style.remove_property("display").map(|_| ())
+        } else {
+            style.set_property("display", "none")
+        };
+
+        if let Err(error) = result {
+            console::warn_2(
+                &format!("Couldn't toggle layer {}", self.name).into(),
+                &error,
+            );
+        }
+    }
+
+    pub fn info(&self) -> LayerInfo {
+        LayerInfo {
+            name: self.name.clone(),
+            visible: self.is_visible(),
+        }
+    }
+}
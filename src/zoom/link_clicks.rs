@@ -0,0 +1,73 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Element, MouseEvent, SvgsvgElement};
+
+use crate::js_utils::*;
+use crate::zoom::matrix::Point2D;
+use crate::zoom::selection::{client_position, CLICK_SLOP};
+use crate::zoom::ArchiZoom;
+
+/// Drills into a zoom element when it's clicked. The links keep their real hrefs, so only a
+/// plain left click is ours. Anything with a modifier or another button is left to the browser,
+/// to open the diagram in a new tab or window.
+pub struct LinkClicks {
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl LinkClicks {
+    pub fn new(
+        svg: &SvgsvgElement,
+        archizoom: Weak<RefCell<ArchiZoom>>,
+    ) -> Result<LinkClicks, JsValue> {
+        // where the last press started, so the click ending a pan doesn't drill in
+        let press_origin: Rc<Cell<Option<Point2D>>> = Rc::new(Cell::new(None));
+
+        let down_origin = press_origin.clone();
+        let listeners = vec![
+            svg.new_event_listener("mousedown", move |event: MouseEvent| {
+                down_origin.set(Some(client_position(&event)));
+            })?,
+            svg.new_event_listener("click", move |event: MouseEvent| {
+                let origin = press_origin.take();
+                if event.button() != 0
+                    || event.ctrl_key()
+                    || event.meta_key()
+                    || event.shift_key()
+                    || event.alt_key()
+                {
+                    return;
+                }
+
+                let (target, archizoom) = match (
+                    event
+                        .target()
+                        .and_then(|target| target.dyn_into::<Element>().ok()),
+                    archizoom.upgrade(),
+                ) {
+                    (Some(target), Some(archizoom)) => (target, archizoom),
+                    _ => return,
+                };
+                let url = match archizoom.borrow().link_target(&target) {
+                    Some(url) => url,
+                    None => return,
+                };
+
+                // the browser would leave the page for the raw svg
+                event.prevent_default();
+
+                let moved = origin
+                    .map(|origin| (&client_position(&event) - &origin).length())
+                    .unwrap_or(0.0);
+                if moved <= CLICK_SLOP {
+                    archizoom.borrow().navigate_to(&url);
+                }
+            })?,
+        ];
+
+        Ok(LinkClicks {
+            _listeners: listeners,
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use futures::future::Either;
+use futures::sync::oneshot;
+use futures::Future;
+use js_sys::{Array, Object, Reflect};
+use serde::Deserialize;
+use wasm_bindgen::JsValue;
+use web_sys::{console, Blob, BlobPropertyBag, Event, MessageEvent, Url, Worker};
+
+use crate::js_utils::*;
+use crate::zoom::prefetch::DiagramInfo;
+use crate::zoom::view_link::parse_view_box;
+
+/// Fetches a diagram, cuts the text down to the svg itself, and reads its title, size, and zoom
+/// links out of the markup. DOMParser doesn't exist in workers, so the DOM is still built back on
+/// the main thread, but the page knows about the diagram without scanning it.
+static WORKER_SOURCE: &str = r##"
+const ENTITIES = { lt: "<", gt: ">", quot: '"', apos: "'", "#39": "'", amp: "&" };
+const decode = (text) => text.replace(/&(lt|gt|quot|apos|#39|amp);/g, (_, name) => ENTITIES[name]);
+
+const attributes = (markup, name) =>
+  Array.from(
+    markup.matchAll(new RegExp(`\\s${name}\\s*=\\s*(["'])([^"']*)\\1`, "g")),
+    ([, , value]) => decode(value),
+  );
+
+const describe = (svg) => {
+  const root = (svg.match(/^<svg[^>]*>/) || [""])[0];
+  const attribute = (name) => attributes(root, name)[0] || null;
+  const title = svg.slice(root.length).match(/^\s*<title[^>]*>([^<]*)<\/title>/);
+
+  // the same links as the page's `[*|href*="#archizoom:link"], [data-archizoom-link]`
+  const links = attributes(svg, "data-archizoom-link").concat(
+    attributes(svg, "(?:xlink:)?href").filter((href) => href.includes("#archizoom:link")),
+  );
+
+  return {
+    title: (title && decode(title[1].trim())) || null,
+    viewBox: attribute("viewBox"),
+    width: attribute("width"),
+    height: attribute("height"),
+    links: Array.from(new Set(links)),
+  };
+};
+
+onmessage = async ({ data: { id, url } }) => {
+  try {
+    const response = await fetch(url);
+    if (!response.ok) {
+      throw `${response.status} ${response.statusText}`;
+    }
+
+    const text = await response.text();
+    const start = text.search(/<svg[\s>]/);
+    const svg = start > 0 ? text.slice(start) : text;
+    postMessage({ id, text: svg, description: describe(svg) });
+  } catch (error) {
+    postMessage({ id, error: String(error) });
+  }
+};
+"##;
+
+/// What the worker read from a diagram's markup
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiagramDescription {
+    pub title: Option<String>,
+    pub view_box: Option<String>,
+    pub width: Option<String>,
+    pub height: Option<String>,
+    /// The hrefs of its zoom links, as they're written
+    pub links: Vec<String>,
+}
+
+impl DiagramDescription {
+    pub fn info(&self) -> DiagramInfo {
+        DiagramInfo {
+            title: self.title.clone(),
+            view_box: self.view_box.as_deref().and_then(parse_view_box),
+            width: self.width.clone(),
+            height: self.height.clone(),
+        }
+    }
+}
+
+/// A fetched diagram's markup, described when it came through the worker
+pub struct LoadedDiagram {
+    pub text: String,
+    pub description: Option<DiagramDescription>,
+}
+
+type Pending = Rc<RefCell<HashMap<u32, oneshot::Sender<Result<LoadedDiagram, JsValue>>>>>;
+
+/// One worker shared by every viewer on the page, loads are matched to responses by id
+struct LoadWorker {
+    worker: Worker,
+    next_id: Cell<u32>,
+    pending: Pending,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+thread_local! {
+    static LOAD_WORKER: RefCell<Option<Rc<LoadWorker>>> = const { RefCell::new(None) };
+}
+
+/// Fetches the diagram at `url`, in a worker when `in_worker` is set and we can start one. The
+/// url has to be absolute since the worker has no base to resolve it against.
+pub fn load_diagram(
+    url: &str,
+    in_worker: bool,
+) -> Result<impl Future<Item = LoadedDiagram, Error = JsValue>, JsValue> {
+    let worker = if in_worker { shared_worker() } else { None };
+
+    Ok(match worker {
+        Some(worker) => Either::A(worker.load(url)?),
+        None => Either::B(fetch_text(url)?.map(|text| LoadedDiagram {
+            text,
+            description: None,
+        })),
+    })
+}
+
+fn shared_worker() -> Option<Rc<LoadWorker>> {
+    LOAD_WORKER.with(|shared| {
+        if shared.borrow().is_none() {
+            match LoadWorker::new() {
+                Ok(worker) => {
+                    shared.replace(Some(Rc::new(worker)));
+                }
+                Err(error) => {
+                    // blocked by a content security policy or unsupported, load on this thread
+                    console::warn_2(&"Couldn't start the loading worker".into(), &error);
+                }
+            }
+        }
+
+        shared.borrow().clone()
+    })
+}
+
+impl LoadWorker {
+    fn new() -> Result<LoadWorker, JsValue> {
+        let options = BlobPropertyBag::new();
+        options.set_type("text/javascript");
+        let blob =
+            Blob::new_with_str_sequence_and_options(&Array::of1(&WORKER_SOURCE.into()), &options)?;
+
+        // the script loads asynchronously, so the url lives as long as the page
+        let worker = Worker::new(&Url::create_object_url_with_blob(&blob)?)?;
+
+        let pending: Pending = Rc::new(RefCell::new(HashMap::new()));
+
+        let on_message = {
+            let pending = pending.clone();
+            worker.new_event_listener("message", move |event: MessageEvent| {
+                let data = event.data();
+                let id = Reflect::get(&data, &"id".into())
+                    .ok()
+                    .and_then(|id| id.as_f64())
+                    .map(|id| id as u32);
+
+                if let Some(sender) = id.and_then(|id| pending.borrow_mut().remove(&id)) {
+                    let result = match Reflect::get(&data, &"text".into())
+                        .ok()
+                        .and_then(|text| text.as_string())
+                    {
+                        Some(text) => Ok(LoadedDiagram {
+                            text,
+                            description: Reflect::get(&data, &"description".into())
+                                .ok()
+                                .and_then(|description| from_js(&description).ok()),
+                        }),
+                        None => Err(Reflect::get(&data, &"error".into())
+                            .unwrap_or_else(|_| "The worker didn't send the diagram".into())),
+                    };
+
+                    // the load was dropped if nobody's listening
+                    let _ = sender.send(result);
+                }
+            })?
+        };
+
+        // a worker that failed to start fails everything waiting on it
+        let on_error = {
+            let pending = pending.clone();
+            worker.new_event_listener("error", move |_: Event| {
+                for (_, sender) in pending.borrow_mut().drain() {
+                    let _ = sender.send(Err("The loading worker failed".into()));
+                }
+            })?
+        };
+
+        Ok(LoadWorker {
+            worker,
+            next_id: Cell::new(0),
+            pending,
+            _listeners: vec![on_message, on_error],
+        })
+    }
+
+    fn load(
+        &self,
+        url: &str,
+    ) -> Result<impl Future<Item = LoadedDiagram, Error = JsValue>, JsValue> {
+        let id = self.next_id.get();
+        self.next_id.set(id.wrapping_add(1));
+
+        let request = Object::new();
+        Reflect::set(&request, &"id".into(), &id.into())?;
+        Reflect::set(&request, &"url".into(), &url.into())?;
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending.borrow_mut().insert(id, sender);
+        if let Err(error) = self.worker.post_message(&request) {
+            self.pending.borrow_mut().remove(&id);
+
+            return Err(error);
+        }
+
+        Ok(receiver.then(|result| match result {
+            Ok(loaded) => loaded,
+            Err(_) => Err("The load was cancelled".into()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_descriptions_become_diagram_info() {
+        let description: DiagramDescription = serde_json::from_str(
+            r#"{ "title": "Context", "viewBox": "0 0 400 300", "width": null, "links": ["a.svg#archizoom:link"] }"#,
+        )
+        .unwrap();
+        let info = description.info();
+
+        assert_eq!(info.title.as_deref(), Some("Context"));
+        assert_eq!(info.view_box.unwrap().bottom_right.x, 400.0);
+        assert_eq!(info.width, None);
+        assert_eq!(description.links, vec!["a.svg#archizoom:link".to_string()]);
+    }
+}
@@ -1,8 +1,47 @@
 use std::fmt::{Display, Formatter, Result};
+use std::ops::{Add, Mul, Sub};
 
-use web_sys::{SvgMatrix, SvgRect};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{DomMatrix, DomMatrixReadOnly, SvgMatrix, SvgRect};
 
-#[derive(Debug, Clone)]
+/// The tolerance used when comparing coordinates, well below anything visible on screen
+pub static EPSILON: f32 = 1e-4;
+
+/// Whether `a` and `b` are equal within `EPSILON`, relative to their magnitude
+#[inline]
+pub fn approx_eq(a: f32, b: f32) -> bool {
+    (a - b).abs() <= EPSILON * 1f32.max(a.abs()).max(b.abs())
+}
+
+/// Moves `rect` the least amount needed to lie within `bounds`. If it's larger than `bounds`
+/// along an axis it's centered on that axis instead.
+pub fn clamp_rect_within(rect: &Rect, bounds: &Rect) -> Rect {
+    #[inline]
+    fn clamp_axis(start: f32, length: f32, min: f32, max: f32) -> f32 {
+        if length >= max - min {
+            ((min + max) / 2.0) - (length / 2.0)
+        } else {
+            start.clamp(min, max - length)
+        }
+    }
+
+    Rect::from_origin(
+        Point2D::new(
+            clamp_axis(rect.left(), rect.width(), bounds.left(), bounds.right()),
+            clamp_axis(rect.top(), rect.height(), bounds.top(), bounds.bottom()),
+        ),
+        &rect.size(),
+    )
+}
+
+/// Linearly interpolates between `a` and `b`, where `t` of 0 is `a` and 1 is `b`
+#[inline]
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + ((b - a) * t)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Point2D {
     pub x: f32,
     pub y: f32,
@@ -14,6 +53,26 @@ impl Point2D {
         Point2D { x, y }
     }
 
+    #[inline]
+    pub fn approx_eq(&self, other: &Point2D) -> bool {
+        approx_eq(self.x, other.x) && approx_eq(self.y, other.y)
+    }
+
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.approx_eq(&Point2D::new(0.0, 0.0))
+    }
+
+    #[inline]
+    pub fn length(&self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    #[inline]
+    pub fn lerp(&self, other: &Point2D, t: f32) -> Point2D {
+        Point2D::new(lerp(self.x, other.x, t), lerp(self.y, other.y, t))
+    }
+
     #[inline]
     pub fn matrix_transform(&self, matrix: &Matrix2D) -> Point2D {
         Point2D {
@@ -23,6 +82,33 @@ impl Point2D {
     }
 }
 
+impl Add for &Point2D {
+    type Output = Point2D;
+
+    #[inline]
+    fn add(self, other: &Point2D) -> Point2D {
+        Point2D::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for &Point2D {
+    type Output = Point2D;
+
+    #[inline]
+    fn sub(self, other: &Point2D) -> Point2D {
+        Point2D::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f32> for &Point2D {
+    type Output = Point2D;
+
+    #[inline]
+    fn mul(self, factor: f32) -> Point2D {
+        Point2D::new(self.x * factor, self.y * factor)
+    }
+}
+
 impl Display for Point2D {
     fn fmt(&self, f: &mut Formatter) -> Result {
         let precision = f.precision().unwrap_or(3);
@@ -39,7 +125,65 @@ impl Display for Point2D {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Size2D {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Size2D {
+    #[inline]
+    pub fn new(width: f32, height: f32) -> Size2D {
+        Size2D { width, height }
+    }
+
+    #[inline]
+    pub fn scale(&self, factor: f32) -> Size2D {
+        Size2D::new(self.width * factor, self.height * factor)
+    }
+
+    #[inline]
+    pub fn lerp(&self, other: &Size2D, t: f32) -> Size2D {
+        Size2D::new(
+            lerp(self.width, other.width, t),
+            lerp(self.height, other.height, t),
+        )
+    }
+}
+
+impl Display for Size2D {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let precision = f.precision().unwrap_or(3);
+        let width = f.width().unwrap_or(6);
+
+        write!(
+            f,
+            "{:w$.p$} x {:w$.p$}",
+            self.width,
+            self.height,
+            w = width,
+            p = precision
+        )
+    }
+}
+
+/// Breathing room left around a framed target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Margin {
+    /// In the same units as the target
+    Absolute(f32),
+    /// A percentage of the target's size on each axis
+    Percent(f32),
+}
+
+impl Default for Margin {
+    fn default() -> Margin {
+        Margin::Percent(5.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Rect {
     pub top_left: Point2D,
     pub bottom_right: Point2D,
@@ -54,6 +198,13 @@ impl Rect {
         }
     }
 
+    #[inline]
+    pub fn from_origin(origin: Point2D, size: &Size2D) -> Rect {
+        let bottom_right = Point2D::new(origin.x + size.width, origin.y + size.height);
+
+        Rect::new(origin, bottom_right)
+    }
+
     pub fn from_svg(js_rect: &SvgRect) -> Rect {
         Rect::new(
             Point2D {
@@ -67,6 +218,19 @@ impl Rect {
         )
     }
 
+    #[inline]
+    pub fn approx_eq(&self, other: &Rect) -> bool {
+        self.top_left.approx_eq(&other.top_left) && self.bottom_right.approx_eq(&other.bottom_right)
+    }
+
+    #[inline]
+    pub fn lerp(&self, other: &Rect, t: f32) -> Rect {
+        Rect::new(
+            self.top_left.lerp(&other.top_left, t),
+            self.bottom_right.lerp(&other.bottom_right, t),
+        )
+    }
+
     pub fn matrix_transform(&self, matrix: &Matrix2D) -> Rect {
         Rect {
             top_left: self.top_left.matrix_transform(matrix),
@@ -74,6 +238,134 @@ impl Rect {
         }
     }
 
+    /// The axis aligned bounds of all four corners after the transform, which unlike
+    /// `matrix_transform` stays correct under rotation and skew
+    pub fn transformed_bounds(&self, matrix: &Matrix2D) -> Rect {
+        let corners = [
+            Point2D::new(self.left(), self.top()),
+            Point2D::new(self.right(), self.top()),
+            Point2D::new(self.right(), self.bottom()),
+            Point2D::new(self.left(), self.bottom()),
+        ];
+
+        let mut bounds = Rect::new(
+            corners[0].matrix_transform(matrix),
+            corners[0].matrix_transform(matrix),
+        );
+        for corner in corners[1..].iter() {
+            let point = corner.matrix_transform(matrix);
+            bounds = bounds.union(&Rect::new(point.clone(), point));
+        }
+
+        bounds
+    }
+
+    /// Grows the rect by `margin` on every side, a negative margin shrinks it
+    pub fn inflate(&self, margin: f32) -> Rect {
+        Rect::new(
+            Point2D::new(self.left() - margin, self.top() - margin),
+            Point2D::new(self.right() + margin, self.bottom() + margin),
+        )
+    }
+
+    /// Grows the rect by `margin`, where percentages are of this rect's width and height
+    pub fn inflate_by(&self, margin: &Margin) -> Rect {
+        match *margin {
+            Margin::Absolute(margin) => self.inflate(margin),
+            Margin::Percent(percent) => {
+                let horizontal = self.width() * (percent / 100.0);
+                let vertical = self.height() * (percent / 100.0);
+
+                Rect::new(
+                    Point2D::new(self.left() - horizontal, self.top() - vertical),
+                    Point2D::new(self.right() + horizontal, self.bottom() + vertical),
+                )
+            }
+        }
+    }
+
+    /// The viewBox that letterboxes this rect into `container`: it has the container's aspect
+    /// ratio, shows all of this rect centered, and leaves at least `padding` container units
+    /// around it
+    pub fn fit_into(&self, container: &Rect, padding: f32) -> Rect {
+        let scale = ((container.width() - (padding * 2.0)) / self.width())
+            .min((container.height() - (padding * 2.0)) / self.height());
+
+        self.framed(container, scale)
+    }
+
+    /// The viewBox that fills `container` with this rect: it has the container's aspect ratio and
+    /// shows as much of this rect as possible, centered, without any empty space
+    pub fn cover(&self, container: &Rect) -> Rect {
+        let scale = (container.width() / self.width()).max(container.height() / self.height());
+
+        self.framed(container, scale)
+    }
+
+    /// The container sized rect centered on this rect at `scale` container units per unit
+    fn framed(&self, container: &Rect, scale: f32) -> Rect {
+        if !scale.is_finite() || scale <= 0.0 {
+            return self.clone();
+        }
+
+        let size = container.size().scale(1.0 / scale);
+        let center = self.center();
+
+        Rect::from_origin(
+            Point2D::new(
+                center.x - (size.width / 2.0),
+                center.y - (size.height / 2.0),
+            ),
+            &size,
+        )
+    }
+
+    #[inline]
+    pub fn contains(&self, point: &Point2D) -> bool {
+        point.x >= self.left()
+            && point.x <= self.right()
+            && point.y >= self.top()
+            && point.y <= self.bottom()
+    }
+
+    #[inline]
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.left() <= other.right()
+            && other.left() <= self.right()
+            && self.top() <= other.bottom()
+            && other.top() <= self.bottom()
+    }
+
+    /// The area the two rects share, which is 0 when they don't overlap
+    pub fn overlap_area(&self, other: &Rect) -> f32 {
+        #[inline]
+        fn overlap(a_start: f32, a_end: f32, b_start: f32, b_end: f32) -> f32 {
+            // disjoint spans give a negative overlap, and two of those would multiply to a
+            // positive area
+            (a_end.min(b_end) - a_start.max(b_start)).max(0.0)
+        }
+
+        overlap(self.left(), self.right(), other.left(), other.right())
+            * overlap(self.top(), self.bottom(), other.top(), other.bottom())
+    }
+
+    /// Whether `other` is completely inside of this rect
+    #[inline]
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        self.contains(&other.top_left) && self.contains(&other.bottom_right)
+    }
+
+    /// The smallest Rect containing both rects
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect::new(
+            Point2D::new(self.left().min(other.left()), self.top().min(other.top())),
+            Point2D::new(
+                self.right().max(other.right()),
+                self.bottom().max(other.bottom()),
+            ),
+        )
+    }
+
     #[inline]
     pub fn left(&self) -> f32 {
         self.top_left.x
@@ -98,12 +390,25 @@ impl Rect {
 
     #[inline]
     pub fn width(&self) -> f32 {
-        self.top_left.x - self.bottom_right.x
+        self.bottom_right.x - self.top_left.x
     }
 
     #[inline]
     pub fn height(&self) -> f32 {
-        self.top_left.y - self.bottom_right.y
+        self.bottom_right.y - self.top_left.y
+    }
+
+    #[inline]
+    pub fn size(&self) -> Size2D {
+        Size2D::new(self.width(), self.height())
+    }
+
+    #[inline]
+    pub fn center(&self) -> Point2D {
+        Point2D::new(
+            (self.left() + self.right()) / 2.0,
+            (self.top() + self.bottom()) / 2.0,
+        )
     }
 }
 
@@ -117,10 +422,32 @@ impl Display for Rect {
     }
 }
 
+/// The parts of a Matrix2D, `rotation` is in radians
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decomposition {
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub rotation: f32,
+    pub translation: Point2D,
+}
+
+impl Decomposition {
+    /// The average scale along both axes, which is what a user perceives as the zoom level
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        (self.scale_x.abs() * self.scale_y.abs()).sqrt()
+    }
+
+    #[inline]
+    pub fn rotation_degrees(&self) -> f32 {
+        self.rotation.to_degrees()
+    }
+}
+
 /// [a c e]
 /// [b d f]
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Matrix2D {
     pub a: f32,
     pub b: f32,
@@ -131,6 +458,79 @@ pub struct Matrix2D {
 }
 
 impl Matrix2D {
+    #[inline]
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Matrix2D {
+        Matrix2D { a, b, c, d, e, f }
+    }
+
+    #[inline]
+    pub fn identity() -> Matrix2D {
+        Matrix2D::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    #[inline]
+    pub fn translation(x: f32, y: f32) -> Matrix2D {
+        Matrix2D::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    #[inline]
+    pub fn scaling(factor: f32) -> Matrix2D {
+        Matrix2D::new(factor, 0.0, 0.0, factor, 0.0, 0.0)
+    }
+
+    /// A scale by `factor` that leaves `point` where it is
+    pub fn scale_about(point: &Point2D, factor: f32) -> Matrix2D {
+        Matrix2D::translation(point.x, point.y)
+            .multiply(&Matrix2D::scaling(factor))
+            .multiply(&Matrix2D::translation(-point.x, -point.y))
+    }
+
+    /// `self * other`, so `other` is applied first
+    pub fn multiply(&self, other: &Matrix2D) -> Matrix2D {
+        Matrix2D {
+            a: (self.a * other.a) + (self.c * other.b),
+            b: (self.b * other.a) + (self.d * other.b),
+            c: (self.a * other.c) + (self.c * other.d),
+            d: (self.b * other.c) + (self.d * other.d),
+            e: (self.a * other.e) + (self.c * other.f) + self.e,
+            f: (self.b * other.e) + (self.d * other.f) + self.f,
+        }
+    }
+
+    /// Splits this into a scale, then a rotation, then a translation. Any skew ends up folded into
+    /// `scale_y`.
+    pub fn decompose(&self) -> Decomposition {
+        let scale_x = ((self.a * self.a) + (self.b * self.b)).sqrt();
+        let determinant = (self.a * self.d) - (self.b * self.c);
+
+        Decomposition {
+            scale_x,
+            scale_y: if scale_x != 0.0 {
+                determinant / scale_x
+            } else {
+                0.0
+            },
+            rotation: self.b.atan2(self.a),
+            translation: Point2D::new(self.e, self.f),
+        }
+    }
+
+    pub fn inverse(&self) -> Option<Matrix2D> {
+        let determinant = (self.a * self.d) - (self.b * self.c);
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+
+        Some(Matrix2D {
+            a: self.d / determinant,
+            b: -self.b / determinant,
+            c: -self.c / determinant,
+            d: self.a / determinant,
+            e: ((self.c * self.f) - (self.d * self.e)) / determinant,
+            f: ((self.b * self.e) - (self.a * self.f)) / determinant,
+        })
+    }
+
     #[inline]
     pub fn from_js(js_matrix: &SvgMatrix) -> Matrix2D {
         Matrix2D {
@@ -142,6 +542,59 @@ impl Matrix2D {
             f: js_matrix.f(),
         }
     }
+
+    #[inline]
+    pub fn from_dom_matrix(dom_matrix: &DomMatrixReadOnly) -> Matrix2D {
+        Matrix2D {
+            a: dom_matrix.a() as f32,
+            b: dom_matrix.b() as f32,
+            c: dom_matrix.c() as f32,
+            d: dom_matrix.d() as f32,
+            e: dom_matrix.e() as f32,
+            f: dom_matrix.f() as f32,
+        }
+    }
+
+    pub fn to_dom_matrix(&self) -> std::result::Result<DomMatrix, JsValue> {
+        DomMatrix::new_with_array64(&mut [
+            f64::from(self.a),
+            f64::from(self.b),
+            f64::from(self.c),
+            f64::from(self.d),
+            f64::from(self.e),
+            f64::from(self.f),
+        ])
+    }
+
+    /// Formats this as a CSS `matrix()` transform function
+    pub fn to_css(&self) -> String {
+        format!(
+            "matrix({}, {}, {}, {}, {}, {})",
+            self.a, self.b, self.c, self.d, self.e, self.f
+        )
+    }
+
+    /// Parses a CSS (or Svg) `matrix()` transform, as returned by `getComputedStyle`.
+    /// `none` is treated as the identity matrix.
+    pub fn from_css(css: &str) -> Option<Matrix2D> {
+        let css = css.trim();
+        if css == "none" {
+            return Some(Matrix2D::identity());
+        }
+
+        let values = css
+            .strip_prefix("matrix(")
+            .and_then(|rest| rest.strip_suffix(')'))?
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<f32>().ok())
+            .collect::<Option<Vec<f32>>>()?;
+
+        match values.as_slice() {
+            [a, b, c, d, e, f] => Some(Matrix2D::new(*a, *b, *c, *d, *e, *f)),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Matrix2D {
@@ -163,3 +616,48 @@ impl Display for Matrix2D {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: f32, top: f32, right: f32, bottom: f32) -> Rect {
+        Rect::new(Point2D::new(left, top), Point2D::new(right, bottom))
+    }
+
+    #[test]
+    fn rects_lerp_between_their_corners() {
+        let from = rect(0.0, 0.0, 100.0, 50.0);
+        let to = rect(100.0, 50.0, 300.0, 250.0);
+
+        assert!(from.lerp(&to, 0.0).approx_eq(&from));
+        assert!(from.lerp(&to, 1.0).approx_eq(&to));
+        assert!(from
+            .lerp(&to, 0.5)
+            .approx_eq(&rect(50.0, 25.0, 200.0, 150.0)));
+    }
+
+    #[test]
+    fn overlap_area_is_the_shared_region() {
+        let view = rect(0.0, 0.0, 100.0, 100.0);
+
+        assert!(approx_eq(
+            view.overlap_area(&rect(50.0, 50.0, 150.0, 150.0)),
+            2500.0
+        ));
+        assert!(approx_eq(
+            view.overlap_area(&rect(-10.0, -10.0, 110.0, 110.0)),
+            10000.0
+        ));
+    }
+
+    #[test]
+    fn overlap_area_is_zero_off_screen() {
+        let view = rect(0.0, 0.0, 100.0, 100.0);
+
+        // up and to the left, where both spans are negative
+        assert_eq!(view.overlap_area(&rect(-200.0, -200.0, -10.0, -10.0)), 0.0);
+        // beside the view, where only one span is negative
+        assert_eq!(view.overlap_area(&rect(150.0, 0.0, 250.0, 100.0)), 0.0);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use web_sys::Element;
+
+/// What a diagram says about one of its elements, so the page can join what happens in the
+/// viewer back up with the model the diagram was exported from. Archi and most ArchiMate tools
+/// put the model's ids and element types into ids, classes, and `data-` attributes.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementInfo {
+    /// The id of the svg element
+    pub id: Option<String>,
+    /// The id of the model element it shows, from `data-archimate-id` or `data-element-id`,
+    /// or an Archi style `id-...` id
+    pub archimate_id: Option<String>,
+    /// Like `business-actor`, from `data-archimate-type` or an `archimate-...` class
+    pub archimate_type: Option<String>,
+    /// From `data-name` or the element's `<title>`
+    pub name: Option<String>,
+    /// Every class that isn't one of ours
+    pub classes: Vec<String>,
+    /// Every other `data-` attribute, without the `data-`
+    pub properties: BTreeMap<String, String>,
+}
+
+static ID_ATTRIBUTES: [&str; 2] = ["data-archimate-id", "data-element-id"];
+static TYPE_ATTRIBUTE: &str = "data-archimate-type";
+static NAME_ATTRIBUTE: &str = "data-name";
+static TYPE_CLASS_PREFIX: &str = "archimate-";
+
+impl ElementInfo {
+    pub fn read(element: &Element) -> ElementInfo {
+        let id = Some(element.id()).filter(|id| !id.is_empty());
+
+        let classes: Vec<String> = element
+            .get_attribute("class")
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter(|class_name| !class_name.starts_with("archizoom-"))
+            .map(str::to_string)
+            .collect();
+
+        let mut properties = BTreeMap::new();
+        for name in element
+            .get_attribute_names()
+            .iter()
+            .filter_map(|name| name.as_string())
+        {
+            if let Some(property) = name.strip_prefix("data-") {
+                if !property.starts_with("archizoom-") {
+                    properties.insert(
+                        property.to_string(),
+                        element.get_attribute(&name).unwrap_or_default(),
+                    );
+                }
+            }
+        }
+
+        let archimate_id = ID_ATTRIBUTES
+            .iter()
+            .find_map(|attribute| element.get_attribute(attribute))
+            .or_else(|| id.clone().filter(|id| id.starts_with("id-")));
+        let archimate_type = element.get_attribute(TYPE_ATTRIBUTE).or_else(|| {
+            classes
+                .iter()
+                .find_map(|class_name| class_name.strip_prefix(TYPE_CLASS_PREFIX))
+                .map(str::to_string)
+        });
+        let name = element
+            .get_attribute(NAME_ATTRIBUTE)
+            .or_else(|| title(element));
+
+        ElementInfo {
+            id,
+            archimate_id,
+            archimate_type,
+            name,
+            classes,
+            properties,
+        }
+    }
+
+    /// What the diagram says about the element of a link, which is either the link itself,
+    /// the first thing inside of it with an id, or the closest thing around it with one
+    pub fn read_link(link: &Element) -> ElementInfo {
+        let described = if !link.id().is_empty() {
+            Some(link.clone())
+        } else {
+            link.query_selector("[id]").ok().flatten().or_else(|| {
+                // but not the svg itself, or whatever is around it on the page
+                link.closest("svg [id]").ok().flatten()
+            })
+        };
+
+        ElementInfo::read(described.as_ref().unwrap_or(link))
+    }
+}
+
+/// The text of the element's own `<title>`
+fn title(element: &Element) -> Option<String> {
+    element
+        .query_selector(":scope > title")
+        .ok()
+        .flatten()
+        .and_then(|title| title.text_content())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Node, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+
+/// Namespaces that only mean something to the editor that saved the diagram
+static EDITOR_PREFIXES: [&str; 5] = ["inkscape", "sodipodi", "rdf", "cc", "dc"];
+
+/// Strips what the browser would parse and keep around but never render: comments, editor
+/// metadata and attributes, whitespace between elements, and defs nothing refers to. Exports
+/// from Archi and Inkscape carry a lot of these.
+///
+/// `text` is the markup the svg was parsed from, used to find every `#id` reference in one pass.
+pub fn minify_svg(svg: &SvgsvgElement, text: &str) {
+    if let Ok(editor_elements) = svg.query_selector_all("metadata") {
+        for metadata in editor_elements.safe_filter::<Element>() {
+            metadata.remove();
+        }
+    }
+
+    strip_node(svg);
+    remove_unused_defs(svg, text);
+}
+
+fn strip_node(node: &Node) {
+    // text is whitespace sensitive
+    let keep_whitespace = node
+        .dyn_ref::<Element>()
+        .map(|element| element.local_name() == "text")
+        .unwrap_or(false);
+
+    for child in node.child_nodes().safe_filter::<Node>() {
+        let remove = match child.node_type() {
+            Node::COMMENT_NODE | Node::PROCESSING_INSTRUCTION_NODE => true,
+            Node::TEXT_NODE => {
+                !keep_whitespace
+                    && child
+                        .text_content()
+                        .map(|text| text.trim().is_empty())
+                        .unwrap_or(true)
+            }
+            Node::ELEMENT_NODE => {
+                let element: &Element = child.unchecked_ref();
+                if is_editor_name(&element.tag_name()) {
+                    true
+                } else {
+                    strip_attributes(element);
+                    strip_node(&child);
+
+                    false
+                }
+            }
+            _ => false,
+        };
+
+        if remove {
+            // removing from a live parent can't fail
+            let _ = node.remove_child(&child);
+        }
+    }
+}
+
+fn strip_attributes(element: &Element) {
+    for name in element.get_attribute_names().iter() {
+        if let Some(name) = name.as_string() {
+            let editor_namespace = name
+                .strip_prefix("xmlns:")
+                .map(|prefix| EDITOR_PREFIXES.contains(&prefix))
+                .unwrap_or(false);
+
+            if editor_namespace || is_editor_name(&name) {
+                let _ = element.remove_attribute(&name);
+            }
+        }
+    }
+}
+
+/// Svg parsed as html keeps prefixes in the names, so `inkscape:label` is just a name
+fn is_editor_name(name: &str) -> bool {
+    name.split_once(':')
+        .map(|(prefix, _)| EDITOR_PREFIXES.contains(&prefix))
+        .unwrap_or(false)
+}
+
+/// Removes the definitions with an id that's never referenced. Ones without an id, like
+/// stylesheets, are kept since they apply without being referenced.
+fn remove_unused_defs(svg: &SvgsvgElement, text: &str) {
+    let definitions = match svg.query_selector_all("defs > [id]") {
+        Ok(definitions) => definitions.safe_filter::<Element>(),
+        Err(_) => return,
+    };
+    if definitions.is_empty() {
+        return;
+    }
+
+    let referenced = referenced_ids(text);
+    for definition in definitions {
+        if !referenced.contains(definition.id().as_str()) {
+            definition.remove();
+        }
+    }
+}
+
+/// Everything that looks like `#id` in the markup, which includes every `url(#id)` and
+/// `href="#id"`. Picking up colours and the like just means keeping a few extra defs.
+fn referenced_ids(text: &str) -> HashSet<&str> {
+    let mut ids = HashSet::new();
+    for after in text.split('#').skip(1) {
+        let end = after
+            .find(|c: char| !(c.is_alphanumeric() || "-_.:".contains(c)))
+            .unwrap_or(after.len());
+        let id = &after[..end];
+
+        // our own links look like `#id:link`, keep both readings
+        if let Some((before_colon, _)) = id.split_once(':') {
+            ids.insert(before_colon);
+        }
+        if !id.is_empty() {
+            ids.insert(id);
+        }
+    }
+
+    ids
+}
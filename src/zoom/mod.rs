@@ -1,123 +1,1892 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, Ref, RefCell};
+use std::rc::{Rc, Weak};
 
+use futures::future::{self, Either};
+use futures::Future;
+use js_sys::Function;
 use wasm_bindgen::prelude::*;
-use web_sys::{console, SvgaElement, SvgsvgElement};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{console, Element, MouseEvent, Node, SvgElement, SvgGraphicsElement, SvgsvgElement};
 
-use svg_view_controller::SvgViewController;
-
-use crate::events::EventSource;
+use crate::diagnostics;
 use crate::js_utils::*;
-use crate::zoom::matrix::{Matrix2D, Rect};
+use crate::leak_check::{Live, Tracked};
+use crate::options::{Options, LIVE_OPTIONS};
+use crate::overlays::context_menu::ContextMenuItem;
+use crate::overlays::crosshair::Crosshair;
+use crate::overlays::{create_overlays, Overlay, ViewerEvent};
+use crate::strings::Strings;
+use crate::styles::{apply_deprecated_properties, apply_rendering_hints, ensure_stylesheet};
+use crate::zoom::analytics::Analytics;
+use crate::zoom::animation::{Transition, ViewportAnimation};
+#[cfg(feature = "annotations")]
+use crate::zoom::annotations::{Annotation, AnnotationSet};
+use crate::zoom::bounds::{covering_viewport, element_bounds, find_elements};
+use crate::zoom::broadcast::Broadcaster;
+use crate::zoom::diagram::{parse_svg, resolve_targets, Diagram, ZoomElement};
+use crate::zoom::diagram_cache::DiagramCache;
+use crate::zoom::diff::Diff;
+use crate::zoom::embed::Embed;
+use crate::zoom::export::export_svg;
+use crate::zoom::filter::Filter;
+use crate::zoom::frame_loop::{schedule_frame, FrameClient};
+use crate::zoom::gamepad::GamepadSupport;
+use crate::zoom::hover_prefetch::HoverPrefetch;
+use crate::zoom::isolate::Isolation;
+use crate::zoom::keyboard::register_keyboard;
+use crate::zoom::layers::LayerInfo;
+use crate::zoom::loader::{load_diagram, DiagramDescription, LoadedDiagram};
+use crate::zoom::matrix::{Decomposition, Margin, Matrix2D, Point2D, Rect};
+use crate::zoom::metadata::ElementInfo;
+#[cfg(feature = "search")]
+use crate::zoom::model_search::{ModelIndex, ModelSearchResult};
+use crate::zoom::navigation::{
+    load_navigation, save_navigation, NavigateDetail, NavigationEntry, PendingView, NAVIGATE_EVENT,
+};
+use crate::zoom::overview::OverviewIndicator;
+use crate::zoom::pins::{Pin, PinData};
+use crate::zoom::prefetch::{DiagramInfo, Prefetcher};
+use crate::zoom::print::PrintSupport;
+use crate::zoom::quality::{AdaptiveQuality, Quality};
+use crate::zoom::recording::{Recorder, Trace};
+use crate::zoom::remembered_view::RememberedView;
+use crate::zoom::scheduler::AnimationScheduler;
+#[cfg(feature = "search")]
+use crate::zoom::search::SearchResults;
+use crate::zoom::space::{
+    ScreenPoint, ScreenRect, ScreenSpace, SvgUserSpace, Transform, UserPoint,
+};
 use crate::zoom::svg_view_controller::ViewUpdateEvent;
-use crate::PREFIX_ALIAS;
+use crate::zoom::timing::{Span, Timings};
+use crate::zoom::view_link::ViewLink;
+use crate::zoom::viewport::{ViewBox, Viewport};
 
-mod matrix;
-mod svg_view_controller;
+pub mod analytics;
+pub mod animation;
+#[cfg(feature = "annotations")]
+pub mod annotations;
+pub mod bounds;
+pub mod broadcast;
+pub mod culling;
+pub mod delegation;
+pub mod diagram;
+pub mod diagram_cache;
+pub mod diff;
+pub mod easing;
+pub mod embed;
+pub mod export;
+pub mod filter;
+pub mod frame_loop;
+pub mod gamepad;
+pub mod geometry;
+pub mod hit_test;
+pub mod hover_prefetch;
+pub mod isolate;
+pub mod keyboard;
+pub mod layers;
+pub mod link_clicks;
+pub mod loader;
+pub mod matrix;
+pub mod metadata;
+pub mod minify;
+#[cfg(feature = "search")]
+pub mod model_search;
+pub mod momentum;
+pub mod navigation;
+pub mod overview;
+pub mod pins;
+pub mod prefetch;
+pub mod print;
+pub mod progressive;
+pub mod quadtree;
+pub mod quality;
+pub mod recording;
+pub mod remembered_view;
+pub mod scheduler;
+#[cfg(feature = "search")]
+pub mod search;
+pub mod selection;
+pub mod semantic_zoom;
+pub mod space;
+pub mod spring;
+pub mod sprite;
+pub mod svg_view_controller;
+pub mod text_culling;
+#[cfg(feature = "canvas")]
+pub mod tiles;
+pub mod timing;
+pub mod transform_cache;
+pub mod view_link;
+pub mod viewport;
+pub mod zoom_path;
 
 #[wasm_bindgen]
 pub struct ArchiZoom {
-    _svg: SvgsvgElement,
-    zoom_elements: Vec<ZoomElement>,
-    view_controller: Rc<RefCell<SvgViewController>>,
+    container: Element,
+    diagram: Diagram,
+    /// The diagrams we drilled down through to get here, the last entry is the current diagram
+    navigation: Vec<NavigationEntry>,
+    /// Set while a new diagram is loading so we don't start another navigation
+    navigating: Cell<bool>,
+    /// Created when the crosshair is turned on and dropped when it's turned off
+    crosshair: RefCell<Option<Crosshair>>,
+    /// Set while the user's gestures are ignored, kept here so it carries over to linked
+    /// diagrams
+    locked: Cell<bool>,
+    /// The zoom element Tab moved to, if any
+    focused_link: Cell<Option<usize>>,
+    scheduler: AnimationScheduler,
+    diagram_cache: DiagramCache,
+    prefetcher: Prefetcher,
+    quality: AdaptiveQuality,
+    timings: Timings,
+    /// Running from when a diagram is mounted until its first view update
+    first_view_update: RefCell<Option<Span>>,
+    options: RefCell<Options>,
+    overlays: Vec<Box<dyn Overlay>>,
+    _keyboard_listeners: Vec<Box<dyn JsEventListener>>,
+    _gamepad: Option<GamepadSupport>,
+    _hover_prefetch: Option<HoverPrefetch>,
+    #[cfg(feature = "annotations")]
+    annotations: RefCell<AnnotationSet>,
+    #[cfg(feature = "search")]
+    search: RefCell<Option<SearchResults>>,
+    /// Built by the first model-wide search
+    #[cfg(feature = "search")]
+    model_index: RefCell<Option<Rc<ModelIndex>>>,
+    /// What to show once we've navigated to the diagram at the url
+    pending_view: RefCell<Option<(String, PendingView)>>,
+    filter: RefCell<Option<Filter>>,
+    isolation: RefCell<Option<Isolation>>,
+    /// Where the view was before peeking out at the whole diagram
+    peek_from: RefCell<Option<Viewport>>,
+    recorder: RefCell<Option<Recorder>>,
+    diff: RefCell<Option<Diff>>,
+    pins: RefCell<Vec<Pin>>,
+    pin_click_handler: RefCell<Option<Function>>,
+    /// Loads `pins.src` again when it's due
+    pin_refresh: Option<Timeout>,
+    broadcaster: Option<Broadcaster>,
+    embed: Option<Embed>,
+    analytics: Option<Analytics>,
+    _print: Option<PrintSupport>,
+    remembered_view: Option<RememberedView>,
+    /// Viewers that follow our pan and zoom, and that we follow
+    synced: RefCell<Vec<Weak<RefCell<ArchiZoom>>>>,
+    /// Set while we're the overview of another viewer
+    overview_of: RefCell<Option<OverviewIndicator>>,
+    /// The viewers showing where we're looking
+    overviews: RefCell<Vec<Weak<RefCell<ArchiZoom>>>>,
+    /// Entries the host page added to the context menu
+    context_menu_items: RefCell<Vec<ContextMenuItem>>,
+    self_ref: Weak<RefCell<ArchiZoom>>,
+    _live: Live,
 }
 
-struct ZoomElement {
-    _link: String,
-    link_element: SvgaElement,
+thread_local! {
+    /// Set while a view is being mirrored, so the viewers it's mirrored to don't mirror it back
+    static MIRRORING: Cell<bool> = const { Cell::new(false) };
 }
 
-static X_LINK_NS: &str = "http://www.w3.org/1999/xlink";
-static VIEW_THRESHOLD: f32 = 0.45;
-
 impl ArchiZoom {
-    pub fn new(svg: SvgsvgElement) -> Result<Rc<RefCell<ArchiZoom>>, JsValue> {
-        let zoom_areas = svg
-            .query_selector_all(&format!("[*|href*=\"#{}:link\"]", PREFIX_ALIAS))?
-            .safe_filter::<SvgaElement>()
-            .into_iter()
-            .map(|link_element| {
-                let zoom_element = ZoomElement {
-                    _link: link_element.href().base_val(),
-                    link_element,
-                };
-
-                // TODO we really need to actually just replace this with some other non-clickable thing
-                zoom_element
-                    .link_element
-                    .set_attribute_ns(Some(X_LINK_NS), "href", "#")
-                    .expect("We should always be able to clear the xlink:href attribute");
-
-                zoom_element
-            })
-            .collect();
-
-        let view_controller = SvgViewController::new(&svg)?;
+    pub fn new(
+        container: Element,
+        svg: SvgsvgElement,
+        url: &str,
+        options: Options,
+    ) -> Result<Rc<RefCell<ArchiZoom>>, JsValue> {
+        let diagram = Diagram::new(svg, url, &container, &options)?;
 
         let archizoom = Rc::new(RefCell::new(ArchiZoom {
-            view_controller,
-            zoom_elements: zoom_areas,
-            _svg: svg,
+            container: container.clone(),
+            navigation: vec![NavigationEntry::new(&diagram)],
+            diagram,
+            navigating: Cell::new(false),
+            crosshair: RefCell::new(None),
+            locked: Cell::new(options.locked),
+            focused_link: Cell::new(None),
+            scheduler: AnimationScheduler::default(),
+            diagram_cache: DiagramCache::new(&options.diagram_cache),
+            prefetcher: Prefetcher::new(&options.prefetch),
+            quality: AdaptiveQuality::new(&container, &options.quality),
+            timings: Timings::default(),
+            first_view_update: RefCell::new(Some(Span::start("first-view-update"))),
+            options: RefCell::new(options.clone()),
+            overlays: vec![],
+            _keyboard_listeners: vec![],
+            _gamepad: None,
+            _hover_prefetch: None,
+            #[cfg(feature = "annotations")]
+            annotations: RefCell::new(AnnotationSet::default()),
+            #[cfg(feature = "search")]
+            search: RefCell::new(None),
+            #[cfg(feature = "search")]
+            model_index: RefCell::new(None),
+            pending_view: RefCell::new(None),
+            filter: RefCell::new(None),
+            isolation: RefCell::new(None),
+            peek_from: RefCell::new(None),
+            recorder: RefCell::new(None),
+            diff: RefCell::new(None),
+            pins: RefCell::new(vec![]),
+            pin_click_handler: RefCell::new(None),
+            pin_refresh: None,
+            broadcaster: None,
+            embed: None,
+            analytics: None,
+            _print: None,
+            remembered_view: RememberedView::new(&options.remember_view),
+            synced: RefCell::new(vec![]),
+            overview_of: RefCell::new(None),
+            overviews: RefCell::new(vec![]),
+            context_menu_items: RefCell::new(vec![]),
+            self_ref: Weak::new(),
+            _live: Live::new(Tracked::Instance),
         }));
 
-        let callback_ref = Rc::downgrade(&archizoom);
+        let self_ref = Rc::downgrade(&archizoom);
+        archizoom.borrow().diagram.attach(self_ref.clone());
+        archizoom.borrow_mut()._keyboard_listeners =
+            register_keyboard(&container, &options.key_bindings, self_ref.clone())?;
+        archizoom.borrow_mut()._gamepad =
+            GamepadSupport::new(&container, &options.gamepad, self_ref.clone())?;
+        archizoom.borrow_mut()._hover_prefetch =
+            HoverPrefetch::new(&container, &options.hover_prefetch, self_ref.clone())?;
+        archizoom.borrow_mut().self_ref = self_ref.clone();
+        archizoom.borrow_mut()._print = PrintSupport::new(&options.print, self_ref.clone())?;
+        archizoom.borrow_mut().broadcaster =
+            Broadcaster::new(&options.broadcast, self_ref.clone())?;
+        archizoom.borrow_mut().embed = Embed::new(&options.embed, self_ref.clone())?;
+        archizoom.borrow_mut().analytics = Analytics::new(&options.analytics)?;
+        if let Some(ref analytics) = archizoom.borrow().analytics {
+            analytics.navigated(&archizoom.borrow().navigation);
+        }
 
-        archizoom
-            .borrow()
-            .view_controller
-            .borrow_mut()
-            .register_listener(move |e: &ViewUpdateEvent| {
-                if let Some(real_ref) = callback_ref.upgrade() {
-                    real_ref.borrow().view_update(e)
+        // catch up on whatever was put off while quality was lowered
+        archizoom.borrow().quality.on_restored(move || {
+            if let Some(real_ref) = self_ref.upgrade() {
+                let archizoom = real_ref.borrow();
+                if let Some(viewport) = archizoom.viewport() {
+                    archizoom.set_viewport(&viewport);
+                }
+            }
+        });
+
+        // announce the widget as something that can be operated, rather than a div of svg
+        ensure_stylesheet()?;
+        container.set_attribute("role", "application")?;
+        container.set_attribute("tabindex", "0")?;
+        apply_rendering_hints(&container, &options.rendering_hints)?;
+        apply_deprecated_properties(&container, &options)?;
+        archizoom.borrow().label_container();
+
+        let overlays = create_overlays(&archizoom, &container, &options)?;
+        archizoom.borrow_mut().overlays = overlays;
+        if options.crosshair.enabled {
+            archizoom.borrow().set_crosshair(true);
+        }
+        archizoom.borrow().prefetch_links();
+        archizoom.borrow().restore_remembered_view();
+        if options.pins.refresh_ms > 0 {
+            let self_ref = Rc::downgrade(&archizoom);
+            archizoom.borrow_mut().pin_refresh = Some(Timeout::new(move || {
+                if let Some(real_ref) = self_ref.upgrade() {
+                    real_ref.borrow().refresh_pins();
                 }
-            });
+            }));
+        }
+        archizoom.borrow().refresh_pins();
+        archizoom.borrow().restore_navigation();
 
         Ok(archizoom)
     }
 
-    fn view_update(&self, event: &ViewUpdateEvent) {
-        let viewport = event.viewport();
-        for zoom_element in self.zoom_elements.iter() {
-            if let Some(element_rect) = zoom_element.element_rect() {
-                #[inline]
-                fn overlap(a_left: f32, a_right: f32, b_left: f32, b_right: f32) -> f32 {
-                    a_right.min(b_right) - a_left.max(b_left)
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.diagram.view_controller.borrow().viewport()
+    }
+
+    pub fn set_viewport(&self, viewport: &Viewport) {
+        self.diagram.view_controller.borrow().set_viewport(viewport)
+    }
+
+    #[inline]
+    pub fn svg(&self) -> &SvgsvgElement {
+        &self.diagram.svg
+    }
+
+    /// The diagram we're showing
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.diagram.url
+    }
+
+    #[inline]
+    pub fn container(&self) -> &Element {
+        &self.container
+    }
+
+    #[inline]
+    pub fn navigation(&self) -> &[NavigationEntry] {
+        &self.navigation
+    }
+
+    pub(crate) fn strings(&self) -> Ref<'_, Strings> {
+        Ref::map(self.options.borrow(), |options| &options.strings)
+    }
+
+    /// The transform from Svg User Coordinates to the screen, cached between layout changes
+    pub fn screen_ctm(&self) -> Option<Transform<SvgUserSpace, ScreenSpace>> {
+        self.diagram.geometry.screen_ctm()
+    }
+
+    /// Forgets every cached position and transform, for when the page moves the diagram in a
+    /// way we can't see, like changing an ancestor's transform
+    pub fn invalidate_geometry(&self) {
+        self.diagram.geometry.invalidate();
+        self.diagram.transform_cache.clear();
+    }
+
+    /// Converts a point on screen to Svg User Coordinates
+    pub fn user_point(&self, position: &ScreenPoint) -> Option<UserPoint> {
+        self.diagram.view_controller.borrow().get_point(position)
+    }
+
+    #[inline]
+    pub fn is_crosshair_active(&self) -> bool {
+        self.crosshair.borrow().is_some()
+    }
+
+    /// Shows or hides the crosshair and coordinate readout
+    pub fn set_crosshair(&self, active: bool) {
+        if active == self.is_crosshair_active() {
+            return;
+        }
+
+        if active {
+            let options = self.options.borrow();
+            match Crosshair::new(self.self_ref.clone(), &self.container, &options.crosshair) {
+                Ok(crosshair) => {
+                    self.crosshair.replace(Some(crosshair));
                 }
+                Err(error) => console::warn_2(&"Couldn't show the crosshair".into(), &error),
+            }
+        } else {
+            self.crosshair.replace(None);
+        }
+    }
 
-                let horizontal_overlap = overlap(
-                    viewport.left(),
-                    viewport.right(),
-                    element_rect.left(),
-                    element_rect.right(),
-                );
-                let vertical_overlap = overlap(
-                    viewport.top(),
-                    viewport.bottom(),
-                    element_rect.top(),
-                    element_rect.bottom(),
-                );
+    /// Pans so `center` (in Svg User Coordinates) is in the middle of the view
+    pub fn center_on(&self, center: &Point2D) {
+        if let Some(mut viewport) = self.viewport() {
+            let delta = center - &viewport.center();
+            viewport.pan(&delta);
 
-                let total_area = viewport.area();
-                let viewable_area = horizontal_overlap * vertical_overlap;
-                let area_percentage = viewable_area / total_area;
+            self.set_viewport(&viewport);
+        }
+    }
+
+    /// Zooms by `factor` around the center of the view
+    pub fn zoom_by(&self, factor: f32) {
+        if let Some(mut viewport) = self.viewport() {
+            let center = viewport.center();
+            viewport.zoom_about(&center, factor);
+
+            self.set_viewport(&viewport);
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.get()
+    }
+
+    /// Stops or starts following the user's pan and zoom gestures
+    pub fn set_locked(&self, locked: bool) {
+        self.locked.set(locked);
+        self.diagram.view_controller.borrow().set_locked(locked);
+    }
+
+    /// Changes the option at `path`, like `motion` or `filter.selector`. Only the options read
+    /// as they're used can be changed, see `Options::is_live`, the rest are rejected.
+    pub fn set_option(&self, path: &str, value: serde_json::Value) -> Result<(), JsValue> {
+        // unknown options and values that don't fit are caught first
+        let options = self.options.borrow().with_option(path, value)?;
+        if !Options::is_live(path) {
+            return Err(JsValue::from(format!(
+                "{} can't be changed on a live viewer, pass it to init, mount, or setDefaults. \
+                 These can: {}",
+                path,
+                LIVE_OPTIONS.join(", ")
+            )));
+        }
+
+        self.diagram
+            .view_controller
+            .borrow()
+            .update_options(&options);
+        if options.locked != self.is_locked() {
+            self.set_locked(options.locked);
+        }
+        apply_deprecated_properties(&self.container, &options)?;
+        self.options.replace(options);
+
+        if path == "pins.src" {
+            self.refresh_pins();
+        }
+
+        Ok(())
+    }
+
+    /// Mirrors pan and zoom between this viewer and `other` in both directions, starting from
+    /// our view
+    pub fn sync_with(&self, other: &Rc<RefCell<ArchiZoom>>) {
+        let other_ref = Rc::downgrade(other);
+        if Weak::ptr_eq(&self.self_ref, &other_ref)
+            || self
+                .synced
+                .borrow()
+                .iter()
+                .any(|peer| peer.ptr_eq(&other_ref))
+        {
+            return;
+        }
+
+        self.synced.borrow_mut().push(other_ref);
+        other
+            .borrow()
+            .synced
+            .borrow_mut()
+            .push(self.self_ref.clone());
+
+        if let Some(viewport) = self.viewport() {
+            self.mirror(&viewport);
+        }
+    }
+
+    /// Stops following and being followed by every viewer we're synced with
+    pub fn unsync(&self) {
+        for peer in self.synced.replace(vec![]).iter().filter_map(Weak::upgrade) {
+            if let Ok(peer) = peer.try_borrow() {
+                peer.synced
+                    .borrow_mut()
+                    .retain(|synced| !synced.ptr_eq(&self.self_ref));
+            }
+        }
+    }
+
+    /// Makes this viewer the overview of `detail`. We stop following the user's gestures, stay
+    /// fitted to the diagram `detail` is showing, and draw its view as a rectangle that can be
+    /// dragged around.
+    pub fn show_overview_of(&self, detail: &Rc<RefCell<ArchiZoom>>) -> Result<(), JsValue> {
+        let detail_ref = Rc::downgrade(detail);
+        if Weak::ptr_eq(&self.self_ref, &detail_ref) {
+            return Ok(());
+        }
+        self.clear_overview();
+
+        let indicator = OverviewIndicator::new(
+            self.self_ref.clone(),
+            &self.container,
+            detail,
+            self.is_locked(),
+        )?;
+        self.overview_of.replace(Some(indicator));
+        self.set_locked(true);
+        detail
+            .borrow()
+            .overviews
+            .borrow_mut()
+            .push(self.self_ref.clone());
+
+        let detail_url = detail.borrow().diagram.url.clone();
+        if detail_url == self.diagram.url {
+            self.fit_to_content();
+            self.update_overview();
+        } else {
+            self.show_diagram(&detail_url);
+        }
 
-                if area_percentage >= VIEW_THRESHOLD {
-                    console::log_1(&"in view".into());
+        Ok(())
+    }
+
+    /// Goes back to being a viewer of our own
+    pub fn clear_overview(&self) {
+        if let Some(indicator) = self.overview_of.replace(None) {
+            self.set_locked(indicator.was_locked);
+
+            if let Some(detail) = indicator.detail() {
+                if let Ok(detail) = detail.try_borrow() {
+                    detail
+                        .overviews
+                        .borrow_mut()
+                        .retain(|overview| !overview.ptr_eq(&self.self_ref));
                 }
             }
         }
     }
-}
 
-impl ZoomElement {
-    /// Gets the element Rect in Svg Viewport Coordinates
-    fn element_rect(&self) -> Option<Rect> {
-        self.link_element.get_b_box().ok().and_then(|element_box| {
-            self.link_element
-                .get_screen_ctm()
-                .map(|m| Rect::from_svg(&element_box).matrix_transform(&Matrix2D::from_js(&m)))
+    /// Where the detail we're the overview of is looking, while it's on our diagram
+    pub(crate) fn detail_view(&self) -> Option<Rect> {
+        let detail = self.overview_of.borrow().as_ref()?.detail()?;
+        let detail = detail.try_borrow().ok()?;
+
+        if detail.diagram.url == self.diagram.url {
+            detail.viewport().map(|viewport| viewport.view_box())
+        } else {
+            None
+        }
+    }
+
+    /// Centers the detail we're the overview of on `center`
+    pub(crate) fn move_detail(&self, center: &Point2D) {
+        let detail = self
+            .overview_of
+            .borrow()
+            .as_ref()
+            .and_then(OverviewIndicator::detail);
+
+        if let Some(detail) = detail {
+            if let Ok(detail) = detail.try_borrow() {
+                detail.scheduler.cancel();
+                detail.center_on(center);
+            }
+        }
+    }
+
+    /// Redraws the rectangle showing the detail's view, if we're an overview
+    fn update_overview(&self) {
+        if let Some(ref indicator) = *self.overview_of.borrow() {
+            indicator.update(self, self.detail_view());
+        }
+    }
+
+    /// Lets our overviews know where we're looking now
+    fn overviews_changed(&self, navigated: bool) {
+        self.overviews
+            .borrow_mut()
+            .retain(|overview| overview.strong_count() > 0);
+        let overviews: Vec<_> = self
+            .overviews
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+
+        for overview in overviews {
+            if let Ok(overview) = overview.try_borrow() {
+                if navigated && overview.diagram.url != self.diagram.url {
+                    overview.show_diagram(&self.diagram.url);
+                } else {
+                    overview.update_overview();
+                }
+            }
+        }
+    }
+
+    /// Moves the synced viewers to the same center and scale as `viewport`
+    fn mirror(&self, viewport: &Viewport) {
+        if MIRRORING.with(|mirroring| mirroring.replace(true)) {
+            return;
+        }
+
+        self.synced
+            .borrow_mut()
+            .retain(|peer| peer.strong_count() > 0);
+        let peers: Vec<_> = self
+            .synced
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for peer in peers {
+            // a peer that's busy is the one that's mirroring to us
+            if let Ok(peer) = peer.try_borrow() {
+                peer.follow_view(viewport);
+            }
+        }
+
+        MIRRORING.with(|mirroring| mirroring.set(false));
+    }
+
+    /// Moves to the same center and scale as `viewport`, which may be from a viewer of a
+    /// different size
+    pub fn follow_view(&self, viewport: &Viewport) {
+        if let Some(current) = self.viewport() {
+            let target = viewport.resized_to(&current.size.scale(current.scale));
+            if !target.approx_eq(&current) {
+                self.scheduler.cancel();
+                self.set_viewport(&target);
+            }
+        }
+    }
+
+    /// The zoom level relative to the initial view, 1.0 is the initial view
+    pub fn zoom_level(&self) -> Option<f32> {
+        match (&self.diagram.initial_view_box, self.viewport()) {
+            (Some(initial), Some(current)) => Some(current.zoom_relative_to(initial)),
+            _ => None,
+        }
+    }
+
+    /// Zooms around the center of the view to `level`, relative to the initial view
+    pub fn set_zoom_level(&self, level: f32) {
+        if let Some(current) = self.zoom_level() {
+            self.zoom_by(level / current);
+        }
+    }
+
+    /// Returns to the viewBox the svg was loaded with
+    pub fn reset(&self) {
+        if let (Some(initial), Some(current)) = (&self.diagram.initial_view_box, self.viewport()) {
+            let pixel_size = current.size.scale(current.scale);
+
+            self.set_viewport(&Viewport::from_view_box(initial, &pixel_size));
+        }
+    }
+
+    /// Animates out to the initial view while the peek key is held, `end_peek` goes back to
+    /// where we were
+    pub fn start_peek(&self) {
+        if self.peek_from.borrow().is_some() {
+            return;
+        }
+
+        if let (Some(initial), Some(current)) = (&self.diagram.initial_view_box, self.viewport()) {
+            let pixel_size = current.size.scale(current.scale);
+            self.peek_from.replace(Some(current));
+
+            self.animate_to(
+                Viewport::from_view_box(initial, &pixel_size),
+                self.transition(),
+            );
+        }
+    }
+
+    pub fn end_peek(&self) {
+        if let Some(from) = self.peek_from.take() {
+            self.animate_to(from, self.transition());
+        }
+    }
+
+    /// Starts capturing input and views for a bug report, over any recording already running
+    pub fn start_recording(&self) -> Result<(), JsValue> {
+        let recorder = Recorder::start(self)?;
+        self.recorder.replace(Some(recorder));
+
+        Ok(())
+    }
+
+    pub fn stop_recording(&self) -> Option<Trace> {
+        self.recorder.take().map(Recorder::finish)
+    }
+
+    /// The bounds of the whole drawing in Svg User Coordinates
+    pub fn content_bounds(&self) -> Option<Rect> {
+        // culled groups don't have any bounds
+        self.unculled(|| element_bounds(self.svg(), self.svg()))
+    }
+
+    /// The smallest element matching `selector` under `position` on screen, found through the
+    /// diagram's quadtree of element bounds
+    pub fn element_at(&self, position: &ScreenPoint, selector: &str) -> Option<SvgGraphicsElement> {
+        let hit_tester = &self.diagram.hit_tester;
+        if !hit_tester.is_indexed(selector) {
+            self.unculled(|| hit_tester.index(selector));
+        }
+
+        let point = self.user_point(position)?;
+
+        hit_tester.element_at(&point.into_inner(), selector)
+    }
+
+    /// Runs `f` with every culled group shown, for measuring or copying the whole drawing
+    pub fn unculled<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        self.diagram.culler.revealed(f)
+    }
+
+    /// Frames a sprite group that didn't say where it is, once it's on the page to be measured
+    pub(crate) fn fit_sprite_group(&self) {
+        if self.diagram.fit_on_show {
+            self.fit_to_content();
+        }
+    }
+
+    /// Frames the whole drawing
+    pub fn fit_to_content(&self) {
+        if let (Some(bounds), Some(mut viewport)) = (self.content_bounds(), self.viewport()) {
+            viewport.fit_with_margin(&bounds, &Margin::default());
+
+            self.set_viewport(&viewport);
+        }
+    }
+
+    /// A standalone svg of what's in view, cropped to it
+    pub fn export_svg(&self) -> Result<String, JsValue> {
+        let viewport = self
+            .viewport()
+            .ok_or_else(|| JsValue::from("The diagram doesn't have a viewBox to crop"))?;
+
+        export_svg(self.svg(), &viewport)
+    }
+
+    /// Moves focus to the search box, if there is one
+    pub fn request_search(&self) -> bool {
+        #[cfg(feature = "search")]
+        {
+            self.notify_overlays(&ViewerEvent::SearchRequested);
+
+            self.options.borrow().search_box.enabled
+        }
+        #[cfg(not(feature = "search"))]
+        false
+    }
+
+    pub fn toggle_fullscreen(&self) {
+        let document = document();
+        if document.fullscreen_element().is_some() {
+            document.exit_fullscreen();
+        } else if let Err(error) = self.container.request_fullscreen() {
+            console::warn_2(&"Couldn't enter fullscreen".into(), &error);
+        }
+    }
+
+    /// Frames everything matching `targets` (ids or selectors), returning false if nothing matched
+    pub fn frame_elements<S: AsRef<str>>(&self, targets: &[S]) -> bool {
+        self.diagram.culler.reveal_all();
+        let framed = self.viewport().and_then(|current| {
+            covering_viewport(self.svg(), targets, &current, &Margin::default())
+        });
+
+        match framed {
+            Some(viewport) => {
+                self.set_viewport(&viewport);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The scale, rotation, and translation from Svg User Coordinates to the screen, including
+    /// any CSS transforms of our ancestors
+    pub fn screen_transform(&self) -> Option<Decomposition> {
+        diagnostics::layout_read();
+        self.svg()
+            .get_screen_ctm()
+            .map(|matrix| Matrix2D::from_js(&matrix).decompose())
+    }
+
+    /// Highlights the elements whose labels contain `query` and frames the first of them,
+    /// returning how many matched. An empty query clears the search.
+    #[cfg(feature = "search")]
+    pub fn search(&self, query: &str) -> usize {
+        // clear the old highlighting before marking the new matches
+        self.search.replace(None);
+
+        let count = if query.trim().is_empty() {
+            0
+        } else {
+            let matches = self.diagram.search_index.find(query);
+            let count = matches.len();
+            self.search.replace(Some(SearchResults::new(matches)));
+
+            count
+        };
+
+        self.search_step(true);
+
+        count
+    }
+
+    /// Searches the labels of every diagram reachable from the first one, grouped by diagram.
+    /// The first search crawls the model, later ones reuse what it found.
+    #[cfg(feature = "search")]
+    pub fn search_model(
+        &self,
+        query: &str,
+    ) -> impl Future<Item = Vec<ModelSearchResult>, Error = JsValue> {
+        let query = query.to_string();
+
+        let index = match *self.model_index.borrow() {
+            Some(ref index) => Either::A(future::ok(index.clone())),
+            None => {
+                let root = self
+                    .navigation
+                    .first()
+                    .map(|root| root.url.clone())
+                    .unwrap_or_else(|| self.diagram.url.clone());
+                let loader_ref = self.self_ref.clone();
+                let self_ref = self.self_ref.clone();
+
+                Either::B(
+                    ModelIndex::crawl(&root, &self.options.borrow(), move |url| {
+                        let archizoom = loader_ref.upgrade().ok_or_else(|| {
+                            JsValue::from("The viewer went away before the search finished")
+                        })?;
+                        let text = archizoom.borrow().diagram_text(url);
+
+                        text
+                    })
+                    .map(move |index| {
+                        let index = Rc::new(index);
+                        if let Some(real_ref) = self_ref.upgrade() {
+                            real_ref.borrow().model_index.replace(Some(index.clone()));
+                        }
+
+                        index
+                    }),
+                )
+            }
+        };
+
+        index.map(move |index| index.find(&query))
+    }
+
+    /// Shows the diagram at `url` and frames the element with `id` on it
+    pub fn open_and_frame(&self, url: &str, id: Option<String>) {
+        self.open_view(url, id.map(PendingView::Frame));
+    }
+
+    /// Shows the diagram at `url`, then `view` on it once it's loaded
+    pub fn open_view(&self, url: &str, view: Option<PendingView>) {
+        if self.diagram.url == url {
+            if let Some(view) = view {
+                self.show_pending_view(view);
+            }
+            return;
+        }
+
+        self.pending_view
+            .replace(view.map(|view| (url.to_string(), view)));
+        self.show_diagram(url);
+    }
+
+    fn show_pending_view(&self, view: PendingView) {
+        match view {
+            PendingView::Frame(id) => {
+                self.frame_elements(&[id]);
+            }
+            PendingView::ViewBox(view_box) => self.show_view_box(&view_box),
+        }
+    }
+
+    /// Moves the view to `view_box`, keeping the current zoom's pixel scale
+    fn show_view_box(&self, view_box: &Rect) {
+        if let Some(current) = self.viewport() {
+            self.set_viewport(&Viewport::new(
+                view_box.top_left.clone(),
+                view_box.size(),
+                current.scale,
+            ));
+        }
+    }
+
+    /// Frames the next or previous search match, returning its index
+    #[cfg(feature = "search")]
+    pub fn search_step(&self, forward: bool) -> Option<usize> {
+        let current = match *self.search.borrow_mut() {
+            Some(ref mut results) => results.step(forward).cloned(),
+            None => None,
+        };
+
+        let index = current.and_then(|element| {
+            self.frame_element(&element);
+
+            self.search
+                .borrow()
+                .as_ref()
+                .and_then(|results| results.current)
+        });
+        self.search_changed();
+
+        index
+    }
+
+    #[cfg(feature = "search")]
+    pub fn clear_search(&self) {
+        self.search.replace(None);
+        self.search_changed();
+    }
+
+    #[cfg(feature = "search")]
+    fn search_changed(&self) {
+        let search = self.search.borrow();
+        self.notify_overlays(&ViewerEvent::SearchChanged(search.as_ref()));
+    }
+
+    /// Animates to frame `element`
+    fn frame_element(&self, element: &SvgGraphicsElement) {
+        self.diagram.culler.reveal_all();
+        if let (Some(bounds), Some(mut viewport)) =
+            (element_bounds(self.svg(), element), self.viewport())
+        {
+            viewport.fit_with_margin(&bounds, &Margin::default());
+
+            self.fly_to(viewport);
+        }
+    }
+
+    /// Dims everything `is_match` rejects, replacing any earlier filter. Returns how many
+    /// elements matched.
+    pub fn filter<P: Fn(&Element) -> bool>(&self, is_match: P) -> usize {
+        // restore the old filter first so it doesn't get saved as the original opacity
+        self.filter.replace(None);
+        self.isolation.replace(None);
+
+        let filter = Filter::apply(self.svg(), &self.options.borrow().filter, is_match);
+        let matched = filter.matched;
+        self.filter.replace(Some(filter));
+
+        matched
+    }
+
+    pub fn clear_filter(&self) {
+        self.filter.replace(None);
+    }
+
+    /// What `filter` checks, for callers that need to test them without the viewer borrowed
+    pub fn filter_candidates(&self) -> Vec<SvgElement> {
+        Filter::candidates(self.svg(), &self.options.borrow().filter)
+    }
+
+    /// Dims everything but `target`, an id or selector, the connectors touching it, and what's
+    /// on their other ends. Isolates the selection when there's no target. Returns how many
+    /// connectors it has, or None when there's nothing to isolate.
+    pub fn isolate(&self, target: Option<&str>) -> Option<usize> {
+        let target = match target {
+            Some(target) => Some(target.to_string()),
+            None => self.selection(),
+        }?;
+        let element = find_elements(self.svg(), &target).into_iter().next()?;
+
+        self.filter.replace(None);
+        self.isolation.replace(None);
+
+        let isolation = Isolation::apply(self.svg(), &element, &self.options.borrow())?;
+        let connections = isolation.connections;
+        self.isolation.replace(Some(isolation));
+
+        Some(connections)
+    }
+
+    pub fn clear_isolation(&self) {
+        self.isolation.replace(None);
+    }
+
+    /// Fetches an earlier version of the diagram from `url` and marks what's been added,
+    /// removed, and changed since, resolving to how many changes there are
+    pub fn compare_with(
+        &self,
+        url: &str,
+    ) -> Result<impl Future<Item = usize, Error = JsValue>, JsValue> {
+        let self_ref = self.self_ref.clone();
+        let text = self.diagram_text(url)?;
+
+        Ok(text.and_then(move |text| {
+            let archizoom = self_ref
+                .upgrade()
+                .ok_or_else(|| JsValue::from("The viewer went away before the diff loaded"))?;
+            let archizoom = archizoom.borrow();
+            let base = parse_svg(&text, &archizoom.options.borrow())?;
+
+            Ok(archizoom.show_diff(&base))
+        }))
+    }
+
+    /// The markup of the diagram at `url`, from the cache when we have it
+    fn diagram_text(
+        &self,
+        url: &str,
+    ) -> Result<impl Future<Item = String, Error = JsValue>, JsValue> {
+        Ok(match self.diagram_cache.get(url) {
+            Some(text) => Either::A(future::ok(text)),
+            None => Either::B(
+                load_diagram(url, self.options.borrow().load_in_worker)?.map(|loaded| loaded.text),
+            ),
         })
     }
+
+    fn show_diff(&self, base: &SvgsvgElement) -> usize {
+        // clear the old marks before comparing, they'd show up as changes
+        self.diff.replace(None);
+
+        let diff = Diff::new(self.svg(), base, &self.options.borrow().diff);
+        let count = diff.changes.len();
+        self.diff.replace(Some(diff));
+        self.diff_changed();
+
+        count
+    }
+
+    /// Frames the next or previous change, returning its index
+    pub fn diff_step(&self, forward: bool) -> Option<usize> {
+        let current = match *self.diff.borrow_mut() {
+            Some(ref mut diff) => diff.step(forward).cloned(),
+            None => None,
+        };
+
+        let index = current.and_then(|element| {
+            self.frame_element(&element);
+
+            self.diff.borrow().as_ref().and_then(|diff| diff.current)
+        });
+        self.diff_changed();
+
+        index
+    }
+
+    pub fn clear_diff(&self) {
+        self.diff.replace(None);
+        self.diff_changed();
+    }
+
+    fn diff_changed(&self) {
+        let diff = self.diff.borrow();
+        self.notify_overlays(&ViewerEvent::DiffChanged(diff.as_ref()));
+    }
+
+    pub fn layers(&self) -> Vec<LayerInfo> {
+        self.diagram
+            .layers
+            .iter()
+            .map(|layer| layer.info())
+            .collect()
+    }
+
+    /// Shows or hides the layer called `name`, returning false if there isn't one
+    pub fn set_layer_visible(&self, name: &str, visible: bool) -> bool {
+        let layer = self.diagram.layers.iter().find(|layer| layer.name == name);
+        if let Some(layer) = layer {
+            layer.set_visible(visible);
+
+            let layers = self.layers();
+            self.notify_overlays(&ViewerEvent::LayersChanged(&layers));
+        }
+
+        layer.is_some()
+    }
+
+    /// Pins `annotation` to its diagram, or the current one if it doesn't say, returning its id
+    #[cfg(feature = "annotations")]
+    pub fn add_annotation(&self, mut annotation: Annotation) -> String {
+        if annotation.diagram.is_none() {
+            annotation.diagram = Some(self.diagram.url.clone());
+        }
+
+        let id = self.annotations.borrow_mut().add(annotation);
+        self.annotations_changed();
+
+        id
+    }
+
+    #[cfg(feature = "annotations")]
+    pub fn remove_annotation(&self, id: &str) -> bool {
+        let removed = self.annotations.borrow_mut().remove(id);
+        if removed {
+            self.annotations_changed();
+        }
+
+        removed
+    }
+
+    #[cfg(feature = "annotations")]
+    pub fn annotations(&self) -> Ref<'_, AnnotationSet> {
+        self.annotations.borrow()
+    }
+
+    /// Replaces all of the annotations, any without a diagram go on the current one
+    #[cfg(feature = "annotations")]
+    pub fn set_annotations(&self, mut annotations: AnnotationSet) {
+        for annotation in annotations.annotations.iter_mut() {
+            if annotation.diagram.is_none() {
+                annotation.diagram = Some(self.diagram.url.clone());
+            }
+        }
+
+        self.annotations.replace(annotations);
+        self.annotations_changed();
+    }
+
+    #[cfg(feature = "annotations")]
+    fn annotations_changed(&self) {
+        let annotations = self.annotations.borrow();
+        self.notify_overlays(&ViewerEvent::AnnotationsChanged(&annotations));
+    }
+
+    pub fn pins(&self) -> Ref<'_, Vec<Pin>> {
+        self.pins.borrow()
+    }
+
+    pub fn set_pins(&self, pins: Vec<Pin>) {
+        self.pins.replace(pins);
+
+        let pins = self.pins.borrow();
+        self.notify_overlays(&ViewerEvent::PinsChanged(&pins));
+    }
+
+    /// Replaces the pins with the `{ pins: [...] }` document at `url`, resolving to how many
+    /// there are
+    pub fn load_pins(
+        &self,
+        url: &str,
+    ) -> Result<impl Future<Item = usize, Error = JsValue>, JsValue> {
+        let self_ref = self.self_ref.clone();
+
+        Ok(fetch_text(url)?.and_then(move |text| {
+            let data: PinData =
+                serde_json::from_str(&text).map_err(|error| JsValue::from(error.to_string()))?;
+            let archizoom = self_ref
+                .upgrade()
+                .ok_or_else(|| JsValue::from("The viewer went away before the pins loaded"))?;
+
+            let count = data.pins.len();
+            archizoom.borrow().set_pins(data.pins);
+
+            Ok(count)
+        }))
+    }
+
+    /// Loads the pins from `pins.src`, and again every `refreshMs` after that
+    fn refresh_pins(&self) {
+        let src = match self.options.borrow().pins.src {
+            Some(ref src) => src.clone(),
+            None => return,
+        };
+
+        let self_ref = self.self_ref.clone();
+        match self.load_pins(&src) {
+            Ok(future) => spawn_local(future.then(move |result| {
+                if let Err(error) = result {
+                    console::warn_2(&"Couldn't load the pins".into(), &error);
+                }
+                if let Some(real_ref) = self_ref.upgrade() {
+                    real_ref.borrow().schedule_pin_refresh();
+                }
+
+                Ok(())
+            })),
+            Err(error) => {
+                console::warn_2(&"Couldn't load the pins".into(), &error);
+                self.schedule_pin_refresh();
+            }
+        }
+    }
+
+    fn schedule_pin_refresh(&self) {
+        if let Some(ref pin_refresh) = self.pin_refresh {
+            pin_refresh.restart(self.options.borrow().pins.refresh_ms);
+        }
+    }
+
+    /// `handler` is called with the pin and the click event when a pin is clicked
+    pub fn set_pin_click_handler(&self, handler: Option<Function>) {
+        self.pin_click_handler.replace(handler);
+    }
+
+    pub(crate) fn pin_clicked(&self, index: usize, event: &MouseEvent) {
+        let pin = match self.pins.borrow().get(index) {
+            Some(pin) => to_js(pin),
+            None => return,
+        };
+
+        if let Some(ref handler) = *self.pin_click_handler.borrow() {
+            if let Err(error) = pin.and_then(|pin| handler.call2(&JsValue::NULL, &pin, event)) {
+                console::error_2(&"The pin click handler failed".into(), &error);
+            }
+        }
+    }
+
+    pub fn add_context_menu_item(&self, item: ContextMenuItem) {
+        self.context_menu_items.borrow_mut().push(item);
+    }
+
+    pub fn context_menu_items(&self) -> Ref<'_, Vec<ContextMenuItem>> {
+        self.context_menu_items.borrow()
+    }
+
+    /// Opens on the view the reader left this diagram at last time
+    fn restore_remembered_view(&self) {
+        if let Some(ref remembered_view) = self.remembered_view {
+            if let Some(view_box) = remembered_view.load(&self.diagram.url) {
+                self.show_view_box(&view_box);
+            }
+        }
+    }
+
+    /// Hands batches of analytics records to `handler`, unless this viewer was sampled out
+    pub fn set_analytics_handler(&self, handler: Option<Function>) {
+        if let Some(ref analytics) = self.analytics {
+            analytics.set_handler(handler);
+        }
+    }
+
+    /// Forgets the view remembered for the diagram we're showing, the next visit opens on its
+    /// initial view
+    pub fn forget_view(&self) {
+        if let Some(ref remembered_view) = self.remembered_view {
+            remembered_view.forget(&self.diagram.url);
+        }
+    }
+
+    /// A link to this page that opens on the current view
+    pub fn view_link(&self) -> Option<String> {
+        let page_url = window().location().href().ok()?;
+
+        // the diagram the page starts on doesn't need to be in the link
+        let diagram = if self.navigation.len() > 1 {
+            Some(self.diagram.url.clone())
+        } else {
+            None
+        };
+
+        self.viewport()
+            .map(|viewport| ViewLink::new(diagram, viewport.view_box()).to_url(&page_url))
+    }
+
+    /// Opens the view a `view_link` points at, on the diagram we're showing when it doesn't
+    /// name one
+    pub fn open_link(&self, link: ViewLink) {
+        let url = link.diagram.unwrap_or_else(|| self.diagram.url.clone());
+
+        self.open_view(&url, link.view_box.map(PendingView::ViewBox));
+    }
+
+    /// Copies the `view_link` to the clipboard, resolving to the link once it's there
+    pub fn copy_view_link(&self) -> Result<impl Future<Item = String, Error = JsValue>, JsValue> {
+        let link = self
+            .view_link()
+            .ok_or_else(|| JsValue::from("There's no view to link to"))?;
+        let copied = window().navigator().clipboard().write_text(&link);
+
+        let self_ref = self.self_ref.clone();
+        Ok(JsFuture::from(copied).map(move |_| {
+            if let Some(real_ref) = self_ref.upgrade() {
+                real_ref
+                    .borrow()
+                    .notify_overlays(&ViewerEvent::ViewLinkCopied);
+            }
+
+            link
+        }))
+    }
+
+    /// The diagram linked to by the zoom element containing `element`
+    pub fn link_target(&self, element: &Element) -> Option<String> {
+        self.zoom_element_containing(element)
+            .map(|zoom_element| zoom_element.target.clone())
+    }
+
+    /// The zoom element containing `element`
+    pub fn link_element(&self, element: &Element) -> Option<SvgGraphicsElement> {
+        self.zoom_element_containing(element)
+            .map(|zoom_element| zoom_element.link_element.clone())
+    }
+
+    fn zoom_element_containing(&self, element: &Element) -> Option<&ZoomElement> {
+        let node: &Node = element.as_ref();
+
+        self.diagram
+            .zoom_elements
+            .iter()
+            .find(|zoom_element| zoom_element.link_element.contains(Some(node)))
+    }
+
+    /// What's been prefetched about the diagram at `url`
+    pub fn diagram_info(&self, url: &str) -> Option<DiagramInfo> {
+        self.prefetcher.info(url)
+    }
+
+    /// Fetches the titles and sizes of the diagrams this one links to while the page is idle
+    fn prefetch_links(&self) {
+        self.prefetcher.prefetch(
+            self.diagram
+                .zoom_elements
+                .iter()
+                .map(|zoom_element| &zoom_element.target),
+        );
+    }
+
+    /// Moves keyboard focus to the next or previous zoom element and pans it into view.
+    /// Returns false once we run off either end, or with the focus ring off, so Tab can leave
+    /// the viewer.
+    pub fn focus_link(&self, forward: bool) -> bool {
+        if !self.options.borrow().focus_ring.enabled {
+            return false;
+        }
+
+        let count = self.diagram.zoom_elements.len();
+        let next = match (self.focused_link.get(), forward) {
+            (None, true) if count > 0 => Some(0),
+            (None, false) if count > 0 => Some(count - 1),
+            (Some(current), true) if current + 1 < count => Some(current + 1),
+            (Some(current), false) if current > 0 => Some(current - 1),
+            _ => None,
+        };
+        self.focused_link.set(next);
+
+        if let Some(index) = next {
+            self.pan_into_view(index);
+        }
+        self.link_focus_changed();
+
+        next.is_some()
+    }
+
+    /// Drills into the focused zoom element, returning false if there isn't one
+    pub fn activate_focused_link(&self) -> bool {
+        let target = self
+            .focused_link
+            .get()
+            .and_then(|index| self.diagram.zoom_elements.get(index))
+            .map(|zoom_element| zoom_element.target.clone());
+
+        match target {
+            Some(target) => {
+                self.navigate_to(&target);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn blur_link(&self) {
+        if self.focused_link.replace(None).is_some() {
+            self.link_focus_changed();
+        }
+    }
+
+    /// The on screen bounds of the zoom element Tab moved to
+    pub fn focused_link_bounds(&self) -> Option<ScreenRect> {
+        self.focused_link
+            .get()
+            .and_then(|index| self.diagram.zoom_elements.get(index))
+            .and_then(|zoom_element| {
+                zoom_element.element_rect(&self.diagram.geometry, &self.diagram.transform_cache)
+            })
+    }
+
+    fn pan_into_view(&self, index: usize) {
+        let bounds = self
+            .diagram
+            .zoom_elements
+            .get(index)
+            .and_then(|zoom_element| element_bounds(self.svg(), &zoom_element.link_element));
+
+        if let (Some(bounds), Some(viewport)) = (bounds, self.viewport()) {
+            if !viewport.view_box().contains_rect(&bounds) {
+                let mut target = viewport.clone();
+                target.pan(&(&bounds.center() - &viewport.center()));
+
+                self.animate_to(target, self.transition());
+            }
+        }
+    }
+
+    fn link_focus_changed(&self) {
+        let bounds = self.focused_link_bounds();
+        self.notify_overlays(&ViewerEvent::LinkFocused(bounds.as_ref()));
+    }
+
+    fn notify_overlays(&self, event: &ViewerEvent) {
+        for overlay in self.overlays.iter() {
+            overlay.notify(event);
+        }
+    }
+
+    /// The id of the selected element
+    pub fn selection(&self) -> Option<String> {
+        self.diagram.selection.selected_id()
+    }
+
+    /// Selects the first element matching `target`, an id or selector, or clears the selection
+    /// for `None`. Returns false if nothing matched.
+    pub fn select(&self, target: Option<&str>) -> bool {
+        self.diagram.selection.select(target)
+    }
+
+    /// Animates from the current viewport to `target`
+    pub fn animate_to(&self, target: Viewport, transition: Transition) {
+        if let Some(from) = self.viewport() {
+            self.animate(from, target, transition);
+        }
+    }
+
+    /// How the options ask for the view to be animated when we move it
+    fn transition(&self) -> Transition {
+        self.options.borrow().transition.clone()
+    }
+
+    /// Keeps the view moving at `velocity` after the user let go of it, slowing to a stop
+    fn glide(&self, view: &Viewport, velocity: &Point2D) {
+        let animation = {
+            let options = self.options.borrow();
+            if !options.momentum.enabled || options.motion.reduce_motion() {
+                return;
+            }
+
+            momentum::glide(view, velocity, &options.momentum)
+        };
+
+        // the view is already where the glide starts, so only the animation has to start
+        if let Some(animation) = animation {
+            if self.scheduler.start(animation) {
+                schedule_frame(self.self_ref.clone());
+            }
+        }
+    }
+
+    /// Animates to `target`, flying there if it's too far away to ease to
+    fn fly_to(&self, target: Viewport) {
+        if let Some(from) = self.viewport() {
+            let transition = Transition::between(&from, &target, self.transition());
+
+            self.animate(from, target, transition);
+        }
+    }
+
+    /// Jumps to `from` and animates to `to`. Drilling down is paused while animating.
+    pub fn animate(&self, from: Viewport, to: Viewport, transition: Transition) {
+        // every animation goes through here, so this is the one place motion gets turned off
+        if self.options.borrow().motion.reduce_motion() {
+            self.scheduler.cancel();
+            self.set_viewport(&to);
+
+            return;
+        }
+
+        let needs_frame =
+            self.scheduler
+                .start(ViewportAnimation::new(from.clone(), to, transition));
+
+        self.set_viewport(&from);
+
+        if needs_frame {
+            schedule_frame(self.self_ref.clone());
+        }
+    }
+
+    fn on_frame(&self, time: f64) -> bool {
+        match self.scheduler.frame(time) {
+            Some((viewport, running)) => {
+                self.set_viewport(&viewport);
+
+                running
+            }
+            None => false,
+        }
+    }
+
+    /// Loads the diagram at `url` and shows it in place of the current one
+    pub fn navigate_to(&self, url: &str) {
+        self.load_diagram(url, self.navigation.len(), None, None);
+    }
+
+    /// Goes back up the drill-down path to the diagram at `index`, zooming out from where we
+    /// left it
+    pub fn navigate_back(&self, index: usize) {
+        if index + 1 >= self.navigation.len() {
+            return;
+        }
+
+        let entry = &self.navigation[index];
+        self.load_diagram(&entry.url, index, entry.viewport.clone(), None);
+    }
+
+    /// Shows the diagram at `url`, going back up the drill-down path when it's on there
+    pub fn show_diagram(&self, url: &str) {
+        if self.diagram.url == url {
+            return;
+        }
+
+        match self.navigation.iter().position(|entry| entry.url == url) {
+            Some(index) => self.navigate_back(index),
+            None => self.navigate_to(url),
+        }
+    }
+
+    /// Drills back down to the diagram the reader was on before the page reloaded
+    fn restore_navigation(&self) {
+        if !self.options.borrow().restore_navigation.enabled {
+            return;
+        }
+
+        if let Some(mut path) = load_navigation(&self.diagram.url) {
+            if let Some(last) = path.pop() {
+                self.load_diagram(&last.url, path.len(), None, Some(path));
+            }
+        }
+    }
+
+    /// Fetches `url` and swaps it in, truncating the navigation path to `depth` first, or
+    /// replacing it with a `restored` one. When `from` is given we animate from that viewport to
+    /// the diagram's initial view.
+    fn load_diagram(
+        &self,
+        url: &str,
+        depth: usize,
+        from: Option<Viewport>,
+        restored: Option<Vec<NavigationEntry>>,
+    ) {
+        if self.navigating.replace(true) {
+            return;
+        }
+
+        let self_ref = self.self_ref.clone();
+        let url = url.to_string();
+
+        let navigation = Span::start("navigation");
+        let fetch = Span::start("fetch");
+        // another group of the sprite we're showing doesn't have to be loaded at all
+        let future = if self.diagram.has_group(&url) {
+            Either::A(future::ok(None))
+        } else {
+            Either::B(match self.diagram_cache.get(&url) {
+                Some(text) => Either::A(future::ok(Some(LoadedDiagram {
+                    text,
+                    description: None,
+                }))),
+                None => match load_diagram(&url, self.options.borrow().load_in_worker) {
+                    Ok(future) => Either::B(future.map(Some)),
+                    Err(error) => {
+                        console::error_2(&format!("Couldn't load {}", url).into(), &error);
+                        self.navigating.set(false);
+
+                        return;
+                    }
+                },
+            })
+        };
+
+        spawn_local(future.then(move |result| {
+            if let Some(archizoom) = self_ref.upgrade() {
+                // the selection doesn't carry over to the new diagram. Clear it before swapping
+                // so selectionchange listeners can still call back into us.
+                if result.is_ok() {
+                    archizoom.borrow().select(None);
+                    archizoom.borrow().timings.end(fetch);
+                }
+
+                let shown = result.and_then(|loaded| {
+                    let LoadedDiagram { text, description } = match loaded {
+                        Some(loaded) => loaded,
+                        None => return archizoom.borrow_mut().switch_group(&url, depth, restored),
+                    };
+                    if let Some(description) = description {
+                        archizoom.borrow().described(&url, &description);
+                    }
+
+                    let parse = Span::start("parse");
+                    let svg = parse_svg(&text, &archizoom.borrow().options.borrow())?;
+                    archizoom.borrow().timings.end(parse);
+
+                    let link = archizoom
+                        .borrow_mut()
+                        .swap_diagram(svg, &url, depth, restored)?;
+                    archizoom.borrow().cache_diagram(&url, text);
+
+                    Ok(link)
+                });
+
+                let archizoom = archizoom.borrow();
+                archizoom.navigating.set(false);
+
+                match shown {
+                    Ok(link) => {
+                        archizoom.after_navigation(from, link);
+                        archizoom.timings.end(navigation);
+                    }
+                    Err(error) => {
+                        console::error_2(&format!("Couldn't show {}", url).into(), &error)
+                    }
+                }
+            }
+
+            Ok(())
+        }));
+    }
+
+    /// The last groups of a progressively injected diagram are in, so everything measured
+    /// while they were missing is measured again
+    pub(crate) fn injection_finished(&self, span: Span) {
+        self.timings.end(span);
+        self.invalidate_geometry();
+        self.diagram.culler.invalidate();
+        self.diagram.text_culler.invalidate();
+        self.diagram.hit_tester.invalidate();
+
+        // culls against the whole diagram
+        if let Some(viewport) = self.viewport() {
+            self.set_viewport(&viewport);
+        }
+    }
+
+    pub(crate) fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Keeps the markup of the mounted diagram so we don't have to fetch it again
+    pub(crate) fn cache_prefetched(&self, url: &str, text: String) {
+        self.diagram_cache.insert_prefetched(url, text);
+    }
+
+    /// What the loading worker read from the markup of the diagram at `url`, so its title and
+    /// links are known before it's parsed
+    pub(crate) fn described(&self, url: &str, description: &DiagramDescription) {
+        self.prefetcher.remember(url, description.info());
+        self.prefetcher
+            .prefetch(resolve_targets(&description.links, url).iter());
+    }
+
+    pub(crate) fn is_cached(&self, url: &str) -> bool {
+        self.diagram_cache.contains(url)
+    }
+
+    /// Fetches the whole diagram at `url`, skipping the cache
+    pub(crate) fn fetch_diagram(
+        &self,
+        url: &str,
+    ) -> Result<impl Future<Item = LoadedDiagram, Error = JsValue>, JsValue> {
+        load_diagram(url, self.options.borrow().load_in_worker)
+    }
+
+    pub(crate) fn cache_diagram(&self, url: &str, text: String) {
+        self.diagram_cache.insert(url, text);
+    }
+
+    fn swap_diagram(
+        &mut self,
+        svg: SvgsvgElement,
+        url: &str,
+        depth: usize,
+        restored: Option<Vec<NavigationEntry>>,
+    ) -> Result<Option<ElementInfo>, JsValue> {
+        // built before it's swapped in, so huge diagrams can hold back their groups first and a
+        // diagram that fails to build leaves the current one in place
+        let diagram = Diagram::new(svg, url, &self.container, &self.options.borrow())?;
+        diagram
+            .view_controller
+            .borrow()
+            .set_locked(self.locked.get());
+        self.container
+            .replace_child(&diagram.svg, &self.diagram.svg)?;
+        diagram.attach(self.self_ref.clone());
+
+        let link = self.leave_diagram(url, depth, restored);
+        self.navigation.push(NavigationEntry::new(&diagram));
+        self.diagram = diagram;
+        self.diagram_changed();
+
+        Ok(link)
+    }
+
+    /// Shows another group of the sprite we're showing, which is already parsed
+    fn switch_group(
+        &mut self,
+        url: &str,
+        depth: usize,
+        restored: Option<Vec<NavigationEntry>>,
+    ) -> Result<Option<ElementInfo>, JsValue> {
+        let link = self.leave_diagram(url, depth, restored);
+        self.diagram.show_group(url, &self.options.borrow())?;
+        self.navigation.push(NavigationEntry::new(&self.diagram));
+        self.diagram_changed();
+
+        Ok(link)
+    }
+
+    /// Remembers where we were in the diagram we're leaving for `url`, or takes the `restored`
+    /// path, returning the zoom element that links to it
+    fn leave_diagram(
+        &mut self,
+        url: &str,
+        depth: usize,
+        restored: Option<Vec<NavigationEntry>>,
+    ) -> Option<ElementInfo> {
+        match restored {
+            Some(path) => self.navigation = path,
+            None => {
+                // remember where we were so coming back up returns to the same spot
+                if let Some(current) = self.navigation.last_mut() {
+                    current.viewport = self.diagram.view_controller.borrow().viewport();
+                }
+
+                self.navigation.truncate(depth);
+            }
+        }
+
+        self.diagram
+            .zoom_elements
+            .iter()
+            .find(|zoom_element| zoom_element.target == url)
+            .map(|zoom_element| zoom_element.info.clone())
+    }
+
+    /// Drops what belonged to the diagram we left
+    fn diagram_changed(&self) {
+        self.scheduler.cancel();
+        self.focused_link.set(None);
+        #[cfg(feature = "search")]
+        self.search.replace(None);
+        self.filter.replace(None);
+        self.isolation.replace(None);
+        self.peek_from.replace(None);
+        self.diff.replace(None);
+        self.first_view_update
+            .replace(Some(Span::start("first-view-update")));
+    }
+
+    /// `link` is the zoom element we drilled into to get here, if we did
+    fn after_navigation(&self, from: Option<Viewport>, link: Option<ElementInfo>) {
+        self.label_container();
+        self.prefetch_links();
+
+        for overlay in self.overlays.iter() {
+            overlay.navigated(&self.navigation);
+        }
+
+        let detail = NavigateDetail::new(&self.navigation, link);
+        if let Err(error) = dispatch_custom_event(&self.container, NAVIGATE_EVENT, &detail) {
+            console::warn_2(&"Couldn't announce the navigation".into(), &error);
+        }
+
+        self.overviews_changed(true);
+        if let Some(ref broadcaster) = self.broadcaster {
+            broadcaster.navigated(&self.diagram.url);
+        }
+        if let Some(ref embed) = self.embed {
+            embed.navigated();
+        }
+        if let Some(ref analytics) = self.analytics {
+            analytics.navigated(&self.navigation);
+        }
+        if self.options.borrow().restore_navigation.enabled {
+            save_navigation(&self.navigation);
+        }
+
+        let pending_view = self
+            .pending_view
+            .take()
+            .filter(|(url, _)| *url == self.diagram.url);
+        if let Some((_, view)) = pending_view {
+            self.show_pending_view(view);
+        } else if self.diagram.fit_on_show || self.overview_of.borrow().is_some() {
+            self.fit_to_content();
+        } else if let (Some(from), Some(initial), Some(current)) =
+            (from, &self.diagram.initial_view_box, self.viewport())
+        {
+            let pixel_size = current.size.scale(current.scale);
+            let target = Viewport::from_view_box(initial, &pixel_size);
+            let transition = Transition::between(&from, &target, self.transition());
+
+            self.animate(from, target, transition);
+        }
+    }
+
+    fn label_container(&self) {
+        let label = Strings::fill(
+            &self.options.borrow().strings.diagram_label,
+            &[("title", &self.diagram.title)],
+        );
+        if let Err(error) = self.container.set_attribute("aria-label", &label) {
+            console::warn_2(&"Couldn't label the diagram".into(), &error);
+        }
+    }
+
+    pub(crate) fn view_update(&self, event: &ViewUpdateEvent) {
+        if let Some(span) = self.first_view_update.take() {
+            self.timings.end(span);
+        }
+
+        // the user grabbing the view takes over from whatever we were animating
+        if event.is_gesture() {
+            self.scheduler.cancel();
+            self.quality.gesture();
+        }
+        if let (Some(velocity), Some(view)) = (event.fling(), event.view()) {
+            self.glide(view, velocity);
+        }
+        let quality = if event.is_gesture() {
+            self.quality.quality()
+        } else {
+            Quality::Full
+        };
+
+        self.diagram.semantic_zoom.update(event.zoom());
+        if quality < Quality::SkipCulling {
+            // text is measured the first time, before any of it can be in a culled group
+            if let Some(view) = event.view() {
+                self.diagram.text_culler.update(view.scale);
+            }
+            if let Some(visible) = event.visible_region() {
+                self.diagram.culler.update(&visible);
+            }
+        }
+
+        // don't drill down while we're moving the view ourselves
+        if !self.navigating.get() && !self.scheduler.is_running() {
+            if let Some(target) = self.zoom_target(event) {
+                self.navigate_to(&target);
+            }
+        }
+
+        if quality < Quality::CoarseUpdates || self.quality.updates_overlays() {
+            for overlay in self.overlays.iter() {
+                overlay.view_update(event);
+            }
+        }
+
+        self.update_overview();
+        if let Some(view) = event.view() {
+            self.mirror(view);
+            self.overviews_changed(false);
+
+            if let Some(ref broadcaster) = self.broadcaster {
+                broadcaster.viewport_changed(&self.diagram.url, view);
+            }
+            if let Some(ref embed) = self.embed {
+                embed.view_changed();
+            }
+            if let Some(ref analytics) = self.analytics {
+                analytics.view_changed(&self.diagram.url, ViewBox::from(&view.view_box()));
+            }
+            if let Some(ref remembered_view) = self.remembered_view {
+                remembered_view.view_changed(&self.diagram.url, view.view_box());
+            }
+            if let Some(ref recorder) = *self.recorder.borrow() {
+                recorder.view_changed(&self.diagram.url, view);
+            }
+        }
+    }
+
+    /// The link of the zoom element that fills enough of the view to drill into
+    fn zoom_target(&self, event: &ViewUpdateEvent) -> Option<String> {
+        let viewport = event.viewport();
+        for zoom_element in self.diagram.zoom_elements.iter() {
+            if let Some(element_rect) =
+                zoom_element.element_rect(&self.diagram.geometry, &self.diagram.transform_cache)
+            {
+                let total_area = viewport.area();
+                let viewable_area = viewport.overlap_area(&element_rect);
+                let area_percentage = viewable_area / total_area;
+
+                if area_percentage >= self.options.borrow().zoom.open_threshold {
+                    return Some(zoom_element.target.clone());
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl FrameClient for RefCell<ArchiZoom> {
+    fn frame(&self, time: f64) -> bool {
+        self.borrow().on_frame(time)
+    }
+
+    fn is_priority(&self) -> bool {
+        self.borrow().container.matches(":hover").unwrap_or(false)
+    }
 }
 
 impl Drop for ArchiZoom {
@@ -0,0 +1,198 @@
+use std::collections::{HashSet, VecDeque};
+
+use futures::future::{self, Either, Loop};
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+use crate::options::Options;
+use crate::zoom::diagram::{diagram_title, link_targets, parse_svg};
+use crate::zoom::search::labels;
+use crate::zoom::sprite::show_group;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModelSearchOptions {
+    /// Stop crawling after this many diagrams, big models can link to a lot of them
+    pub max_diagrams: usize,
+}
+
+impl Default for ModelSearchOptions {
+    fn default() -> ModelSearchOptions {
+        ModelSearchOptions { max_diagrams: 100 }
+    }
+}
+
+/// The labels of every diagram reachable from the first one, so we can search the whole
+/// model rather than only what's on screen
+pub struct ModelIndex {
+    diagrams: Vec<IndexedDiagram>,
+}
+
+struct IndexedDiagram {
+    url: String,
+    title: String,
+    labels: Vec<IndexedLabel>,
+}
+
+struct IndexedLabel {
+    /// Lowercased so searches are case insensitive
+    search_text: String,
+    text: String,
+    /// The id of the shape it labels, when it has one to frame
+    id: Option<String>,
+}
+
+/// The matches of one diagram
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelSearchResult {
+    pub url: String,
+    pub title: String,
+    pub matches: Vec<ModelMatch>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelMatch {
+    /// Pass this to `openSearchResult` to frame the match
+    pub id: Option<String>,
+    pub text: String,
+}
+
+struct Crawl {
+    queue: VecDeque<String>,
+    seen: HashSet<String>,
+    diagrams: Vec<IndexedDiagram>,
+}
+
+impl ModelIndex {
+    /// Walks the links breadth first from `root`, getting the markup of each diagram from
+    /// `load`, which should use whatever we already have cached
+    pub fn crawl<L, F>(
+        root: &str,
+        options: &Options,
+        load: L,
+    ) -> impl Future<Item = ModelIndex, Error = JsValue>
+    where
+        L: Fn(&str) -> Result<F, JsValue> + 'static,
+        F: Future<Item = String, Error = JsValue> + 'static,
+    {
+        let options = options.clone();
+        let mut crawl = Crawl {
+            queue: VecDeque::new(),
+            seen: HashSet::new(),
+            diagrams: vec![],
+        };
+        crawl.queue.push_back(root.to_string());
+        crawl.seen.insert(root.to_string());
+
+        future::loop_fn(crawl, move |mut crawl| {
+            let url = match crawl.queue.pop_front() {
+                Some(url) if crawl.diagrams.len() < options.model_search.max_diagrams => url,
+                _ => return Either::A(future::ok(Loop::Break(crawl))),
+            };
+
+            let text = match load(&url) {
+                Ok(text) => text,
+                Err(error) => {
+                    console::warn_3(&"Couldn't search".into(), &url.into(), &error);
+                    return Either::A(future::ok(Loop::Continue(crawl)));
+                }
+            };
+
+            let options = options.clone();
+            Either::B(text.then(move |result| {
+                // one broken diagram shouldn't stop us searching the rest
+                let indexed = result.and_then(|text| crawl.index(&url, &text, &options));
+                if let Err(error) = indexed {
+                    console::warn_3(&"Couldn't search".into(), &url.into(), &error);
+                }
+
+                Ok(Loop::Continue(crawl))
+            }))
+        })
+        .map(|crawl| ModelIndex {
+            diagrams: crawl.diagrams,
+        })
+    }
+
+    /// The diagrams with labels containing `query`, in the order we found them
+    pub fn find(&self, query: &str) -> Vec<ModelSearchResult> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        self.diagrams
+            .iter()
+            .filter_map(|diagram| {
+                let mut matches: Vec<ModelMatch> = vec![];
+                for label in diagram.labels.iter() {
+                    if !label.search_text.contains(&query) {
+                        continue;
+                    }
+
+                    // a shape with a multi-line label has an entry per line
+                    match matches
+                        .iter_mut()
+                        .find(|found| found.id.is_some() && found.id == label.id)
+                    {
+                        Some(found) => {
+                            found.text.push(' ');
+                            found.text.push_str(&label.text);
+                        }
+                        None => matches.push(ModelMatch {
+                            id: label.id.clone(),
+                            text: label.text.clone(),
+                        }),
+                    }
+                }
+
+                if matches.is_empty() {
+                    None
+                } else {
+                    Some(ModelSearchResult {
+                        url: diagram.url.clone(),
+                        title: diagram.title.clone(),
+                        matches,
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+impl Crawl {
+    fn index(&mut self, url: &str, text: &str, options: &Options) -> Result<(), JsValue> {
+        let svg = parse_svg(text, options)?;
+        let sprite = show_group(&svg, url)?;
+
+        let labels = labels(&svg)
+            .into_iter()
+            .map(|(text, element)| IndexedLabel {
+                search_text: text.to_lowercase(),
+                id: Some(element.id()).filter(|id| !id.is_empty()),
+                text,
+            })
+            .collect();
+
+        for target in link_targets(&svg, url) {
+            if self.seen.insert(target.clone()) {
+                self.queue.push_back(target);
+            }
+        }
+
+        self.diagrams.push(IndexedDiagram {
+            url: url.to_string(),
+            title: match sprite.and_then(|sprite| sprite.title) {
+                Some(title) => title,
+                None => diagram_title(&svg, url, &options.titles),
+            },
+            labels,
+        });
+
+        Ok(())
+    }
+}
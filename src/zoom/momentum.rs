@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::zoom::animation::{Transition, ViewportAnimation};
+use crate::zoom::matrix::Point2D;
+use crate::zoom::spring::Spring;
+use crate::zoom::viewport::Viewport;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MomentumOptions {
+    /// Keep the view gliding after a drag is let go. Off by default since it changes how
+    /// panning feels.
+    pub enabled: bool,
+    /// How quickly the glide slows down, per second. The view travels its release speed divided
+    /// by this before it stops.
+    pub friction: f32,
+}
+
+impl Default for MomentumOptions {
+    fn default() -> MomentumOptions {
+        MomentumOptions {
+            enabled: false,
+            friction: 4.0,
+        }
+    }
+}
+
+/// Flings slower than this many pixels a second just stop
+static MIN_FLING_SPEED: f32 = 50.0;
+/// How much of each new sample goes into the velocity, the rest is the earlier samples
+static SMOOTHING: f32 = 0.7;
+/// Samples closer together than this are too noisy to measure a speed from
+static MIN_SAMPLE_MS: f64 = 4.0;
+/// A drag that hasn't moved for this long was stopped before it was let go
+static STILL_MS: f64 = 80.0;
+
+/// Follows how fast a drag is moving the view, so letting go can carry on at that speed
+#[derive(Debug, Default)]
+pub struct FlingTracker {
+    /// In Svg User Coordinates per second
+    velocity: Option<Point2D>,
+    last_time: Option<f64>,
+}
+
+impl FlingTracker {
+    /// Starts following a new drag at `time` in milliseconds
+    pub fn start(&mut self, time: f64) {
+        self.velocity = None;
+        self.last_time = Some(time);
+    }
+
+    /// The drag moved the view by `delta` at `time`
+    pub fn moved(&mut self, delta: &Point2D, time: f64) {
+        let last_time = match self.last_time {
+            Some(last_time) if time - last_time >= MIN_SAMPLE_MS => last_time,
+            _ => return,
+        };
+
+        let sample = delta * (1000.0 / (time - last_time) as f32);
+        self.velocity = Some(match self.velocity {
+            Some(ref velocity) => &(&sample * SMOOTHING) + &(velocity * (1.0 - SMOOTHING)),
+            None => sample,
+        });
+        self.last_time = Some(time);
+    }
+
+    /// Ends the drag at `time`, returning how fast the view was moving when it was let go
+    pub fn release(&mut self, time: f64) -> Option<Point2D> {
+        let last_time = self.last_time.take()?;
+        let velocity = self.velocity.take()?;
+
+        if time - last_time > STILL_MS {
+            None
+        } else {
+            Some(velocity)
+        }
+    }
+}
+
+/// Glides `view` on from moving at `velocity` (in Svg User Coordinates per second), slowing down
+/// with a friction spring. Returns `None` for flings too slow to carry on.
+pub fn glide(
+    view: &Viewport,
+    velocity: &Point2D,
+    options: &MomentumOptions,
+) -> Option<ViewportAnimation> {
+    if velocity.length() * view.scale < MIN_FLING_SPEED || options.friction <= 0.0 {
+        return None;
+    }
+
+    // a friction spring thrown at the target travels its starting speed over the friction
+    let mut target = view.clone();
+    target.pan(&(velocity * (1.0 / options.friction)));
+
+    Some(ViewportAnimation::new(
+        view.clone(),
+        target,
+        Transition::Spring(Spring::friction(options.friction)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zoom::matrix::Size2D;
+
+    fn view() -> Viewport {
+        Viewport::new(Point2D::new(0.0, 0.0), Size2D::new(400.0, 300.0), 1.0)
+    }
+
+    fn options() -> MomentumOptions {
+        MomentumOptions {
+            enabled: true,
+            friction: 4.0,
+        }
+    }
+
+    #[test]
+    fn steady_drags_fling_at_their_speed() {
+        let mut tracker = FlingTracker::default();
+        tracker.start(0.0);
+        for frame in 1..=10 {
+            tracker.moved(&Point2D::new(10.0, 0.0), frame as f64 * 10.0);
+        }
+
+        let velocity = tracker.release(105.0).unwrap();
+        assert!((velocity.x - 1000.0).abs() < 1.0);
+        assert!(velocity.y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn drags_held_still_before_release_dont_fling() {
+        let mut tracker = FlingTracker::default();
+        tracker.start(0.0);
+        tracker.moved(&Point2D::new(10.0, 0.0), 16.0);
+
+        assert!(tracker.release(16.0 + STILL_MS + 1.0).is_none());
+    }
+
+    #[test]
+    fn glides_start_at_the_fling_speed_and_slow_to_a_stop() {
+        let velocity = Point2D::new(800.0, 0.0);
+        let mut animation = glide(&view(), &velocity, &options()).unwrap();
+
+        let first = animation.step(16.0);
+        let first_speed = first.origin.x / 0.016;
+        assert!((first_speed - 800.0).abs() < 800.0 * 0.1);
+
+        let mut last_x = first.origin.x;
+        let mut last_step = last_x;
+        let mut frames = 0;
+        while !animation.is_finished() && frames < 10_000 {
+            let x = animation.step(16.0).origin.x;
+            // the last frame lands on the target, a hair further than the spring had slowed to
+            assert!(x >= last_x && x - last_x <= last_step + 0.1);
+
+            last_step = x - last_x;
+            last_x = x;
+            frames += 1;
+        }
+
+        let target = 800.0 / options().friction;
+        assert!(animation.is_finished());
+        assert!((animation.step(16.0).origin.x - target).abs() < 1e-3);
+    }
+
+    #[test]
+    fn slow_flings_dont_glide() {
+        assert!(glide(&view(), &Point2D::new(10.0, 0.0), &options()).is_none());
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use web_sys::{console, Storage};
+
+use crate::js_utils::*;
+use crate::zoom::diagram::Diagram;
+use crate::zoom::matrix::Rect;
+use crate::zoom::metadata::ElementInfo;
+use crate::zoom::viewport::Viewport;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RestoreNavigationOptions {
+    pub enabled: bool,
+}
+
+/// One step of the drill-down path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavigationEntry {
+    pub url: String,
+    pub title: String,
+    /// Where the view was when we navigated away, so going back can return to it
+    pub viewport: Option<Viewport>,
+}
+
+impl NavigationEntry {
+    pub fn new(diagram: &Diagram) -> NavigationEntry {
+        NavigationEntry {
+            url: diagram.url.clone(),
+            title: diagram.title.clone(),
+            viewport: None,
+        }
+    }
+}
+
+/// What to show once we've navigated to a diagram, instead of its initial view
+#[derive(Debug, Clone)]
+pub enum PendingView {
+    /// The element with this id
+    Frame(String),
+    ViewBox(Rect),
+}
+
+pub static NAVIGATE_EVENT: &str = "archizoom-navigate";
+
+/// The detail of the `archizoom-navigate` event fired on the container after each navigation
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigateDetail {
+    url: String,
+    title: String,
+    /// The urls of the drill-down path, ending with `url`
+    path: Vec<String>,
+    /// The zoom element that was drilled into, `null` when going back up
+    link: Option<ElementInfo>,
+}
+
+impl NavigateDetail {
+    pub fn new(navigation: &[NavigationEntry], link: Option<ElementInfo>) -> NavigateDetail {
+        let (url, title) = navigation
+            .last()
+            .map(|current| (current.url.clone(), current.title.clone()))
+            .unwrap_or_default();
+
+        NavigateDetail {
+            url,
+            title,
+            path: navigation.iter().map(|entry| entry.url.clone()).collect(),
+            link,
+        }
+    }
+}
+
+static KEY_PREFIX: &str = "archizoom-navigation:";
+
+/// Keeps the drill-down path in sessionStorage under the diagram it starts from, so a reload
+/// can put the reader back where they were. Back at the root there's nothing to keep.
+pub fn save_navigation(navigation: &[NavigationEntry]) {
+    let (root, storage) = match (navigation.first(), storage()) {
+        (Some(root), Some(storage)) => (root, storage),
+        _ => return,
+    };
+
+    let result = if navigation.len() > 1 {
+        serde_json::to_string(navigation)
+            .map_err(|error| error.to_string().into())
+            .and_then(|json| storage.set_item(&key(&root.url), &json))
+    } else {
+        storage.remove_item(&key(&root.url))
+    };
+
+    if let Err(error) = result {
+        console::warn_2(&"Couldn't save the navigation path".into(), &error);
+    }
+}
+
+/// The drill-down path saved for the diagram at `root_url`, if it went anywhere
+pub fn load_navigation(root_url: &str) -> Option<Vec<NavigationEntry>> {
+    let json = storage()?.get_item(&key(root_url)).ok().flatten()?;
+
+    serde_json::from_str::<Vec<NavigationEntry>>(&json)
+        .ok()
+        .filter(|navigation| {
+            navigation.len() > 1
+                && navigation
+                    .first()
+                    .map(|root| root.url == root_url)
+                    .unwrap_or(false)
+        })
+}
+
+fn key(root_url: &str) -> String {
+    format!("{}{}", KEY_PREFIX, root_url)
+}
+
+fn storage() -> Option<Storage> {
+    // reading sessionStorage throws when the page isn't allowed to use it
+    window().session_storage().ok().flatten()
+}
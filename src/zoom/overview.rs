@@ -0,0 +1,160 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, HtmlElement, Node, PointerEvent};
+
+use crate::js_utils::*;
+use crate::zoom::matrix::{Point2D, Rect};
+use crate::zoom::space::{ScreenPoint, Transform, UserPoint, UserRect};
+use crate::zoom::ArchiZoom;
+
+/// Draws the view of another viewer, the detail, as a rectangle on this one. Dragging the
+/// rectangle, or clicking anywhere else, moves the detail's view there. It's the minimap
+/// built out of two viewers the page lays out itself.
+pub struct OverviewIndicator {
+    detail: Weak<RefCell<ArchiZoom>>,
+    element: HtmlElement,
+    container: Element,
+    /// Whether the overview was locked before it started following, to put back when it stops
+    pub was_locked: bool,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl OverviewIndicator {
+    pub fn new(
+        overview: Weak<RefCell<ArchiZoom>>,
+        container: &Element,
+        detail: &Rc<RefCell<ArchiZoom>>,
+        was_locked: bool,
+    ) -> Result<OverviewIndicator, JsValue> {
+        let element = document()
+            .safe_create_element::<HtmlElement>("div")
+            .ok_or_else(|| JsValue::from("Couldn't create the overview indicator"))?;
+        element.set_class_name("archizoom-overview-viewport");
+
+        let style = element.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("box-sizing", "border-box")?;
+        style.set_property("pointer-events", "none")?;
+        style.set_property("display", "none")?;
+
+        container.append_child(&element)?;
+
+        // the offset from the pointer to the center of the detail's view while dragging
+        let drag: Rc<RefCell<Option<Point2D>>> = Rc::new(RefCell::new(None));
+
+        let down_overview = overview.clone();
+        let down_drag = drag.clone();
+        let down_container = container.clone();
+        let move_drag = drag.clone();
+        let up_drag = drag.clone();
+        let cancel_drag = drag;
+        let listeners = vec![
+            container.new_event_listener("pointerdown", move |event: PointerEvent| {
+                let overview = match down_overview.upgrade() {
+                    Some(overview) => overview,
+                    None => return,
+                };
+                let overview = overview.borrow();
+                // the overview's own controls still work
+                let on_diagram = event
+                    .target()
+                    .and_then(|target| target.dyn_into::<Node>().ok())
+                    .map(|target| overview.svg().contains(Some(&target)))
+                    .unwrap_or(false);
+                let (point, view) = match (user_point(&overview, &event), overview.detail_view()) {
+                    (Some(point), Some(view)) if on_diagram => (point, view),
+                    _ => return,
+                };
+                event.prevent_default();
+
+                // grabbing the rectangle keeps the grab point under the pointer, clicking
+                // anywhere else jumps there first
+                down_drag.replace(Some(if view.contains(&point) {
+                    &view.center() - &point
+                } else {
+                    overview.move_detail(&point);
+                    Point2D::new(0.0, 0.0)
+                }));
+
+                let _ = down_container.set_pointer_capture(event.pointer_id());
+            })?,
+            container.new_event_listener("pointermove", move |event: PointerEvent| {
+                let offset = move_drag.borrow().clone();
+                let (offset, overview) = match (offset, overview.upgrade()) {
+                    (Some(offset), Some(overview)) => (offset, overview),
+                    _ => return,
+                };
+                let overview = overview.borrow();
+                if let Some(point) = user_point(&overview, &event) {
+                    overview.move_detail(&(&point + &offset));
+                }
+            })?,
+            container.new_event_listener("pointerup", move |_: PointerEvent| {
+                up_drag.replace(None);
+            })?,
+            container.new_event_listener("pointercancel", move |_: PointerEvent| {
+                cancel_drag.replace(None);
+            })?,
+        ];
+
+        Ok(OverviewIndicator {
+            detail: Rc::downgrade(detail),
+            element,
+            container: container.clone(),
+            was_locked,
+            _listeners: listeners,
+        })
+    }
+
+    pub fn detail(&self) -> Option<Rc<RefCell<ArchiZoom>>> {
+        self.detail.upgrade()
+    }
+
+    /// Moves the rectangle over where the detail's view is, hiding it while the two are
+    /// showing different diagrams
+    pub fn update(&self, overview: &ArchiZoom, view: Option<Rect>) {
+        if let Err(error) = self.place(overview, view) {
+            console::warn_2(&"Couldn't move the overview indicator".into(), &error);
+        }
+    }
+
+    fn place(&self, overview: &ArchiZoom, view: Option<Rect>) -> Result<(), JsValue> {
+        let style = self.element.style();
+
+        let to_client = match (
+            overview.screen_ctm(),
+            Transform::client_to_screen(&self.container).inverse(),
+        ) {
+            (Some(to_screen), Some(to_client)) => to_screen.then(&to_client),
+            _ => return style.set_property("display", "none"),
+        };
+        let view = match view {
+            Some(view) => to_client.rect(&UserRect::new(view)),
+            None => return style.set_property("display", "none"),
+        };
+
+        style.set_property("left", &format!("{}px", view.left()))?;
+        style.set_property("top", &format!("{}px", view.top()))?;
+        style.set_property("width", &format!("{}px", view.width()))?;
+        style.set_property("height", &format!("{}px", view.height()))?;
+        style.set_property("display", "block")
+    }
+}
+
+impl Drop for OverviewIndicator {
+    fn drop(&mut self) {
+        self.element.remove();
+    }
+}
+
+/// Where the pointer is on the overview's diagram
+fn user_point(overview: &ArchiZoom, event: &PointerEvent) -> Option<Point2D> {
+    let position = ScreenPoint::point(event.client_x() as f32, event.client_y() as f32);
+
+    overview
+        .screen_ctm()
+        .and_then(|to_screen| to_screen.inverse())
+        .map(|to_user| UserPoint::into_inner(to_user.point(&position)))
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use web_sys::SvgsvgElement;
+
+use crate::zoom::bounds::{element_bounds, find_elements};
+use crate::zoom::matrix::Point2D;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PinsOptions {
+    /// A JSON document of `{ pins: [...] }` to load when the viewer starts
+    pub src: Option<String>,
+    /// Load `src` again this often, for pins showing live status. Never when it's 0.
+    pub refresh_ms: i32,
+}
+
+/// A marker on a diagram, placed over an element or at a point. Pins come from outside the
+/// diagram, like the status of the services it describes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pin {
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The url of the diagram it goes on, every diagram with its element when it's missing
+    #[serde(default)]
+    pub diagram: Option<String>,
+    /// The id of the element it sits on top of
+    #[serde(default)]
+    pub element: Option<String>,
+    /// Or where it goes in Svg User Coordinates
+    #[serde(default)]
+    pub x: Option<f32>,
+    #[serde(default)]
+    pub y: Option<f32>,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Added as an `archizoom-pin-{status}` class, for styling
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// What `loadPins` expects the document at its url to be
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PinData {
+    #[serde(default)]
+    pub pins: Vec<Pin>,
+}
+
+impl Pin {
+    /// Where the pin points on the diagram at `url`, the top middle of its element or its
+    /// point, if it goes on that diagram at all
+    pub fn anchor(&self, url: &str, svg: &SvgsvgElement) -> Option<Point2D> {
+        if self.diagram.as_deref().map(|diagram| diagram != url) == Some(true) {
+            return None;
+        }
+
+        match (&self.element, self.x, self.y) {
+            (Some(ref id), _, _) => find_elements(svg, id)
+                .first()
+                .and_then(|element| element_bounds(svg, element))
+                .map(|bounds| Point2D::new(bounds.center().x, bounds.top())),
+            (None, Some(x), Some(y)) => Some(Point2D::new(x, y)),
+            _ => None,
+        }
+    }
+
+    /// The `archizoom-pin-{status}` class, if the status makes a usable class name
+    pub fn status_class(&self) -> Option<String> {
+        self.status
+            .as_ref()
+            .filter(|status| {
+                !status.is_empty()
+                    && status
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            })
+            .map(|status| format!("archizoom-pin-{}", status))
+    }
+}
@@ -0,0 +1,255 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::{Rc, Weak};
+
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{console, Headers, Request, RequestInit, Response};
+
+use crate::js_utils::*;
+use crate::zoom::matrix::Rect;
+use crate::zoom::view_link::parse_view_box;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrefetchOptions {
+    pub enabled: bool,
+    /// How much of each linked diagram to request, the root element and its `<title>` are
+    /// almost always near the start
+    pub bytes: usize,
+}
+
+impl Default for PrefetchOptions {
+    fn default() -> PrefetchOptions {
+        PrefetchOptions {
+            enabled: false,
+            bytes: 4096,
+        }
+    }
+}
+
+/// What we know about a diagram from the start of its markup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagramInfo {
+    pub title: Option<String>,
+    pub view_box: Option<Rect>,
+    pub width: Option<String>,
+    pub height: Option<String>,
+}
+
+/// Fetches the start of every linked diagram while the page is idle, one at a time, so titles
+/// can be shown before they're opened
+pub struct Prefetcher {
+    inner: Rc<PrefetcherInner>,
+}
+
+struct PrefetcherInner {
+    enabled: bool,
+    bytes: usize,
+    infos: RefCell<HashMap<String, DiagramInfo>>,
+    queue: RefCell<VecDeque<String>>,
+    /// Set while waiting on an idle callback or a fetch
+    busy: Cell<bool>,
+    on_idle: Closure<dyn FnMut()>,
+}
+
+/// Browsers without `requestIdleCallback` wait this long between fetches instead
+static FALLBACK_DELAY_MS: i32 = 200;
+
+impl Prefetcher {
+    pub fn new(options: &PrefetchOptions) -> Prefetcher {
+        let inner = Rc::new_cyclic(|weak_ref: &Weak<PrefetcherInner>| {
+            let weak_ref = weak_ref.clone();
+
+            PrefetcherInner {
+                enabled: options.enabled,
+                bytes: options.bytes,
+                infos: RefCell::new(HashMap::new()),
+                queue: RefCell::new(VecDeque::new()),
+                busy: Cell::new(false),
+                on_idle: Closure::wrap(Box::new(move || {
+                    if let Some(inner) = weak_ref.upgrade() {
+                        PrefetcherInner::fetch_next(&inner);
+                    }
+                }) as Box<dyn FnMut()>),
+            }
+        });
+
+        Prefetcher { inner }
+    }
+
+    /// Queues every url we haven't already fetched
+    pub fn prefetch<'a, I: Iterator<Item = &'a String>>(&self, urls: I) {
+        if !self.inner.enabled {
+            return;
+        }
+
+        {
+            let infos = self.inner.infos.borrow();
+            let mut queue = self.inner.queue.borrow_mut();
+            for url in urls {
+                if !infos.contains_key(url) && !queue.contains(url) {
+                    queue.push_back(url.clone());
+                }
+            }
+        }
+
+        self.inner.schedule();
+    }
+
+    pub fn info(&self, url: &str) -> Option<DiagramInfo> {
+        self.inner.infos.borrow().get(url).cloned()
+    }
+
+    /// Keeps what we learned about `url` some other way, so it isn't fetched again
+    pub fn remember(&self, url: &str, info: DiagramInfo) {
+        self.inner.queue.borrow_mut().retain(|queued| queued != url);
+        self.inner.infos.borrow_mut().insert(url.to_string(), info);
+    }
+}
+
+impl PrefetcherInner {
+    fn schedule(&self) {
+        if self.queue.borrow().is_empty() || self.busy.replace(true) {
+            return;
+        }
+
+        let callback = self.on_idle.as_ref().unchecked_ref();
+        let scheduled = window()
+            .request_idle_callback(callback)
+            .map(|_| ())
+            .or_else(|_| {
+                window()
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(
+                        callback,
+                        FALLBACK_DELAY_MS,
+                    )
+                    .map(|_| ())
+            });
+
+        if let Err(error) = scheduled {
+            console::warn_2(&"Couldn't schedule a prefetch".into(), &error);
+            self.busy.set(false);
+        }
+    }
+
+    fn fetch_next(inner: &Rc<PrefetcherInner>) {
+        let url = match inner.queue.borrow_mut().pop_front() {
+            Some(url) => url,
+            None => {
+                inner.busy.set(false);
+                return;
+            }
+        };
+
+        let future = match fetch_start(&url, inner.bytes) {
+            Ok(future) => future,
+            Err(error) => {
+                console::warn_2(&format!("Couldn't prefetch {}", url).into(), &error);
+                inner.busy.set(false);
+                inner.schedule();
+
+                return;
+            }
+        };
+
+        let weak_ref = Rc::downgrade(inner);
+        spawn_local(future.then(move |result| {
+            if let Some(inner) = weak_ref.upgrade() {
+                // a failed prefetch is remembered too, so we don't keep asking for it
+                let info = result.map(|text| parse_info(&text)).unwrap_or_default();
+                inner.infos.borrow_mut().insert(url, info);
+
+                inner.busy.set(false);
+                inner.schedule();
+            }
+
+            Ok(())
+        }));
+    }
+}
+
+/// Asks for just the first `bytes` of `url`. Servers that ignore the range send the whole
+/// diagram, which still works.
+fn fetch_start(
+    url: &str,
+    bytes: usize,
+) -> Result<impl Future<Item = String, Error = JsValue>, JsValue> {
+    let headers = Headers::new()?;
+    headers.append("Range", &format!("bytes=0-{}", bytes.saturating_sub(1)))?;
+
+    let opts = RequestInit::new();
+    opts.set_method("GET");
+    opts.set_headers(&headers);
+
+    let request = Request::new_with_str_and_init(url, &opts)?;
+
+    Ok(JsFuture::from(window().fetch_with_request(&request))
+        .and_then(|response| {
+            response.dyn_into::<Response>().and_then(|response| {
+                if response.ok() {
+                    response.text()
+                } else {
+                    Err(format!("{} {}", response.status(), response.status_text()).into())
+                }
+            })
+        })
+        .and_then(JsFuture::from)
+        .map(|text| text.as_string().unwrap_or_default()))
+}
+
+/// Reads what it can from the root `<svg>` tag and the `<title>` right after it. The markup
+/// may be cut off anywhere.
+fn parse_info(text: &str) -> DiagramInfo {
+    let start = match text.find("<svg") {
+        Some(start) => start,
+        None => return DiagramInfo::default(),
+    };
+    let tag_end = match text[start..].find('>') {
+        Some(end) => start + end,
+        None => return DiagramInfo::default(),
+    };
+    let tag = &text[start..tag_end];
+
+    let title = text[tag_end + 1..]
+        .trim_start()
+        .strip_prefix("<title")
+        .and_then(|title| title.split_once('>'))
+        .and_then(|(_, title)| title.split_once("</title>"))
+        .map(|(title, _)| decode_entities(title.trim()))
+        .filter(|title| !title.is_empty());
+
+    DiagramInfo {
+        title,
+        view_box: attribute(tag, "viewBox").and_then(|view_box| parse_view_box(&view_box)),
+        width: attribute(tag, "width"),
+        height: attribute(tag, "height"),
+    }
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    ['"', '\''].iter().find_map(|quote| {
+        let prefix = format!("{}={}", name, quote);
+        let start = tag
+            .match_indices(&prefix)
+            .map(|(index, _)| index)
+            .find(|index| tag[..*index].ends_with(char::is_whitespace))?
+            + prefix.len();
+        let end = tag[start..].find(*quote)?;
+
+        Some(tag[start..start + end].to_string())
+    })
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,127 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{console, Element, Event, HtmlElement};
+
+use crate::js_utils::*;
+use crate::zoom::export::prefix_ids;
+use crate::zoom::ArchiZoom;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrintOptions {
+    pub enabled: bool,
+}
+
+/// Prints the current view instead of the interactive viewer. The container is swapped for a
+/// copy of what's in view, as vectors sized to the page and without any overlays, until
+/// printing is done. The copy's ids are prefixed so they don't clash with the diagram's.
+pub struct PrintSupport {
+    stand_in: Rc<RefCell<Option<Element>>>,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl PrintSupport {
+    pub fn new(
+        options: &PrintOptions,
+        archizoom: Weak<RefCell<ArchiZoom>>,
+    ) -> Result<Option<PrintSupport>, JsValue> {
+        if !options.enabled {
+            return Ok(None);
+        }
+
+        let stand_in: Rc<RefCell<Option<Element>>> = Rc::new(RefCell::new(None));
+
+        let before_print = {
+            let stand_in = stand_in.clone();
+            let archizoom = archizoom.clone();
+            window().new_event_listener("beforeprint", move |_: Event| {
+                // a print that never finished leaves its copy behind
+                if let Some(previous) = stand_in.replace(None) {
+                    previous.remove();
+                }
+
+                if let Some(real_ref) = archizoom.upgrade() {
+                    match print_copy(&real_ref.borrow()) {
+                        Ok(copy) => {
+                            stand_in.replace(Some(copy));
+                        }
+                        Err(error) => {
+                            console::warn_2(&"Couldn't prepare the diagram to print".into(), &error)
+                        }
+                    }
+                }
+            })?
+        };
+
+        let after_print = {
+            let stand_in = stand_in.clone();
+            window().new_event_listener("afterprint", move |_: Event| {
+                if let Some(copy) = stand_in.replace(None) {
+                    copy.remove();
+                }
+                if let Some(real_ref) = archizoom.upgrade() {
+                    show(real_ref.borrow().container(), true);
+                }
+            })?
+        };
+
+        Ok(Some(PrintSupport {
+            stand_in,
+            _listeners: vec![before_print, after_print],
+        }))
+    }
+}
+
+/// Puts a copy of the current view in front of the container and hides the container
+fn print_copy(archizoom: &ArchiZoom) -> Result<Element, JsValue> {
+    let markup = archizoom.export_svg()?;
+
+    let copy = document()
+        .safe_create_element::<HtmlElement>("div")
+        .ok_or_else(|| JsValue::from("Couldn't create the print copy"))?;
+    copy.set_class_name("archizoom-print");
+    copy.set_inner_html(&markup);
+
+    // the viewBox keeps the shape, the page decides the size
+    if let Some(svg) = copy.first_element_child() {
+        prefix_ids(&svg, "archizoom-print-")?;
+        svg.set_attribute("width", "100%")?;
+        svg.remove_attribute("height")?;
+        svg.set_attribute(
+            "style",
+            "display: block; max-width: 100%; max-height: 100vh; break-inside: avoid;",
+        )?;
+    }
+
+    let container = archizoom.container();
+    let parent = container
+        .parent_node()
+        .ok_or_else(|| JsValue::from("The viewer isn't on the page"))?;
+    parent.insert_before(&copy, Some(container))?;
+    show(container, false);
+
+    Ok(copy.into())
+}
+
+fn show(container: &Element, visible: bool) {
+    let result = if visible {
+        container.remove_attribute("hidden")
+    } else {
+        container.set_attribute("hidden", "")
+    };
+
+    if let Err(error) = result {
+        console::warn_2(&"Couldn't swap the diagram for printing".into(), &error);
+    }
+}
+
+impl Drop for PrintSupport {
+    fn drop(&mut self) {
+        if let Some(copy) = self.stand_in.replace(None) {
+            copy.remove();
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use web_sys::{console, Element, SvgsvgElement};
+
+use crate::js_utils::*;
+use crate::zoom::frame_loop::{schedule_frame, FrameClient};
+use crate::zoom::timing::Span;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProgressiveOptions {
+    pub enabled: bool,
+    /// Diagrams with fewer elements than this are shown all at once
+    pub min_elements: u32,
+    /// How long each frame can spend putting groups back
+    pub frame_budget_ms: f64,
+}
+
+impl Default for ProgressiveOptions {
+    fn default() -> ProgressiveOptions {
+        ProgressiveOptions {
+            enabled: false,
+            min_elements: 5000,
+            frame_budget_ms: 4.0,
+        }
+    }
+}
+
+type OnFinished = Box<dyn FnOnce(Span)>;
+
+/// Top level elements that don't paint anything, and that what does paint may depend on
+pub static NON_PAINTING_TAGS: [&str; 5] = ["defs", "style", "title", "desc", "metadata"];
+
+/// Holds back the top level groups of a huge diagram so it can go on the page right away, then
+/// puts them back a few per frame. They go back in document order, so the background shows up
+/// first and the detail painted over it follows.
+pub struct ProgressiveInjection {
+    /// Each held back element and the parent it goes back into
+    pending: RefCell<VecDeque<(Element, Element)>>,
+    frame_budget_ms: f64,
+    span: RefCell<Option<Span>>,
+    on_finished: RefCell<Option<OnFinished>>,
+}
+
+impl ProgressiveInjection {
+    /// Takes the groups out of `svg` if it's big enough to be worth it. Call this before the svg
+    /// is on the page, after it's been indexed.
+    pub fn hold_back(
+        svg: &SvgsvgElement,
+        options: &ProgressiveOptions,
+    ) -> Option<Rc<ProgressiveInjection>> {
+        let elements = svg
+            .query_selector_all("*")
+            .map(|nodes| nodes.length())
+            .unwrap_or(0);
+        if !options.enabled || elements < options.min_elements {
+            return None;
+        }
+
+        // exports usually wrap everything in a single group, so split up whatever's inside it
+        let mut parent: Element = svg.clone().into();
+        while let Some(group) = only_painting_group(&parent) {
+            parent = group;
+        }
+
+        let mut pending = VecDeque::new();
+        let mut child = parent.first_element_child();
+        while let Some(element) = child {
+            child = element.next_element_sibling();
+
+            if !NON_PAINTING_TAGS.contains(&element.tag_name().as_str()) {
+                element.remove();
+                pending.push_back((element, parent.clone()));
+            }
+        }
+
+        if pending.is_empty() {
+            return None;
+        }
+
+        Some(Rc::new(ProgressiveInjection {
+            pending: RefCell::new(pending),
+            frame_budget_ms: options.frame_budget_ms,
+            span: RefCell::new(Some(Span::start("progressive-inject"))),
+            on_finished: RefCell::new(None),
+        }))
+    }
+
+    /// Starts putting the groups back, `on_finished` is called with the span of the whole
+    /// injection once they're all in
+    pub fn start<F: FnOnce(Span) + 'static>(injection: &Rc<ProgressiveInjection>, on_finished: F) {
+        injection.on_finished.replace(Some(Box::new(on_finished)));
+
+        let client: Rc<dyn FrameClient> = injection.clone();
+        schedule_frame(Rc::downgrade(&client));
+    }
+
+    fn finish(&self) {
+        let on_finished = self.on_finished.borrow_mut().take();
+        let span = self.span.borrow_mut().take();
+        if let (Some(on_finished), Some(span)) = (on_finished, span) {
+            on_finished(span);
+        }
+    }
+}
+
+impl FrameClient for ProgressiveInjection {
+    fn frame(&self, _time: f64) -> bool {
+        let start = now();
+
+        loop {
+            let next = self.pending.borrow_mut().pop_front();
+            let (element, parent) = match next {
+                Some(next) => next,
+                None => break,
+            };
+
+            if let Err(error) = parent.append_child(&element) {
+                console::warn_2(&"Couldn't put a group back into the diagram".into(), &error);
+            }
+
+            if now() - start > self.frame_budget_ms {
+                break;
+            }
+        }
+
+        let done = self.pending.borrow().is_empty();
+        if done {
+            self.finish();
+        }
+
+        !done
+    }
+}
+
+/// The child of `parent` that paints, if it's the only one and it's a group
+fn only_painting_group(parent: &Element) -> Option<Element> {
+    let mut painting = vec![];
+    let mut child = parent.first_element_child();
+    while let Some(element) = child {
+        child = element.next_element_sibling();
+
+        if !NON_PAINTING_TAGS.contains(&element.tag_name().as_str()) {
+            painting.push(element);
+        }
+    }
+
+    match painting.pop() {
+        Some(group) if painting.is_empty() && group.tag_name() == "g" => Some(group),
+        _ => None,
+    }
+}
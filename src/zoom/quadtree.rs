@@ -0,0 +1,259 @@
+use crate::zoom::matrix::{Point2D, Rect};
+
+/// How many items a node holds before it splits into quadrants
+static NODE_CAPACITY: usize = 8;
+/// Stop splitting past this depth so overlapping items can't recurse forever
+static MAX_DEPTH: usize = 10;
+
+/// A spatial index over item bounds, for finding what's under a point or inside a region without
+/// scanning every item
+#[derive(Debug)]
+pub struct QuadTree<T> {
+    root: Node<T>,
+}
+
+#[derive(Debug)]
+struct Node<T> {
+    bounds: Rect,
+    depth: usize,
+    /// Items that don't fit entirely within a single child stay at this level
+    items: Vec<(Rect, T)>,
+    children: Option<Box<[Node<T>; 4]>>,
+}
+
+impl<T> QuadTree<T> {
+    pub fn new(bounds: Rect) -> QuadTree<T> {
+        QuadTree {
+            root: Node::new(bounds, 0),
+        }
+    }
+
+    /// Builds a tree sized to fit all of `items`
+    pub fn from_items<I: IntoIterator<Item = (Rect, T)>>(items: I) -> QuadTree<T> {
+        let items: Vec<(Rect, T)> = items.into_iter().collect();
+
+        let bounds = items
+            .iter()
+            .map(|(rect, _)| rect.clone())
+            .fold(None, |union: Option<Rect>, rect| match union {
+                Some(union) => Some(union.union(&rect)),
+                None => Some(rect),
+            })
+            .unwrap_or_else(|| Rect::new(Point2D::new(0.0, 0.0), Point2D::new(0.0, 0.0)));
+
+        let mut tree = QuadTree::new(bounds);
+        for (rect, item) in items {
+            tree.insert(rect, item);
+        }
+
+        tree
+    }
+
+    /// Inserts an item, items outside of the tree's bounds are kept at the root
+    pub fn insert(&mut self, rect: Rect, item: T) {
+        self.root.insert(rect, item);
+    }
+
+    /// All the items whose bounds contain `point`, with those bounds
+    pub fn query_point(&self, point: &Point2D) -> Vec<(&Rect, &T)> {
+        let mut results = vec![];
+        self.root
+            .query_entries(&|rect: &Rect| rect.contains(point), &mut results);
+
+        results
+    }
+
+    /// All the items whose bounds intersect `area`
+    pub fn query_rect(&self, area: &Rect) -> Vec<&T> {
+        let mut results = vec![];
+        self.root
+            .query(&|rect: &Rect| rect.intersects(area), &mut results);
+
+        results
+    }
+
+    /// The smallest item under `point`, which is usually the innermost shape
+    pub fn smallest_at(&self, point: &Point2D) -> Option<&T> {
+        self.query_point(point)
+            .into_iter()
+            .min_by(|(a, _), (b, _)| {
+                a.area()
+                    .partial_cmp(&b.area())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(_, item)| item)
+    }
+}
+
+impl<T> Node<T> {
+    fn new(bounds: Rect, depth: usize) -> Node<T> {
+        Node {
+            bounds,
+            depth,
+            items: vec![],
+            children: None,
+        }
+    }
+
+    fn insert(&mut self, rect: Rect, item: T) {
+        if let Some(ref mut children) = self.children {
+            if let Some(child) = children
+                .iter_mut()
+                .find(|child| child.bounds.contains_rect(&rect))
+            {
+                return child.insert(rect, item);
+            }
+        }
+
+        self.items.push((rect, item));
+
+        if self.children.is_none() && self.items.len() > NODE_CAPACITY && self.depth < MAX_DEPTH {
+            self.split();
+        }
+    }
+
+    fn split(&mut self) {
+        let center = self.bounds.center();
+        let (left, top, right, bottom) = (
+            self.bounds.left(),
+            self.bounds.top(),
+            self.bounds.right(),
+            self.bounds.bottom(),
+        );
+        let depth = self.depth + 1;
+
+        self.children = Some(Box::new([
+            Node::new(Rect::new(Point2D::new(left, top), center.clone()), depth),
+            Node::new(
+                Rect::new(Point2D::new(center.x, top), Point2D::new(right, center.y)),
+                depth,
+            ),
+            Node::new(
+                Rect::new(Point2D::new(left, center.y), Point2D::new(center.x, bottom)),
+                depth,
+            ),
+            Node::new(Rect::new(center, Point2D::new(right, bottom)), depth),
+        ]));
+
+        // push down everything that now fits in a child
+        for (rect, item) in std::mem::take(&mut self.items) {
+            self.insert(rect, item);
+        }
+    }
+
+    fn query<'a, F: Fn(&Rect) -> bool>(&'a self, matches: &F, results: &mut Vec<&'a T>) {
+        let mut entries = vec![];
+        self.query_entries(matches, &mut entries);
+
+        results.extend(entries.into_iter().map(|(_, item)| item));
+    }
+
+    fn query_entries<'a, F: Fn(&Rect) -> bool>(
+        &'a self,
+        matches: &F,
+        results: &mut Vec<(&'a Rect, &'a T)>,
+    ) {
+        for (rect, item) in self.items.iter() {
+            if matches(rect) {
+                results.push((rect, item));
+            }
+        }
+
+        if let Some(ref children) = self.children {
+            for child in children.iter() {
+                if matches(&child.bounds) {
+                    child.query_entries(matches, results);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: f32, top: f32, right: f32, bottom: f32) -> Rect {
+        Rect::new(Point2D::new(left, top), Point2D::new(right, bottom))
+    }
+
+    /// A 10x10 grid of 10 unit cells, numbered row by row
+    fn grid() -> QuadTree<usize> {
+        QuadTree::from_items((0..100).map(|i| {
+            let (x, y) = ((i % 10) as f32 * 10.0, (i / 10) as f32 * 10.0);
+
+            (rect(x + 1.0, y + 1.0, x + 9.0, y + 9.0), i)
+        }))
+    }
+
+    fn sorted(mut items: Vec<&usize>) -> Vec<usize> {
+        items.sort();
+        items.into_iter().cloned().collect()
+    }
+
+    fn items_at(tree: &QuadTree<usize>, x: f32, y: f32) -> Vec<usize> {
+        sorted(
+            tree.query_point(&Point2D::new(x, y))
+                .into_iter()
+                .map(|(_, item)| item)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn inserting_splits_full_nodes() {
+        let tree = grid();
+
+        assert!(tree.root.bounds.approx_eq(&rect(1.0, 1.0, 99.0, 99.0)));
+        assert!(tree.root.children.is_some());
+        assert!(tree.root.items.len() <= NODE_CAPACITY);
+        assert_eq!(tree.query_rect(&tree.root.bounds).len(), 100);
+    }
+
+    #[test]
+    fn items_outside_of_the_bounds_stay_at_the_root() {
+        let mut tree = grid();
+        tree.insert(rect(500.0, 500.0, 510.0, 510.0), 100);
+
+        assert_eq!(items_at(&tree, 505.0, 505.0), vec![100]);
+        assert!(tree.root.items.iter().any(|(_, item)| *item == 100));
+    }
+
+    #[test]
+    fn point_queries_find_the_items_containing_the_point() {
+        let tree = grid();
+
+        assert_eq!(items_at(&tree, 35.0, 72.0), vec![73]);
+        // the gap between cells
+        assert!(items_at(&tree, 30.0, 70.0).is_empty());
+    }
+
+    #[test]
+    fn rect_queries_find_the_items_intersecting_the_rect() {
+        let tree = grid();
+
+        assert_eq!(
+            sorted(tree.query_rect(&rect(15.0, 5.0, 35.0, 15.0))),
+            vec![1, 2, 3, 11, 12, 13]
+        );
+        assert!(tree
+            .query_rect(&rect(200.0, 200.0, 300.0, 300.0))
+            .is_empty());
+    }
+
+    #[test]
+    fn smallest_at_picks_the_innermost_item() {
+        let mut tree = QuadTree::new(rect(0.0, 0.0, 100.0, 100.0));
+        tree.insert(rect(0.0, 0.0, 100.0, 100.0), "diagram");
+        tree.insert(rect(10.0, 10.0, 60.0, 60.0), "group");
+        tree.insert(rect(20.0, 20.0, 30.0, 30.0), "shape");
+
+        assert_eq!(tree.smallest_at(&Point2D::new(25.0, 25.0)), Some(&"shape"));
+        assert_eq!(tree.smallest_at(&Point2D::new(50.0, 50.0)), Some(&"group"));
+        assert_eq!(
+            tree.smallest_at(&Point2D::new(90.0, 90.0)),
+            Some(&"diagram")
+        );
+        assert_eq!(tree.smallest_at(&Point2D::new(150.0, 150.0)), None);
+    }
+}
@@ -0,0 +1,180 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use web_sys::{console, Element};
+
+use crate::js_utils::*;
+use crate::zoom::frame_loop::{schedule_frame, FrameClient};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QualityOptions {
+    pub enabled: bool,
+    /// Frames during a gesture that take longer than this count as slow
+    pub frame_budget_ms: f64,
+    /// How many slow frames in a row it takes to drop down a level
+    pub slow_frames: u32,
+    /// How long gestures have to stop before full quality comes back
+    pub restore_ms: f64,
+}
+
+impl Default for QualityOptions {
+    fn default() -> QualityOptions {
+        QualityOptions {
+            enabled: false,
+            frame_budget_ms: 1000.0 / 30.0,
+            slow_frames: 3,
+            restore_ms: 300.0,
+        }
+    }
+}
+
+/// What we give up to keep gestures moving, each level gives up everything the ones before it
+/// did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quality {
+    Full,
+    /// Culling waits until the gesture is over
+    SkipCulling,
+    /// Overlays only follow every few gesture updates
+    CoarseUpdates,
+    /// Text and detail are hidden until the gesture is over
+    Placeholder,
+}
+
+impl Quality {
+    fn lower(self) -> Quality {
+        match self {
+            Quality::Full => Quality::SkipCulling,
+            Quality::SkipCulling => Quality::CoarseUpdates,
+            Quality::CoarseUpdates | Quality::Placeholder => Quality::Placeholder,
+        }
+    }
+}
+
+/// Overlays hear one in this many gesture updates at `CoarseUpdates`
+static COARSE_STRIDE: u32 = 3;
+static PLACEHOLDER_CLASS: &str = "archizoom-degraded";
+
+/// Watches how long frames take while gestures are running, and lowers the quality a level
+/// at a time while they're over budget. Full quality comes back once the gestures stop.
+pub struct AdaptiveQuality {
+    inner: Rc<AdaptiveQualityInner>,
+}
+
+struct AdaptiveQualityInner {
+    options: QualityOptions,
+    container: Element,
+    quality: Cell<Quality>,
+    /// Set while we're watching frames
+    monitoring: Cell<bool>,
+    last_frame: Cell<Option<f64>>,
+    last_gesture: Cell<f64>,
+    slow_frames: Cell<u32>,
+    gesture_updates: Cell<u32>,
+    on_restored: RefCell<Option<Box<dyn Fn()>>>,
+}
+
+impl AdaptiveQuality {
+    pub fn new(container: &Element, options: &QualityOptions) -> AdaptiveQuality {
+        AdaptiveQuality {
+            inner: Rc::new(AdaptiveQualityInner {
+                options: options.clone(),
+                container: container.clone(),
+                quality: Cell::new(Quality::Full),
+                monitoring: Cell::new(false),
+                last_frame: Cell::new(None),
+                last_gesture: Cell::new(0.0),
+                slow_frames: Cell::new(0),
+                gesture_updates: Cell::new(0),
+                on_restored: RefCell::new(None),
+            }),
+        }
+    }
+
+    /// `callback` is called when full quality comes back, so whatever was put off can catch up
+    pub fn on_restored<F: Fn() + 'static>(&self, callback: F) {
+        self.inner.on_restored.replace(Some(Box::new(callback)));
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.inner.quality.get()
+    }
+
+    /// Call for every gesture update, starts watching frames if we aren't already
+    pub fn gesture(&self) {
+        let inner = &self.inner;
+        if !inner.options.enabled {
+            return;
+        }
+
+        inner.last_gesture.set(now());
+        inner
+            .gesture_updates
+            .set(inner.gesture_updates.get().wrapping_add(1));
+
+        if !inner.monitoring.replace(true) {
+            inner.last_frame.set(None);
+            inner.slow_frames.set(0);
+
+            let client: Rc<dyn FrameClient> = inner.clone();
+            schedule_frame(Rc::downgrade(&client));
+        }
+    }
+
+    /// Whether overlays should follow the latest gesture update at `CoarseUpdates`
+    pub fn updates_overlays(&self) -> bool {
+        self.inner
+            .gesture_updates
+            .get()
+            .is_multiple_of(COARSE_STRIDE)
+    }
+}
+
+impl AdaptiveQualityInner {
+    fn set_quality(&self, quality: Quality) {
+        self.quality.set(quality);
+
+        if let Err(error) = self
+            .container
+            .class_list()
+            .toggle_with_force(PLACEHOLDER_CLASS, quality == Quality::Placeholder)
+        {
+            console::warn_2(&"Couldn't toggle the placeholder rendering".into(), &error);
+        }
+    }
+}
+
+impl FrameClient for AdaptiveQualityInner {
+    fn frame(&self, time: f64) -> bool {
+        if let Some(last) = self.last_frame.replace(Some(time)) {
+            if time - last > self.options.frame_budget_ms {
+                let slow_frames = self.slow_frames.get() + 1;
+                if slow_frames >= self.options.slow_frames {
+                    self.set_quality(self.quality.get().lower());
+                    self.slow_frames.set(0);
+                } else {
+                    self.slow_frames.set(slow_frames);
+                }
+            } else {
+                self.slow_frames.set(0);
+            }
+        }
+
+        if now() - self.last_gesture.get() < self.options.restore_ms {
+            return true;
+        }
+
+        self.monitoring.set(false);
+        if self.quality.get() != Quality::Full {
+            self.set_quality(Quality::Full);
+
+            if let Some(ref on_restored) = *self.on_restored.borrow() {
+                on_restored();
+            }
+        }
+
+        false
+    }
+}
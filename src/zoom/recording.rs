@@ -0,0 +1,471 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use js_sys::{Function, Promise};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    console, Element, Event, KeyboardEvent, KeyboardEventInit, MouseEvent, PointerEvent,
+    PointerEventInit, WheelEvent, WheelEventInit,
+};
+
+use crate::js_utils::*;
+use crate::zoom::space::{ClientPoint, ScreenPoint, Transform};
+use crate::zoom::viewport::{ViewBox, Viewport};
+use crate::zoom::ArchiZoom;
+
+/// What `stopRecording()` hands back and `replay()` takes, everything a bug report needs to show
+/// us how the view moved. It's plain JSON, so it can be pasted into an issue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    pub diagram: String,
+    /// The viewer's size in pixels, replaying into a different size won't land on the same views
+    pub width: f32,
+    pub height: f32,
+    pub start: Option<ViewBox>,
+    pub inputs: Vec<RecordedInput>,
+    pub views: Vec<RecordedView>,
+}
+
+/// One event, anything left out was zero or false
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedInput {
+    /// Milliseconds since the recording started
+    pub t: u32,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Pixels from the top left of the viewer
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub x: f32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub y: f32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub pointer_id: i32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub pointer_type: String,
+    /// The second finger of a pinch
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub secondary: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub buttons: u16,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub delta_x: f64,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub delta_y: f64,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub delta_mode: u32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub key: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub ctrl: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub shift: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub alt: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub meta: bool,
+}
+
+/// Where the view ended up after the inputs before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedView {
+    pub t: u32,
+    pub view_box: ViewBox,
+    /// Set when we drilled into another diagram
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagram: Option<String>,
+}
+
+/// What `replay()` resolves to
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    pub inputs: usize,
+    /// Where the recording ended up and where the replay did, these differing is the bug
+    pub recorded: Option<ViewBox>,
+    pub replayed: Option<ViewBox>,
+}
+
+/// Pointers on browsers without pointer events aren't recorded, those are old enough that we
+/// wouldn't be able to replay into them anyway
+static RECORDED_EVENTS: [&str; 7] = [
+    "pointerdown",
+    "pointermove",
+    "pointerup",
+    "pointerleave",
+    "wheel",
+    "keydown",
+    "keyup",
+];
+
+/// Captures the input going into a viewer and the views that came out of it
+pub struct Recorder {
+    started: f64,
+    trace: Rc<RefCell<Trace>>,
+    _listeners: Vec<Box<dyn JsEventListener>>,
+}
+
+impl Recorder {
+    pub fn start(archizoom: &ArchiZoom) -> Result<Recorder, JsValue> {
+        let container = archizoom.container().clone();
+        let size = container.get_bounding_client_rect();
+        let trace = Rc::new(RefCell::new(Trace {
+            diagram: archizoom.url().to_string(),
+            width: size.width() as f32,
+            height: size.height() as f32,
+            start: archizoom
+                .viewport()
+                .map(|view| ViewBox::from(&view.view_box())),
+            inputs: vec![],
+            views: vec![],
+        }));
+        let started = now();
+
+        let mut listeners = vec![];
+        for event_type in RECORDED_EVENTS.iter() {
+            let trace = trace.clone();
+            let container = container.clone();
+            // capturing so we see the events before anything stops them
+            listeners.push(container.clone().new_capturing_event_listener(
+                event_type,
+                move |event: Event| {
+                    // our own replays shouldn't end up in the trace
+                    if !event.is_trusted() {
+                        return;
+                    }
+                    if let Some(input) = RecordedInput::new(&event, &container, now() - started) {
+                        trace.borrow_mut().inputs.push(input);
+                    }
+                },
+            )?);
+        }
+
+        Ok(Recorder {
+            started,
+            trace,
+            _listeners: listeners,
+        })
+    }
+
+    pub fn view_changed(&self, url: &str, view: &Viewport) {
+        let mut trace = self.trace.borrow_mut();
+        let last_diagram = trace
+            .views
+            .iter()
+            .rev()
+            .find_map(|view| view.diagram.clone())
+            .unwrap_or_else(|| trace.diagram.clone());
+
+        trace.views.push(RecordedView {
+            t: (now() - self.started) as u32,
+            view_box: ViewBox::from(&view.view_box()),
+            diagram: Some(url.to_string()).filter(|url| *url != last_diagram),
+        });
+    }
+
+    pub fn finish(self) -> Trace {
+        self.trace.borrow().clone()
+    }
+}
+
+impl RecordedInput {
+    fn new(event: &Event, container: &Element, t: f64) -> Option<RecordedInput> {
+        let mut input = RecordedInput {
+            t: t as u32,
+            event_type: event.type_(),
+            ..RecordedInput::default()
+        };
+
+        if let Some(event) = event.dyn_ref::<KeyboardEvent>() {
+            input.key = event.key();
+            input.ctrl = event.ctrl_key();
+            input.shift = event.shift_key();
+            input.alt = event.alt_key();
+            input.meta = event.meta_key();
+
+            return Some(input);
+        }
+
+        // the controls over the diagram aren't replayed, only what landed on it
+        let on_svg = event
+            .target()
+            .and_then(|target| target.dyn_into::<Element>().ok())
+            .and_then(|target| target.closest(".archizoom-diagram").ok().flatten())
+            .is_some();
+        if !on_svg {
+            return None;
+        }
+
+        let event = event.dyn_ref::<MouseEvent>()?;
+        let position = Transform::client_to_screen(container)
+            .inverse()?
+            .point(&ScreenPoint::point(
+                event.client_x() as f32,
+                event.client_y() as f32,
+            ))
+            .into_inner();
+        input.x = (position.x * 10.0).round() / 10.0;
+        input.y = (position.y * 10.0).round() / 10.0;
+        input.buttons = event.buttons();
+        input.ctrl = event.ctrl_key();
+        input.shift = event.shift_key();
+        input.alt = event.alt_key();
+        input.meta = event.meta_key();
+
+        if let Some(event) = event.dyn_ref::<PointerEvent>() {
+            input.pointer_id = event.pointer_id();
+            input.pointer_type = event.pointer_type();
+            input.secondary = !event.is_primary();
+        }
+        if let Some(event) = event.dyn_ref::<WheelEvent>() {
+            input.delta_x = event.delta_x();
+            input.delta_y = event.delta_y();
+            input.delta_mode = event.delta_mode();
+        }
+
+        Some(input)
+    }
+
+    /// Rebuilds the event with the pointer over the same spot of the viewer
+    fn to_event(&self, container: &Element) -> Result<Event, JsValue> {
+        let position = Transform::client_to_screen(container)
+            .point(&ClientPoint::point(self.x, self.y))
+            .into_inner();
+        let (client_x, client_y) = (position.x.round() as i32, position.y.round() as i32);
+
+        match self.event_type.as_str() {
+            "wheel" => {
+                let init = WheelEventInit::new();
+                init.set_bubbles(true);
+                init.set_cancelable(true);
+                init.set_client_x(client_x);
+                init.set_client_y(client_y);
+                init.set_buttons(self.buttons);
+                init.set_ctrl_key(self.ctrl);
+                init.set_shift_key(self.shift);
+                init.set_alt_key(self.alt);
+                init.set_meta_key(self.meta);
+                init.set_delta_x(self.delta_x);
+                init.set_delta_y(self.delta_y);
+                init.set_delta_mode(self.delta_mode);
+
+                Ok(WheelEvent::new_with_event_init_dict("wheel", &init)?.into())
+            }
+            "keydown" | "keyup" => {
+                let init = KeyboardEventInit::new();
+                init.set_bubbles(true);
+                init.set_cancelable(true);
+                init.set_key(&self.key);
+                init.set_ctrl_key(self.ctrl);
+                init.set_shift_key(self.shift);
+                init.set_alt_key(self.alt);
+                init.set_meta_key(self.meta);
+
+                Ok(
+                    KeyboardEvent::new_with_keyboard_event_init_dict(&self.event_type, &init)?
+                        .into(),
+                )
+            }
+            event_type => {
+                let init = PointerEventInit::new();
+                init.set_bubbles(true);
+                init.set_cancelable(true);
+                init.set_client_x(client_x);
+                init.set_client_y(client_y);
+                init.set_buttons(self.buttons);
+                init.set_ctrl_key(self.ctrl);
+                init.set_shift_key(self.shift);
+                init.set_alt_key(self.alt);
+                init.set_meta_key(self.meta);
+                init.set_pointer_id(self.pointer_id);
+                init.set_pointer_type(&self.pointer_type);
+                init.set_is_primary(!self.secondary);
+
+                Ok(PointerEvent::new_with_event_init_dict(event_type, &init)?.into())
+            }
+        }
+    }
+}
+
+/// Sends a trace's inputs back through the viewer at the pace they were recorded, the same
+/// synthetic events `benchmark()` uses
+struct Replay {
+    archizoom: Weak<RefCell<ArchiZoom>>,
+    trace: Trace,
+    started: Cell<Option<f64>>,
+    /// The next input to send
+    next: Cell<usize>,
+    resolve: Function,
+    on_frame: Closure<dyn FnMut(f64)>,
+}
+
+/// Replays `trace` into `archizoom`, from the view it started at when it's the same diagram,
+/// resolving to a `ReplayReport`
+pub fn replay(archizoom: Weak<RefCell<ArchiZoom>>, trace: Trace) -> Promise {
+    let mut trace = Some(trace);
+
+    Promise::new(&mut move |resolve, reject| {
+        let trace = match trace.take() {
+            Some(trace) => trace,
+            None => return,
+        };
+        let archizoom = archizoom.clone();
+
+        let replay = Rc::new_cyclic(|weak_ref: &Weak<Replay>| {
+            let frame_ref = weak_ref.clone();
+
+            Replay {
+                archizoom,
+                trace,
+                started: Cell::new(None),
+                next: Cell::new(0),
+                resolve,
+                on_frame: Closure::wrap(Box::new(move |time: f64| {
+                    if let Some(replay) = frame_ref.upgrade() {
+                        replay.frame(time);
+                    }
+                }) as Box<dyn FnMut(f64)>),
+            }
+        });
+
+        if let Err(error) = Replay::start(&replay) {
+            let _ = reject.call1(&JsValue::NULL, &error);
+        }
+    })
+}
+
+thread_local! {
+    /// Keeps the running replays alive between frames
+    static RUNNING: RefCell<Vec<Rc<Replay>>> = const { RefCell::new(vec![]) };
+}
+
+impl Replay {
+    fn start(replay: &Rc<Replay>) -> Result<(), JsValue> {
+        let archizoom = replay
+            .archizoom
+            .upgrade()
+            .ok_or_else(|| JsValue::from("The viewer is gone"))?;
+
+        {
+            let archizoom = archizoom.borrow();
+            let trace = &replay.trace;
+            if trace.diagram != archizoom.url() {
+                console::warn_2(
+                    &"This trace was recorded on another diagram".into(),
+                    &trace.diagram.as_str().into(),
+                );
+            } else if let (Some(start), Some(current)) = (&trace.start, archizoom.viewport()) {
+                let pixel_size = current.size.scale(current.scale);
+                archizoom.set_viewport(&Viewport::from_view_box(&start.into(), &pixel_size));
+            }
+
+            let size = archizoom.container().get_bounding_client_rect();
+            if (size.width() as f32 - trace.width).abs() > 1.0
+                || (size.height() as f32 - trace.height).abs() > 1.0
+            {
+                console::warn_1(
+                    &format!(
+                        "This trace was recorded at {}x{}, the views won't match exactly",
+                        trace.width, trace.height
+                    )
+                    .into(),
+                );
+            }
+        }
+
+        RUNNING.with(|running| running.borrow_mut().push(replay.clone()));
+        replay.request_frame()
+    }
+
+    fn request_frame(&self) -> Result<(), JsValue> {
+        window()
+            .request_animation_frame(self.on_frame.as_ref().unchecked_ref())
+            .map(|_| ())
+    }
+
+    fn frame(&self, time: f64) {
+        let started = self.started.get().unwrap_or(time);
+        self.started.set(Some(started));
+        let elapsed = (time - started) as u32;
+
+        let archizoom = match self.archizoom.upgrade() {
+            Some(archizoom) => archizoom,
+            None => return self.finish(None),
+        };
+        // the viewer can't be borrowed while its listeners run
+        let (container, svg) = {
+            let archizoom = archizoom.borrow();
+            (archizoom.container().clone(), archizoom.svg().clone())
+        };
+
+        let inputs = &self.trace.inputs;
+        while let Some(input) = inputs
+            .get(self.next.get())
+            .filter(|input| input.t <= elapsed)
+        {
+            self.next.set(self.next.get() + 1);
+
+            let target: &Element = if input.event_type.starts_with("key") {
+                &container
+            } else {
+                &svg
+            };
+            if let Err(error) = input
+                .to_event(&container)
+                .and_then(|event| target.dispatch_event(&event))
+            {
+                console::warn_2(&"Couldn't replay an event".into(), &error);
+            }
+        }
+
+        // let whatever the last input started settle for as long as it did in the recording
+        let end = self
+            .trace
+            .views
+            .last()
+            .map(|view| view.t)
+            .into_iter()
+            .chain(inputs.last().map(|input| input.t))
+            .max()
+            .unwrap_or(0);
+        if self.next.get() >= inputs.len() && elapsed >= end {
+            let replayed = archizoom.borrow().viewport();
+            return self.finish(replayed);
+        }
+
+        if let Err(error) = self.request_frame() {
+            console::error_2(&"Couldn't request a replay frame".into(), &error);
+            self.finish(None);
+        }
+    }
+
+    fn finish(&self, replayed: Option<Viewport>) {
+        let report = ReplayReport {
+            inputs: self.next.get(),
+            recorded: self.trace.views.last().map(|view| view.view_box.clone()),
+            replayed: replayed.map(|view| ViewBox::from(&view.view_box())),
+        };
+
+        match to_js(&report) {
+            Ok(report) => {
+                let _ = self.resolve.call1(&JsValue::NULL, &report);
+            }
+            Err(error) => console::error_2(&"Couldn't report the replay".into(), &error),
+        }
+
+        let this: *const Replay = self;
+        RUNNING.with(|running| {
+            running
+                .borrow_mut()
+                .retain(|replay| !std::ptr::eq(Rc::as_ptr(replay), this))
+        });
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use web_sys::{console, Storage};
+
+use crate::js_utils::*;
+use crate::zoom::matrix::Rect;
+use crate::zoom::view_link::parse_view_box;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RememberViewOptions {
+    pub enabled: bool,
+    /// How long the view has to stay put before it's saved
+    pub save_delay_ms: i32,
+}
+
+impl Default for RememberViewOptions {
+    fn default() -> RememberViewOptions {
+        RememberViewOptions {
+            enabled: false,
+            save_delay_ms: 500,
+        }
+    }
+}
+
+/// Keeps the last view of each diagram in localStorage, so coming back to the page picks up
+/// where the reader left off
+pub struct RememberedView {
+    /// The diagram and view waiting to be saved
+    pending: Rc<RefCell<Option<(String, Rect)>>>,
+    save: Timeout,
+    save_delay_ms: i32,
+}
+
+static KEY_PREFIX: &str = "archizoom-view:";
+
+impl RememberedView {
+    pub fn new(options: &RememberViewOptions) -> Option<RememberedView> {
+        if !options.enabled {
+            return None;
+        }
+
+        let pending: Rc<RefCell<Option<(String, Rect)>>> = Rc::new(RefCell::new(None));
+        let save = {
+            let pending = pending.clone();
+            Timeout::new(move || {
+                if let (Some((url, view_box)), Some(storage)) = (pending.take(), storage()) {
+                    let size = view_box.size();
+                    let value = format!(
+                        "{},{},{},{}",
+                        view_box.top_left.x, view_box.top_left.y, size.width, size.height
+                    );
+
+                    // full or blocked storage just means we won't remember this one
+                    if let Err(error) = storage.set_item(&key(&url), &value) {
+                        console::warn_2(&"Couldn't remember the view".into(), &error);
+                    }
+                }
+            })
+        };
+
+        Some(RememberedView {
+            pending,
+            save,
+            save_delay_ms: options.save_delay_ms,
+        })
+    }
+
+    /// The view `url` was left at last time, if we have one
+    pub fn load(&self, url: &str) -> Option<Rect> {
+        storage()?
+            .get_item(&key(url))
+            .ok()
+            .flatten()
+            .and_then(|value| parse_view_box(&value))
+    }
+
+    pub fn view_changed(&self, url: &str, view_box: Rect) {
+        self.pending.replace(Some((url.to_string(), view_box)));
+        self.save.restart(self.save_delay_ms);
+    }
+
+    pub fn forget(&self, url: &str) {
+        let pending_here =
+            matches!(*self.pending.borrow(), Some((ref pending, _)) if pending == url);
+        if pending_here {
+            self.save.cancel();
+            self.pending.replace(None);
+        }
+
+        if let Some(storage) = storage() {
+            if let Err(error) = storage.remove_item(&key(url)) {
+                console::warn_2(&"Couldn't forget the view".into(), &error);
+            }
+        }
+    }
+}
+
+fn key(url: &str) -> String {
+    format!("{}{}", KEY_PREFIX, url)
+}
+
+fn storage() -> Option<Storage> {
+    // reading localStorage throws when the page isn't allowed to use it
+    window().local_storage().ok().flatten()
+}
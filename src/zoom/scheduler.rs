@@ -0,0 +1,92 @@
+use std::cell::{Cell, RefCell};
+
+use crate::zoom::animation::ViewportAnimation;
+use crate::zoom::viewport::Viewport;
+
+/// Something that moves the viewport over several frames
+pub trait Animation {
+    /// Advances by `delta_ms` and returns the viewport for this frame
+    fn step(&mut self, delta_ms: f32) -> Viewport;
+
+    fn is_finished(&self) -> bool;
+}
+
+impl Animation for ViewportAnimation {
+    fn step(&mut self, delta_ms: f32) -> Viewport {
+        ViewportAnimation::step(self, delta_ms)
+    }
+
+    fn is_finished(&self) -> bool {
+        ViewportAnimation::is_finished(self)
+    }
+}
+
+/// Owns whatever animation is moving the viewport. Starting another animation or a gesture
+/// interrupts it, so there's only ever one of them writing the viewBox, once per frame.
+#[derive(Default)]
+pub struct AnimationScheduler {
+    animation: RefCell<Option<Box<dyn Animation>>>,
+    /// Set while a frame is requested, so there's never more than one loop running
+    frame_requested: Cell<bool>,
+    last_time: Cell<Option<f64>>,
+}
+
+/// Used for the first frame of an animation, when we don't have a previous frame to measure from
+static FRAME_MS: f64 = 1000.0 / 60.0;
+
+impl AnimationScheduler {
+    /// Replaces any running animation with `animation`. Returns true if a frame needs to be
+    /// requested to start running it.
+    pub fn start<A: Animation + 'static>(&self, animation: A) -> bool {
+        self.animation.replace(Some(Box::new(animation)));
+
+        !self.frame_requested.replace(true)
+    }
+
+    /// Stops the running animation where it is
+    pub fn cancel(&self) {
+        self.animation.replace(None);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.animation.borrow().is_some()
+    }
+
+    /// Steps the animation to `time`, returning the viewport to show this frame and whether
+    /// another frame is needed
+    pub fn frame(&self, time: f64) -> Option<(Viewport, bool)> {
+        let delta_ms = self
+            .last_time
+            .replace(Some(time))
+            .map(|last| time - last)
+            .unwrap_or(FRAME_MS) as f32;
+
+        let stepped = self
+            .animation
+            .borrow_mut()
+            .as_mut()
+            .map(|animation| (animation.step(delta_ms), animation.is_finished()));
+
+        match stepped {
+            Some((viewport, finished)) => {
+                if finished {
+                    self.animation.replace(None);
+                    self.stop();
+                }
+
+                Some((viewport, !finished))
+            }
+            None => {
+                // cancelled since the frame was requested
+                self.stop();
+
+                None
+            }
+        }
+    }
+
+    fn stop(&self) {
+        self.frame_requested.set(false);
+        self.last_time.set(None);
+    }
+}
@@ -0,0 +1,149 @@
+use wasm_bindgen::JsCast;
+use web_sys::{console, Element, SvgGraphicsElement, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::zoom::sprite::is_shown;
+
+/// The text of every label in a diagram, for finding elements by what they say
+pub struct SearchIndex {
+    entries: Vec<SearchEntry>,
+}
+
+struct SearchEntry {
+    /// Lowercased so searches are case insensitive
+    text: String,
+    element: SvgGraphicsElement,
+}
+
+/// The matches of a search and which one we're showing. Dropping the results clears their
+/// highlighting.
+pub struct SearchResults {
+    pub matches: Vec<SvgGraphicsElement>,
+    pub current: Option<usize>,
+}
+
+static MATCH_CLASS: &str = "archizoom-search-match";
+static CURRENT_CLASS: &str = "archizoom-search-current";
+
+impl SearchIndex {
+    pub fn new(svg: &SvgsvgElement) -> SearchIndex {
+        let entries = labels(svg)
+            .into_iter()
+            .map(|(text, element)| SearchEntry {
+                text: text.to_lowercase(),
+                element,
+            })
+            .collect();
+
+        SearchIndex { entries }
+    }
+
+    /// The elements whose labels contain `query`, in document order
+    pub fn find(&self, query: &str) -> Vec<SvgGraphicsElement> {
+        let query = query.trim().to_lowercase();
+        let mut matches: Vec<SvgGraphicsElement> = vec![];
+
+        for entry in self.entries.iter() {
+            // a shape with a multi-line label has an entry per line
+            if entry.text.contains(&query) && !matches.contains(&entry.element) {
+                matches.push(entry.element.clone());
+            }
+        }
+
+        matches
+    }
+}
+
+impl SearchResults {
+    pub fn new(matches: Vec<SvgGraphicsElement>) -> SearchResults {
+        for element in matches.iter() {
+            mark(element, MATCH_CLASS, true);
+        }
+
+        SearchResults {
+            matches,
+            current: None,
+        }
+    }
+
+    /// Moves to the next or previous match, wrapping around at the ends
+    pub fn step(&mut self, forward: bool) -> Option<&SvgGraphicsElement> {
+        let count = self.matches.len();
+        if count == 0 {
+            return None;
+        }
+
+        if let Some(previous) = self.current_match() {
+            mark(previous, CURRENT_CLASS, false);
+        }
+
+        let next = match self.current {
+            Some(current) if forward => (current + 1) % count,
+            Some(current) => (current + count - 1) % count,
+            None if forward => 0,
+            None => count - 1,
+        };
+        self.current = Some(next);
+
+        let current = &self.matches[next];
+        mark(current, CURRENT_CLASS, true);
+
+        Some(current)
+    }
+
+    pub fn current_match(&self) -> Option<&SvgGraphicsElement> {
+        self.current.and_then(|current| self.matches.get(current))
+    }
+}
+
+impl Drop for SearchResults {
+    fn drop(&mut self) {
+        for element in self.matches.iter() {
+            mark(element, MATCH_CLASS, false);
+            mark(element, CURRENT_CLASS, false);
+        }
+    }
+}
+
+/// Every line of text in a diagram and the shape it labels
+pub fn labels(svg: &SvgsvgElement) -> Vec<(String, SvgGraphicsElement)> {
+    svg.query_selector_all("text")
+        .map(|nodes| nodes.safe_filter::<SvgGraphicsElement>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|text_element| is_shown(text_element))
+        .filter_map(|text_element| {
+            let text = text_element.text_content()?.trim().to_string();
+            if text.is_empty() {
+                return None;
+            }
+
+            Some((text, labelled_element(svg, text_element)))
+        })
+        .collect()
+}
+
+/// The shape a label belongs to, its closest group with an id, or the label itself
+fn labelled_element(svg: &SvgsvgElement, text: SvgGraphicsElement) -> SvgGraphicsElement {
+    let root: &Element = svg.as_ref();
+
+    text.closest("g[id]")
+        .ok()
+        .and_then(|group| group)
+        .filter(|group| group != root)
+        .and_then(|group| group.dyn_into().ok())
+        .unwrap_or(text)
+}
+
+fn mark(element: &SvgGraphicsElement, class_name: &str, on: bool) {
+    let class_list = element.class_list();
+    let result = if on {
+        class_list.add_1(class_name)
+    } else {
+        class_list.remove_1(class_name)
+    };
+
+    if let Err(error) = result {
+        console::warn_2(&"Couldn't mark a search result".into(), &error);
+    }
+}
@@ -0,0 +1,198 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{console, Element, MouseEvent, SvgElement, SvgsvgElement};
+
+use crate::js_utils::*;
+use crate::zoom::bounds::find_elements;
+use crate::zoom::matrix::Point2D;
+use crate::zoom::metadata::ElementInfo;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SelectionOptions {
+    /// Select elements by clicking them, off by default. The selection API works either way.
+    pub enabled: bool,
+    /// Which elements can be selected, the closest match to the click wins
+    pub selector: String,
+    /// Deprecated, set `--archizoom-selection-filter` on the container instead. When it's set
+    /// it overrides that property.
+    pub filter: Option<String>,
+}
+
+impl Default for SelectionOptions {
+    fn default() -> SelectionOptions {
+        SelectionOptions {
+            enabled: false,
+            selector: "g[id]".to_string(),
+            filter: None,
+        }
+    }
+}
+
+/// The selected element of a diagram. Clicking an element selects it, clicking the background
+/// clears it, and every change fires an `archizoom-selectionchange` event on the container.
+pub struct Selection {
+    svg: SvgsvgElement,
+    container: Element,
+    options: SelectionOptions,
+    selected: RefCell<Option<SvgElement>>,
+    /// Where the last press started, so the click ending a pan doesn't change the selection
+    press_origin: Cell<Option<Point2D>>,
+    listeners: RefCell<Vec<Box<dyn JsEventListener>>>,
+}
+
+#[derive(Debug, Serialize)]
+struct SelectionDetail {
+    /// The id of the selected element, `null` once the selection is cleared
+    id: Option<String>,
+    element: Option<ElementInfo>,
+}
+
+static SELECTED_CLASS: &str = "archizoom-selected";
+static SELECTION_EVENT: &str = "archizoom-selectionchange";
+/// How far in CSS pixels the pointer can move between press and release and still be a click
+pub static CLICK_SLOP: f32 = 4.0;
+
+impl Selection {
+    pub fn new(
+        svg: &SvgsvgElement,
+        container: &Element,
+        options: &SelectionOptions,
+    ) -> Result<Rc<Selection>, JsValue> {
+        let selection = Rc::new(Selection {
+            svg: svg.clone(),
+            container: container.clone(),
+            options: options.clone(),
+            selected: RefCell::new(None),
+            press_origin: Cell::new(None),
+            listeners: RefCell::new(vec![]),
+        });
+
+        if options.enabled {
+            let down_ref = Rc::downgrade(&selection);
+            let click_ref = Rc::downgrade(&selection);
+
+            *selection.listeners.borrow_mut() = vec![
+                svg.new_event_listener("mousedown", move |event: MouseEvent| {
+                    if let Some(selection) = down_ref.upgrade() {
+                        selection.press_origin.set(Some(client_position(&event)));
+                    }
+                })?,
+                svg.new_event_listener("click", move |event: MouseEvent| {
+                    if let Some(selection) = click_ref.upgrade() {
+                        selection.on_click(event);
+                    }
+                })?,
+            ];
+        }
+
+        Ok(selection)
+    }
+
+    fn on_click(&self, event: MouseEvent) {
+        let moved = self
+            .press_origin
+            .take()
+            .map(|origin| (&client_position(&event) - &origin).length())
+            .unwrap_or(0.0);
+        if moved > CLICK_SLOP {
+            return;
+        }
+
+        let target = event
+            .target()
+            .and_then(|target| target.dyn_into::<Element>().ok())
+            .and_then(|target| self.selectable(target));
+
+        self.change(target);
+    }
+
+    /// The closest element to `target` matching our selector, inside of the svg
+    fn selectable(&self, target: Element) -> Option<SvgElement> {
+        let root: &Element = self.svg.as_ref();
+
+        target
+            .closest(&self.options.selector)
+            .ok()
+            .and_then(|element| element)
+            .filter(|element| element != root && root.contains(Some(element)))
+            .and_then(|element| element.dyn_into().ok())
+    }
+
+    /// The id of the selected element
+    pub fn selected_id(&self) -> Option<String> {
+        self.selected
+            .borrow()
+            .as_ref()
+            .map(|selected| selected.id())
+            .filter(|id| !id.is_empty())
+    }
+
+    /// Selects the first element matching `target`, an id or selector, or clears the selection
+    /// for `None`. Returns false if nothing matched.
+    pub fn select(&self, target: Option<&str>) -> bool {
+        match target {
+            Some(target) => match find_elements(&self.svg, target).into_iter().next() {
+                Some(element) => {
+                    self.change(Some(element.unchecked_into()));
+
+                    true
+                }
+                None => false,
+            },
+            None => {
+                self.change(None);
+
+                true
+            }
+        }
+    }
+
+    fn change(&self, target: Option<SvgElement>) {
+        let unchanged = match (&*self.selected.borrow(), &target) {
+            (Some(selected), Some(target)) => selected == target,
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+
+        if let Err(error) = self.mark(target) {
+            console::warn_2(&"Couldn't change the selection".into(), &error);
+        }
+    }
+
+    fn mark(&self, target: Option<SvgElement>) -> Result<(), JsValue> {
+        if let Some(previous) = self.selected.replace(None) {
+            previous.class_list().remove_1(SELECTED_CLASS)?;
+        }
+
+        // the stylesheet marks the selection, see --archizoom-selection-filter
+        if let Some(target) = target {
+            target.class_list().add_1(SELECTED_CLASS)?;
+
+            self.selected.replace(Some(target));
+        }
+
+        dispatch_custom_event(
+            &self.container,
+            SELECTION_EVENT,
+            &SelectionDetail {
+                id: self.selected_id(),
+                element: self
+                    .selected
+                    .borrow()
+                    .as_ref()
+                    .map(|selected| ElementInfo::read(selected)),
+            },
+        )
+    }
+}
+
+pub fn client_position(event: &MouseEvent) -> Point2D {
+    Point2D::new(event.client_x() as f32, event.client_y() as f32)
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use web_sys::{console, Element, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::PREFIX_ALIAS;
+
+/// A class that's on the svg root while the zoom level is at least `min_zoom`, so diagrams can
+/// style fine detail to only show up when zoomed in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoomBreakpoint {
+    pub class_name: String,
+    /// Relative to the fitted view, 1.0 is fitted
+    pub min_zoom: f32,
+}
+
+/// A simpler rendering for when the diagram is too small to read. Below `max_zoom` the svg root
+/// gets the `archizoom-overview` class, which hides anything marked `data-archizoom-detail` and
+/// shows anything marked `data-archizoom-overview`, so diagrams can ship their own placeholders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OverviewOptions {
+    /// Relative to the fitted view, 1.0 is fitted
+    pub max_zoom: f32,
+    /// Also treat everything matching `detail_selector` as detail, for diagrams that don't mark
+    /// their own
+    pub derive: bool,
+    pub detail_selector: String,
+}
+
+impl Default for OverviewOptions {
+    fn default() -> OverviewOptions {
+        OverviewOptions {
+            max_zoom: 0.5,
+            derive: false,
+            detail_selector: "text, line, polyline, path[fill=\"none\"]".to_string(),
+        }
+    }
+}
+
+/// Toggles the breakpoint classes of a diagram as its zoom level changes
+pub struct SemanticZoom {
+    svg: SvgsvgElement,
+    breakpoints: Vec<ZoomBreakpoint>,
+    overview_zoom: f32,
+}
+
+static OVERVIEW_CLASS: &str = "archizoom-overview";
+static DETAIL_CLASS: &str = "archizoom-detail";
+
+impl SemanticZoom {
+    /// Combines the `configured` breakpoints with any the diagram declares on its groups as
+    /// `data-archizoom-breakpoints="detail:2, labels:0.5"`
+    pub fn new(
+        svg: &SvgsvgElement,
+        configured: &[ZoomBreakpoint],
+        overview: &OverviewOptions,
+    ) -> SemanticZoom {
+        let attribute = format!("data-{}-breakpoints", PREFIX_ALIAS);
+
+        let mut breakpoints = configured.to_vec();
+        let declared = svg
+            .query_selector_all(&format!("[{}]", attribute))
+            .map(|nodes| nodes.safe_filter::<Element>())
+            .unwrap_or_default();
+        for element in declared.iter() {
+            if let Some(value) = element.get_attribute(&attribute) {
+                breakpoints.extend(value.split(',').filter_map(parse_breakpoint));
+            }
+        }
+
+        if overview.derive {
+            mark_detail(svg, &overview.detail_selector);
+        }
+
+        let semantic_zoom = SemanticZoom {
+            svg: svg.clone(),
+            breakpoints,
+            overview_zoom: overview.max_zoom,
+        };
+        semantic_zoom.update(1.0);
+
+        semantic_zoom
+    }
+
+    pub fn update(&self, zoom: f32) {
+        let class_list = self.svg.class_list();
+
+        for breakpoint in self.breakpoints.iter() {
+            if let Err(error) =
+                class_list.toggle_with_force(&breakpoint.class_name, zoom >= breakpoint.min_zoom)
+            {
+                console::warn_2(
+                    &format!("Couldn't toggle {}", breakpoint.class_name).into(),
+                    &error,
+                );
+            }
+        }
+
+        if let Err(error) = class_list.toggle_with_force(OVERVIEW_CLASS, zoom < self.overview_zoom)
+        {
+            console::warn_2(&"Couldn't toggle the overview".into(), &error);
+        }
+    }
+}
+
+/// Tags everything matching `selector` as detail that the overview hides
+fn mark_detail(svg: &SvgsvgElement, selector: &str) {
+    let details = match svg.query_selector_all(selector) {
+        Ok(details) => details.safe_filter::<Element>(),
+        Err(error) => {
+            console::warn_2(&"Invalid overview detail selector".into(), &error);
+
+            return;
+        }
+    };
+
+    for detail in details.iter() {
+        if let Err(error) = detail.class_list().add_1(DETAIL_CLASS) {
+            console::warn_2(&"Couldn't mark overview detail".into(), &error);
+        }
+    }
+}
+
+/// Parses `class:min_zoom`
+fn parse_breakpoint(value: &str) -> Option<ZoomBreakpoint> {
+    let mut parts = value.trim().splitn(2, ':');
+
+    match (
+        parts.next(),
+        parts.next().map(|zoom| zoom.trim().parse::<f32>()),
+    ) {
+        (Some(class_name), Some(Ok(min_zoom))) if !class_name.trim().is_empty() => {
+            Some(ZoomBreakpoint {
+                class_name: class_name.trim().to_string(),
+                min_zoom,
+            })
+        }
+        _ => {
+            console::warn_1(&format!("Invalid zoom breakpoint: {}", value).into());
+
+            None
+        }
+    }
+}
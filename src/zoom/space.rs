@@ -0,0 +1,204 @@
+use std::fmt::{Display, Formatter, Result};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use wasm_bindgen::JsCast;
+use web_sys::{DomRect, Element, HtmlElement, SvgGraphicsElement};
+
+use crate::diagnostics;
+use crate::js_utils::device_pixel_ratio;
+use crate::zoom::matrix::{Matrix2D, Point2D, Rect, Size2D};
+
+/// CSS pixels relative to the top left of an element's box
+#[derive(Debug, Clone, Copy)]
+pub enum ClientSpace {}
+
+/// CSS pixels relative to the browser viewport. This is what `clientX`/`clientY`,
+/// `getBoundingClientRect`, and `getScreenCTM` all use.
+#[derive(Debug, Clone, Copy)]
+pub enum ScreenSpace {}
+
+/// Device pixels relative to the top left of an element's box, what a canvas or exported image
+/// needs to be sized in to stay crisp on HiDPI displays
+#[derive(Debug, Clone, Copy)]
+pub enum RasterSpace {}
+
+/// Svg User Coordinates, the units the diagram itself is drawn in
+#[derive(Debug, Clone, Copy)]
+pub enum SvgUserSpace {}
+
+/// A value tagged with the coordinate space it's in, so values from different spaces can't be
+/// mixed up without an explicit `Transform`
+#[derive(Debug, Clone)]
+pub struct InSpace<S, T> {
+    value: T,
+    space: PhantomData<S>,
+}
+
+pub type ClientPoint = InSpace<ClientSpace, Point2D>;
+pub type ClientRect = InSpace<ClientSpace, Rect>;
+pub type ScreenPoint = InSpace<ScreenSpace, Point2D>;
+pub type ScreenRect = InSpace<ScreenSpace, Rect>;
+pub type UserPoint = InSpace<SvgUserSpace, Point2D>;
+pub type UserRect = InSpace<SvgUserSpace, Rect>;
+
+impl<S, T> InSpace<S, T> {
+    #[inline]
+    pub fn new(value: T) -> InSpace<S, T> {
+        InSpace {
+            value,
+            space: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<S> InSpace<S, Point2D> {
+    #[inline]
+    pub fn point(x: f32, y: f32) -> InSpace<S, Point2D> {
+        InSpace::new(Point2D::new(x, y))
+    }
+
+    /// The offset from `other` to this point, which is only meaningful within the same space
+    #[inline]
+    pub fn delta_from(&self, other: &InSpace<S, Point2D>) -> Point2D {
+        &self.value - &other.value
+    }
+}
+
+impl<S> InSpace<S, Rect> {
+    #[inline]
+    pub fn intersects(&self, other: &InSpace<S, Rect>) -> bool {
+        self.value.intersects(&other.value)
+    }
+
+    #[inline]
+    pub fn union(&self, other: &InSpace<S, Rect>) -> InSpace<S, Rect> {
+        InSpace::new(self.value.union(&other.value))
+    }
+}
+
+impl ScreenRect {
+    pub fn from_dom_rect(dom_rect: &DomRect) -> ScreenRect {
+        InSpace::new(Rect::new(
+            Point2D::new(dom_rect.left() as f32, dom_rect.top() as f32),
+            Point2D::new(dom_rect.right() as f32, dom_rect.bottom() as f32),
+        ))
+    }
+}
+
+impl<S, T> Deref for InSpace<S, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<S, T: Display> Display for InSpace<S, T> {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A transform that takes values from the `From` space to the `To` space
+#[derive(Debug, Clone)]
+pub struct Transform<From, To> {
+    matrix: Matrix2D,
+    spaces: PhantomData<(From, To)>,
+}
+
+impl<From, To> Transform<From, To> {
+    #[inline]
+    pub fn new(matrix: Matrix2D) -> Transform<From, To> {
+        Transform {
+            matrix,
+            spaces: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn matrix(&self) -> &Matrix2D {
+        &self.matrix
+    }
+
+    #[inline]
+    pub fn point(&self, point: &InSpace<From, Point2D>) -> InSpace<To, Point2D> {
+        InSpace::new(point.value.matrix_transform(&self.matrix))
+    }
+
+    #[inline]
+    pub fn rect(&self, rect: &InSpace<From, Rect>) -> InSpace<To, Rect> {
+        InSpace::new(rect.value.transformed_bounds(&self.matrix))
+    }
+
+    pub fn inverse(&self) -> Option<Transform<To, From>> {
+        self.matrix.inverse().map(Transform::new)
+    }
+
+    /// This transform followed by `next`
+    pub fn then<Next>(&self, next: &Transform<To, Next>) -> Transform<From, Next> {
+        Transform::new(next.matrix.multiply(&self.matrix))
+    }
+}
+
+impl Transform<SvgUserSpace, ScreenSpace> {
+    /// The user space of `element` to the screen, via its screen CTM
+    pub fn screen_ctm(
+        element: &SvgGraphicsElement,
+    ) -> Option<Transform<SvgUserSpace, ScreenSpace>> {
+        diagnostics::layout_read();
+        element
+            .get_screen_ctm()
+            .map(|matrix| Transform::new(Matrix2D::from_js(&matrix)))
+    }
+}
+
+impl Transform<ClientSpace, ScreenSpace> {
+    /// The box of `element` to the screen. A CSS transform on one of its ancestors, like a
+    /// scaled modal, makes the box's pixels bigger or smaller than the screen's.
+    pub fn client_to_screen(element: &Element) -> Transform<ClientSpace, ScreenSpace> {
+        diagnostics::layout_read();
+        let client_rect = element.get_bounding_client_rect();
+
+        // the bounding rect is after any transforms and the offset size is before them
+        let (scale_x, scale_y) = match element.dyn_ref::<HtmlElement>() {
+            Some(element) if element.offset_width() > 0 && element.offset_height() > 0 => (
+                client_rect.width() as f32 / element.offset_width() as f32,
+                client_rect.height() as f32 / element.offset_height() as f32,
+            ),
+            _ => (1.0, 1.0),
+        };
+
+        Transform::new(Matrix2D::new(
+            scale_x,
+            0.0,
+            0.0,
+            scale_y,
+            client_rect.left() as f32,
+            client_rect.top() as f32,
+        ))
+    }
+}
+
+impl Transform<ClientSpace, RasterSpace> {
+    /// CSS pixels to device pixels at the current `devicePixelRatio`
+    pub fn device_pixels() -> Transform<ClientSpace, RasterSpace> {
+        Transform::new(Matrix2D::scaling(device_pixel_ratio()))
+    }
+}
+
+/// The whole number of device pixels needed to back `size` CSS pixels, e.g. for a canvas
+pub fn raster_size(size: &Size2D) -> (u32, u32) {
+    let ratio = device_pixel_ratio();
+
+    (
+        (size.width * ratio).round().max(1.0) as u32,
+        (size.height * ratio).round().max(1.0) as u32,
+    )
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+/// A damped spring pulling a value towards a target.
+///
+/// A spring with no stiffness only has friction, which is what momentum decay uses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Spring {
+    pub stiffness: f32,
+    pub damping: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpringState {
+    pub position: f32,
+    pub velocity: f32,
+}
+
+/// The largest step in seconds we integrate at once, larger frames are split up to stay stable
+static MAX_STEP: f32 = 1.0 / 120.0;
+static REST_EPSILON: f32 = 1e-3;
+
+impl Spring {
+    #[inline]
+    pub fn new(stiffness: f32, damping: f32) -> Spring {
+        Spring { stiffness, damping }
+    }
+
+    /// A spring that reaches its target as fast as possible without overshooting
+    #[inline]
+    pub fn critically_damped(stiffness: f32) -> Spring {
+        Spring::new(stiffness, 2.0 * stiffness.sqrt())
+    }
+
+    /// A spring without stiffness whose velocity decays by `friction` per second
+    #[inline]
+    pub fn friction(friction: f32) -> Spring {
+        Spring::new(0.0, friction)
+    }
+
+    /// Where a spring starting at `position` begins. Springs with stiffness pull themselves to
+    /// `target` from rest, a spring with only friction has to be thrown so it coasts to a stop
+    /// there instead of never moving.
+    pub fn start(&self, position: f32, target: f32) -> SpringState {
+        let velocity = if self.stiffness == 0.0 {
+            self.damping * (target - position)
+        } else {
+            0.0
+        };
+
+        SpringState { position, velocity }
+    }
+
+    /// Advances `state` towards `target` by `delta_seconds`
+    pub fn step(&self, state: &SpringState, target: f32, delta_seconds: f32) -> SpringState {
+        if self.stiffness == 0.0 && self.damping > 0.0 {
+            // friction alone has an exact solution, stepping it would coast short of the target
+            let decay = (-self.damping * delta_seconds.max(0.0)).exp();
+
+            return SpringState {
+                position: state.position + (state.velocity * (1.0 - decay) / self.damping),
+                velocity: state.velocity * decay,
+            };
+        }
+
+        let mut position = state.position;
+        let mut velocity = state.velocity;
+
+        let mut remaining = delta_seconds.max(0.0);
+        while remaining > 0.0 {
+            let dt = remaining.min(MAX_STEP);
+
+            let acceleration = (self.stiffness * (target - position)) - (self.damping * velocity);
+            velocity += acceleration * dt;
+            position += velocity * dt;
+
+            remaining -= dt;
+        }
+
+        SpringState { position, velocity }
+    }
+
+    /// Whether `state` has settled, for a spring without stiffness this is when it stopped moving
+    pub fn is_at_rest(&self, state: &SpringState, target: f32) -> bool {
+        state.velocity.abs() < REST_EPSILON
+            && (self.stiffness == 0.0 || (target - state.position).abs() < REST_EPSILON)
+    }
+}
+
+impl Default for Spring {
+    fn default() -> Spring {
+        Spring::critically_damped(170.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settle(spring: &Spring, target: f32) -> (SpringState, usize) {
+        let mut state = spring.start(0.0, target);
+        let mut frames = 0;
+        while !spring.is_at_rest(&state, target) && frames < 10_000 {
+            state = spring.step(&state, target, 1.0 / 60.0);
+            frames += 1;
+        }
+
+        (state, frames)
+    }
+
+    #[test]
+    fn friction_only_springs_coast_to_the_target() {
+        let spring = Spring::friction(4.0);
+        assert!(!spring.is_at_rest(&spring.start(0.0, 1.0), 1.0));
+
+        let (state, frames) = settle(&spring, 1.0);
+        assert!(frames > 1 && frames < 10_000);
+        assert!((state.position - 1.0).abs() < REST_EPSILON);
+    }
+
+    #[test]
+    fn stiff_springs_settle_on_the_target() {
+        let spring = Spring::default();
+        assert_eq!(spring.start(0.0, 1.0).velocity, 0.0);
+
+        let (state, frames) = settle(&spring, 1.0);
+        assert!(frames > 1 && frames < 10_000);
+        assert!((state.position - 1.0).abs() < REST_EPSILON);
+    }
+}
@@ -0,0 +1,95 @@
+use wasm_bindgen::JsValue;
+use web_sys::{Element, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::PREFIX_ALIAS;
+
+/// A single svg can hold several diagrams as top level groups marked with
+/// `data-archizoom-diagram`, for reports exported to one file. Each is opened as
+/// `file.svg#group-id` and links between them look like `#archizoom:link:group-id`. The value
+/// of the attribute is the diagram's title, and `data-archizoom-view-box` its initial view.
+/// Every group stays in the one parsed svg, the ones that aren't open are hidden with `display`.
+pub struct SpriteGroup {
+    pub title: Option<String>,
+    /// The group didn't say what its view box is, so it's framed once it's on screen
+    pub fit: bool,
+}
+
+static GROUP_SELECTOR: &str = "[data-archizoom-diagram]";
+static TITLE_ATTRIBUTE: &str = "data-archizoom-diagram";
+static VIEW_BOX_ATTRIBUTE: &str = "data-archizoom-view-box";
+static HIDDEN_SELECTOR: &str = "[data-archizoom-diagram][display=\"none\"]";
+
+/// Splits a diagram url into the file and the group it opens, if any
+pub fn split_url(url: &str) -> (&str, Option<&str>) {
+    match url.split_once('#') {
+        Some((file, group)) if !group.is_empty() => (file, Some(group)),
+        Some((file, _)) => (file, None),
+        None => (url, None),
+    }
+}
+
+/// The group a zoom link's fragment points at, `group-id` of `#archizoom:link:group-id`
+pub fn link_group(href: &str) -> Option<&str> {
+    let (_, fragment) = href.split_once('#')?;
+
+    fragment
+        .strip_prefix(PREFIX_ALIAS)
+        .and_then(|rest| rest.strip_prefix(":link:"))
+        .filter(|group| !group.is_empty())
+}
+
+/// Whether `svg` is a sprite that `url` opens a group of, so it can be switched to without
+/// loading the file again
+pub fn has_group(svg: &SvgsvgElement, url: &str) -> bool {
+    match split_url(url).1 {
+        Some(id) => svg
+            .get_element_by_id(id)
+            .is_some_and(|group| group.has_attribute(TITLE_ATTRIBUTE)),
+        None => svg.query_selector(GROUP_SELECTOR).ok().flatten().is_some(),
+    }
+}
+
+/// Whether `element` is outside of the sprite groups that are hidden
+pub fn is_shown(element: &Element) -> bool {
+    element.closest(HIDDEN_SELECTOR).ok().flatten().is_none()
+}
+
+/// Shows the group `url` opens, the first group when it doesn't name one, and hides the rest.
+/// Does nothing to an svg that isn't a sprite.
+pub fn show_group(svg: &SvgsvgElement, url: &str) -> Result<Option<SpriteGroup>, JsValue> {
+    let groups = svg
+        .query_selector_all(GROUP_SELECTOR)?
+        .safe_filter::<Element>();
+    if groups.is_empty() {
+        return Ok(None);
+    }
+
+    let wanted = split_url(url).1;
+    let shown = wanted
+        .and_then(|id| groups.iter().find(|group| group.id() == id))
+        .unwrap_or(&groups[0])
+        .clone();
+
+    // search, links, and layers skip what's hidden, see `is_shown`
+    for group in groups.iter() {
+        if group.contains(Some(&shown)) {
+            group.remove_attribute("display")?;
+        } else {
+            group.set_attribute("display", "none")?;
+        }
+    }
+
+    let view_box = shown.get_attribute(VIEW_BOX_ATTRIBUTE);
+    if let Some(ref view_box) = view_box {
+        svg.set_attribute("viewBox", view_box)?;
+    }
+
+    Ok(Some(SpriteGroup {
+        title: shown
+            .get_attribute(TITLE_ATTRIBUTE)
+            .filter(|title| !title.trim().is_empty())
+            .or_else(|| Some(shown.id()).filter(|id| !id.is_empty())),
+        fit: view_box.is_none(),
+    }))
+}
@@ -1,299 +1,695 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
 
-use wasm_bindgen::convert::FromWasmAbi;
-use wasm_bindgen::JsValue;
-use web_sys::{Event, MouseEvent, PointerEvent, SvgPoint, SvgsvgElement, TouchEvent, WheelEvent};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    console, Event, MouseEvent, PointerEvent, SvgElement, SvgsvgElement, TouchEvent, WheelEvent,
+};
 
+use crate::diagnostics;
 use crate::events::{EventListener, EventSource};
-use crate::js_utils::{EnhancedEventTarget, JsEventListener};
-use crate::zoom::matrix::{Point2D, Rect};
+use crate::js_utils::{
+    device_pixel_ratio, document, now, EnhancedDocument, EnhancedEventTarget, JsEventListener,
+    Timeout, SVG_NS,
+};
+use crate::leak_check::CountedListener;
+use crate::options::Options;
+use crate::zoom::delegation::delegate_events;
+use crate::zoom::frame_loop::{schedule_frame, FrameClient};
+use crate::zoom::geometry::ScreenGeometry;
+use crate::zoom::matrix::{approx_eq, Point2D, Rect};
+use crate::zoom::momentum::FlingTracker;
+use crate::zoom::progressive::NON_PAINTING_TAGS;
+use crate::zoom::space::{ScreenPoint, ScreenRect, UserPoint};
+use crate::zoom::viewport::Viewport;
+
+/// When the wheel is ours to zoom with rather than the page's to scroll
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreventDefaultPolicy {
+    /// Every wheel event zooms the diagram
+    #[default]
+    Always,
+    /// Only ctrl/cmd + wheel and trackpad pinches zoom, plain scrolling scrolls the page
+    WhenZooming,
+    /// Only zoom while focus is inside the viewer, e.g. after it's been clicked
+    WhenActive,
+}
+
+/// How the diagram is redrawn while a pan or zoom is in progress
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GestureRendering {
+    /// Every move rewrites the viewBox, so the browser draws the diagram again each time
+    #[default]
+    ViewBox,
+    /// Moves transform a group around the content with CSS, which the browser can composite
+    /// without redrawing it. The viewBox is only written when the gesture ends.
+    CssTransform,
+}
+
+/// How gestures zoom the view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ZoomOptions {
+    /// How much each pixel of wheel scrolling zooms the view
+    pub wheel_factor: f32,
+    /// How much of the view a zoom element has to fill, from 0 to 1, before zooming in opens the
+    /// diagram it links to
+    pub open_threshold: f32,
+}
+
+impl Default for ZoomOptions {
+    fn default() -> ZoomOptions {
+        ZoomOptions {
+            wheel_factor: 0.003,
+            open_threshold: 0.45,
+        }
+    }
+}
+
+impl ZoomOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.wheel_factor <= 0.0 {
+            return Err("zoom.wheelFactor has to be more than 0".to_string());
+        }
+        if self.open_threshold <= 0.0 || self.open_threshold > 1.0 {
+            return Err("zoom.openThreshold has to be more than 0 and at most 1".to_string());
+        }
+
+        Ok(())
+    }
+}
 
 pub struct SvgViewController {
     svg: SvgsvgElement,
+    geometry: Rc<ScreenGeometry>,
+    pixel_snapping: Cell<bool>,
+    prevent_default: Cell<PreventDefaultPolicy>,
+    wheel_factor: Cell<f32>,
+    /// Ignores new gestures, the one in progress still ends when the pointer comes up
+    locked: Cell<bool>,
+    /// Pointer events don't scroll, `touch-action` takes care of that, so only the mouse and
+    /// touch fallbacks have to cancel their moves
+    cancel_moves: bool,
+    /// The viewBox the svg was loaded with, zoom levels are relative to fitting this
+    fitted_view_box: RefCell<Option<Rect>>,
 
     is_pointer_down: bool,
-    pointer_origin: SvgPoint,
-
-    listeners: Vec<Box<EventListener<ViewUpdateEvent>>>,
-    event_listeners: Vec<Box<JsEventListener>>,
+    pointer_origin: UserPoint,
+    /// The latest position the pointer moved to, applied once per frame
+    pending_move: RefCell<Option<ScreenPoint>>,
+    /// How fast the drag is moving the view, for the momentum when it's let go
+    fling: RefCell<FlingTracker>,
+
+    gesture_rendering: Cell<GestureRendering>,
+    /// Wraps the content once `CssTransform` mode is used, it stays wrapped if it's switched off
+    content: RefCell<Option<SvgElement>>,
+    /// Where a `CssTransform` gesture has moved the view to, ahead of the viewBox
+    gesture_view: RefCell<Option<Viewport>>,
+    /// Ends a `CssTransform` wheel gesture once the wheel has been still for a moment
+    wheel_end: Option<Timeout>,
+
+    listeners: Vec<Box<dyn EventListener<ViewUpdateEvent>>>,
+    event_listeners: Vec<Box<dyn JsEventListener>>,
 }
 
 #[derive(Debug)]
 pub struct ViewUpdateEvent {
-    /// The coordinates in Svg Viewport Coordinates in pixels
-    viewport: Rect,
+    /// The on screen bounds of the svg
+    viewport: ScreenRect,
+    /// The visible region in Svg User Coordinates
+    view: Option<Viewport>,
+    /// The zoom level relative to the fitted view, 1.0 is fitted
+    zoom: f32,
+    /// Whether the user caused this update by panning or zooming, rather than our own api
+    gesture: bool,
+    /// How fast a drag was moving the view when it was let go, in Svg User Coordinates per second
+    fling: Option<Point2D>,
 }
 
-static ZOOM_FACTOR: f32 = 0.003;
+static WHEEL_END_MS: i32 = 150;
+static CONTENT_CLASS: &str = "archizoom-content";
 
 impl SvgViewController {
-    pub fn new(svg: &SvgsvgElement) -> Result<Rc<RefCell<SvgViewController>>, JsValue> {
+    pub fn new(
+        svg: &SvgsvgElement,
+        geometry: &Rc<ScreenGeometry>,
+        options: &Options,
+    ) -> Result<Rc<RefCell<SvgViewController>>, JsValue> {
         let view_controller = Rc::new(RefCell::new(SvgViewController {
-            pointer_origin: svg.create_svg_point(),
+            pointer_origin: UserPoint::point(0.0, 0.0),
             svg: svg.clone(),
+            geometry: geometry.clone(),
+            pixel_snapping: Cell::new(options.pixel_snapping),
+            prevent_default: Cell::new(options.prevent_default),
+            wheel_factor: Cell::new(options.zoom.wheel_factor),
+            locked: Cell::new(options.locked),
+            cancel_moves: PointerEvent::new("pointerdown").is_err(),
+            fitted_view_box: RefCell::new(
+                svg.view_box()
+                    .base_val()
+                    .map(|view_box| Rect::from_svg(&view_box)),
+            ),
             is_pointer_down: false,
+            pending_move: RefCell::new(None),
+            fling: RefCell::new(FlingTracker::default()),
+            gesture_rendering: Cell::new(options.gesture_rendering),
+            content: RefCell::new(None),
+            gesture_view: RefCell::new(None),
+            wheel_end: None,
             listeners: vec![],
             event_listeners: vec![],
         }));
 
-        get_drag_events(&view_controller)?;
-        register_scroll_events(&view_controller)?;
+        // made either way since `gestureRendering` can change while the viewer is running
+        let weak_ref = Rc::downgrade(&view_controller);
+        view_controller.borrow_mut().wheel_end = Some(Timeout::new(move || {
+            if let Some(real_ref) = weak_ref.upgrade() {
+                real_ref.borrow().end_wheel_gesture();
+            }
+        }));
+
+        if options.delegate_events {
+            let registration = delegate_events(&view_controller)?;
+            view_controller
+                .borrow_mut()
+                .event_listeners
+                .push(registration);
+        } else {
+            register_events(&view_controller)?;
+        }
 
         Ok(view_controller)
     }
 
-    fn on_pointer_down(&mut self, position: Point2D, _event: Event) {
+    /// In `CssTransform` mode, moves everything that paints into a group for gestures to
+    /// transform. This changes what's at the top of the svg, so call it once the diagram has
+    /// been indexed.
+    pub fn wrap_content(&self) -> Result<(), JsValue> {
+        if self.gesture_rendering.get() != GestureRendering::CssTransform
+            || self.content.borrow().is_some()
+        {
+            return Ok(());
+        }
+
+        let content = document()
+            .safe_create_element_ns::<SvgElement>(Some(SVG_NS), "g")
+            .ok_or_else(|| JsValue::from("Couldn't create a group for the content"))?;
+        content.class_list().add_1(CONTENT_CLASS)?;
+
+        let mut child = self.svg.first_element_child();
+        while let Some(element) = child {
+            child = element.next_element_sibling();
+
+            if !NON_PAINTING_TAGS.contains(&element.tag_name().as_str()) {
+                content.append_child(&element)?;
+            }
+        }
+        self.svg.append_child(&content)?;
+        self.content.replace(Some(content));
+
+        Ok(())
+    }
+
+    fn on_pointer_down(&mut self, position: ScreenPoint, _event: Event) {
         if let Some(point) = self.get_point(&position) {
             self.is_pointer_down = true;
 
             self.pointer_origin = point;
+            self.fling.borrow_mut().start(now());
         }
     }
 
-    fn on_pointer_move(&self, position: Point2D, event: Event) {
-        if self.is_pointer_down {
-            event.prevent_default();
+    /// Queues the move for the next frame, returning true if a frame needs to be scheduled.
+    /// Moves can come in much faster than frames, and since panning keeps the origin under
+    /// the latest position, skipping the ones in between doesn't lose any distance.
+    fn on_pointer_move(&self, position: ScreenPoint, event: Event) -> bool {
+        if !self.is_pointer_down {
+            return false;
+        }
 
-            if let Some(point) = self.get_point(&position) {
-                if let Some(view_box) = self.svg.view_box().base_val() {
-                    let delta_x = point.x() - self.pointer_origin.x();
-                    let delta_y = point.y() - self.pointer_origin.y();
+        if self.cancel_moves {
+            event.prevent_default();
+        }
 
-                    view_box.set_x(view_box.x() - delta_x);
-                    view_box.set_y(view_box.y() - delta_y);
+        self.pending_move.replace(Some(position)).is_none()
+    }
 
-                    self.dispatch_event();
+    fn apply_pending_move(&self) {
+        let position = self.pending_move.borrow_mut().take();
+        if let Some(position) = position {
+            if let Some(point) = self.get_point(&position) {
+                if let Some(mut viewport) = self.current_view() {
+                    // the point under the pointer should stay under the pointer
+                    let delta = self.pointer_origin.delta_from(&point);
+                    if delta.is_zero() {
+                        return;
+                    }
+
+                    viewport.pan(&delta);
+                    self.show_gesture_view(viewport);
+                    self.fling.borrow_mut().moved(&delta, now());
                 }
             }
         }
     }
 
     fn on_pointer_up(&mut self, _event: Event) {
+        if self.is_pointer_down {
+            // land exactly where the pointer was released
+            self.apply_pending_move();
+            self.commit_gesture_view();
+
+            self.on_gesture_end();
+
+            let fling = self.fling.borrow_mut().release(now());
+            if fling.is_some() {
+                self.dispatch(true, fling);
+            }
+        }
+
         self.is_pointer_down = false;
     }
 
-    fn on_scroll(&self, delta_y: f32, _position: Point2D, event: Event) {
+    fn on_scroll(&self, delta_y: f32, position: ScreenPoint, zoom_key: bool, event: Event) {
+        if !self.wants_wheel(zoom_key) {
+            return;
+        }
         event.prevent_default();
 
-        if let Some(view_box) = self.svg.view_box().base_val() {
-            let delta_width = view_box.width() * (delta_y * ZOOM_FACTOR);
-            let delta_height = view_box.height() * (delta_y * ZOOM_FACTOR);
+        if let Some(mut viewport) = self.current_view() {
+            // keep whatever is under the cursor in place
+            let anchor = self
+                .get_point(&position)
+                .map(UserPoint::into_inner)
+                .unwrap_or_else(|| viewport.center());
 
-            view_box.set_width(view_box.width() + delta_width);
-            view_box.set_height(view_box.height() + delta_height);
-            view_box.set_x(view_box.x() - (delta_width / 2.0));
-            view_box.set_y(view_box.y() - (delta_height / 2.0));
+            let factor = 1.0 / (1.0 + (delta_y * self.wheel_factor.get()));
+            if approx_eq(factor, 1.0) {
+                return;
+            }
 
-            self.dispatch_event();
+            viewport.zoom_about(&anchor, factor);
+            self.show_gesture_view(viewport);
+        }
+
+        match self.wheel_end {
+            // wheels send a stream of ticks, so wait for them to stop before writing the viewBox
+            Some(ref wheel_end) if self.transforms_gestures() => wheel_end.restart(WHEEL_END_MS),
+            // every wheel tick is a complete gesture
+            _ => self.on_gesture_end(),
         }
     }
 
-    fn dispatch_event(&self) {
-        let client_rect = self.svg.get_bounding_client_rect();
-        let viewport = Rect::new(
-            Point2D { x: 0.0, y: 0.0 },
-            Point2D {
-                x: client_rect.width() as f32,
-                y: client_rect.height() as f32,
-            },
-        );
+    fn end_wheel_gesture(&self) {
+        if self.commit_gesture_view() {
+            self.on_gesture_end();
+        }
+    }
 
-        let event = ViewUpdateEvent { viewport };
+    /// The view being shown, which runs ahead of the viewBox during a `CssTransform` gesture
+    fn current_view(&self) -> Option<Viewport> {
+        self.gesture_view
+            .borrow()
+            .clone()
+            .or_else(|| Viewport::from_svg(&self.svg))
+    }
 
-        for listener in self.listeners.iter() {
-            listener.receive(&event);
+    /// Whether gestures move the content with a CSS transform rather than the viewBox
+    fn transforms_gestures(&self) -> bool {
+        self.gesture_rendering.get() == GestureRendering::CssTransform
+            && self.content.borrow().is_some()
+    }
+
+    /// Moves the view as part of a gesture
+    fn show_gesture_view(&self, viewport: Viewport) {
+        let content = self
+            .content
+            .borrow()
+            .clone()
+            .filter(|_| self.transforms_gestures());
+        let content = match content {
+            Some(content) => content,
+            None => {
+                viewport.apply(&self.svg);
+                self.dispatch_event(true);
+
+                return;
+            }
+        };
+
+        if self.gesture_view.borrow().is_none() {
+            // nothing has moved yet, but whatever was animating the view hears the user took over
+            self.dispatch_event(true);
+        }
+
+        if let Some(committed) = Viewport::from_svg(&self.svg) {
+            let (scale, translation) = content_transform(&committed, &viewport);
+            let transform = format!(
+                "translate({}px, {}px) scale({})",
+                translation.x, translation.y, scale
+            );
+            if let Err(error) = content.style().set_property("transform", &transform) {
+                console::warn_2(&"Couldn't transform the content".into(), &error);
+            }
+        }
+
+        self.gesture_view.replace(Some(viewport));
+    }
+
+    /// Writes the view a `CssTransform` gesture moved to into the viewBox, returning whether
+    /// there was one
+    fn commit_gesture_view(&self) -> bool {
+        match self.discard_gesture_view() {
+            Some(viewport) => {
+                viewport.apply(&self.svg);
+                self.dispatch_event(true);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn discard_gesture_view(&self) -> Option<Viewport> {
+        let viewport = self.gesture_view.borrow_mut().take()?;
+        if let Some(ref content) = *self.content.borrow() {
+            let _ = content.style().remove_property("transform");
+        }
+
+        Some(viewport)
+    }
+
+    fn on_gesture_end(&self) {
+        if self.pixel_snapping.get() {
+            if let Some(mut viewport) = Viewport::from_svg(&self.svg) {
+                viewport.snap_to_pixels(device_pixel_ratio());
+                viewport.apply(&self.svg);
+
+                self.dispatch_event(true);
+            }
+        }
+    }
+
+    fn wants_wheel(&self, zoom_key: bool) -> bool {
+        match self.prevent_default.get() {
+            PreventDefaultPolicy::Always => true,
+            PreventDefaultPolicy::WhenZooming => zoom_key,
+            PreventDefaultPolicy::WhenActive => {
+                let active = document().active_element();
+
+                self.svg
+                    .parent_element()
+                    .map(|container| container.contains(active.as_ref().map(|a| a.as_ref())))
+                    .unwrap_or(false)
+            }
         }
     }
 
-    fn get_point(&self, position: &Point2D) -> Option<SvgPoint> {
-        let point = self.svg.create_svg_point();
+    /// What zoom levels are relative to, after a sprite switches groups
+    pub fn set_fitted_view_box(&self, view_box: Option<Rect>) {
+        self.fitted_view_box.replace(view_box);
+    }
+
+    pub fn set_locked(&self, locked: bool) {
+        self.locked.set(locked);
+    }
+
+    /// Picks up changes to the options read on every gesture
+    pub fn update_options(&self, options: &Options) {
+        self.pixel_snapping.set(options.pixel_snapping);
+        self.prevent_default.set(options.prevent_default);
+        self.wheel_factor.set(options.zoom.wheel_factor);
+
+        if self.gesture_rendering.replace(options.gesture_rendering) != options.gesture_rendering {
+            // a gesture shown the old way is written to the viewBox before the next one starts
+            self.end_wheel_gesture();
+            if let Err(error) = self.wrap_content() {
+                console::warn_2(&"Couldn't wrap the content".into(), &error);
+            }
+        }
+    }
+
+    pub fn svg(&self) -> &SvgsvgElement {
+        &self.svg
+    }
+
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.current_view()
+    }
+
+    pub fn set_viewport(&self, viewport: &Viewport) {
+        self.discard_gesture_view();
+        viewport.apply(&self.svg);
+
+        self.dispatch_event(false);
+    }
+
+    fn dispatch_event(&self, gesture: bool) {
+        self.dispatch(gesture, None);
+    }
+
+    fn dispatch(&self, gesture: bool, fling: Option<Point2D>) {
+        // every viewBox change comes through here
+        self.geometry.invalidate();
+
+        diagnostics::layout_read();
+        let viewport = ScreenRect::from_dom_rect(&self.svg.get_bounding_client_rect());
+        let view = Viewport::from_svg(&self.svg);
+        let zoom = match (&view, &*self.fitted_view_box.borrow()) {
+            (Some(view), Some(fitted)) => view.zoom_relative_to(fitted),
+            _ => 1.0,
+        };
+
+        let event = ViewUpdateEvent {
+            viewport,
+            view,
+            zoom,
+            gesture,
+            fling,
+        };
 
-        point.set_x(position.x);
-        point.set_y(position.y);
+        for listener in self.listeners.iter() {
+            listener.receive(&event);
+        }
+    }
 
-        if let Some(svg_matrix) = self.svg.get_screen_ctm() {
-            if let Ok(inverted_svg_matrix) = svg_matrix.inverse() {
-                return Some(point.matrix_transform(&inverted_svg_matrix));
+    /// Converts a point on screen to Svg User Coordinates
+    pub fn get_point(&self, position: &ScreenPoint) -> Option<UserPoint> {
+        let point = self
+            .geometry
+            .screen_ctm()
+            .and_then(|to_screen| to_screen.inverse())
+            .map(|to_user| to_user.point(position))?;
+
+        // mid gesture the content is transformed away from where the viewBox puts it
+        if let Some(ref shown) = *self.gesture_view.borrow() {
+            if let Some(committed) = Viewport::from_svg(&self.svg) {
+                let (scale, translation) = content_transform(&committed, shown);
+
+                return Some(UserPoint::point(
+                    (point.x - translation.x) / scale,
+                    (point.y - translation.y) / scale,
+                ));
             }
         }
 
-        return None;
+        Some(point)
     }
 }
 
+/// The scale and then translation of the content that makes the `committed` viewBox show
+/// `shown`. Both views center on the middle of the svg, so their centers have to line up.
+fn content_transform(committed: &Viewport, shown: &Viewport) -> (f32, Point2D) {
+    let scale = shown.scale / committed.scale;
+    let committed_center = committed.center();
+    let shown_center = shown.center();
+
+    (
+        scale,
+        Point2D::new(
+            committed_center.x - shown_center.x * scale,
+            committed_center.y - shown_center.y * scale,
+        ),
+    )
+}
+
 impl EventSource<ViewUpdateEvent> for SvgViewController {
     fn register_listener<T: EventListener<ViewUpdateEvent> + 'static>(&mut self, callback: T) {
-        self.listeners.push(Box::new(callback));
+        self.listeners
+            .push(Box::new(CountedListener::new(callback)));
     }
 }
 
 impl ViewUpdateEvent {
     #[inline]
-    pub fn viewport(&self) -> &Rect {
+    pub fn viewport(&self) -> &ScreenRect {
         &self.viewport
     }
+
+    #[inline]
+    pub fn view(&self) -> Option<&Viewport> {
+        self.view.as_ref()
+    }
+
+    /// Everything the svg shows in Svg User Coordinates, which is more than the viewBox when the
+    /// svg's aspect ratio doesn't match it
+    pub fn visible_region(&self) -> Option<Rect> {
+        self.view.as_ref().map(|view| {
+            let size = self.viewport.size().scale(1.0 / view.scale);
+            let center = view.center();
+
+            Rect::from_origin(
+                Point2D::new(center.x - size.width / 2.0, center.y - size.height / 2.0),
+                &size,
+            )
+        })
+    }
+
+    #[inline]
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    #[inline]
+    pub fn is_gesture(&self) -> bool {
+        self.gesture
+    }
+
+    /// Set on the update for letting go of a drag that was still moving
+    #[inline]
+    pub fn fling(&self) -> Option<&Point2D> {
+        self.fling.as_ref()
+    }
 }
 
-fn get_drag_events(view_controller_ref: &Rc<RefCell<SvgViewController>>) -> Result<(), JsValue> {
-    // check if pointer events are supported
-    let mut events = match PointerEvent::new("pointerdown") {
-        Ok(_) => {
-            // pointers are supported
-            vec![
-                add_svg_event(
-                    view_controller_ref,
-                    &"pointerdown",
-                    |controller_ref, event: PointerEvent| {
-                        controller_ref.borrow_mut().on_pointer_down(
-                            Point2D::new(event.client_x() as f32, event.client_y() as f32),
-                            event.into(),
-                        );
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"pointermove",
-                    |controller_ref, event: PointerEvent| {
-                        controller_ref.borrow().on_pointer_move(
-                            Point2D::new(event.client_x() as f32, event.client_y() as f32),
-                            event.into(),
-                        );
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"pointerup",
-                    |controller_ref, event: PointerEvent| {
-                        controller_ref.borrow_mut().on_pointer_up(event.into());
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"pointerleave",
-                    |controller_ref, event: PointerEvent| {
-                        controller_ref.borrow_mut().on_pointer_up(event.into());
-                    },
-                )?,
-            ]
-        }
-        Err(_) => {
-            fn touch_position(event: &TouchEvent) -> Point2D {
-                if let Some(ref touch) = event.touches().get(0) {
-                    Point2D::new(touch.client_x() as f32, touch.client_y() as f32)
-                } else {
-                    Point2D::new(0.0, 0.0)
-                }
-            }
+impl FrameClient for RefCell<SvgViewController> {
+    fn frame(&self, _time: f64) -> bool {
+        self.borrow().apply_pending_move();
 
-            // no pointer support, so use something else
-            vec![
-                add_svg_event(
-                    view_controller_ref,
-                    &"mousedown",
-                    |controller_ref, event: MouseEvent| {
-                        controller_ref.borrow_mut().on_pointer_down(
-                            Point2D::new(event.client_x() as f32, event.client_y() as f32),
-                            event.into(),
-                        );
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"mousemove",
-                    |controller_ref, event: MouseEvent| {
-                        controller_ref.borrow().on_pointer_move(
-                            Point2D::new(event.client_x() as f32, event.client_y() as f32),
-                            event.into(),
-                        );
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"mouseup",
-                    |controller_ref, event: MouseEvent| {
-                        controller_ref.borrow_mut().on_pointer_up(event.into());
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"mouseleave",
-                    |controller_ref, event: MouseEvent| {
-                        controller_ref.borrow_mut().on_pointer_up(event.into());
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"touchstart",
-                    |controller_ref, event: TouchEvent| {
-                        controller_ref
-                            .borrow_mut()
-                            .on_pointer_down(touch_position(&event), event.into());
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"touchmove",
-                    |controller_ref, event: TouchEvent| {
-                        controller_ref
-                            .borrow()
-                            .on_pointer_move(touch_position(&event), event.into());
-                    },
-                )?,
-                add_svg_event(
-                    view_controller_ref,
-                    &"touchend",
-                    |controller_ref, event: TouchEvent| {
-                        controller_ref.borrow_mut().on_pointer_up(event.into());
-                    },
-                )?,
-            ]
-        }
+        false
+    }
+
+    /// Whoever is dragging is watching
+    fn is_priority(&self) -> bool {
+        true
+    }
+}
+
+/// The events a controller handles and whether each can be passive. Pointer events are
+/// passive since `touch-action` stops them from scrolling, the mouse and touch fallbacks aren't.
+/// Whether the wheel is cancelled depends on the policy, so it's never passive.
+pub(crate) fn controller_events() -> Vec<(&'static str, bool)> {
+    let mut events = if PointerEvent::new("pointerdown").is_ok() {
+        vec![
+            ("pointerdown", true),
+            ("pointermove", true),
+            ("pointerup", true),
+            ("pointerleave", true),
+        ]
+    } else {
+        vec![
+            ("mousedown", false),
+            ("mousemove", false),
+            ("mouseup", false),
+            ("mouseleave", false),
+            ("touchstart", false),
+            ("touchmove", false),
+            ("touchend", false),
+        ]
     };
+    events.push(("wheel", false));
 
-    view_controller_ref
-        .borrow_mut()
-        .event_listeners
-        .append(&mut events);
+    events
+}
 
-    Ok(())
+/// Whether `event_type` is only meant for the element being left, rather than bubbling up from
+/// its children
+pub(crate) fn is_leave_event(event_type: &str) -> bool {
+    event_type == "pointerleave" || event_type == "mouseleave"
 }
 
-fn register_scroll_events(
-    view_controller_ref: &Rc<RefCell<SvgViewController>>,
-) -> Result<(), JsValue> {
-    let event = add_svg_event(
-        view_controller_ref,
-        &"wheel",
-        |controller_ref, event: WheelEvent| {
-            controller_ref.borrow().on_scroll(
-                event.delta_y() as f32,
-                Point2D::new(event.client_x() as f32, event.client_y() as f32),
-                event.into(),
-            );
-        },
-    )?;
+/// Routes one of the `controller_events` to the controller
+pub(crate) fn handle_event(controller_ref: &Rc<RefCell<SvgViewController>>, event: Event) {
+    fn client_position(event: &Event) -> ScreenPoint {
+        if let Some(touch) = event
+            .dyn_ref::<TouchEvent>()
+            .and_then(|event| event.touches().get(0))
+        {
+            ScreenPoint::point(touch.client_x() as f32, touch.client_y() as f32)
+        } else if let Some(mouse) = event.dyn_ref::<MouseEvent>() {
+            ScreenPoint::point(mouse.client_x() as f32, mouse.client_y() as f32)
+        } else {
+            ScreenPoint::point(0.0, 0.0)
+        }
+    }
 
-    view_controller_ref.borrow_mut().event_listeners.push(event);
+    let event_type = event.type_();
+    let ends_gesture = matches!(
+        event_type.as_str(),
+        "pointerup" | "pointerleave" | "mouseup" | "mouseleave" | "touchend"
+    );
+    if controller_ref.borrow().locked.get() && !ends_gesture {
+        return;
+    }
 
-    Ok(())
+    match event_type.as_str() {
+        "pointerdown" | "mousedown" | "touchstart" => controller_ref
+            .borrow_mut()
+            .on_pointer_down(client_position(&event), event),
+        "pointermove" | "mousemove" | "touchmove" => {
+            let needs_frame = controller_ref
+                .borrow()
+                .on_pointer_move(client_position(&event), event);
+
+            if needs_frame {
+                let weak_ref: Weak<RefCell<SvgViewController>> = Rc::downgrade(controller_ref);
+                schedule_frame(weak_ref);
+            }
+        }
+        "pointerup" | "pointerleave" | "mouseup" | "mouseleave" | "touchend" => {
+            controller_ref.borrow_mut().on_pointer_up(event)
+        }
+        "wheel" => {
+            if let Some(wheel) = event.dyn_ref::<WheelEvent>() {
+                controller_ref.borrow().on_scroll(
+                    wheel.delta_y() as f32,
+                    ScreenPoint::point(wheel.client_x() as f32, wheel.client_y() as f32),
+                    wheel.ctrl_key() || wheel.meta_key(),
+                    event.clone(),
+                );
+            }
+        }
+        _ => (),
+    }
 }
 
-fn add_svg_event<C, E>(
-    controller_ref: &Rc<RefCell<SvgViewController>>,
-    event_type: &str,
-    callback: C,
-) -> Result<Box<JsEventListener>, JsValue>
-where
-    C: Fn(Rc<RefCell<SvgViewController>>, E) + 'static,
-    E: FromWasmAbi + 'static,
-{
-    let svg = &controller_ref.borrow().svg;
-
-    let weak_ref = Rc::downgrade(controller_ref);
-    svg.new_event_listener(event_type, move |event: E| {
-        if let Some(real_ref) = weak_ref.upgrade() {
-            callback(real_ref, event)
-        }
-    })
+/// Listens for the `controller_events` on the controller's own svg
+fn register_events(view_controller_ref: &Rc<RefCell<SvgViewController>>) -> Result<(), JsValue> {
+    let svg = view_controller_ref.borrow().svg.clone();
+
+    let mut listeners = vec![];
+    for (event_type, passive) in controller_events() {
+        let weak_ref = Rc::downgrade(view_controller_ref);
+        let listener = move |event: Event| {
+            if let Some(real_ref) = weak_ref.upgrade() {
+                handle_event(&real_ref, event)
+            }
+        };
+
+        listeners.push(if passive {
+            svg.new_passive_event_listener(event_type, listener)?
+        } else {
+            svg.new_event_listener(event_type, listener)?
+        });
+    }
+
+    view_controller_ref
+        .borrow_mut()
+        .event_listeners
+        .append(&mut listeners);
+
+    Ok(())
 }
@@ -0,0 +1,106 @@
+use std::cell::{Cell, RefCell};
+
+use serde::{Deserialize, Serialize};
+use web_sys::{console, SvgGraphicsElement, SvgsvgElement};
+
+use crate::js_utils::EnhancedNodeList;
+use crate::zoom::bounds::element_bounds;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TextCullingOptions {
+    pub enabled: bool,
+    /// Text shorter than this many CSS pixels on screen is hidden
+    pub min_pixels: f32,
+}
+
+impl Default for TextCullingOptions {
+    fn default() -> TextCullingOptions {
+        TextCullingOptions {
+            enabled: false,
+            min_pixels: 4.0,
+        }
+    }
+}
+
+/// Hides text that's too small to read, which is a lot of paint for nothing on dense diagrams
+pub struct TextCuller {
+    svg: SvgsvgElement,
+    enabled: bool,
+    min_pixels: f32,
+    /// Every text element by its height in Svg User Coordinates, shortest first. Measured the
+    /// first time we cull since the text has to be rendered.
+    texts: RefCell<Option<Vec<(f32, SvgGraphicsElement)>>>,
+    /// How many of the shortest texts are hidden
+    hidden: Cell<usize>,
+}
+
+static ILLEGIBLE_CLASS: &str = "archizoom-illegible";
+
+impl TextCuller {
+    pub fn new(svg: &SvgsvgElement, options: &TextCullingOptions) -> TextCuller {
+        TextCuller {
+            svg: svg.clone(),
+            enabled: options.enabled,
+            min_pixels: options.min_pixels,
+            texts: RefCell::new(None),
+            hidden: Cell::new(0),
+        }
+    }
+
+    /// Hides or shows text for `scale` pixels per user unit. Since the texts are sorted, only
+    /// the ones between the old and new cutoffs change.
+    pub fn update(&self, scale: f32) {
+        if !self.enabled || scale <= 0.0 {
+            return;
+        }
+
+        let mut texts = self.texts.borrow_mut();
+        let texts = texts.get_or_insert_with(|| self.measure());
+
+        let min_height = self.min_pixels / scale;
+        let hidden = texts.partition_point(|(height, _)| *height < min_height);
+        let previous = self.hidden.replace(hidden);
+
+        let (changed, hide) = if hidden > previous {
+            (&texts[previous..hidden], true)
+        } else {
+            (&texts[hidden..previous], false)
+        };
+
+        for (_, text) in changed.iter() {
+            if let Err(error) = text.class_list().toggle_with_force(ILLEGIBLE_CLASS, hide) {
+                console::warn_2(&"Couldn't toggle illegible text".into(), &error);
+            }
+        }
+    }
+
+    /// Shows any hidden text and drops the measurements, so the next update finds and measures
+    /// every text again
+    pub fn invalidate(&self) {
+        if let Some(texts) = self.texts.replace(None) {
+            for (_, text) in texts.iter().take(self.hidden.replace(0)) {
+                if let Err(error) = text.class_list().remove_1(ILLEGIBLE_CLASS) {
+                    console::warn_2(&"Couldn't show illegible text".into(), &error);
+                }
+            }
+        }
+    }
+
+    fn measure(&self) -> Vec<(f32, SvgGraphicsElement)> {
+        let mut texts: Vec<(f32, SvgGraphicsElement)> = self
+            .svg
+            .query_selector_all("text")
+            .map(|nodes| nodes.safe_filter::<SvgGraphicsElement>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|text| {
+                element_bounds(&self.svg, &text).map(|bounds| (bounds.height(), text))
+            })
+            .collect();
+
+        texts.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        texts
+    }
+}
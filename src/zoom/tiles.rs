@@ -0,0 +1,166 @@
+use crate::zoom::matrix::{Point2D, Rect, Size2D};
+
+/// The deepest level a pyramid goes to, which keeps a level's `1 << level` columns and rows well
+/// clear of overflowing
+pub const MAX_LEVEL: u32 = 16;
+
+/// A tile in a `TilePyramid`, `level` 0 is a single tile covering the whole diagram and each
+/// level after it splits every tile into four
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub level: u32,
+    pub column: i32,
+    pub row: i32,
+}
+
+/// Square tiles over a diagram at zoom levels that double, like a map. Each tile is rendered at
+/// `tile_pixels` across no matter the level, so deeper levels hold more detail.
+#[derive(Debug, Clone)]
+pub struct TilePyramid {
+    origin: Point2D,
+    /// How many user units a level 0 tile spans
+    extent: f32,
+    tile_pixels: f32,
+    max_level: u32,
+}
+
+impl TilePyramid {
+    pub fn new(bounds: &Rect, tile_pixels: f32, max_level: u32) -> TilePyramid {
+        TilePyramid {
+            origin: bounds.top_left.clone(),
+            extent: bounds.width().max(bounds.height()).max(1.0),
+            tile_pixels,
+            max_level: max_level.min(MAX_LEVEL),
+        }
+    }
+
+    /// The shallowest level with at least as many pixels per user unit as `scale`
+    pub fn level_for(&self, scale: f32) -> u32 {
+        let needed = (scale * self.extent / self.tile_pixels).log2().ceil();
+
+        if needed.is_finite() && needed > 0.0 {
+            (needed as u32).min(self.max_level)
+        } else {
+            0
+        }
+    }
+
+    /// How many user units a tile at `level` spans
+    #[inline]
+    pub fn tile_extent(&self, level: u32) -> f32 {
+        self.extent / (1u32 << level) as f32
+    }
+
+    /// The region of the diagram `key` covers, in Svg User Coordinates
+    pub fn tile_rect(&self, key: &TileKey) -> Rect {
+        let extent = self.tile_extent(key.level);
+
+        Rect::from_origin(
+            Point2D::new(
+                self.origin.x + key.column as f32 * extent,
+                self.origin.y + key.row as f32 * extent,
+            ),
+            &Size2D::new(extent, extent),
+        )
+    }
+
+    /// The tiles at `level` that overlap `region` (in Svg User Coordinates)
+    pub fn tiles_in(&self, level: u32, region: &Rect) -> Vec<TileKey> {
+        let extent = self.tile_extent(level);
+        // nothing is drawn outside of level 0's tile, so there's nothing to cover there
+        let count = 1i32 << level;
+        let span = |start: f32, end: f32, origin: f32| {
+            let first = ((start - origin) / extent).floor() as i32;
+            let last = ((end - origin) / extent).ceil() as i32;
+
+            first.max(0)..last.min(count)
+        };
+
+        let columns = span(region.left(), region.right(), self.origin.x);
+        let rows = span(region.top(), region.bottom(), self.origin.y);
+
+        rows.flat_map(|row| {
+            columns
+                .clone()
+                .map(move |column| TileKey { level, column, row })
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pyramid(max_level: u32) -> TilePyramid {
+        let bounds = Rect::new(Point2D::new(100.0, 50.0), Point2D::new(1124.0, 562.0));
+        TilePyramid::new(&bounds, 256.0, max_level)
+    }
+
+    #[test]
+    fn levels_double_the_detail_up_to_the_max() {
+        let pyramid = pyramid(4);
+
+        // level 0 spans 1024 units with 256 pixels
+        assert_eq!(pyramid.level_for(0.1), 0);
+        assert_eq!(pyramid.level_for(0.25), 0);
+        assert_eq!(pyramid.level_for(0.5), 1);
+        assert_eq!(pyramid.level_for(1.0), 2);
+        assert_eq!(pyramid.level_for(1000.0), 4);
+        assert_eq!(pyramid.level_for(f32::NAN), 0);
+    }
+
+    #[test]
+    fn the_max_level_is_capped() {
+        let pyramid = pyramid(40);
+
+        assert_eq!(pyramid.level_for(1e9), MAX_LEVEL);
+        assert_eq!(pyramid.tile_extent(MAX_LEVEL), 1024.0 / 65536.0);
+    }
+
+    #[test]
+    fn tiles_cover_the_region_inside_the_diagram() {
+        let pyramid = pyramid(4);
+
+        let rect = pyramid.tile_rect(&TileKey {
+            level: 2,
+            column: 1,
+            row: 3,
+        });
+        assert_eq!(rect.left(), 356.0);
+        assert_eq!(rect.top(), 818.0);
+        assert_eq!(rect.width(), 256.0);
+
+        // straddles the top left corner of the diagram, so only tiles from (0, 0) count
+        let region = Rect::new(Point2D::new(0.0, 0.0), Point2D::new(400.0, 320.0));
+        let keys = pyramid.tiles_in(2, &region);
+        assert_eq!(
+            keys,
+            vec![
+                TileKey {
+                    level: 2,
+                    column: 0,
+                    row: 0
+                },
+                TileKey {
+                    level: 2,
+                    column: 1,
+                    row: 0
+                },
+                TileKey {
+                    level: 2,
+                    column: 0,
+                    row: 1
+                },
+                TileKey {
+                    level: 2,
+                    column: 1,
+                    row: 1
+                },
+            ]
+        );
+
+        let outside = Rect::new(Point2D::new(5000.0, 5000.0), Point2D::new(6000.0, 6000.0));
+        assert!(pyramid.tiles_in(2, &outside).is_empty());
+    }
+}
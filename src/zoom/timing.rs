@@ -0,0 +1,69 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use web_sys::Performance;
+
+use crate::js_utils::*;
+
+/// One phase of loading or navigating. It's marked and measured with the User Timing API so it
+/// shows up in the browser's performance profiles as `archizoom:<name>`.
+pub struct Span {
+    name: &'static str,
+    start: f64,
+}
+
+impl Span {
+    pub fn start(name: &'static str) -> Span {
+        let start = performance()
+            .map(|performance| {
+                let _ = performance.mark(&format!("archizoom:{}:start", name));
+
+                performance.now()
+            })
+            .unwrap_or(0.0);
+
+        Span { name, start }
+    }
+
+    /// Marks the end of the phase and returns how long it took in milliseconds
+    pub fn end(self) -> f64 {
+        match performance() {
+            Some(performance) => {
+                let start_mark = format!("archizoom:{}:start", self.name);
+                let _ = performance.mark(&format!("archizoom:{}:end", self.name));
+                let _ = performance
+                    .measure_with_start_mark(&format!("archizoom:{}", self.name), &start_mark);
+
+                performance.now() - self.start
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// How long the last run of each phase took, in milliseconds
+#[derive(Default)]
+pub struct Timings {
+    durations: RefCell<HashMap<&'static str, f64>>,
+}
+
+impl Timings {
+    pub fn end(&self, span: Span) {
+        let name = span.name;
+        let duration = span.end();
+
+        self.durations.borrow_mut().insert(name, duration);
+    }
+
+    pub fn record(&self, name: &'static str, duration: f64) {
+        self.durations.borrow_mut().insert(name, duration);
+    }
+
+    pub fn durations(&self) -> HashMap<&'static str, f64> {
+        self.durations.borrow().clone()
+    }
+}
+
+fn performance() -> Option<Performance> {
+    window().performance()
+}
@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use js_sys::{Array, Map};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    console, Element, MutationObserver, MutationObserverInit, MutationRecord, NodeList,
+    SvgGraphicsElement, SvgsvgElement,
+};
+
+use crate::zoom::matrix::Matrix2D;
+
+type MutationCallback = Closure<dyn FnMut(Array)>;
+
+/// Caches the composed transform from each element's user space to the root svg's user space.
+///
+/// Elements deep inside transformed groups would otherwise need every ancestor's transform list
+/// multiplied on each lookup. Each group's composed transform is cached once and reused by all of
+/// its descendants, and a MutationObserver drops only the subtree whose transforms changed.
+/// Removing elements drops the whole cache so it doesn't hold on to them.
+pub struct TransformCache {
+    svg: SvgsvgElement,
+    /// Element -> index into `matrices`
+    indices: Map,
+    matrices: RefCell<Vec<Option<Matrix2D>>>,
+    observer: RefCell<Option<MutationObserver>>,
+    _callback: RefCell<Option<MutationCallback>>,
+}
+
+impl TransformCache {
+    pub fn new(svg: &SvgsvgElement) -> Result<Rc<TransformCache>, JsValue> {
+        let cache = Rc::new(TransformCache {
+            svg: svg.clone(),
+            indices: Map::new(),
+            matrices: RefCell::new(vec![]),
+            observer: RefCell::new(None),
+            _callback: RefCell::new(None),
+        });
+
+        let weak_cache: Weak<TransformCache> = Rc::downgrade(&cache);
+        let callback = Closure::wrap(Box::new(move |records: Array| {
+            if let Some(cache) = weak_cache.upgrade() {
+                cache.on_mutations(records);
+            }
+        }) as Box<dyn FnMut(Array)>);
+
+        let observer = MutationObserver::new(callback.as_ref().unchecked_ref())?;
+        let options = MutationObserverInit::new();
+        options.set_subtree(true);
+        options.set_child_list(true);
+        options.set_attributes(true);
+        options.set_attribute_filter(&Array::of1(&"transform".into()));
+        observer.observe_with_options(svg, &options)?;
+
+        cache.observer.replace(Some(observer));
+        cache._callback.replace(Some(callback));
+
+        Ok(cache)
+    }
+
+    /// The transform from `element`'s user space to the root svg's user space. Returns `None` if
+    /// the element isn't inside of the svg or sits inside a nested svg, whose viewBox we don't
+    /// model.
+    pub fn user_transform(&self, element: &Element) -> Option<Matrix2D> {
+        let root: &Element = self.svg.as_ref();
+        if element == root {
+            return Some(Matrix2D::identity());
+        }
+
+        let cached = self.indices.get(element);
+        if let Some(index) = cached.as_f64() {
+            if let Some(Some(matrix)) = self.matrices.borrow().get(index as usize) {
+                return Some(matrix.clone());
+            }
+        }
+
+        if element.dyn_ref::<SvgsvgElement>().is_some() {
+            return None;
+        }
+
+        let parent_transform = self.user_transform(&element.parent_element()?)?;
+        let composed = parent_transform.multiply(&local_transform(element));
+
+        let mut matrices = self.matrices.borrow_mut();
+        match cached.as_f64() {
+            Some(index) => matrices[index as usize] = Some(composed.clone()),
+            None => {
+                self.indices
+                    .set(element, &JsValue::from(matrices.len() as f64));
+                matrices.push(Some(composed.clone()));
+            }
+        }
+
+        Some(composed)
+    }
+
+    /// Drops the cached transforms of `element` and everything inside of it
+    pub fn invalidate(&self, element: &Element) {
+        let mut matrices = self.matrices.borrow_mut();
+
+        self.indices.for_each(&mut |index, key| {
+            if let Some(node) = key.dyn_ref::<Element>() {
+                if element.contains(Some(node)) {
+                    if let Some(matrix) = matrices.get_mut(index.as_f64().unwrap_or(-1.0) as usize)
+                    {
+                        *matrix = None;
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn clear(&self) {
+        self.indices.clear();
+        self.matrices.borrow_mut().clear();
+    }
+
+    fn on_mutations(&self, records: Array) {
+        for record in records.iter() {
+            let record = match record.dyn_into::<MutationRecord>() {
+                Ok(record) => record,
+                Err(_) => continue,
+            };
+
+            if let Some(target) = record.target().and_then(|t| t.dyn_into::<Element>().ok()) {
+                if record.type_() == "attributes" {
+                    self.invalidate(&target);
+                } else if has_elements(&record.removed_nodes()) {
+                    // the map would keep removed elements alive for as long as the diagram is
+                    // open, starting over is cheaper than finding everything inside of them
+                    self.clear();
+                    return;
+                } else {
+                    // moved elements have new ancestors, so anything added needs recomputing
+                    let added = record.added_nodes();
+                    for i in 0..added.length() {
+                        if let Some(element) =
+                            added.get(i).and_then(|n| n.dyn_into::<Element>().ok())
+                        {
+                            self.invalidate(&element);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for TransformCache {
+    fn drop(&mut self) {
+        if let Some(observer) = self.observer.borrow_mut().take() {
+            observer.disconnect();
+        }
+    }
+}
+
+fn has_elements(nodes: &NodeList) -> bool {
+    (0..nodes.length()).any(|i| {
+        nodes
+            .get(i)
+            .map(|node| node.dyn_ref::<Element>().is_some())
+            .unwrap_or(false)
+    })
+}
+
+/// The product of an element's own transform list, without touching the DOM
+fn local_transform(element: &Element) -> Matrix2D {
+    let mut local = Matrix2D::identity();
+
+    if let Some(graphics) = element.dyn_ref::<SvgGraphicsElement>() {
+        let transforms = graphics.transform().base_val();
+        for i in 0..transforms.number_of_items() {
+            match transforms.get_item(i) {
+                Ok(transform) => local = local.multiply(&Matrix2D::from_js(&transform.matrix())),
+                Err(error) => console::warn_2(&"Couldn't read a transform".into(), &error),
+            }
+        }
+    }
+
+    local
+}
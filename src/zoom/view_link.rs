@@ -0,0 +1,156 @@
+use std::cell::Cell;
+
+use js_sys::encode_uri_component;
+use web_sys::{console, Url, UrlSearchParams};
+
+use crate::js_utils::*;
+use crate::zoom::matrix::{Point2D, Rect, Size2D};
+
+/// A link to a view of a diagram, as `archizoom-view=diagram-url&archizoom-box=x,y,width,height`
+/// in either the query or the fragment of the page url. Servers generating links put it in the
+/// query, `copyViewLink` puts it in the fragment. The diagram is relative to the page and can
+/// name a sprite group, it's left out when it's the one the page loads. Diagrams on other
+/// origins are ignored.
+#[derive(Debug, Clone)]
+pub struct ViewLink {
+    pub diagram: Option<String>,
+    pub view_box: Option<Rect>,
+}
+
+static DIAGRAM_KEY: &str = "archizoom-view";
+static BOX_KEY: &str = "archizoom-box";
+
+thread_local! {
+    /// The page's link is for the first viewer that asks for it
+    static TAKEN: Cell<bool> = const { Cell::new(false) };
+}
+
+impl ViewLink {
+    pub fn new(diagram: Option<String>, view_box: Rect) -> ViewLink {
+        ViewLink {
+            diagram,
+            view_box: Some(view_box),
+        }
+    }
+
+    pub fn to_hash(&self) -> String {
+        let mut pairs = vec![];
+        if let Some(ref diagram) = self.diagram {
+            pairs.push(format!("{}={}", DIAGRAM_KEY, encode_uri_component(diagram)));
+        }
+        if let Some(ref view_box) = self.view_box {
+            let size = view_box.size();
+            pairs.push(format!(
+                "{}={:.2},{:.2},{:.2},{:.2}",
+                BOX_KEY, view_box.top_left.x, view_box.top_left.y, size.width, size.height
+            ));
+        }
+
+        format!("#{}", pairs.join("&"))
+    }
+
+    /// `page_url` with its fragment replaced by this view
+    pub fn to_url(&self, page_url: &str) -> String {
+        let base = page_url.split('#').next().unwrap_or(page_url);
+
+        format!("{}{}", base, self.to_hash())
+    }
+
+    /// The link in the page url, from the query or else the fragment. Only the first call gets
+    /// it, so it opens one viewer rather than every viewer on the page.
+    pub fn take_from_page() -> Option<ViewLink> {
+        if TAKEN.with(|taken| taken.replace(true)) {
+            return None;
+        }
+
+        let location = window().location();
+        [location.search().ok()?, location.hash().ok()?]
+            .iter()
+            .find_map(|part| ViewLink::parse(part.trim_start_matches(['?', '#'])))
+    }
+
+    fn parse(params: &str) -> Option<ViewLink> {
+        let params = UrlSearchParams::new_with_str(params).ok()?;
+
+        let diagram = params
+            .get(DIAGRAM_KEY)
+            .filter(|diagram| !diagram.is_empty())
+            .and_then(|diagram| same_origin(&diagram));
+        let view_box = params
+            .get(BOX_KEY)
+            .and_then(|view_box| parse_view_box(&view_box));
+
+        if diagram.is_none() && view_box.is_none() {
+            None
+        } else {
+            Some(ViewLink { diagram, view_box })
+        }
+    }
+}
+
+/// `url` resolved against the page, as long as it's on the page's own origin. Diagrams are put
+/// straight into the page, so a link mustn't be able to point us at someone else's markup.
+pub fn same_origin(url: &str) -> Option<String> {
+    let page_url = window().location().href().ok()?;
+    let page = Url::new(&page_url).ok()?;
+    let url = Url::new_with_base(url, &page_url).ok()?;
+
+    if url.origin() == page.origin() {
+        Some(url.href())
+    } else {
+        console::warn_2(
+            &"Ignoring a link to a diagram on another origin".into(),
+            &url.href().into(),
+        );
+        None
+    }
+}
+
+/// A viewBox written as four numbers split by commas or whitespace, like in our links or the
+/// `viewBox` attribute. It needs a positive width and height.
+pub fn parse_view_box(value: &str) -> Option<Rect> {
+    let numbers = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<f32>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<f32>>>()?;
+
+    match numbers.as_slice() {
+        [x, y, width, height] if *width > 0.0 && *height > 0.0 => Some(Rect::from_origin(
+            Point2D::new(*x, *y),
+            &Size2D::new(*width, *height),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_boxes_parse_from_links_and_attributes() {
+        for value in ["10,20,300,400", "10 20 300 400", " 10, 20  300,400 "] {
+            let rect = parse_view_box(value).unwrap();
+            assert_eq!(rect.left(), 10.0);
+            assert_eq!(rect.top(), 20.0);
+            assert_eq!(rect.width(), 300.0);
+            assert_eq!(rect.height(), 400.0);
+        }
+    }
+
+    #[test]
+    fn broken_view_boxes_are_rejected() {
+        for value in [
+            "",
+            "1,2,3",
+            "1,2,3,4,5",
+            "1,2,0,4",
+            "1,2,3,-4",
+            "1,2,NaN,4",
+            "a,b,c,d",
+        ] {
+            assert!(parse_view_box(value).is_none(), "{}", value);
+        }
+    }
+}
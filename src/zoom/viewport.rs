@@ -0,0 +1,244 @@
+use std::fmt::{Display, Formatter, Result};
+
+use serde::{Deserialize, Serialize};
+use web_sys::SvgsvgElement;
+
+use crate::diagnostics;
+use crate::zoom::matrix::{approx_eq, clamp_rect_within, Margin, Matrix2D, Point2D, Rect, Size2D};
+
+/// A viewBox as the plain `{ x, y, width, height }` object we hand to other pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl From<&Rect> for ViewBox {
+    fn from(rect: &Rect) -> ViewBox {
+        ViewBox {
+            x: rect.left(),
+            y: rect.top(),
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+impl From<&ViewBox> for Rect {
+    fn from(view_box: &ViewBox) -> Rect {
+        Rect::from_origin(
+            Point2D::new(view_box.x, view_box.y),
+            &Size2D::new(view_box.width, view_box.height),
+        )
+    }
+}
+
+/// The visible region of an Svg.
+///
+/// `origin` and `size` are the viewBox in Svg User Coordinates, `scale` is how many pixels a
+/// single user unit takes up on screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Viewport {
+    pub origin: Point2D,
+    pub size: Size2D,
+    pub scale: f32,
+}
+
+impl Viewport {
+    #[inline]
+    pub fn new(origin: Point2D, size: Size2D, scale: f32) -> Viewport {
+        Viewport {
+            origin,
+            size,
+            scale,
+        }
+    }
+
+    /// Builds a viewport from a viewBox and the pixel size of the element it's rendered into
+    pub fn from_view_box(view_box: &Rect, client_size: &Size2D) -> Viewport {
+        // the default preserveAspectRatio (xMidYMid meet) fits the whole viewBox into the element
+        let scale =
+            (client_size.width / view_box.width()).min(client_size.height / view_box.height());
+
+        Viewport::new(view_box.top_left.clone(), view_box.size(), scale)
+    }
+
+    /// Reads the current viewport of the svg, if it has a viewBox
+    pub fn from_svg(svg: &SvgsvgElement) -> Option<Viewport> {
+        svg.view_box().base_val().map(|view_box| {
+            diagnostics::layout_read();
+            let client_rect = svg.get_bounding_client_rect();
+
+            Viewport::from_view_box(
+                &Rect::from_svg(&view_box),
+                &Size2D::new(client_rect.width() as f32, client_rect.height() as f32),
+            )
+        })
+    }
+
+    /// Writes this viewport back into the viewBox of the svg
+    pub fn apply(&self, svg: &SvgsvgElement) {
+        if let Some(view_box) = svg.view_box().base_val() {
+            view_box.set_x(self.origin.x);
+            view_box.set_y(self.origin.y);
+            view_box.set_width(self.size.width);
+            view_box.set_height(self.size.height);
+        }
+    }
+
+    #[inline]
+    pub fn view_box(&self) -> Rect {
+        Rect::from_origin(self.origin.clone(), &self.size)
+    }
+
+    #[inline]
+    pub fn center(&self) -> Point2D {
+        self.view_box().center()
+    }
+
+    /// The viewport with the same center and scale, in an element of `client_size` pixels
+    pub fn resized_to(&self, client_size: &Size2D) -> Viewport {
+        let size = client_size.scale(1.0 / self.scale);
+        let center = self.center();
+        let origin = Point2D::new(center.x - size.width / 2.0, center.y - size.height / 2.0);
+
+        Viewport::new(origin, size, self.scale)
+    }
+
+    /// Zooms by `factor` while keeping `point` (in Svg User Coordinates) fixed on screen.
+    /// A factor greater than 1 zooms in.
+    pub fn zoom_about(&mut self, point: &Point2D, factor: f32) {
+        // zooming the view in is the same as shrinking the viewBox around the point
+        let view_box = self
+            .view_box()
+            .matrix_transform(&Matrix2D::scale_about(point, 1.0 / factor));
+
+        self.origin = view_box.top_left.clone();
+        self.size = view_box.size();
+        self.scale *= factor;
+    }
+
+    /// The viewport `t` of the way towards `target`. The center moves linearly while the scale
+    /// changes geometrically, so zooming looks like it happens at a constant speed.
+    pub fn interpolate_towards(&self, target: &Viewport, t: f32) -> Viewport {
+        let pixel_size = self.size.scale(self.scale);
+        let scale = self.scale * (target.scale / self.scale).powf(t);
+        let size = pixel_size.scale(1.0 / scale);
+        let center = self.center().lerp(&target.center(), t);
+
+        Viewport::new(
+            &center - &Point2D::new(size.width / 2.0, size.height / 2.0),
+            size,
+            scale,
+        )
+    }
+
+    /// How far we're zoomed in compared to fitting all of `view_box` into the same element,
+    /// 1.0 is fitted
+    pub fn zoom_relative_to(&self, view_box: &Rect) -> f32 {
+        let fitted = Viewport::from_view_box(view_box, &self.size.scale(self.scale));
+
+        self.scale / fitted.scale
+    }
+
+    /// Moves the viewport by `delta` in Svg User Coordinates
+    pub fn pan(&mut self, delta: &Point2D) {
+        self.origin = &self.origin + delta;
+    }
+
+    /// Rounds the origin so user space lines up with the device pixel grid, at `pixel_ratio`
+    /// device pixels per CSS pixel. The scale is left alone, rounding it to a fixed step makes
+    /// small zoom steps stall or jump once we're far enough out.
+    pub fn snap_to_pixels(&mut self, pixel_ratio: f32) {
+        let device_scale = self.scale * pixel_ratio;
+        if !(device_scale > 0.0 && device_scale.is_finite()) {
+            return;
+        }
+
+        let snap = |value: f32| (value * device_scale).round() / device_scale;
+        self.origin = Point2D::new(snap(self.origin.x), snap(self.origin.y));
+    }
+
+    /// Keeps the view inside of `bounds` (in Svg User Coordinates) without changing the zoom
+    pub fn clamp_within(&mut self, bounds: &Rect) {
+        self.origin = clamp_rect_within(&self.view_box(), bounds).top_left;
+    }
+
+    #[inline]
+    pub fn approx_eq(&self, other: &Viewport) -> bool {
+        self.view_box().approx_eq(&other.view_box()) && approx_eq(self.scale, other.scale)
+    }
+
+    /// Frames `rect` (in Svg User Coordinates) in the middle of the viewport, leaving at least
+    /// `padding` pixels around it and keeping the current aspect ratio
+    pub fn fit(&mut self, rect: &Rect, padding: f32) {
+        let pixel_size = self.size.scale(self.scale);
+        let view_box = rect.fit_into(
+            &Rect::from_origin(Point2D::new(0.0, 0.0), &pixel_size),
+            padding,
+        );
+
+        if view_box.width() > 0.0 {
+            self.scale = pixel_size.width / view_box.width();
+            self.size = view_box.size();
+            self.origin = view_box.top_left;
+        }
+    }
+
+    /// Frames `rect` (in Svg User Coordinates) with `margin` of breathing room around it
+    pub fn fit_with_margin(&mut self, rect: &Rect, margin: &Margin) {
+        self.fit(&rect.inflate_by(margin), 0.0);
+    }
+}
+
+impl Display for Viewport {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        self.view_box().fmt(f)?;
+        write!(f, " @ {:.3}x", self.scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolating_starts_and_ends_on_the_viewports() {
+        let from = Viewport::new(Point2D::new(0.0, 0.0), Size2D::new(400.0, 300.0), 1.0);
+        let to = Viewport::new(Point2D::new(500.0, -200.0), Size2D::new(100.0, 75.0), 4.0);
+
+        assert!(from.interpolate_towards(&to, 0.0).approx_eq(&from));
+        assert!(from.interpolate_towards(&to, 1.0).approx_eq(&to));
+
+        // the scale changes geometrically
+        let halfway = from.interpolate_towards(&to, 0.5);
+        assert!(approx_eq(halfway.scale, 2.0));
+        assert!(halfway
+            .center()
+            .approx_eq(&from.center().lerp(&to.center(), 0.5)));
+    }
+
+    #[test]
+    fn snapping_keeps_the_scale_when_zoomed_far_out() {
+        for scale in [0.01, 0.03, 0.045, 0.5, 1.3] {
+            let mut viewport =
+                Viewport::new(Point2D::new(10.3, 20.7), Size2D::new(800.0, 600.0), scale);
+            viewport.snap_to_pixels(2.0);
+
+            assert_eq!(viewport.scale, scale);
+        }
+    }
+
+    #[test]
+    fn snapping_puts_the_origin_on_a_device_pixel() {
+        let mut viewport = Viewport::new(Point2D::new(10.3, 20.7), Size2D::new(400.0, 300.0), 1.5);
+        viewport.snap_to_pixels(2.0);
+
+        let device_x = viewport.origin.x * 3.0;
+        let device_y = viewport.origin.y * 3.0;
+        assert!((device_x - device_x.round()).abs() < 1e-3);
+        assert!((device_y - device_y.round()).abs() < 1e-3);
+    }
+}
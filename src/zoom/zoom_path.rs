@@ -0,0 +1,170 @@
+use crate::zoom::matrix::{Point2D, Size2D};
+use crate::zoom::viewport::Viewport;
+
+/// The curvature recommended by van Wijk and Nuij, larger values zoom out further
+pub static DEFAULT_RHO: f32 = std::f32::consts::SQRT_2;
+
+static MIN_DISTANCE: f32 = 1e-6;
+
+/// A smooth zoom out, pan, zoom in trajectory between two viewports, following
+/// "Smooth and efficient zooming and panning" (van Wijk & Nuij, 2003).
+///
+/// Linearly interpolating two distant viewports pans across the diagram at full zoom, which shows
+/// nothing but a blur. Instead this zooms out far enough that both ends are briefly in view.
+#[derive(Debug, Clone)]
+pub struct ZoomPath {
+    start_center: Point2D,
+    end_center: Point2D,
+    start_width: f32,
+    end_width: f32,
+    /// The pixel size of the view, which stays the same along the whole path
+    pixel_size: Size2D,
+    rho: f32,
+    shape: PathShape,
+}
+
+#[derive(Debug, Clone)]
+enum PathShape {
+    /// The centers are (nearly) the same so we only zoom
+    ZoomOnly,
+    Curve {
+        distance: f32,
+        r0: f32,
+        length: f32,
+    },
+}
+
+impl ZoomPath {
+    pub fn new(from: &Viewport, to: &Viewport, rho: f32) -> ZoomPath {
+        let start_center = from.center();
+        let end_center = to.center();
+        let start_width = from.size.width;
+        let end_width = to.size.width;
+
+        let delta = &end_center - &start_center;
+        let distance = delta.length();
+
+        let rho_squared = rho * rho;
+        let shape = if distance < MIN_DISTANCE {
+            PathShape::ZoomOnly
+        } else {
+            let rho_distance = rho_squared * rho_squared * distance * distance;
+            let width_difference = (end_width * end_width) - (start_width * start_width);
+
+            let b0 =
+                (width_difference + rho_distance) / (2.0 * start_width * rho_squared * distance);
+            let b1 = (width_difference - rho_distance) / (2.0 * end_width * rho_squared * distance);
+            let r0 = ((b0 * b0) + 1.0).sqrt() - b0;
+            let r1 = ((b1 * b1) + 1.0).sqrt() - b1;
+            let r0 = r0.ln();
+
+            PathShape::Curve {
+                distance,
+                r0,
+                length: (r1.ln() - r0) / rho,
+            }
+        };
+
+        ZoomPath {
+            start_center,
+            end_center,
+            start_width,
+            end_width,
+            pixel_size: from.size.scale(from.scale),
+            rho,
+            shape,
+        }
+    }
+
+    /// The length of the path in the paper's units, proportional to how long it should take to
+    /// travel at a constant perceived speed
+    pub fn length(&self) -> f32 {
+        match self.shape {
+            PathShape::ZoomOnly => ((self.end_width / self.start_width).ln() / self.rho).abs(),
+            PathShape::Curve { length, .. } => length,
+        }
+    }
+
+    /// How long the path takes at `velocity` path units per second, clamped to something sensible
+    pub fn duration_ms(&self, velocity: f32, min_ms: f32, max_ms: f32) -> f32 {
+        ((self.length() / velocity) * 1000.0).clamp(min_ms, max_ms)
+    }
+
+    /// The viewport `t` of the way along the path
+    pub fn at(&self, t: f32) -> Viewport {
+        let (center, width) = match self.shape {
+            PathShape::ZoomOnly => (
+                self.start_center.lerp(&self.end_center, t),
+                self.start_width * (self.end_width / self.start_width).powf(t),
+            ),
+            PathShape::Curve {
+                distance,
+                r0,
+                length,
+            } => {
+                let s = t * length;
+                let cosh_r0 = r0.cosh();
+                let u = (self.start_width / (self.rho * self.rho * distance))
+                    * ((cosh_r0 * ((self.rho * s) + r0).tanh()) - r0.sinh());
+
+                (
+                    // u is the fraction of the distance between the centers travelled so far
+                    self.start_center.lerp(&self.end_center, u),
+                    self.start_width * cosh_r0 / ((self.rho * s) + r0).cosh(),
+                )
+            }
+        };
+
+        let scale = self.pixel_size.width / width;
+        let size = self.pixel_size.scale(1.0 / scale);
+
+        Viewport::new(
+            &center - &Point2D::new(size.width / 2.0, size.height / 2.0),
+            size,
+            scale,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &Viewport, expected: &Viewport) {
+        let tolerance = 1e-2 * expected.size.width;
+        let (a, e) = (actual.view_box(), expected.view_box());
+
+        for (a, e) in [
+            (a.left(), e.left()),
+            (a.top(), e.top()),
+            (a.right(), e.right()),
+            (a.bottom(), e.bottom()),
+        ] {
+            assert!((a - e).abs() < tolerance, "{} isn't {}", actual, expected);
+        }
+        assert!((actual.scale - expected.scale).abs() < 1e-2 * expected.scale);
+    }
+
+    #[test]
+    fn paths_start_and_end_on_the_viewports() {
+        let from = Viewport::new(Point2D::new(0.0, 0.0), Size2D::new(400.0, 300.0), 2.0);
+        let to = Viewport::new(Point2D::new(3000.0, -800.0), Size2D::new(100.0, 75.0), 8.0);
+        let path = ZoomPath::new(&from, &to, DEFAULT_RHO);
+
+        assert_close(&path.at(0.0), &from);
+        assert_close(&path.at(1.0), &to);
+        // zoomed out half way
+        assert!(path.at(0.5).size.width > from.size.width);
+    }
+
+    #[test]
+    fn paths_between_the_same_viewport_stay_put() {
+        let view = Viewport::new(Point2D::new(10.0, 20.0), Size2D::new(400.0, 300.0), 2.0);
+        let path = ZoomPath::new(&view, &view, DEFAULT_RHO);
+
+        assert_eq!(path.length(), 0.0);
+        for t in [0.0, 0.5, 1.0] {
+            assert_close(&path.at(t), &view);
+        }
+    }
+}
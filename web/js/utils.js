@@ -7,7 +7,9 @@
 // Work around this for now by deleting the function to ensure that the
 // `no_modules.js` script doesn't have access to it. You won't need this
 // hack when deploying over HTTP.
-delete WebAssembly.instantiateStreaming;
+if (typeof WebAssembly !== 'undefined') {
+    delete WebAssembly.instantiateStreaming;
+}
 
 (function () {
     let _libReady = null;
@@ -15,6 +17,13 @@ delete WebAssembly.instantiateStreaming;
         if (_libReady !== null) {
             return _libReady;
         } else {
+            // without wasm the page is left as it is, the images and their links still work
+            if (typeof WebAssembly === 'undefined') {
+                _libReady = Promise.reject(new Error('WebAssembly is not supported'));
+
+                return _libReady;
+            }
+
             _libReady = Promise
                 .all([
                     new Promise((resolve, reject) => {